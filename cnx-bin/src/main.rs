@@ -57,7 +57,12 @@ fn main() -> Result<()> {
         pango_markup_single_render(Color::white(), default_text)
     });
 
-    let battery = Battery::new(attr.clone(), Color::red(), None, Some(battery_render));
+    let battery = Battery::new(
+        attr.clone(),
+        Color::role(Role::Crit),
+        None,
+        Some(battery_render),
+    );
     let render = Box::new(|load| {
         let mut color = Color::yellow().to_hex();
         if load < 5 {
@@ -0,0 +1,270 @@
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities::{Capabilities, Capability};
+use cnx::error::WidgetError;
+use cnx::services::Services;
+use cnx::teardown::AbortOnDrop;
+use std::future::{self, Future};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task;
+use tokio::time;
+use tokio_stream::{Stream, StreamExt};
+use zbus::{MatchRule, Message, MessageStream, MessageType};
+
+// The bounds of the delay between attempts to reconnect to a bus.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A message bus.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bus {
+    /// The system bus, e.g. for UPower, NetworkManager, BlueZ and logind
+    System,
+    /// The user's session bus, e.g. for MPRIS media players
+    Session,
+}
+
+impl Bus {
+    fn capability(self) -> Capability {
+        match self {
+            Bus::System => Capability::SystemBus,
+            Bus::Session => Capability::SessionBus,
+        }
+    }
+}
+
+// A connection to a bus, and whether the bus has since closed it.
+#[derive(Clone)]
+struct Current {
+    connection: zbus::Connection,
+    closed: watch::Receiver<bool>,
+    // The task watching for the connection to close, which holds a stream
+    // of its messages, and so would otherwise keep it open forever.
+    _watcher: Rc<AbortOnDrop<()>>,
+}
+
+impl Current {
+    async fn connect(bus: Bus) -> Result<Self> {
+        let connection = match bus {
+            Bus::System => zbus::Connection::system().await?,
+            Bus::Session => zbus::Connection::session().await?,
+        };
+
+        // Once the connection is closed, every stream of its messages ends,
+        // so we watch for a signal which is otherwise hardly ever sent.
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender("org.freedesktop.DBus")?
+            .interface("org.freedesktop.DBus")?
+            .member("NameLost")?
+            .build();
+        let mut messages = MessageStream::for_match_rule(rule, &connection, Some(1)).await?;
+        let (sender, closed) = watch::channel(false);
+        let watcher = AbortOnDrop::new(task::spawn(async move {
+            while messages.next().await.is_some() {}
+            let _ = sender.send(true);
+        }));
+
+        Ok(Current {
+            connection,
+            closed,
+            _watcher: Rc::new(watcher),
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    // Waits until the bus closes the connection, e.g. because dbus-daemon
+    // was restarted.
+    async fn wait_closed(&mut self) {
+        while !self.is_closed() {
+            if self.closed.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Connections to the system and session buses, shared between widgets.
+///
+/// Rather than each DBus widget (e.g. UPower, NetworkManager, MPRIS or BlueZ)
+/// opening its own connections, they all share one connection to each bus.
+/// As the connection is shared, the match rules which widgets' signal
+/// streams add are shared too: each one is added to the bus once, and
+/// removed again when the last stream using it is dropped.
+///
+/// If the bus closes a connection, e.g. because dbus-daemon was restarted,
+/// the next connection asked for is a new one. Widgets can be told about this
+/// with [`DbusPool::connections()`], subscribe again with each connection
+/// with [`DbusPool::watch()`], or receive signals across reconnections with
+/// [`DbusPool::signals()`]. If there is no bus to connect to, e.g. in a
+/// container, the streams yield an [`ErrorKind::Unsupported`] error once,
+/// and then stop trying.
+///
+/// Clone a `DbusPool` to share it between widgets. Widgets added to
+/// [`Cnx`] share the pool in its [`Services`]; see [`DbusPool::shared()`].
+///
+/// [`Cnx`]: cnx::Cnx
+/// [`ErrorKind::Unsupported`]: cnx::error::ErrorKind::Unsupported
+#[derive(Clone, Default)]
+pub struct DbusPool {
+    system: Rc<Mutex<Option<Current>>>,
+    session: Rc<Mutex<Option<Current>>>,
+    capabilities: Capabilities,
+}
+
+impl DbusPool {
+    /// Returns the pool shared by the widgets using `services`, creating it
+    /// if there isn't one yet.
+    pub fn shared(services: &Services) -> Self {
+        let pool = services.get_or_insert_with(|| DbusPool {
+            capabilities: Capabilities::shared(services),
+            ..DbusPool::default()
+        });
+        (*pool).clone()
+    }
+
+    async fn current(&self, bus: Bus) -> Result<Current> {
+        let current = match bus {
+            Bus::System => &self.system,
+            Bus::Session => &self.session,
+        };
+        let mut current = current.lock().await;
+        match &*current {
+            Some(current) if !current.is_closed() => Ok(current.clone()),
+            _ => {
+                let connected = Current::connect(bus).await?;
+                *current = Some(connected.clone());
+                Ok(connected)
+            }
+        }
+    }
+
+    /// Returns the shared connection to `bus`, connecting to it if there
+    /// isn't one or the bus has closed it.
+    pub async fn connection(&self, bus: Bus) -> Result<zbus::Connection> {
+        Ok(self.current(bus).await?.connection)
+    }
+
+    /// Returns a stream which yields the shared connection to `bus`, and then
+    /// a new one each time the bus closes the previous one.
+    ///
+    /// When the bus closes a connection, the streams of its signals and
+    /// property changes end, after which a widget can wait for the next
+    /// connection and subscribe again. Failed attempts to connect are
+    /// yielded as errors, and retried with increasing delays, unless there
+    /// is no bus.
+    pub fn connections(&self, bus: Bus) -> impl Stream<Item = Result<zbus::Connection>> {
+        let pool = self.clone();
+        stream! {
+            let mut backoff = MIN_BACKOFF;
+            loop {
+                match pool.current(bus).await {
+                    Ok(mut current) => {
+                        backoff = MIN_BACKOFF;
+                        yield Ok(current.connection.clone());
+                        current.wait_closed().await;
+                        continue;
+                    }
+                    Err(err) => {
+                        // Without a bus, trying again won't help.
+                        if let Err(missing) = pool.capabilities.require(bus.capability()) {
+                            yield Err(missing.into());
+                            future::pending::<()>().await;
+                        }
+                        yield Err(err);
+                    }
+                }
+                time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+
+    /// Returns a stream which yields what `watch` returns for the shared
+    /// connection to `bus`, and then for each new connection, as
+    /// [`DbusPool::connections()`] does.
+    ///
+    /// `watch` typically subscribes to the signals and property changes a
+    /// widget shows, whose streams end when the bus closes the connection,
+    /// after which the widget asks for what `watch` returns for the next
+    /// one. Failed attempts to connect, and errors from `watch`, are yielded
+    /// as errors.
+    pub fn watch<F, Fut, T>(&self, bus: Bus, mut watch: F) -> impl Stream<Item = Result<T>>
+    where
+        F: FnMut(zbus::Connection) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut connections = Box::pin(self.connections(bus));
+        stream! {
+            while let Some(connection) = connections.next().await {
+                match connection {
+                    Ok(connection) => yield watch(connection).await,
+                    Err(err) => yield Err(err),
+                }
+            }
+        }
+    }
+
+    /// Returns a stream of the messages on `bus` which match `rule`, which
+    /// is added again whenever the pool reconnects to the bus.
+    pub fn signals(
+        &self,
+        bus: Bus,
+        rule: MatchRule<'static>,
+    ) -> impl Stream<Item = Result<Arc<Message>>> {
+        let mut watches = Box::pin(self.watch(bus, move |connection| {
+            let rule = rule.clone();
+            async move { Ok(MessageStream::for_match_rule(rule, &connection, None).await?) }
+        }));
+        stream! {
+            while let Some(messages) = watches.next().await {
+                let mut messages = match messages {
+                    Ok(messages) => messages,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                // The stream yields an error as the connection closes.
+                while let Some(Ok(message)) = messages.next().await {
+                    yield Ok(message);
+                }
+            }
+        }
+    }
+}
+
+// Returns the pool shared by the widgets using `services`, or a pool of
+// its own for a widget that isn't added to a `Cnx`.
+#[cfg(any(
+    feature = "upower",
+    feature = "logind",
+    feature = "systemd",
+    feature = "bluetooth",
+    feature = "dunst",
+    feature = "dbus-property"
+))]
+pub(crate) fn pool(services: Option<&Services>) -> DbusPool {
+    services.map_or_else(DbusPool::default, DbusPool::shared)
+}
+
+// Returns the error to disable a widget with if there is no `bus` for it to
+// use.
+#[cfg(any(
+    feature = "upower",
+    feature = "logind",
+    feature = "systemd",
+    feature = "bluetooth",
+    feature = "dunst",
+    feature = "dbus-property"
+))]
+pub(crate) fn require(services: Option<&Services>, bus: Bus) -> Result<(), WidgetError> {
+    let capabilities = services.map_or_else(Capabilities::default, Capabilities::shared);
+    capabilities.require(bus.capability())
+}
@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Result};
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+
+// A shared library, loaded at runtime so that it is only needed by those who
+// use the widgets which call into it. It is unloaded when dropped.
+pub(crate) struct Library {
+    handle: *mut c_void,
+    name: String,
+}
+
+impl Library {
+    // Loads the library `name`, e.g. `libjack.so.0`. If it can't be loaded,
+    // the error says that it `hint`, e.g. "is installed with JACK".
+    pub(crate) fn open(name: &str, hint: &str) -> Result<Library> {
+        let c_name = CString::new(name)?;
+        let flags = libc::RTLD_NOW | libc::RTLD_LOCAL;
+        let handle = unsafe { libc::dlopen(c_name.as_ptr(), flags) };
+        if handle.is_null() {
+            return Err(anyhow!("Failed to load {}, which {}", name, hint));
+        }
+        Ok(Library {
+            handle,
+            name: name.to_owned(),
+        })
+    }
+
+    // Looks up the function called `name`, which must end with a nul.
+    //
+    // Safety: `T` must be the type of the function.
+    pub(crate) unsafe fn symbol<T>(&self, name: &[u8]) -> Result<T> {
+        let function = libc::dlsym(self.handle, name.as_ptr() as *const c_char);
+        if function.is_null() {
+            let name = String::from_utf8_lossy(&name[..name.len() - 1]);
+            return Err(anyhow!("Missing {} in {}", name, self.name));
+        }
+        Ok(mem::transmute_copy(&function))
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
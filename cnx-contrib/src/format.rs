@@ -0,0 +1,274 @@
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// The units used by [`NumberFormat::bytes`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByteUnits {
+    /// Powers of 1000: kB, MB, GB, ...
+    Si,
+    /// Powers of 1024: KiB, MiB, GiB, ...
+    Iec,
+}
+
+/// How to format numbers, i.e. which decimal point and thousands separator
+/// to use.
+///
+/// Use [`NumberFormat::from_locale`] to format numbers as the user expects.
+/// The default is `1,234.5`.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::format::{ByteUnits, NumberFormat};
+/// let format = NumberFormat::default();
+/// assert_eq!(format.integer(1234567), "1,234,567");
+/// assert_eq!(format.bytes(1536, ByteUnits::Iec), "1.5 KiB");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NumberFormat {
+    pub decimal_point: String,
+    /// The separator between groups of three digits, which may be empty
+    pub thousands_separator: String,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal_point: ".".to_owned(),
+            thousands_separator: ",".to_owned(),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns the format of the numeric locale (`LC_NUMERIC`) set in the
+    /// environment, or the default if it can't be read.
+    pub fn from_locale() -> Self {
+        locale_separators()
+            .map(|(decimal_point, thousands_separator)| NumberFormat {
+                decimal_point,
+                thousands_separator,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Formats an integer, e.g. `1,234,567`.
+    pub fn integer(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut formatted = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                formatted.push_str(&self.thousands_separator);
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    /// Formats a number with `precision` digits after the decimal point,
+    /// e.g. `1,234.57`.
+    pub fn decimal(&self, n: f64, precision: usize) -> String {
+        let formatted = format!("{:.*}", precision, n.abs());
+        let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let integer = integer
+            .parse()
+            .map_or_else(|_| integer.to_owned(), |integer| self.integer(integer));
+        // Don't show e.g. `-0.0`.
+        let sign = match formatted
+            .bytes()
+            .any(|digit| (b'1'..=b'9').contains(&digit))
+        {
+            true if n < 0.0 => "-",
+            _ => "",
+        };
+        match fraction {
+            "" => format!("{sign}{integer}"),
+            fraction => format!("{sign}{integer}{}{fraction}", self.decimal_point),
+        }
+    }
+
+    /// Formats a number of bytes in the largest unit in which it is at
+    /// least one, e.g. `1.5 GiB`. Bytes are shown as a whole number, and
+    /// larger units to one decimal place.
+    pub fn bytes(&self, bytes: u64, units: ByteUnits) -> String {
+        let (base, names) = match units {
+            ByteUnits::Si => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]),
+            ByteUnits::Iec => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+        };
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit < names.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        match unit {
+            0 => format!("{} {}", self.integer(bytes), names[0]),
+            _ => format!("{} {}", self.decimal(value, 1), names[unit]),
+        }
+    }
+}
+
+/// Formats a duration in its two largest units, e.g. `1h 23m` or `2d 4h`.
+///
+/// Durations of less than a minute are shown in seconds, and anything less
+/// than a second is `0s`.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::format::duration;
+/// # use std::time::Duration;
+/// assert_eq!(duration(Duration::from_secs(5000)), "1h 23m");
+/// assert_eq!(duration(Duration::from_secs(3600)), "1h");
+/// ```
+pub fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let largest = units
+        .iter()
+        .position(|(value, _)| *value > 0)
+        .unwrap_or(units.len() - 1);
+    units[largest..]
+        .iter()
+        .take(2)
+        .enumerate()
+        .filter(|(i, (value, _))| *i == 0 || *value > 0)
+        .map(|(_, (value, unit))| format!("{value}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses a duration written as numbers of days, hours, minutes and
+/// seconds, as formatted by [`duration()`], e.g. `25m`, `1h30m` or `1h 30m`.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::format::parse_duration;
+/// # use std::time::Duration;
+/// assert_eq!(parse_duration("1h 23m").unwrap(), Duration::from_secs(4980));
+/// assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+/// assert!(parse_duration("25").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(anyhow!("Unknown unit {:?} in duration: {}", c, s)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| anyhow!("Expected a number before {:?} in duration: {}", c, s))?;
+        seconds = value.saturating_mul(unit).saturating_add(seconds);
+        number.clear();
+    }
+    if !number.is_empty() || s.trim().is_empty() {
+        return Err(anyhow!(
+            "Expected a duration such as 25m or 1h30m, got: {}",
+            s
+        ));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+// Reads the decimal point and thousands separator of the numeric locale set
+// in the environment.
+#[cfg(target_os = "linux")]
+fn locale_separators() -> Option<(String, String)> {
+    use std::ffi::CStr;
+
+    // Unlike `setlocale()` and `localeconv()`, this doesn't change the
+    // locale of the whole process.
+    unsafe {
+        let locale = libc::newlocale(
+            libc::LC_NUMERIC_MASK,
+            b"\0".as_ptr().cast(),
+            std::ptr::null_mut(),
+        );
+        if locale.is_null() {
+            return None;
+        }
+        let item = |item| {
+            CStr::from_ptr(libc::nl_langinfo_l(item, locale))
+                .to_string_lossy()
+                .into_owned()
+        };
+        let separators = (item(libc::RADIXCHAR), item(libc::THOUSEP));
+        libc::freelocale(locale);
+        Some(separators)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn locale_separators() -> Option<(String, String)> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        let format = NumberFormat {
+            decimal_point: ",".to_owned(),
+            thousands_separator: ".".to_owned(),
+        };
+        assert_eq!(format.integer(0), "0");
+        assert_eq!(format.integer(999), "999");
+        assert_eq!(format.integer(1000), "1.000");
+        assert_eq!(format.integer(123456789), "123.456.789");
+        assert_eq!(format.decimal(1234.567, 2), "1.234,57");
+        assert_eq!(format.decimal(-0.04, 1), "0,0");
+        assert_eq!(format.decimal(-1500.0, 0), "-1.500");
+    }
+
+    #[test]
+    fn bytes() {
+        let format = NumberFormat::default();
+        assert_eq!(format.bytes(512, ByteUnits::Si), "512 B");
+        assert_eq!(format.bytes(1000, ByteUnits::Si), "1.0 kB");
+        assert_eq!(format.bytes(1000, ByteUnits::Iec), "1,000 B");
+        assert_eq!(format.bytes(5 << 30, ByteUnits::Iec), "5.0 GiB");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration(Duration::from_millis(500)), "0s");
+        assert_eq!(duration(Duration::from_secs(45)), "45s");
+        assert_eq!(duration(Duration::from_secs(63)), "1m 3s");
+        assert_eq!(
+            duration(Duration::from_secs(3 * 86400 + 7200 + 60)),
+            "3d 2h"
+        );
+        assert_eq!(duration(Duration::from_secs(86400 + 60)), "1d");
+
+        assert_eq!(
+            parse_duration("1d2h3m4s").unwrap(),
+            Duration::from_secs(86400 + 7200 + 180 + 4)
+        );
+        assert_eq!(
+            parse_duration(&duration(Duration::from_secs(63)))
+                .unwrap()
+                .as_secs(),
+            63
+        );
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5 minutes").is_err());
+        assert!(parse_duration("").is_err());
+    }
+}
@@ -0,0 +1,28 @@
+//! Entry points for the fuzz targets in the repository's `fuzz` directory,
+//! which feed arbitrary data to the parsers of data from other programs.
+//!
+//! This is only built by `cargo fuzz`, which sets `--cfg fuzzing`.
+
+use crate::widgets;
+
+/// Reads `response` as MPD's replies to commands.
+pub fn mpd_response(response: &[u8]) {
+    widgets::mpd::fuzz(response);
+}
+
+/// Parses `tree` as i3's reply to `GET_TREE`.
+#[cfg(feature = "wm")]
+pub fn i3_tree(tree: &[u8]) {
+    crate::wm::i3::fuzz(tree);
+}
+
+/// Parses `body` as a response from each of the web APIs widgets use.
+pub fn http_body(body: &[u8]) {
+    let body = String::from_utf8_lossy(body);
+    widgets::air_quality::fuzz(&body);
+    widgets::feeds::fuzz(&body);
+    widgets::github_notifications::fuzz(&body);
+    #[cfg(feature = "json")]
+    widgets::http_json::fuzz(&body);
+    widgets::update_check::fuzz(&body);
+}
@@ -0,0 +1,91 @@
+use cnx::text::Canvas;
+use std::collections::VecDeque;
+
+/// A fixed-size history of samples, e.g. of CPU usage.
+///
+/// Once it is full, pushing a new sample drops the oldest one.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::history::History;
+/// let mut history = History::new(2);
+/// history.push(1.0);
+/// history.push(2.0);
+/// history.push(3.0);
+/// assert_eq!(history.iter().copied().collect::<Vec<_>>(), vec![2.0, 3.0]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct History<T> {
+    samples: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> History<T> {
+    /// Creates an empty `History` which keeps the last `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        History {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a new sample, dropping the oldest one if the history is full.
+    pub fn push(&mut self, sample: T) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// The number of samples the history keeps.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of samples in the history.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether there are no samples yet.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The most recent sample, if any.
+    pub fn latest(&self) -> Option<&T> {
+        self.samples.back()
+    }
+
+    /// Iterates over the samples, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.samples.iter()
+    }
+}
+
+impl History<f64> {
+    /// Returns a [`Canvas`] drawing the samples as a histogram, with the
+    /// newest sample on the right.
+    ///
+    /// Each sample gets a bar of `width / capacity` pixels, whose height is
+    /// its value relative to `max`. The histogram is `width` pixels wide and
+    /// drawn the full height of the bar.
+    pub fn histogram(&self, width: f64, max: f64) -> Canvas {
+        let samples: Vec<f64> = self.samples.iter().copied().collect();
+        let capacity = self.capacity;
+        Canvas::new(width, 0.0, move |cr, width, height| {
+            let bar_width = width / capacity as f64;
+            // Right-align the samples, so a history that isn't full yet
+            // grows from the right.
+            let offset = capacity - samples.len();
+            for (i, sample) in samples.iter().enumerate() {
+                let bar_height = (sample / max).clamp(0.0, 1.0) * height;
+                let x = (offset + i) as f64 * bar_width;
+                cr.rectangle(x, height - bar_height, bar_width, bar_height);
+            }
+            let _ = cr.fill();
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use cnx::untrusted;
+use reqwest::{Certificate, Client, Proxy, Response, StatusCode};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time;
+
+// The delay before the first retry, which doubles for each one after it.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How a widget makes HTTP requests: through which proxy, trusting which
+/// certificates, and how patiently.
+///
+/// Like most HTTP clients, this uses the proxy given by the `HTTP_PROXY`,
+/// `HTTPS_PROXY` and `ALL_PROXY` environment variables (or their lowercase
+/// forms), except for hosts listed in `NO_PROXY`, unless another proxy is set
+/// with [`HttpConfig::with_proxy`].
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::http::HttpConfig;
+/// # use std::time::Duration;
+/// let http = HttpConfig::default()
+///     .with_ca_bundle("/etc/ssl/certs/corporate.pem".into())
+///     .with_timeout(Duration::from_secs(10))
+///     .with_retries(3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpConfig {
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            proxy: None,
+            ca_bundle: None,
+            timeout: Duration::from_secs(30),
+            retries: 2,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Sends all requests through the proxy at `proxy`, e.g.
+    /// `http://proxy.example.com:8080`, ignoring the environment.
+    pub fn with_proxy(self, proxy: String) -> Self {
+        Self {
+            proxy: Some(proxy),
+            ..self
+        }
+    }
+
+    /// Trusts the certificates in the PEM file at `ca_bundle`, as well as the
+    /// system's, e.g. for a proxy which intercepts TLS connections.
+    pub fn with_ca_bundle(self, ca_bundle: PathBuf) -> Self {
+        Self {
+            ca_bundle: Some(ca_bundle),
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for each request to complete. The default is
+    /// 30 seconds.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Sets how many times to retry a request which times out, fails to
+    /// connect or receives a server error. The default is twice.
+    pub fn with_retries(self, retries: u32) -> Self {
+        Self { retries, ..self }
+    }
+
+    pub(crate) fn client(&self) -> Result<HttpClient> {
+        // Some APIs, e.g. crates.io's, reject requests without a User-Agent.
+        let mut builder = Client::builder()
+            .user_agent(concat!("cnx-contrib/", env!("CARGO_PKG_VERSION")))
+            .timeout(self.timeout);
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy: {proxy}"))?);
+        }
+        if let Some(path) = &self.ca_bundle {
+            let pem = fs::read(path)
+                .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(HttpClient {
+            client: builder.build()?,
+            retries: self.retries,
+        })
+    }
+}
+
+// An HTTP client configured by an `HttpConfig`.
+pub(crate) struct HttpClient {
+    client: Client,
+    retries: u32,
+}
+
+impl HttpClient {
+    // Fetches `url`, retrying with backoff if the request fails in a way
+    // which might not happen again, and returns the body of the response.
+    pub(crate) async fn get_text(&self, url: &str) -> Result<String> {
+        self.get_text_with_headers(url, &[]).await
+    }
+
+    // Like `get_text`, but sends `headers` with the request, e.g. an API key.
+    pub(crate) async fn get_text_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<String> {
+        text(self.get(url, headers).await?).await
+    }
+
+    // Fetches `url` as `get_text_with_headers` does, but returns the whole
+    // response, for its status and headers.
+    pub(crate) async fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.get(url);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let result = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let err = match result {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if attempt >= self.retries
+                || !is_transient(err.is_timeout(), err.is_connect(), err.status())
+            {
+                return Err(err).with_context(|| format!("Failed to fetch {url}"));
+            }
+            time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+// Reads the body of `response` as text, failing rather than reading on if it
+// is larger than `untrusted::MAX_MESSAGE`.
+pub(crate) async fn text(mut response: Response) -> Result<String> {
+    let url = response.url().clone();
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        untrusted::check_len(body.len() + chunk.len(), untrusted::MAX_MESSAGE)
+            .with_context(|| format!("Response from {url} is too large"))?;
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// Returns whether a failed request is worth retrying.
+fn is_transient(timeout: bool, connect: bool, status: Option<StatusCode>) -> bool {
+    match status {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => timeout || connect,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transient_errors() {
+        assert!(is_transient(true, false, None));
+        assert!(is_transient(false, true, None));
+        assert!(!is_transient(false, false, None));
+        assert!(is_transient(false, false, Some(StatusCode::BAD_GATEWAY)));
+        assert!(is_transient(
+            false,
+            false,
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        ));
+        assert!(!is_transient(false, false, Some(StatusCode::NOT_FOUND)));
+    }
+}
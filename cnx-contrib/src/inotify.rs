@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use async_stream::try_stream;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::unistd::close;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
+use tokio::io::unix::AsyncFd;
+use tokio_stream::Stream;
+
+// An inotify instance, closed when dropped.
+struct Watcher(Inotify);
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = close(self.0.as_raw_fd());
+    }
+}
+
+/// Returns a stream which yields whenever any of the events in `flags`
+/// happens to one of `paths`, or, for a directory, to a file in it.
+pub(crate) fn watch(
+    paths: &[PathBuf],
+    flags: AddWatchFlags,
+) -> Result<impl Stream<Item = Result<()>>> {
+    let inotify = Watcher(Inotify::init(
+        InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC,
+    )?);
+    for path in paths {
+        inotify
+            .0
+            .add_watch(path, flags)
+            .with_context(|| format!("Failed to watch {}", path.display()))?;
+    }
+    let fd = AsyncFd::new(inotify)?;
+
+    let stream = try_stream! {
+        loop {
+            let mut guard = fd.readable().await?;
+            let result = guard.try_io(|fd| {
+                fd.get_ref().0.read_events().map_err(|err| {
+                    err.as_errno()
+                        .map_or_else(|| io::Error::new(io::ErrorKind::Other, err), io::Error::from)
+                })
+            });
+            match result {
+                Ok(events) => {
+                    events?;
+                    yield ();
+                }
+                // Spurious wake-up, the watcher has been marked as not ready.
+                Err(_would_block) => {}
+            }
+        }
+    };
+    Ok(stream)
+}
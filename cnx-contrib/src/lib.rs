@@ -1,2 +1,35 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
+/// Connections to the DBus system and session buses shared between widgets
+#[cfg(feature = "zbus")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zbus")))]
+pub mod dbus;
+// Shared libraries loaded at runtime, for the widgets which call into them.
+mod dylib;
+/// Helpers for formatting numbers, byte counts and durations
+pub mod format;
+// Entry points for the fuzz targets, which are built by `cargo fuzz`.
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing;
+/// Fixed-size histories of samples, which can be drawn as histograms
+pub mod history;
+/// Shared configuration of the HTTP clients used by widgets
+pub mod http;
+#[cfg(target_os = "linux")]
+mod inotify;
+#[cfg(target_os = "linux")]
+mod netlink;
+#[cfg(target_os = "linux")]
+mod procfs;
+/// Limits on the commands which widgets run, such as a timeout
+pub mod sandbox;
+/// Where to look up the passwords and tokens that widgets need
+pub mod secret;
+mod state;
+/// A shared sampler of system statistics for system widgets
+pub mod sysinfo;
 pub mod widgets;
+/// Helpers for talking to window managers over their IPC interfaces
+#[cfg(feature = "wm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wm")))]
+pub mod wm;
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use async_stream::try_stream;
+use nix::sys::socket::{bind, recv, send, MsgFlags, NetlinkAddr, SockAddr};
+use nix::unistd::close;
+use std::io;
+use std::iter;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use tokio::io::unix::AsyncFd;
+use tokio_stream::Stream;
+
+// See `include/uapi/linux/netlink.h` in the kernel sources.
+const NLMSG_HDRLEN: usize = 16;
+const NLA_HDRLEN: usize = 4;
+// The flags in the top bits of an attribute's type.
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+// Rounds `len` up to the 4 byte alignment of netlink messages and
+// attributes.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn io_error(err: nix::Error) -> io::Error {
+    err.as_errno().map_or_else(
+        || io::Error::new(io::ErrorKind::Other, err),
+        io::Error::from,
+    )
+}
+
+/// Appends an attribute to a message.
+#[cfg(any(feature = "wireless", test))]
+pub(crate) fn put_attr(buf: &mut Vec<u8>, kind: u16, payload: &[u8]) {
+    buf.extend_from_slice(&((NLA_HDRLEN + payload.len()) as u16).to_ne_bytes());
+    buf.extend_from_slice(&kind.to_ne_bytes());
+    buf.extend_from_slice(payload);
+    buf.resize(align(buf.len()), 0);
+}
+
+/// Splits a buffer into its attributes' types and payloads.
+pub(crate) fn attrs(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    iter::from_fn(move || {
+        let len = usize::from(u16::from_ne_bytes(buf.get(0..2)?.try_into().ok()?));
+        let kind = u16::from_ne_bytes(buf.get(2..4)?.try_into().ok()?) & NLA_TYPE_MASK;
+        let payload = buf.get(NLA_HDRLEN..len)?;
+        buf = buf.get(align(len)..).unwrap_or_default();
+        Some((kind, payload))
+    })
+}
+
+/// Returns the payload of the first attribute of type `kind`.
+pub(crate) fn attr(buf: &[u8], kind: u16) -> Option<&[u8]> {
+    attrs(buf)
+        .find(|(k, _)| *k == kind)
+        .map(|(_, payload)| payload)
+}
+
+// A netlink message header.
+struct Header {
+    kind: u16,
+    seq: u32,
+}
+
+// Splits a buffer received from netlink into its messages' headers and
+// payloads.
+fn messages(mut buf: &[u8]) -> impl Iterator<Item = (Header, &[u8])> {
+    iter::from_fn(move || {
+        let len = u32::from_ne_bytes(buf.get(0..4)?.try_into().ok()?) as usize;
+        let header = Header {
+            kind: u16::from_ne_bytes(buf.get(4..6)?.try_into().ok()?),
+            seq: u32::from_ne_bytes(buf.get(8..12)?.try_into().ok()?),
+        };
+        let payload = buf.get(NLMSG_HDRLEN..len)?;
+        buf = buf.get(align(len)..).unwrap_or_default();
+        Some((header, payload))
+    })
+}
+
+/// A netlink socket, closed when dropped.
+pub(crate) struct Socket {
+    fd: RawFd,
+    seq: u32,
+}
+
+impl Socket {
+    /// Opens a socket for a netlink protocol, e.g. `libc::NETLINK_ROUTE`.
+    ///
+    /// Sockets used with [`Socket::request()`] must be blocking, and those
+    /// used with [`Socket::events()`] non-blocking.
+    pub(crate) fn open(protocol: libc::c_int, nonblocking: bool) -> Result<Socket> {
+        let mut kind = libc::SOCK_RAW | libc::SOCK_CLOEXEC;
+        if nonblocking {
+            kind |= libc::SOCK_NONBLOCK;
+        }
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, kind, protocol) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let socket = Socket { fd, seq: 0 };
+        bind(fd, &SockAddr::Netlink(NetlinkAddr::new(0, 0)))?;
+        Ok(socket)
+    }
+
+    /// Subscribes to a multicast group, e.g. `RTNLGRP_LINK`.
+    pub(crate) fn subscribe(&self, group: u32) -> Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_NETLINK,
+                libc::NETLINK_ADD_MEMBERSHIP,
+                &group as *const u32 as *const libc::c_void,
+                mem::size_of::<u32>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Sends a request of type `kind` with the given `body`, and returns the
+    /// bodies of the replies, of which there are several if it is a dump.
+    pub(crate) fn request(&mut self, kind: u16, flags: u16, body: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.seq = self.seq.wrapping_add(1);
+        let len = (NLMSG_HDRLEN + body.len()) as u32;
+        let flags = flags | libc::NLM_F_REQUEST as u16 | libc::NLM_F_ACK as u16;
+        let mut buf = Vec::with_capacity(len as usize);
+        buf.extend_from_slice(&len.to_ne_bytes());
+        buf.extend_from_slice(&kind.to_ne_bytes());
+        buf.extend_from_slice(&flags.to_ne_bytes());
+        buf.extend_from_slice(&self.seq.to_ne_bytes());
+        // The port ID, which the kernel fills in.
+        buf.extend_from_slice(&0u32.to_ne_bytes());
+        buf.extend_from_slice(body);
+        send(self.fd, &buf, MsgFlags::empty()).map_err(io_error)?;
+
+        let mut replies = Vec::new();
+        let mut buf = vec![0; 32 * 1024];
+        loop {
+            let len = recv(self.fd, &mut buf, MsgFlags::empty()).map_err(io_error)?;
+            for (header, payload) in messages(&buf[..len]) {
+                if header.seq != self.seq {
+                    continue;
+                }
+                match i32::from(header.kind) {
+                    libc::NLMSG_ERROR => {
+                        let code = payload
+                            .get(0..4)
+                            .and_then(|code| Some(i32::from_ne_bytes(code.try_into().ok()?)))
+                            .unwrap_or(-libc::EIO);
+                        // An error of zero acknowledges the request.
+                        if code == 0 {
+                            return Ok(replies);
+                        }
+                        return Err(io::Error::from_raw_os_error(-code).into());
+                    }
+                    libc::NLMSG_DONE => return Ok(replies),
+                    _ => replies.push(payload.to_vec()),
+                }
+            }
+        }
+    }
+
+    /// Returns a stream which yields whenever a message is received, e.g.
+    /// on one of the multicast groups the socket is subscribed to.
+    pub(crate) fn events(self) -> Result<impl Stream<Item = Result<()>>> {
+        let fd = AsyncFd::new(self)?;
+
+        let stream = try_stream! {
+            let mut buf = vec![0; 32 * 1024];
+            loop {
+                let mut guard = fd.readable().await?;
+                let result = guard.try_io(|fd| {
+                    recv(fd.as_raw_fd(), &mut buf, MsgFlags::empty()).map_err(io_error)
+                });
+                match result {
+                    Ok(len) => {
+                        len?;
+                        yield ();
+                    }
+                    // Spurious wake-up, the socket has been marked as not ready.
+                    Err(_would_block) => {}
+                }
+            }
+        };
+        Ok(stream)
+    }
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = close(self.fd);
+    }
+}
@@ -0,0 +1,238 @@
+use std::fs;
+
+// A process, as described by `/proc/<pid>/stat`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Process {
+    pub(crate) pid: u32,
+    // The name of the process's executable, which may be truncated.
+    pub(crate) name: String,
+    pub(crate) parent: u32,
+    // The process's state, e.g. `R` for running or `Z` for a zombie.
+    pub(crate) state: char,
+    // The time spent running the process in user and kernel mode, in clock
+    // ticks.
+    pub(crate) cpu_ticks: u64,
+    // The process's resident set size, in pages.
+    pub(crate) rss_pages: u64,
+}
+
+/// Reads the process `pid`, if it exists.
+pub(crate) fn process(pid: u32) -> Option<Process> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    parse_stat(pid, &stat)
+}
+
+// Returns the IDs of all of the processes that exist.
+fn pids() -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect()
+}
+
+/// Reads all of the processes that exist.
+pub(crate) fn processes() -> Vec<Process> {
+    pids().into_iter().filter_map(process).collect()
+}
+
+/// Returns whether the process `pid` is `ancestor`, or one of its
+/// descendants.
+pub(crate) fn is_descendant(mut pid: u32, ancestor: u32) -> bool {
+    loop {
+        if pid == ancestor {
+            return true;
+        }
+        match process(pid) {
+            // The kernel's threads, and init, have a parent of 0.
+            Some(process) if process.parent > 0 => pid = process.parent,
+            _ => return false,
+        }
+    }
+}
+
+/// Returns the process `pid` followed by all of its descendants, out of
+/// `processes`.
+pub(crate) fn tree(processes: &[Process], pid: u32) -> Vec<&Process> {
+    let mut tree: Vec<&Process> = processes.iter().filter(|p| p.pid == pid).collect();
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i].pid;
+        tree.extend(processes.iter().filter(|p| p.parent == parent));
+        i += 1;
+    }
+    tree
+}
+
+/// Counts the open file descriptors of the process `pid`, if it is the
+/// user's own.
+pub(crate) fn open_fds(pid: u32) -> Option<usize> {
+    let fds = fs::read_dir(format!("/proc/{pid}/fd")).ok()?;
+    Some(fds.count())
+}
+
+/// The soft limit on the number of files the process `pid` can open, unless
+/// it is unlimited.
+pub(crate) fn fd_limit(pid: u32) -> Option<u64> {
+    let limits = fs::read_to_string(format!("/proc/{pid}/limits")).ok()?;
+    parse_fd_limit(&limits)
+}
+
+/// Counts the user's inotify instances, and the watches they have added.
+///
+/// An instance shared between processes, e.g. after a fork, is counted once
+/// for each of them.
+pub(crate) fn inotify_usage() -> (usize, usize) {
+    let mut instances = 0;
+    let mut watches = 0;
+    // Only the user's own processes' file descriptors can be read.
+    for pid in pids() {
+        let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            match fs::read_link(fd.path()) {
+                Ok(target) if target.as_os_str() == "anon_inode:inotify" => {}
+                _ => continue,
+            }
+            instances += 1;
+            let fdinfo = format!("/proc/{}/fdinfo/{}", pid, fd.file_name().to_string_lossy());
+            if let Ok(fdinfo) = fs::read_to_string(fdinfo) {
+                watches += fdinfo
+                    .lines()
+                    .filter(|line| line.starts_with("inotify wd:"))
+                    .count();
+            }
+        }
+    }
+    (instances, watches)
+}
+
+/// Reads a kernel parameter, e.g. `fs/inotify/max_user_watches`, which is a
+/// number.
+pub(crate) fn sysctl(name: &str) -> Option<u64> {
+    let value = fs::read_to_string(format!("/proc/sys/{name}")).ok()?;
+    value.trim().parse().ok()
+}
+
+/// The number of clock ticks in a second, in which CPU times are counted.
+pub(crate) fn clock_ticks() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    }
+}
+
+/// The size of a page of memory, in bytes.
+pub(crate) fn page_size() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    }
+}
+
+// Parses `/proc/<pid>/stat`, in which the fields after the process's name,
+// which is in parentheses and may contain anything, are separated by spaces.
+// See proc(5).
+fn parse_stat(pid: u32, stat: &str) -> Option<Process> {
+    let (head, fields) = stat.rsplit_once(')')?;
+    let (_, name) = head.split_once('(')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n)?.parse().ok() };
+    Some(Process {
+        pid,
+        name: name.to_owned(),
+        state: fields.first()?.chars().next()?,
+        parent: fields.get(1)?.parse().ok()?,
+        cpu_ticks: field(11)? + field(12)?,
+        rss_pages: field(21)?,
+    })
+}
+
+// Parses the soft limit on open files out of `/proc/<pid>/limits`, e.g.
+//
+// ```text
+// Limit                     Soft Limit           Hard Limit           Units
+// Max open files            1024                 524288               files
+// ```
+fn parse_fd_limit(limits: &str) -> Option<u64> {
+    let line = limits
+        .lines()
+        .find(|line| line.starts_with("Max open files"))?;
+    line["Max open files".len()..]
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stat() {
+        let stat = "4242 (Web Content) (1)) S 4200 4200 4200 0 -1 4194560 \
+            152437 0 12 0 9310 1722 0 0 20 0 28 0 1404583 3132309504 98304 \
+            18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 17 3 0 0 0 0 0\n";
+        assert_eq!(
+            parse_stat(4242, stat),
+            Some(Process {
+                pid: 4242,
+                name: "Web Content) (1)".to_owned(),
+                state: 'S',
+                parent: 4200,
+                cpu_ticks: 11032,
+                rss_pages: 98304,
+            })
+        );
+        assert_eq!(parse_stat(1, "1 (init"), None);
+    }
+
+    #[test]
+    fn descendants() {
+        let entry = |pid, parent| Process {
+            pid,
+            name: String::new(),
+            parent,
+            state: 'S',
+            cpu_ticks: 0,
+            rss_pages: 0,
+        };
+        let processes = [
+            entry(1, 0),
+            entry(10, 1),
+            entry(11, 10),
+            entry(12, 11),
+            entry(20, 1),
+        ];
+        let pids: Vec<u32> = tree(&processes, 10).iter().map(|p| p.pid).collect();
+        assert_eq!(pids, [10, 11, 12]);
+        assert!(tree(&processes, 30).is_empty());
+
+        let pid = std::process::id();
+        let parent = process(pid).unwrap().parent;
+        assert!(is_descendant(pid, pid));
+        assert!(is_descendant(pid, parent));
+        assert!(!is_descendant(parent, pid));
+    }
+
+    #[test]
+    fn limits() {
+        let limits = "\
+Limit                     Soft Limit           Hard Limit           Units
+Max cpu time              unlimited            unlimited            seconds
+Max open files            1024                 524288               files
+Max locked memory         8388608              8388608              bytes
+";
+        assert_eq!(parse_fd_limit(limits), Some(1024));
+        let unlimited =
+            "Max open files            unlimited            unlimited            files\n";
+        assert_eq!(parse_fd_limit(unlimited), None);
+
+        let pid = std::process::id();
+        assert!(open_fds(pid).unwrap() > 0);
+        assert!(fd_limit(pid).is_some());
+    }
+}
@@ -0,0 +1,341 @@
+use crate::format;
+use anyhow::{anyhow, Context, Result};
+use cnx::capabilities::{Capabilities, Capability};
+use cnx::untrusted;
+use std::env;
+use std::io;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::time;
+
+// The environment variables a command keeps with a clean environment.
+const KEPT_VARIABLES: [&str; 7] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "XDG_RUNTIME_DIR",
+    "DISPLAY",
+];
+
+/// How the commands which widgets run are confined, so that a misbehaving
+/// status script can't hang a widget, flood the bar or use up the system.
+///
+/// By default, a command runs as it would from a shell, except that only the
+/// first 64 KiB of its output is kept. A sandbox can also:
+///
+/// * Give it a clean environment, with only `PATH`, `HOME`, `USER`, `LANG`,
+///   `LC_ALL`, `XDG_RUNTIME_DIR` and `DISPLAY` kept from Cnx's
+/// * Limit the CPU time, memory and size of files it and its children use
+/// * Kill it and its children if it takes too long
+/// * Cut it off from the network, by running it in new user and network
+///   namespaces, which needs the system to allow unprivileged users to
+///   create them (see [`Capability::UserNamespaces`])
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::sandbox::Sandbox;
+/// # use std::time::Duration;
+/// let sandbox = Sandbox::default()
+///     .with_clean_env()
+///     .with_timeout(Duration::from_secs(5))
+///     .with_memory_limit(256 * 1024 * 1024)
+///     .without_network();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    clean_env: bool,
+    cpu_time: Option<Duration>,
+    memory: Option<u64>,
+    file_size: Option<u64>,
+    timeout: Option<Duration>,
+    network: Network,
+    max_output: usize,
+}
+
+// Whether commands may use the network.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Network {
+    Allowed,
+    Denied,
+    // Denied where the system has user namespaces, and allowed otherwise.
+    DeniedIfAvailable,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox {
+            clean_env: false,
+            cpu_time: None,
+            memory: None,
+            file_size: None,
+            timeout: None,
+            network: Network::Allowed,
+            max_output: untrusted::MAX_LINE,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Runs commands with only the few environment variables listed above,
+    /// e.g. to keep tokens in Cnx's environment from them.
+    pub fn with_clean_env(self) -> Self {
+        Self {
+            clean_env: true,
+            ..self
+        }
+    }
+
+    /// Limits the CPU time each process of a command may use, after which
+    /// it is killed.
+    pub fn with_cpu_limit(self, cpu_time: Duration) -> Self {
+        Self {
+            cpu_time: Some(cpu_time),
+            ..self
+        }
+    }
+
+    /// Limits the memory, in bytes, each process of a command may allocate.
+    pub fn with_memory_limit(self, memory: u64) -> Self {
+        Self {
+            memory: Some(memory),
+            ..self
+        }
+    }
+
+    /// Limits the size, in bytes, of the files a command may write.
+    pub fn with_file_size_limit(self, file_size: u64) -> Self {
+        Self {
+            file_size: Some(file_size),
+            ..self
+        }
+    }
+
+    /// Kills a command, and any processes it started, if it hasn't finished
+    /// after `timeout`, which is then an error.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Runs commands without network access. Running a command is then an
+    /// [`ErrorKind::Unsupported`](cnx::error::ErrorKind::Unsupported) error
+    /// if the system doesn't have [`Capability::UserNamespaces`].
+    pub fn without_network(self) -> Self {
+        Self {
+            network: Network::Denied,
+            ..self
+        }
+    }
+
+    /// Runs commands without network access where the system has
+    /// [`Capability::UserNamespaces`], and with it otherwise.
+    pub fn without_network_if_available(self) -> Self {
+        Self {
+            network: Network::DeniedIfAvailable,
+            ..self
+        }
+    }
+
+    /// Sets how many bytes of a command's output, and of its errors, are
+    /// kept. The rest is read and thrown away. The default is 64 KiB.
+    pub fn with_max_output(self, max_output: usize) -> Self {
+        Self { max_output, ..self }
+    }
+
+    /// Runs `command` in the sandbox, and returns its output once it exits.
+    ///
+    /// `capabilities` says whether the system can cut the command off from
+    /// the network, e.g. from [`Capabilities::shared()`].
+    pub async fn output(
+        &self,
+        mut command: Command,
+        capabilities: &Capabilities,
+    ) -> Result<Output> {
+        if self.clean_env {
+            command.env_clear();
+            for variable in KEPT_VARIABLES {
+                if let Some(value) = env::var_os(variable) {
+                    command.env(variable, value);
+                }
+            }
+        }
+        if self.network != Network::Allowed {
+            // Detecting user namespaces runs a process, which would block.
+            capabilities.prefetch(Capability::UserNamespaces).await;
+        }
+        let unshare = match self.network {
+            Network::Allowed => false,
+            Network::Denied => {
+                capabilities
+                    .require(Capability::UserNamespaces)
+                    .context("Can't run the command without network access")?;
+                true
+            }
+            Network::DeniedIfAvailable => capabilities.is_available(Capability::UserNamespaces),
+        };
+        let limits = [
+            (
+                libc::RLIMIT_CPU,
+                self.cpu_time.map(|cpu_time| cpu_time.as_secs().max(1)),
+            ),
+            (libc::RLIMIT_DATA, self.memory),
+            (libc::RLIMIT_FSIZE, self.file_size),
+        ];
+        // Safe, as the calls in the child are all async-signal-safe, and it
+        // doesn't allocate.
+        unsafe {
+            command.pre_exec(move || {
+                // A group of its own, so that it can be killed with its
+                // children.
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                for (resource, limit) in limits {
+                    let Some(limit) = limit else {
+                        continue;
+                    };
+                    let limit = libc::rlimit {
+                        rlim_cur: limit as libc::rlim_t,
+                        rlim_max: limit as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                match unshare {
+                    true => unshare_network(),
+                    false => Ok(()),
+                }
+            });
+        }
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start command")?;
+        let pid = child.id();
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(anyhow!("Failed to read the command's output"));
+        };
+
+        let run = async {
+            let (stdout, stderr, status) = tokio::try_join!(
+                read_capped(stdout, self.max_output),
+                read_capped(stderr, self.max_output),
+                child.wait(),
+            )?;
+            Ok::<_, io::Error>(Output {
+                status,
+                stdout,
+                stderr,
+            })
+        };
+        let output = match self.timeout {
+            Some(timeout) => match time::timeout(timeout, run).await {
+                Ok(output) => output,
+                Err(_) => {
+                    if let Some(pid) = pid.and_then(|pid| i32::try_from(pid).ok()) {
+                        // Safe, as it only sends a signal to the group.
+                        unsafe { libc::kill(-pid, libc::SIGKILL) };
+                    }
+                    return Err(anyhow!(
+                        "Command timed out after {}",
+                        format::duration(timeout)
+                    ));
+                }
+            },
+            None => run.await,
+        };
+        Ok(output?)
+    }
+}
+
+// Moves the calling process into new user and network namespaces, in which
+// there are no network interfaces but loopback, which is down.
+#[cfg(target_os = "linux")]
+unsafe fn unshare_network() -> io::Result<()> {
+    match libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// Only Linux has namespaces, and `Capability::UserNamespaces` is never
+// available elsewhere.
+#[cfg(not(target_os = "linux"))]
+unsafe fn unshare_network() -> io::Result<()> {
+    Ok(())
+}
+
+// Reads `pipe` to its end, keeping at most `max` bytes of it.
+async fn read_capped<R>(mut pipe: R, max: usize) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut kept = Vec::new();
+    (&mut pipe).take(max as u64).read_to_end(&mut kept).await?;
+    // The rest is read so that the command doesn't block writing it.
+    tokio::io::copy(&mut pipe, &mut tokio::io::sink()).await?;
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sandboxed() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let capabilities = Capabilities::default();
+        let shell = |script: &str| {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(script);
+            command
+        };
+        runtime.block_on(async {
+            let sandbox = Sandbox::default().with_max_output(4);
+            let output = sandbox
+                .output(shell("echo 0123456789; exit 3"), &capabilities)
+                .await
+                .unwrap();
+            assert_eq!(output.stdout, b"0123");
+            assert_eq!(output.status.code(), Some(3));
+
+            env::set_var("CNX_SANDBOX_TEST", "secret");
+            let sandbox = Sandbox::default().with_clean_env();
+            let output = sandbox
+                .output(shell("echo \"$CNX_SANDBOX_TEST\""), &capabilities)
+                .await
+                .unwrap();
+            assert_eq!(output.stdout, b"\n");
+
+            let sandbox = Sandbox::default().with_timeout(Duration::from_millis(100));
+            let err = sandbox
+                .output(shell("sleep 10 & wait"), &capabilities)
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("timed out"));
+
+            // Where commands can't be cut off from the network, they aren't
+            // run with it instead.
+            if !capabilities.is_available(Capability::UserNamespaces) {
+                let sandbox = Sandbox::default().without_network();
+                assert!(sandbox.output(shell("true"), &capabilities).await.is_err());
+                let sandbox = Sandbox::default().without_network_if_available();
+                assert!(sandbox.output(shell("true"), &capabilities).await.is_ok());
+            }
+        });
+    }
+}
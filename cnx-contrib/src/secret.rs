@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+#[cfg(feature = "secret-service")]
+mod secret_service;
+
+/// Where to find a secret, such as a password or API token, that a widget
+/// needs.
+///
+/// This lets you keep secrets out of your configuration. A secret is looked
+/// up each time it is needed, e.g. whenever a widget reconnects, so changes
+/// to it are picked up without restarting Cnx.
+///
+/// A `String` converts into [`Secret::Value`], so widgets which take an
+/// `impl Into<Secret>` still accept a password given directly.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx_contrib::secret::Secret;
+/// let token = Secret::Env("GITHUB_TOKEN".to_owned());
+/// let password = Secret::Pass("music/mpd".to_owned());
+/// let password = Secret::File("/run/secrets/mpd".into());
+/// ```
+#[derive(Clone)]
+pub enum Secret {
+    /// The secret itself.
+    Value(String),
+    /// The value of an environment variable.
+    Env(String),
+    /// The contents of a file, without any trailing newline.
+    File(PathBuf),
+    /// The password stored in [pass] under this name, i.e. the first line of
+    /// the output of `pass show <name>`.
+    ///
+    /// [pass]: https://www.passwordstore.org/
+    Pass(String),
+    /// The secret of the item in the [freedesktop Secret Service] (e.g.
+    /// GNOME Keyring or KeePassXC) with these attributes, as with
+    /// `secret-tool lookup <attribute> <value> ...`. The item's collection
+    /// must be unlocked.
+    ///
+    /// [freedesktop Secret Service]: https://specifications.freedesktop.org/secret-service/latest/
+    #[cfg(feature = "secret-service")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "secret-service")))]
+    SecretService(Vec<(String, String)>),
+}
+
+impl Secret {
+    /// Looks up the secret.
+    pub async fn get(&self) -> Result<String> {
+        match self {
+            Secret::Value(value) => Ok(value.clone()),
+            Secret::Env(name) => {
+                env::var(name).with_context(|| format!("Failed to read secret from ${name}"))
+            }
+            Secret::File(path) => {
+                let contents = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read secret from {}", path.display()))?;
+                Ok(trim_newline(&contents).to_owned())
+            }
+            Secret::Pass(name) => {
+                let output = Command::new("pass")
+                    .args(["show", name])
+                    .output()
+                    .await
+                    .context("Failed to run `pass`")?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "`pass show {}` exited with {}",
+                        name,
+                        output.status
+                    ));
+                }
+                let output =
+                    String::from_utf8(output.stdout).context("Invalid UTF-8 in `pass` output")?;
+                Ok(output.lines().next().unwrap_or_default().to_owned())
+            }
+            #[cfg(feature = "secret-service")]
+            Secret::SecretService(attributes) => secret_service::lookup(attributes).await,
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret::Value(value)
+    }
+}
+
+// Removes a single trailing newline, as left by most editors, but keeps any
+// other whitespace, which may be part of the secret.
+fn trim_newline(contents: &str) -> &str {
+    let contents = contents.strip_suffix('\n').unwrap_or(contents);
+    contents.strip_suffix('\r').unwrap_or(contents)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trim_newlines() {
+        assert_eq!(trim_newline("hunter2\n"), "hunter2");
+        assert_eq!(trim_newline("hunter2\r\n"), "hunter2");
+        assert_eq!(trim_newline(" hunter2 \n\n"), " hunter2 \n");
+        assert_eq!(trim_newline("hunter2"), "hunter2");
+    }
+}
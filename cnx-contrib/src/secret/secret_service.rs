@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use zbus::dbus_proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+// A secret as returned by the Secret Service: the session it is encoded
+// for, the encoding parameters, the value and its content type.
+type EncodedSecret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[dbus_proxy(
+    interface = "org.freedesktop.Secret.Service",
+    default_service = "org.freedesktop.secrets",
+    default_path = "/org/freedesktop/secrets",
+    gen_blocking = false
+)]
+trait Service {
+    fn open_session(
+        &self,
+        algorithm: &str,
+        input: &Value<'_>,
+    ) -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+
+    fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+
+    fn get_secrets(
+        &self,
+        items: &[ObjectPath<'_>],
+        session: &ObjectPath<'_>,
+    ) -> zbus::Result<HashMap<OwnedObjectPath, EncodedSecret>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.Secret.Session",
+    default_service = "org.freedesktop.secrets",
+    gen_blocking = false
+)]
+trait Session {
+    fn close(&self) -> zbus::Result<()>;
+}
+
+/// Returns the secret of the first unlocked item with `attributes`.
+pub(super) async fn lookup(attributes: &[(String, String)]) -> Result<String> {
+    let connection = zbus::Connection::session().await?;
+    let service = ServiceProxy::new(&connection).await?;
+
+    let query = attributes
+        .iter()
+        .map(|(attribute, value)| (attribute.as_str(), value.as_str()))
+        .collect();
+    let (unlocked, locked) = service.search_items(query).await?;
+    let item = match (unlocked.first(), locked.is_empty()) {
+        (Some(item), _) => item,
+        (None, false) => return Err(anyhow!("Secret {:?} is locked", attributes)),
+        (None, true) => return Err(anyhow!("No secret found for {:?}", attributes)),
+    };
+
+    // The secret is only sent over the session bus, so we don't need it to
+    // be encrypted.
+    let (_, session) = service.open_session("plain", &Value::from("")).await?;
+    let secrets = service
+        .get_secrets(&[item.as_ref()], &session.as_ref())
+        .await;
+    let _ = SessionProxy::builder(&connection)
+        .path(session)?
+        .build()
+        .await?
+        .close()
+        .await;
+
+    let (_, _, value, _) = secrets?
+        .remove(item)
+        .ok_or_else(|| anyhow!("No secret returned for {:?}", attributes))?;
+    Ok(String::from_utf8(value)?)
+}
@@ -0,0 +1,28 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Returns the file in which a widget keeps state between runs, such as
+/// which interface is selected, in `$XDG_STATE_HOME/cnx` (or
+/// `~/.local/state/cnx`).
+pub(crate) fn path(name: &str) -> Option<PathBuf> {
+    let dir = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(dir.join("cnx").join(name))
+}
+
+/// Reads the state kept in the file `name`, if there is any.
+pub(crate) fn read(name: &str) -> Option<String> {
+    fs::read_to_string(path(name)?).ok()
+}
+
+/// Replaces the state kept in the file `name`.
+pub(crate) fn write(name: &str, contents: &str) -> Result<()> {
+    let path = path(name).ok_or_else(|| anyhow!("Neither XDG_STATE_HOME nor HOME is set"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
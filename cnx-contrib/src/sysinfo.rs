@@ -0,0 +1,413 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::bus::Bus;
+use cnx::teardown::{self, AbortOnDrop};
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::time;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Time spent by a CPU (or all CPUs), in clock ticks since boot.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CpuTimes {
+    /// Time spent running user, niced and system tasks
+    pub busy: u64,
+    /// `busy` plus the time spent idle or waiting for I/O
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// The percentage of time the CPU was busy since `previous`.
+    pub fn usage_since(&self, previous: &CpuTimes) -> f64 {
+        let busy = self.busy.saturating_sub(previous.busy);
+        match self.total.saturating_sub(previous.total) {
+            0 => 0.0,
+            total => busy as f64 / total as f64 * 100.0,
+        }
+    }
+}
+
+/// Memory usage, in bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Memory {
+    pub total: u64,
+    /// Memory available for starting new applications, without swapping
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+/// The system load averages over 1, 5 and 15 minutes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+/// A temperature reported by a hardware monitoring chip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Temperature {
+    /// The name of the chip, e.g. `coretemp`
+    pub chip: String,
+    /// The label of the sensor, e.g. `Package id 0`, or e.g. `temp1` if the
+    /// sensor has no label
+    pub label: String,
+    pub celsius: f64,
+    /// The temperature at which the chip considers the sensor critical, if
+    /// it reports one
+    pub critical: Option<f64>,
+}
+
+/// The traffic of a network interface, in bytes since boot.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// A snapshot of the system's statistics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// When the sample was taken
+    pub time: Instant,
+    /// All CPUs combined
+    pub cpu: CpuTimes,
+    /// Each logical CPU
+    pub cores: Vec<CpuTimes>,
+    pub memory: Memory,
+    pub load: LoadAverage,
+    /// Every temperature sensor. This is empty if hwmon isn't available.
+    pub temperatures: Vec<Temperature>,
+    pub network: Vec<NetworkInterface>,
+}
+
+impl Sample {
+    /// Reads the current statistics from `/proc` and `/sys/class/hwmon`.
+    pub fn read() -> Result<Sample> {
+        let (cpu, cores) = parse_stat(&fs::read_to_string("/proc/stat")?)?;
+        Ok(Sample {
+            time: Instant::now(),
+            cpu,
+            cores,
+            memory: parse_meminfo(&fs::read_to_string("/proc/meminfo")?)?,
+            load: parse_loadavg(&fs::read_to_string("/proc/loadavg")?)?,
+            temperatures: read_hwmon(Path::new("/sys/class/hwmon")),
+            network: parse_net_dev(&fs::read_to_string("/proc/net/dev")?)?,
+        })
+    }
+}
+
+/// Reads the combined CPU times from `/proc/stat`, without taking a full
+/// [`Sample`].
+pub fn cpu_times() -> Result<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat")?;
+    let line = stat.lines().next().unwrap_or_default();
+    parse_cpu_line(line)
+}
+
+/// Reads every temperature sensor from `/sys/class/hwmon`, without taking a
+/// full [`Sample`].
+pub fn temperatures() -> Vec<Temperature> {
+    read_hwmon(Path::new("/sys/class/hwmon"))
+}
+
+/// Reads the times of each logical CPU from `/proc/stat`, without taking a
+/// full [`Sample`].
+pub fn core_times() -> Result<Vec<CpuTimes>> {
+    let (_, cores) = parse_stat(&fs::read_to_string("/proc/stat")?)?;
+    Ok(cores)
+}
+
+// https://www.kernel.org/doc/Documentation/filesystems/proc.txt
+fn parse_cpu_line(line: &str) -> Result<CpuTimes> {
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .map(str::parse)
+        .collect::<Result<Vec<u64>, _>>()
+        .with_context(|| format!("Invalid line in /proc/stat: {line}"))?;
+    match values[..] {
+        [user, nice, system, idle, iowait, ..] => {
+            let busy = user + nice + system;
+            Ok(CpuTimes {
+                busy,
+                total: busy + idle + iowait,
+            })
+        }
+        _ => Err(anyhow!("Missing data in /proc/stat")),
+    }
+}
+
+fn parse_stat(stat: &str) -> Result<(CpuTimes, Vec<CpuTimes>)> {
+    let mut lines = stat.lines().filter(|line| line.starts_with("cpu"));
+    let cpu = parse_cpu_line(lines.next().unwrap_or_default())?;
+    let cores = lines.map(parse_cpu_line).collect::<Result<_>>()?;
+    Ok((cpu, cores))
+}
+
+fn parse_meminfo(meminfo: &str) -> Result<Memory> {
+    let mut memory = Memory::default();
+    for line in meminfo.lines() {
+        let mut words = line.split_whitespace();
+        let field = match words.next() {
+            Some("MemTotal:") => &mut memory.total,
+            Some("MemAvailable:") => &mut memory.available,
+            Some("SwapTotal:") => &mut memory.swap_total,
+            Some("SwapFree:") => &mut memory.swap_free,
+            _ => continue,
+        };
+        let kib: u64 = words
+            .next()
+            .ok_or_else(|| anyhow!("Missing value in /proc/meminfo: {}", line))?
+            .parse()?;
+        *field = kib * 1024;
+    }
+    Ok(memory)
+}
+
+fn parse_loadavg(loadavg: &str) -> Result<LoadAverage> {
+    let values = loadavg
+        .split_whitespace()
+        .take(3)
+        .map(str::parse)
+        .collect::<Result<Vec<f64>, _>>()?;
+    match values[..] {
+        [one, five, fifteen] => Ok(LoadAverage { one, five, fifteen }),
+        _ => Err(anyhow!("Missing data in /proc/loadavg")),
+    }
+}
+
+fn parse_net_dev(net_dev: &str) -> Result<Vec<NetworkInterface>> {
+    // The first two lines are headers.
+    net_dev
+        .lines()
+        .skip(2)
+        .map(|line| {
+            let (name, stats) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid line in /proc/net/dev: {}", line))?;
+            let stats: Vec<&str> = stats.split_whitespace().collect();
+            match stats[..] {
+                [rx_bytes, _, _, _, _, _, _, _, tx_bytes, ..] => Ok(NetworkInterface {
+                    name: name.trim().to_owned(),
+                    rx_bytes: rx_bytes.parse()?,
+                    tx_bytes: tx_bytes.parse()?,
+                }),
+                _ => Err(anyhow!("Missing data in /proc/net/dev: {}", line)),
+            }
+        })
+        .collect()
+}
+
+// Reads every `temp*_input` of every chip. Unreadable chips and sensors are
+// skipped, as some only respond while the device they monitor is powered.
+fn read_hwmon(hwmon: &Path) -> Vec<Temperature> {
+    let mut temperatures = Vec::new();
+    let chips = match fs::read_dir(hwmon) {
+        Ok(chips) => chips,
+        Err(_) => return temperatures,
+    };
+    for chip in chips.flatten() {
+        let path = chip.path();
+        let name = match fs::read_to_string(path.join("name")) {
+            Ok(name) => name.trim().to_owned(),
+            Err(_) => continue,
+        };
+        let mut sensors: Vec<_> = match fs::read_dir(&path) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|entry| {
+                    let file = entry.file_name().into_string().ok()?;
+                    let sensor = file.strip_suffix("_input")?;
+                    sensor.starts_with("temp").then(|| sensor.to_owned())
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+        sensors.sort();
+        let celsius = |sensor: &str, file: &str| {
+            fs::read_to_string(path.join(format!("{sensor}_{file}")))
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .map(|millidegrees| millidegrees / 1000.0)
+        };
+        for sensor in sensors {
+            if let Some(value) = celsius(&sensor, "input") {
+                let critical = celsius(&sensor, "crit");
+                let label = fs::read_to_string(path.join(format!("{sensor}_label")))
+                    .map_or(sensor, |label| label.trim().to_owned());
+                temperatures.push(Temperature {
+                    chip: name.clone(),
+                    label,
+                    celsius: value,
+                    critical,
+                });
+            }
+        }
+    }
+    temperatures
+}
+
+type Receiver = watch::Receiver<Option<Arc<Sample>>>;
+
+// The task taking samples, which is aborted once the last subscriber's
+// stream, which holds a reference to it, is dropped.
+struct Sampler {
+    receiver: Receiver,
+    task: Weak<AbortOnDrop<()>>,
+}
+
+/// A shared sampler of system statistics.
+///
+/// Rather than each system widget reading `/proc` on its own schedule, a
+/// single task takes a [`Sample`] of everything on every tick, and each
+/// subscribed widget is given the same sample. Clone a `SysInfo` to share it
+/// between widgets, e.g. with [`Cpu::with_sysinfo`].
+///
+/// The task is started when the first widget subscribes, and stopped once
+/// every subscriber's stream has been dropped.
+///
+/// With [`SysInfo::with_bus`], each sample is also published on the
+/// [`Bus`], e.g. for a widget which only needs the CPU temperature.
+///
+/// [`Cpu::with_sysinfo`]: crate::widgets::cpu::Cpu::with_sysinfo
+#[derive(Clone)]
+pub struct SysInfo {
+    update_interval: Duration,
+    bus: Option<Bus>,
+    sampler: Rc<RefCell<Option<Sampler>>>,
+}
+
+impl SysInfo {
+    /// Creates a new `SysInfo`, which samples every `update_interval`.
+    pub fn new(update_interval: Duration) -> Self {
+        SysInfo {
+            update_interval,
+            bus: None,
+            sampler: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Publishes each sample, as an `Arc<Sample>`, on `bus`, e.g. the one
+    /// returned by [`Cnx::bus`].
+    ///
+    /// [`Cnx::bus`]: cnx::Cnx::bus
+    pub fn with_bus(self, bus: Bus) -> Self {
+        Self {
+            bus: Some(bus),
+            ..self
+        }
+    }
+
+    /// Returns a stream of samples, starting with the latest one if there
+    /// is one.
+    ///
+    /// This must be called from within a `task::LocalSet`, as [`Widget`]s'
+    /// `into_stream()` methods are.
+    ///
+    /// [`Widget`]: cnx::widgets::Widget
+    pub fn subscribe(&self) -> impl Stream<Item = Result<Arc<Sample>>> {
+        let mut sampler = self.sampler.borrow_mut();
+        let running = sampler
+            .as_ref()
+            .and_then(|sampler| Some((sampler.receiver.clone(), sampler.task.upgrade()?)));
+        let (receiver, task) = running.unwrap_or_else(|| {
+            let (sender, receiver) = watch::channel(None);
+            let mut interval = time::interval(self.update_interval);
+            let bus = self.bus.clone();
+            let task = Rc::new(teardown::spawn_local(async move {
+                loop {
+                    interval.tick().await;
+                    match Sample::read() {
+                        Ok(sample) => {
+                            let sample = Arc::new(sample);
+                            if let Some(bus) = &bus {
+                                bus.publish(sample.clone());
+                            }
+                            let _ = sender.send(Some(sample));
+                        }
+                        Err(err) => println!("Error sampling system statistics: {err}"),
+                    }
+                }
+            }));
+            *sampler = Some(Sampler {
+                receiver: receiver.clone(),
+                task: Rc::downgrade(&task),
+            });
+            (receiver, task)
+        });
+        WatchStream::new(receiver).filter_map(move |sample| {
+            // Keep the task running for as long as the stream is.
+            let _task = &task;
+            sample.map(Ok)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stat() {
+        let stat = "cpu  100 10 50 800 40 5 5 0 0 0\n\
+                    cpu0 60 5 25 390 20 3 2 0 0 0\n\
+                    cpu1 40 5 25 410 20 2 3 0 0 0\n\
+                    intr 12345 0 0\n";
+        let (cpu, cores) = parse_stat(stat).unwrap();
+        assert_eq!(
+            cpu,
+            CpuTimes {
+                busy: 160,
+                total: 1000
+            }
+        );
+        assert_eq!(cores.len(), 2);
+        assert_eq!(cores[1].busy, 70);
+
+        let later = CpuTimes {
+            busy: 210,
+            total: 1200,
+        };
+        assert_eq!(later.usage_since(&cpu), 25.0);
+    }
+
+    #[test]
+    fn meminfo() {
+        let meminfo = "MemTotal:       16000000 kB\n\
+                       MemFree:         1000000 kB\n\
+                       MemAvailable:    8000000 kB\n\
+                       SwapTotal:       2000000 kB\n\
+                       SwapFree:        2000000 kB\n";
+        let memory = parse_meminfo(meminfo).unwrap();
+        assert_eq!(memory.total, 16_000_000 * 1024);
+        assert_eq!(memory.available, 8_000_000 * 1024);
+        assert_eq!(memory.swap_free, 2_000_000 * 1024);
+    }
+
+    #[test]
+    fn loadavg() {
+        let load = parse_loadavg("0.52 0.58 0.59 2/1024 12345\n").unwrap();
+        assert_eq!(load.five, 0.58);
+    }
+
+    #[test]
+    fn net_dev() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit\n \
+                       face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    \
+                       lo: 1000 10 0 0 0 0 0 0 1000 10 0 0 0 0 0 0\n  \
+                       wlan0: 123456 100 0 0 0 0 0 0 65432 80 0 0 0 0 0 0\n";
+        let interfaces = parse_net_dev(net_dev).unwrap();
+        assert_eq!(interfaces.len(), 2);
+        assert_eq!(interfaces[1].name, "wlan0");
+        assert_eq!(interfaces[1].rx_bytes, 123456);
+        assert_eq!(interfaces[1].tx_bytes, 65432);
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use async_stream::{stream, try_stream};
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::time;
+use tokio_stream::{Stream, StreamExt};
+
+use super::battery::uevent;
+
+const ACPID_SOCKET: &str = "/run/acpid.socket";
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+const LID: &str = "/proc/acpi/button/lid";
+
+// How long a button press is shown for.
+const BUTTON_DURATION: Duration = Duration::from_secs(2);
+
+/// An ACPI event, as reported by acpid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AcpiEvent {
+    /// The lid was opened or closed.
+    Lid { open: bool },
+    /// The AC adapter was plugged in or unplugged.
+    AcAdapter { online: bool },
+    /// A battery's status changed, e.g. it was inserted or removed.
+    Battery,
+    /// A special button was pressed, e.g. `button/power` or
+    /// `video/brightnessup`.
+    Button(String),
+}
+
+/// The power state shown by [`Acpi`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AcpiState {
+    /// Whether the lid is open, if there is one
+    pub lid_open: Option<bool>,
+    /// Whether the AC adapter is plugged in, if there is one
+    pub ac_online: Option<bool>,
+    /// The button that was just pressed, e.g. `button/sleep`, which is only
+    /// set for a couple of seconds after it is pressed
+    pub button: Option<String>,
+}
+
+impl AcpiState {
+    fn read() -> Self {
+        AcpiState {
+            lid_open: read_lid(),
+            ac_online: read_ac_adapter(),
+            button: None,
+        }
+    }
+
+    fn apply(&mut self, event: AcpiEvent) {
+        match event {
+            AcpiEvent::Lid { open } => self.lid_open = Some(open),
+            AcpiEvent::AcAdapter { online } => self.ac_online = Some(online),
+            AcpiEvent::Battery => {}
+            AcpiEvent::Button(button) => self.button = Some(button),
+        }
+    }
+}
+
+/// Shows the lid and AC adapter state, and briefly any special button
+/// presses, as soon as ACPI reports them.
+///
+/// Events are read from acpid's socket at `/run/acpid.socket`. If acpid isn't
+/// running, the widget falls back to the kernel's power supply uevents,
+/// which only report the AC adapter being plugged in or unplugged.
+///
+/// The same events can be used in other widgets through [`events()`].
+pub struct Acpi {
+    attr: Attributes,
+    render: Option<Box<dyn Fn(AcpiState) -> String>>,
+}
+
+impl Acpi {
+    /// Creates a new [`Acpi`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`AcpiState`] is the current power state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::acpi::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|state: AcpiState| match state.ac_online {
+    ///     Some(true) => "🔌".to_owned(),
+    ///     _ => "🔋".to_owned(),
+    /// });
+    /// cnx.add_widget(Acpi::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(AcpiState) -> String>>) -> Self {
+        Self { attr, render }
+    }
+
+    fn render(&self, state: &AcpiState) -> Vec<Text> {
+        let default_text = || match (&state.button, state.ac_online) {
+            (Some(button), _) => button.clone(),
+            (None, Some(true)) => i18n::tr("AC"),
+            (None, Some(false)) => i18n::tr("Battery"),
+            (None, None) => String::new(),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(state.clone()));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Parses an event line from acpid's socket, e.g.
+// `ac_adapter ACPI0003:00 00000080 00000001` or `button/lid LID close`.
+fn parse_event(line: &str) -> Option<AcpiEvent> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let (kind, rest) = fields.split_first()?;
+    let category = kind.split('/').next().unwrap_or_default();
+    match (*kind, category) {
+        ("button/lid", _) => Some(AcpiEvent::Lid {
+            open: rest.contains(&"open"),
+        }),
+        ("ac_adapter", _) => {
+            let data = u32::from_str_radix(rest.last()?, 16).ok()?;
+            Some(AcpiEvent::AcAdapter { online: data != 0 })
+        }
+        ("battery", _) => Some(AcpiEvent::Battery),
+        (_, "button" | "video" | "cd") => Some(AcpiEvent::Button((*kind).to_owned())),
+        _ => None,
+    }
+}
+
+// Returns whether the lid is open, from e.g. `state:      open`.
+fn read_lid() -> Option<bool> {
+    let entry = fs::read_dir(LID).ok()?.next()?.ok()?;
+    let state = fs::read_to_string(entry.path().join("state")).ok()?;
+    Some(state.split_whitespace().last()? == "open")
+}
+
+// Returns whether the first mains power supply is online.
+fn read_ac_adapter() -> Option<bool> {
+    let read = |dir: &Path, file: &str| fs::read_to_string(dir.join(file)).ok();
+    fs::read_dir(POWER_SUPPLY)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .find(|dir| read(dir, "type").map_or(false, |kind| kind.trim() == "Mains"))
+        .and_then(|dir| read(&dir, "online"))
+        .map(|online| online.trim() == "1")
+}
+
+/// Returns a stream of ACPI events, e.g. for a widget to update as soon as
+/// the AC adapter is plugged in.
+///
+/// This reads acpid's socket, falling back to the kernel's power supply
+/// uevents if acpid isn't running, in which case only
+/// [`AcpiEvent::AcAdapter`] is reported.
+pub fn events() -> impl Stream<Item = Result<AcpiEvent>> {
+    try_stream! {
+        match UnixStream::connect(ACPID_SOCKET).await {
+            Ok(socket) => {
+                let mut lines = BufReader::new(socket).lines();
+                while let Some(line) = lines.next_line().await? {
+                    if let Some(event) = parse_event(&line) {
+                        yield event;
+                    }
+                }
+            }
+            Err(_) => {
+                let mut changes = Box::pin(
+                    uevent::power_supply_events().context("Failed to listen for ACPI events")?,
+                );
+                while let Some(change) = changes.next().await {
+                    change?;
+                    if let Some(online) = read_ac_adapter() {
+                        yield AcpiEvent::AcAdapter { online };
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for Acpi {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let mut events = Box::pin(events());
+
+        let stream = stream! {
+            let mut state = AcpiState::read();
+            yield Ok(self.render(&state));
+
+            loop {
+                // Stop showing a button press after a while.
+                let event = match state.button {
+                    Some(_) => match time::timeout(BUTTON_DURATION, events.next()).await {
+                        Ok(event) => event,
+                        Err(_elapsed) => {
+                            state.button = None;
+                            yield Ok(self.render(&state));
+                            continue;
+                        }
+                    },
+                    None => events.next().await,
+                };
+                match event {
+                    Some(Ok(event)) => {
+                        state.apply(event);
+                        yield Ok(self.render(&state));
+                    }
+                    Some(Err(err)) => yield Err(err),
+                    None => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn acpid_events() {
+        assert_eq!(
+            parse_event("ac_adapter ACPI0003:00 00000080 00000001"),
+            Some(AcpiEvent::AcAdapter { online: true })
+        );
+        assert_eq!(
+            parse_event("ac_adapter ACPI0003:00 00000080 00000000"),
+            Some(AcpiEvent::AcAdapter { online: false })
+        );
+        assert_eq!(
+            parse_event("button/lid LID close"),
+            Some(AcpiEvent::Lid { open: false })
+        );
+        assert_eq!(
+            parse_event("button/lid LID open"),
+            Some(AcpiEvent::Lid { open: true })
+        );
+        assert_eq!(
+            parse_event("battery PNP0C0A:00 00000080 00000001"),
+            Some(AcpiEvent::Battery)
+        );
+        assert_eq!(
+            parse_event("button/power PBTN 00000080 00000000"),
+            Some(AcpiEvent::Button("button/power".to_owned()))
+        );
+        assert_eq!(
+            parse_event("video/brightnessup BRTUP 00000086 00000000"),
+            Some(AcpiEvent::Button("video/brightnessup".to_owned()))
+        );
+        assert_eq!(parse_event("jack/headphone HEADPHONE plug"), None);
+        assert_eq!(parse_event(""), None);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::http::{HttpClient, HttpConfig};
+use crate::secret::Secret;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Ramp, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use regex::Regex;
+use reqwest::Url;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time;
+
+const WAQI_URL: &str = "https://api.waqi.info/feed/";
+const OPENAQ_URL: &str = "https://api.openaq.org/v3/sensors/";
+
+// How long to wait before trying again after the air quality couldn't be
+// fetched.
+const RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+// The US EPA's breakpoints for PM2.5, as of 2024: each range of 24-hour
+// concentrations, in µg/m³, and the range of AQI values it maps onto.
+const PM25_BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 9.0, 0.0, 50.0),
+    (9.1, 35.4, 51.0, 100.0),
+    (35.5, 55.4, 101.0, 150.0),
+    (55.5, 125.4, 151.0, 200.0),
+    (125.5, 225.4, 201.0, 300.0),
+    (225.5, 325.4, 301.0, 500.0),
+];
+
+// The AQI categories, each with the highest AQI in it.
+const CATEGORIES: [(u32, &str); 6] = [
+    (50, "Good"),
+    (100, "Moderate"),
+    (150, "Unhealthy for sensitive groups"),
+    (200, "Unhealthy"),
+    (300, "Very unhealthy"),
+    (u32::MAX, "Hazardous"),
+];
+
+/// Where [`AirQuality`] gets the air quality from.
+#[derive(Clone)]
+pub enum Provider {
+    /// The [World Air Quality Index project](https://aqicn.org/api/), whose
+    /// free API needs a token. The `station` is a city such as `london`, a
+    /// station ID such as `@5724`, a position such as `geo:51.5;-0.12`, or
+    /// `here` for the station nearest to your IP address's location.
+    Waqi { token: Secret, station: String },
+    /// The latest measurement of the [OpenAQ](https://openaq.org) sensor
+    /// with this ID, which must measure PM2.5. OpenAQ's API needs a free API
+    /// key.
+    OpenAq { api_key: Secret, sensor: u64 },
+}
+
+/// Shows the air quality index (AQI) near you, from the World Air Quality
+/// Index project or OpenAQ, in the color of its category.
+///
+/// The text is given by a format string, `AQI %a` by default, in which
+///
+/// * `%a` - the AQI, e.g. `42`
+/// * `%c` - its category, e.g. `Good`
+///
+/// The default colors are the US EPA's colors for the categories, from
+/// green for Good to maroon for Hazardous. Measurements from OpenAQ are of
+/// PM2.5, and are converted with the EPA's breakpoints; WAQI gives the AQI
+/// itself.
+///
+/// The air quality is fetched every 30 minutes by default. If the latest
+/// measurement is more than 3 hours old, because either the station hasn't
+/// reported since or it can't be fetched, it is shown as stale: in the
+/// widget's own color rather than the category's, and marked `(stale)`.
+pub struct AirQuality {
+    attr: Attributes,
+    provider: Provider,
+    format: String,
+    colors: Ramp<Color>,
+    update_interval: Duration,
+    max_age: Duration,
+    http: HttpConfig,
+}
+
+impl AirQuality {
+    /// Creates a new [`AirQuality`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `provider` - Where to get the air quality from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::secret::Secret;
+    /// # use cnx_contrib::widgets::air_quality::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(AirQuality::new(
+    ///     attr,
+    ///     Provider::Waqi {
+    ///         token: Secret::Env("WAQI_TOKEN".into()),
+    ///         station: "here".into(),
+    ///     },
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, provider: Provider) -> Self {
+        Self {
+            attr,
+            provider,
+            format: "AQI %a".to_owned(),
+            colors: Ramp::new(vec![
+                (50.0, Color::from_hex("#00E400")),
+                (100.0, Color::from_hex("#FFFF00")),
+                (150.0, Color::from_hex("#FF7E00")),
+                (200.0, Color::from_hex("#FF0000")),
+                (300.0, Color::from_hex("#8F3F97")),
+                (f64::INFINITY, Color::from_hex("#7E0023")),
+            ]),
+            update_interval: Duration::from_secs(30 * 60),
+            max_age: Duration::from_secs(3 * 60 * 60),
+            http: HttpConfig::default(),
+        }
+    }
+
+    /// Sets the format of the text. The default is `AQI %a`.
+    pub fn with_format(self, format: &str) -> Self {
+        Self {
+            format: format.to_owned(),
+            ..self
+        }
+    }
+
+    /// Sets the colors of the AQI, e.g. [`Ramp::roles()`] to use the bar's
+    /// palette rather than the EPA's colors.
+    pub fn with_colors(self, colors: Ramp<Color>) -> Self {
+        Self { colors, ..self }
+    }
+
+    /// Sets how often to fetch the air quality. The default is every 30
+    /// minutes.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets how old a measurement can be before it is shown as stale. The
+    /// default is 3 hours.
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        Self { max_age, ..self }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to fetch the
+    /// air quality.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    async fn fetch(&self, client: &HttpClient) -> Result<Reading> {
+        match &self.provider {
+            Provider::Waqi { token, station } => {
+                let token = token.get().await?;
+                let mut url = Url::parse(WAQI_URL)?;
+                url.path_segments_mut()
+                    .map_err(|_| anyhow!("Invalid WAQI URL"))?
+                    .pop_if_empty()
+                    .push(station)
+                    .push("");
+                url.query_pairs_mut().append_pair("token", &token);
+                let body = client.get_text(url.as_str()).await?;
+                parse_waqi(&body)
+            }
+            Provider::OpenAq { api_key, sensor } => {
+                let url = format!("{OPENAQ_URL}{sensor}");
+                let api_key = api_key.get().await?;
+                let body = client
+                    .get_text_with_headers(&url, &[("X-API-Key", &api_key)])
+                    .await?;
+                parse_openaq(&body).with_context(|| format!("Bad response for sensor {sensor}"))
+            }
+        }
+    }
+
+    fn render(&self, reading: &Reading) -> Vec<Text> {
+        let text = self
+            .format
+            .replace("%a", &reading.aqi.to_string())
+            .replace("%c", &i18n::tr(category(reading.aqi)));
+        let stale = reading.measured.map_or(false, |measured| {
+            SystemTime::now()
+                .duration_since(measured)
+                .map_or(false, |age| age > self.max_age)
+        });
+        let (text, fg_color) = if stale {
+            (
+                i18n::tr_with("{} (stale)", &text),
+                self.attr.fg_color.clone(),
+            )
+        } else {
+            let color = self.colors.get(f64::from(reading.aqi));
+            (text, color.unwrap_or(&self.attr.fg_color).clone())
+        };
+        vec![Text {
+            attr: Attributes {
+                fg_color,
+                ..self.attr.clone()
+            },
+            text,
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for AirQuality {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let client = self.http.client()?;
+        let stream = stream! {
+            let mut last: Option<Reading> = None;
+            loop {
+                match self.fetch(&client).await {
+                    Ok(reading) => {
+                        yield Ok(self.render(&reading));
+                        last = Some(reading);
+                        time::sleep(self.update_interval).await;
+                        continue;
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        // Show the last measurement again, which may have
+                        // become stale since.
+                        if let Some(reading) = &last {
+                            yield Ok(self.render(reading));
+                        }
+                    }
+                }
+                time::sleep(RETRY_DELAY.min(self.update_interval)).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// A measurement of the air quality.
+#[derive(Debug, PartialEq)]
+struct Reading {
+    aqi: u32,
+    // When it was measured, if the provider says.
+    measured: Option<SystemTime>,
+}
+
+// Parses a response from WAQI's feed API, e.g.
+// `{"status":"ok","data":{"aqi":42,...,"time":{"s":"...","tz":"+01:00","v":1705320000}}}`.
+fn parse_waqi(body: &str) -> Result<Reading> {
+    let status = Regex::new(r#""status"\s*:\s*"(\w+)""#)?;
+    if status.captures(body).map_or(true, |c| &c[1] != "ok") {
+        let message = Regex::new(r#""data"\s*:\s*"([^"]*)""#)?;
+        return Err(match message.captures(body) {
+            Some(captures) => anyhow!("WAQI error: {}", &captures[1]),
+            None => anyhow!("Unexpected response from WAQI"),
+        });
+    }
+    // The AQI is `"-"` when the station has no recent measurement.
+    let aqi = Regex::new(r#""aqi"\s*:\s*(\d+)"#)?
+        .captures(body)
+        .ok_or_else(|| anyhow!("No AQI in response from WAQI"))?[1]
+        .parse()?;
+    // WAQI gives the time at the station as if it were UTC, with the
+    // station's offset from UTC alongside.
+    let local = Regex::new(r#""time"\s*:\s*\{[^}]*"v"\s*:\s*(\d+)"#)?
+        .captures(body)
+        .and_then(|c| c[1].parse::<u64>().ok());
+    let offset = Regex::new(r#""time"\s*:\s*\{[^}]*"tz"\s*:\s*"([+-])(\d{2}):(\d{2})""#)?
+        .captures(body)
+        .and_then(|c| {
+            let seconds = c[2].parse::<i64>().ok()? * 3600 + c[3].parse::<i64>().ok()? * 60;
+            Some(if &c[1] == "-" { -seconds } else { seconds })
+        })
+        .unwrap_or(0);
+    let measured = local
+        .and_then(|local| u64::try_from(local as i64 - offset).ok())
+        .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+    Ok(Reading { aqi, measured })
+}
+
+// Parses a response from OpenAQ's sensors API, e.g.
+// `{"results":[{"id":1,"parameter":{"name":"pm25",...},"latest":{"datetime":{"utc":"2024-01-15T11:00:00Z",...},"value":12.5,...}}]}`.
+fn parse_openaq(body: &str) -> Result<Reading> {
+    let parameter = Regex::new(r#""parameter"\s*:\s*\{[^}]*"name"\s*:\s*"(\w+)""#)?
+        .captures(body)
+        .ok_or_else(|| anyhow!("No parameter in response from OpenAQ"))?;
+    if &parameter[1] != "pm25" {
+        return Err(anyhow!("Sensor measures {}, not PM2.5", &parameter[1]));
+    }
+    // The `datetime` object has no `value`, so the first one after `latest`
+    // is the measurement's.
+    let value = Regex::new(r#"(?s)"latest"\s*:\s*\{.*?"value"\s*:\s*(-?[0-9.]+)"#)?
+        .captures(body)
+        .ok_or_else(|| anyhow!("No measurement in response from OpenAQ"))?[1]
+        .parse()?;
+    let measured = Regex::new(r#"(?s)"latest"\s*:\s*\{.*?"utc"\s*:\s*"([^"]+)""#)?
+        .captures(body)
+        .and_then(|c| parse_utc(&c[1]));
+    Ok(Reading {
+        aqi: pm25_aqi(value),
+        measured,
+    })
+}
+
+// Parses a UTC time such as `2024-01-15T11:00:00Z`, ignoring any fraction
+// of a second.
+fn parse_utc(time: &str) -> Option<SystemTime> {
+    let (date, time) = time.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.split('-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.split(['.', ':']).map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    // Counts the days since 1970-01-01 of the proleptic Gregorian calendar,
+    // from years starting in March, so that the leap day is the last.
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+// Converts a PM2.5 concentration in µg/m³ into the US EPA's AQI.
+fn pm25_aqi(concentration: f64) -> u32 {
+    // The breakpoints have one decimal place, so the concentration is
+    // truncated to one too.
+    let concentration = (concentration.max(0.0) * 10.0).floor() / 10.0;
+    let (c_low, c_high, i_low, i_high) = PM25_BREAKPOINTS
+        .iter()
+        .copied()
+        .find(|&(_, c_high, _, _)| concentration <= c_high)
+        .unwrap_or(PM25_BREAKPOINTS[PM25_BREAKPOINTS.len() - 1]);
+    let aqi = (i_high - i_low) / (c_high - c_low) * (concentration - c_low) + i_low;
+    aqi.round().min(500.0) as u32
+}
+
+// Returns the name of the category of `aqi`.
+fn category(aqi: u32) -> &'static str {
+    CATEGORIES
+        .iter()
+        .find(|(highest, _)| aqi <= *highest)
+        .map_or("Hazardous", |&(_, name)| name)
+}
+
+// Parses `body` as a response from each API, for the `http_body` fuzz
+// target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(body: &str) {
+    let _ = parse_waqi(body);
+    let _ = parse_openaq(body);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn waqi() {
+        let body = r#"{"status":"ok","data":{"aqi":42,"idx":5724,"iaqi":{"pm25":{"v":42}},"time":{"s":"2024-01-15 12:00:00","tz":"+01:00","v":1705320000,"iso":"2024-01-15T12:00:00+01:00"}}}"#;
+        assert_eq!(
+            parse_waqi(body).unwrap(),
+            Reading {
+                aqi: 42,
+                measured: Some(UNIX_EPOCH + Duration::from_secs(1705316400)),
+            }
+        );
+
+        let body = r#"{"status":"ok","data":{"aqi":"-","time":{"s":"","tz":"-05:00","v":0}}}"#;
+        assert!(parse_waqi(body).is_err());
+        let body = r#"{"status":"error","data":"Invalid key"}"#;
+        assert_eq!(
+            parse_waqi(body).unwrap_err().to_string(),
+            "WAQI error: Invalid key"
+        );
+    }
+
+    #[test]
+    fn openaq() {
+        let body = r#"{"meta":{"found":1},"results":[{"id":3917,"name":"pm25 µg/m³","parameter":{"id":2,"name":"pm25","units":"µg/m³"},"datetimeLast":{"utc":"2024-01-15T11:00:00Z"},"latest":{"datetime":{"utc":"2024-01-15T11:00:00Z","local":"2024-01-15T12:00:00+01:00"},"value":12.5,"coordinates":{"latitude":51.5,"longitude":-0.12}}}]}"#;
+        assert_eq!(
+            parse_openaq(body).unwrap(),
+            Reading {
+                aqi: 57,
+                measured: Some(UNIX_EPOCH + Duration::from_secs(1705316400)),
+            }
+        );
+
+        let body = r#"{"results":[{"parameter":{"name":"o3"},"latest":{"value":0.03}}]}"#;
+        assert!(parse_openaq(body).is_err());
+    }
+
+    #[test]
+    fn utc_times() {
+        assert_eq!(parse_utc("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+        assert_eq!(
+            parse_utc("2024-02-29T23:59:59.5Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(1709251199))
+        );
+        assert_eq!(parse_utc("2024-01-15T11:00:00+01:00"), None);
+    }
+
+    #[test]
+    fn aqi_from_pm25() {
+        assert_eq!(pm25_aqi(0.0), 0);
+        assert_eq!(pm25_aqi(9.0), 50);
+        assert_eq!(pm25_aqi(9.05), 50);
+        assert_eq!(pm25_aqi(35.4), 100);
+        assert_eq!(pm25_aqi(55.5), 151);
+        assert_eq!(pm25_aqi(1000.0), 500);
+        assert_eq!(category(50), "Good");
+        assert_eq!(category(151), "Unhealthy");
+        assert_eq!(category(501), "Hazardous");
+    }
+}
@@ -0,0 +1,364 @@
+use crate::procfs;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use cnx::xcb::xcb_properties_stream;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio_stream::wrappers::LinesStream;
+use tokio_stream::{self as stream, StreamExt};
+use xcb_util::ewmh;
+
+/// The audio of the focused window's application, as shown by
+/// [`AppVolume`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppAudio {
+    /// The application's name, as it gave it to the sound server
+    pub name: String,
+    /// The volume of its first stream, in percent
+    pub volume: u32,
+    /// Whether its first stream is muted
+    pub muted: bool,
+}
+
+// A PulseAudio sink input, i.e. a stream an application is playing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct SinkInput {
+    index: u32,
+    pid: Option<u32>,
+    name: String,
+    volume: u32,
+    muted: bool,
+}
+
+type Render = Box<dyn Fn(&AppAudio) -> String>;
+
+/// Shows the volume of the audio the focused window is playing, e.g.
+/// `Firefox 65%`, and lets you change it without changing the volume of
+/// everything else.
+///
+/// Scrolling up or down over the widget raises or lowers the volume of the
+/// application's streams, and left clicking it mutes or unmutes them. The
+/// widget is hidden while the focused window isn't playing anything.
+///
+/// Streams are matched to the window by the process ID the window gives in
+/// `_NET_WM_PID`, and the one the stream gives in `application.process.id`,
+/// or that of any of the process's descendants, as e.g. browsers play audio
+/// from child processes. This uses `pactl`, so works with PulseAudio and
+/// PipeWire's PulseAudio server, and follows streams as they change with
+/// `pactl subscribe`.
+pub struct AppVolume {
+    attr: Attributes,
+    step: u8,
+    render: Option<Render>,
+}
+
+impl AppVolume {
+    /// Creates a new [`AppVolume`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the focused application's
+    /// [`AppAudio`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::app_volume::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|audio: &AppAudio| match audio.muted {
+    ///     true => "🔇".to_owned(),
+    ///     false => format!("🔊 {}%", audio.volume),
+    /// });
+    /// cnx.add_widget(AppVolume::new(attr, Some(render)).with_step(2));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            step: 5,
+            render,
+        }
+    }
+
+    /// Sets by how many percent each scroll changes the volume. The default
+    /// is 5.
+    pub fn with_step(self, step: u8) -> Self {
+        Self { step, ..self }
+    }
+
+    fn render(&self, inputs: &[SinkInput]) -> Vec<Text> {
+        let Some(input) = inputs.first() else {
+            return Vec::new();
+        };
+        let audio = AppAudio {
+            name: input.name.clone(),
+            volume: input.volume,
+            muted: input.muted,
+        };
+        let text = match &self.render {
+            Some(render) => (render)(&audio),
+            None if audio.muted => format!("{} muted", audio.name),
+            None => format!("{} {}%", audio.name, audio.volume),
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+
+    // Changes the volume or mute of all of `inputs`.
+    async fn click(&self, button: MouseButton, inputs: &[SinkInput]) -> Result<()> {
+        let (command, value) = match button {
+            MouseButton::ScrollUp => ("set-sink-input-volume", format!("+{}%", self.step)),
+            MouseButton::ScrollDown => ("set-sink-input-volume", format!("-{}%", self.step)),
+            MouseButton::Left => ("set-sink-input-mute", "toggle".to_owned()),
+            _ => return Ok(()),
+        };
+        for input in inputs {
+            pactl(&[command, &input.index.to_string(), &value]).await?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the process ID of the focused window, if it gives one.
+fn focused_pid(conn: &ewmh::Connection, screen_idx: i32) -> Option<u32> {
+    let window = ewmh::get_active_window(conn, screen_idx).get_reply().ok()?;
+    ewmh::get_wm_pid(conn, window).get_reply().ok()
+}
+
+async fn pactl(args: &[&str]) -> Result<String> {
+    // pactl's output is translated.
+    let output = Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .await
+        .context("Failed to run pactl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`pactl {}` exited with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Returns the sink inputs which belong to the process `pid`.
+async fn sink_inputs(pid: Option<u32>) -> Result<Vec<SinkInput>> {
+    let Some(pid) = pid else {
+        return Ok(Vec::new());
+    };
+    let inputs = parse_sink_inputs(&pactl(&["list", "sink-inputs"]).await?);
+    Ok(inputs
+        .into_iter()
+        .filter(|input| {
+            input
+                .pid
+                .map_or(false, |child| procfs::is_descendant(child, pid))
+        })
+        .collect())
+}
+
+// Parses the output of `pactl list sink-inputs`, e.g.
+//
+// ```text
+// Sink Input #42
+//         Driver: protocol-native.c
+//         Mute: no
+//         Volume: front-left: 42598 /  65% / -11.23 dB,   front-right: 42598 /  65% / -11.23 dB
+//                 balance 0.00
+//         Properties:
+//                 application.name = "Firefox"
+//                 application.process.id = "12345"
+// ```
+fn parse_sink_inputs(output: &str) -> Vec<SinkInput> {
+    let mut inputs = Vec::new();
+    for section in output.split("Sink Input #").skip(1) {
+        let Some(index) = section.lines().next().and_then(|line| line.trim().parse().ok()) else {
+            continue;
+        };
+        let mut input = SinkInput {
+            index,
+            pid: None,
+            name: String::new(),
+            volume: 0,
+            muted: false,
+        };
+        for line in section.lines().map(str::trim) {
+            if let Some(mute) = line.strip_prefix("Mute: ") {
+                input.muted = mute == "yes";
+            } else if let Some(volume) = line.strip_prefix("Volume: ") {
+                // The average of the channels' volumes.
+                let percentages: Vec<u32> = volume
+                    .split('/')
+                    .filter_map(|part| part.trim().strip_suffix('%')?.parse().ok())
+                    .collect();
+                if !percentages.is_empty() {
+                    input.volume = percentages.iter().sum::<u32>() / percentages.len() as u32;
+                }
+            } else if let Some((key, value)) = line.split_once(" = ") {
+                let value = value.trim_matches('"');
+                match key {
+                    "application.name" => input.name = value.to_owned(),
+                    "application.process.id" => input.pid = value.parse().ok(),
+                    _ => {}
+                }
+            }
+        }
+        inputs.push(input);
+    }
+    inputs
+}
+
+enum Event {
+    Focused,
+    // Something happened to a sink input, or the subscription ended.
+    Changed(Result<()>),
+    Click(MouseButton),
+}
+
+impl Widget for AppVolume {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let screen_idx = 0; // XXX assume
+        let (conn, focus) =
+            xcb_properties_stream(&["_NET_ACTIVE_WINDOW"]).context("Initialising AppVolume")?;
+
+        let mut child = Command::new("pactl")
+            .arg("subscribe")
+            .env("LC_ALL", "C")
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to spawn `pactl subscribe`")?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture stdout of `pactl subscribe`"))?;
+        // e.g. `Event 'change' on sink-input #42`
+        let changes =
+            LinesStream::new(BufReader::new(stdout).lines()).filter_map(|line| match line {
+                Ok(line) if line.contains(" sink-input ") => Some(Event::Changed(Ok(()))),
+                Ok(_) => None,
+                Err(err) => Some(Event::Changed(Err(err.into()))),
+            });
+
+        let mut events = Box::pin(
+            focus
+                .map(|()| Event::Focused)
+                .merge(changes)
+                .merge(clicks.map(|click| Event::Click(click.button))),
+        );
+        let stream = stream! {
+            // Keep `pactl subscribe` running for as long as the widget is.
+            let _child = child;
+            let mut pid = None;
+            let mut inputs = Vec::new();
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Focused => pid = focused_pid(&conn, screen_idx),
+                    Event::Changed(Ok(())) => {}
+                    Event::Changed(Err(err)) => {
+                        yield Err(err);
+                        continue;
+                    }
+                    // The new volume is shown once the change is reported.
+                    Event::Click(button) => {
+                        if let Err(err) = self.click(button, &inputs).await {
+                            yield Err(err);
+                        }
+                        continue;
+                    }
+                }
+                match sink_inputs(pid).await {
+                    Ok(matching) => {
+                        inputs = matching;
+                        yield Ok(self.render(&inputs));
+                    }
+                    Err(err) => yield Err(err),
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let output = "\
+Sink Input #42
+\tDriver: protocol-native.c
+\tOwner Module: 9
+\tMute: no
+\tVolume: front-left: 42598 /  65% / -11.23 dB,   front-right: 39321 /  60% / -13.31 dB
+\t        balance -0.08
+\tProperties:
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.id = \"12345\"
+
+Sink Input #57
+\tDriver: PipeWire
+\tMute: yes
+\tVolume: mono: 65536 / 100% / 0.00 dB
+\tProperties:
+\t\tapplication.name = \"mpv\"
+";
+        assert_eq!(
+            parse_sink_inputs(output),
+            vec![
+                SinkInput {
+                    index: 42,
+                    pid: Some(12345),
+                    name: "Firefox".to_owned(),
+                    volume: 62,
+                    muted: false,
+                },
+                SinkInput {
+                    index: 57,
+                    pid: None,
+                    name: "mpv".to_owned(),
+                    volume: 100,
+                    muted: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use crate::inotify;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+#[cfg(feature = "logind")]
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use nix::sys::inotify::AddWatchFlags;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio_stream::{self as stream, Stream, StreamExt};
+
+#[cfg(feature = "logind")]
+mod logind;
+
+const BACKLIGHT: &str = "/sys/class/backlight";
+
+/// How [`Backlight`] changes the brightness when it is scrolled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Control {
+    /// Write to the device's `brightness` file in sysfs. This usually needs
+    /// root, or a udev rule giving the user write access to it.
+    Sysfs,
+    /// Ask systemd-logind to set the brightness on behalf of the current
+    /// session over DBus, which doesn't need root.
+    #[cfg(feature = "logind")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "logind")))]
+    Logind,
+}
+
+/// Shows the brightness of a backlight, e.g. a laptop's screen.
+///
+/// The brightness is read from [`/sys/class/backlight/`], and updated
+/// whenever the kernel reports that it has changed, whether by this widget,
+/// another program or the laptop's brightness keys.
+///
+/// Scrolling up or down over the widget increases or decreases the
+/// brightness by 5% by default. Writing to sysfs usually needs root, so with
+/// the `logind` feature enabled the brightness can instead be set through
+/// systemd-logind; see [`Backlight::with_control`].
+///
+/// [`/sys/class/backlight/`]: https://www.kernel.org/doc/Documentation/ABI/stable/sysfs-class-backlight
+pub struct Backlight {
+    attr: Attributes,
+    device: Option<String>,
+    step: u8,
+    control: Control,
+    render: Option<Box<dyn Fn(u8) -> String>>,
+    #[cfg(feature = "logind")]
+    services: Option<Services>,
+}
+
+impl Backlight {
+    /// Creates a new [`Backlight`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the brightness as a percentage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::backlight::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|percentage| format!("☀ {percentage}%"));
+    /// cnx.add_widget(Backlight::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(u8) -> String>>) -> Self {
+        Self {
+            attr,
+            device: None,
+            step: 5,
+            control: Control::Sysfs,
+            render,
+            #[cfg(feature = "logind")]
+            services: None,
+        }
+    }
+
+    /// Sets which device in `/sys/class/backlight/` to show, e.g.
+    /// `intel_backlight`. By default, the first one is shown.
+    pub fn with_device(self, device: String) -> Self {
+        Self {
+            device: Some(device),
+            ..self
+        }
+    }
+
+    /// Sets by how many percent each scroll changes the brightness. The
+    /// default is 5%.
+    pub fn with_step(self, step: u8) -> Self {
+        Self { step, ..self }
+    }
+
+    /// Sets how the brightness is changed when the widget is scrolled. The
+    /// default is [`Control::Sysfs`].
+    pub fn with_control(self, control: Control) -> Self {
+        Self { control, ..self }
+    }
+
+    // Changes the brightness of `device` by one step, up or down.
+    async fn scroll(
+        &self,
+        device: &str,
+        up: bool,
+        #[cfg(feature = "logind")] logind: &mut logind::Logind,
+    ) -> Result<()> {
+        let dir = Path::new(BACKLIGHT).join(device);
+        let value = Brightness::read(&dir)?.step(self.step, up);
+        match self.control {
+            Control::Sysfs => fs::write(dir.join("brightness"), value.to_string())
+                .with_context(|| format!("Failed to set brightness of {device}")),
+            #[cfg(feature = "logind")]
+            Control::Logind => logind.set_brightness(device, value).await,
+        }
+    }
+
+    fn render(&self, brightness: &Brightness) -> Vec<Text> {
+        let percentage = brightness.percentage();
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| format!("{percentage}%"), |x| (x)(percentage));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: true,
+            canvas: None,
+        }]
+    }
+}
+
+// A device's raw brightness, which ranges from zero to `max`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Brightness {
+    current: u64,
+    max: u64,
+}
+
+impl Brightness {
+    fn read(dir: &Path) -> Result<Self> {
+        let read = |file: &str| -> Result<u64> {
+            let path = dir.join(file);
+            let value = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok(value.trim().parse()?)
+        };
+        Ok(Brightness {
+            current: read("brightness")?,
+            max: read("max_brightness")?,
+        })
+    }
+
+    fn percentage(&self) -> u8 {
+        match self.max {
+            0 => 0,
+            max => (self.current as f64 * 100.0 / max as f64).round() as u8,
+        }
+    }
+
+    // Returns the raw brightness after changing it by `step` percent, up or
+    // down. It always changes by at least one, so that small steps still
+    // have an effect on devices with only a few levels.
+    fn step(&self, step: u8, up: bool) -> u64 {
+        let delta = ((self.max * step as u64) as f64 / 100.0).round().max(1.0) as u64;
+        match up {
+            true => self.current.saturating_add(delta).min(self.max),
+            false => self.current.saturating_sub(delta),
+        }
+    }
+}
+
+// Returns the first backlight device, in alphabetical order.
+fn default_device() -> Result<String> {
+    let mut devices = fs::read_dir(BACKLIGHT)
+        .with_context(|| format!("Failed to read {BACKLIGHT}"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>>>()?;
+    devices.sort();
+    devices
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No backlight devices found in {}", BACKLIGHT))
+}
+
+// Returns a stream which yields whenever the brightness of the device in
+// `dir` changes.
+//
+// Writes to `brightness` are reported by inotify, and the kernel notifies
+// watchers of `actual_brightness` when the firmware changes it, e.g. in
+// response to the laptop's brightness keys.
+fn brightness_changes(dir: &Path) -> Result<impl Stream<Item = Result<()>>> {
+    let files = [dir.join("brightness"), dir.join("actual_brightness")];
+    inotify::watch(&files, AddWatchFlags::IN_MODIFY)
+}
+
+enum Event {
+    Changed(Result<()>),
+    Scroll { up: bool },
+}
+
+impl Widget for Backlight {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let device = match &self.device {
+            Some(device) => device.clone(),
+            None => default_device()?,
+        };
+        let dir = PathBuf::from(BACKLIGHT).join(&device);
+
+        let changes = brightness_changes(&dir)?.map(Event::Changed);
+        let scrolls = clicks.filter_map(|click| match click.button {
+            MouseButton::ScrollUp => Some(Event::Scroll { up: true }),
+            MouseButton::ScrollDown => Some(Event::Scroll { up: false }),
+            _ => None,
+        });
+        let mut events = Box::pin(changes.merge(scrolls));
+
+        #[cfg(feature = "logind")]
+        let mut logind = logind::Logind::new(self.services.clone());
+        let stream = stream! {
+            yield Brightness::read(&dir).map(|brightness| self.render(&brightness));
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Changed(Ok(())) => {
+                        yield Brightness::read(&dir).map(|brightness| self.render(&brightness));
+                    }
+                    Event::Changed(Err(err)) => yield Err(err),
+                    // The new brightness is shown once the kernel reports
+                    // the change.
+                    Event::Scroll { up } => {
+                        #[cfg(feature = "logind")]
+                        let result = self.scroll(&device, up, &mut logind).await;
+                        #[cfg(not(feature = "logind"))]
+                        let result = self.scroll(&device, up).await;
+                        if let Err(err) = result {
+                            yield Err(err);
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    #[cfg(feature = "logind")]
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        let brightness = Brightness {
+            current: 480,
+            max: 960,
+        };
+        assert_eq!(brightness.percentage(), 50);
+        assert_eq!(brightness.step(5, true), 528);
+        assert_eq!(brightness.step(5, false), 432);
+
+        let brightness = Brightness { current: 3, max: 7 };
+        assert_eq!(brightness.percentage(), 43);
+        assert_eq!(brightness.step(5, true), 4);
+        assert_eq!(brightness.step(5, false), 2);
+
+        let brightness = Brightness {
+            current: 950,
+            max: 960,
+        };
+        assert_eq!(brightness.step(5, true), 960);
+        assert_eq!(brightness.step(100, false), 0);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::dbus::{self, Bus, DbusPool};
+use anyhow::Result;
+use cnx::services::Services;
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto",
+    gen_blocking = false
+)]
+trait Session {
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
+
+/// Asks logind to set the brightness of a backlight device on behalf of the
+/// current session, which doesn't need root.
+pub(super) struct Logind {
+    pool: DbusPool,
+}
+
+impl Logind {
+    pub(super) fn new(services: Option<Services>) -> Self {
+        Logind {
+            pool: dbus::pool(services.as_ref()),
+        }
+    }
+
+    /// Sets the brightness of `device`, e.g. `intel_backlight`, to the raw
+    /// value `brightness`.
+    pub(super) async fn set_brightness(&mut self, device: &str, brightness: u64) -> Result<()> {
+        // The pool reconnects if the bus has closed the connection.
+        let connection = self.pool.connection(Bus::System).await?;
+        let proxy = SessionProxy::new(&connection).await?;
+        proxy
+            .set_brightness("backlight", device, brightness as u32)
+            .await?;
+        Ok(())
+    }
+}
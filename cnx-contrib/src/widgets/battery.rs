@@ -5,4 +5,8 @@ mod battery_linux;
 #[cfg(feature = "openbsd")]
 pub use battery_bsd::Battery;
 #[cfg(target_os = "linux")]
-pub use battery_linux::{Battery, BatteryInfo, Status};
+pub(crate) mod uevent;
+#[cfg(all(target_os = "linux", feature = "upower"))]
+mod upower;
+#[cfg(target_os = "linux")]
+pub use battery_linux::{Backend, Battery, BatteryInfo, Status};
@@ -1,3 +1,4 @@
+use crate::format;
 use anyhow::{Context, Result};
 use cnx::cmd::{command_output, from_command_output};
 use cnx::text::{Attributes, Color, Text};
@@ -23,13 +24,9 @@ struct Info {
 }
 
 impl Info {
-    fn time_remaining(&self) -> Option<(u16, u16)> {
-        if let Some(minutes) = self.minutes {
-            let hours = minutes / 60;
-            let minutes = minutes % 60;
-            return Some((hours, minutes));
-        }
-        None
+    fn time_remaining(&self) -> Option<Duration> {
+        self.minutes
+            .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
     }
 }
 
@@ -79,7 +76,7 @@ type BatteryInfo = OpenBsd;
 ///
 /// This widget shows the battery's current charge percentage and the amount of
 /// remaining (dis)charge time, depending on whether the battery is charging or
-/// discharging. The format of the output is `(PP% HH:MM)`.
+/// discharging. The format of the output is e.g. `(PP% - 1h 23m)`.
 ///
 /// When the battery has less than 10% charge remaining, the widget's text will
 /// change to the specified `warning_color`.
@@ -119,8 +116,8 @@ impl Battery {
             _ => "(".to_owned(),
         };
         text += &format!("{:.0}%", info.percentage);
-        if let Some((hours, minutes)) = info.time_remaining() {
-            text += &format!(" - {hours}:{minutes:02})", hours = hours, minutes = minutes);
+        if let Some(time_remaining) = info.time_remaining() {
+            text += &format!(" - {})", format::duration(time_remaining));
         } else {
             text += ")";
         }
@@ -137,6 +134,7 @@ impl Battery {
             text,
             stretch: false,
             markup: false,
+            canvas: None,
         }])
     }
 }
@@ -1,13 +1,25 @@
 use anyhow::{anyhow, Context, Error, Result};
-use cnx::text::{Attributes, Color, Text};
+use cnx::bus::Bus;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Color, Ramp, Text};
 use cnx::widgets::{Widget, WidgetStream};
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
+use tokio_stream::{self as stream, Stream, StreamExt};
+
+use super::uevent;
+#[cfg(feature = "upower")]
+use super::upower;
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
 
 /// Represent Battery's operating status
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -15,9 +27,25 @@ pub enum Status {
     Full,
     Charging,
     Discharging,
+    NotCharging,
     Unknown,
 }
 
+/// Shows the status as a word, e.g. `Charging`, translated by
+/// [`cnx::i18n`].
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            Status::Full => "Full",
+            Status::Charging => "Charging",
+            Status::Discharging => "Discharging",
+            Status::NotCharging => "Not charging",
+            Status::Unknown => "Unknown",
+        };
+        f.write_str(&i18n::tr(label))
+    }
+}
+
 impl FromStr for Status {
     type Err = Error;
 
@@ -26,28 +54,62 @@ impl FromStr for Status {
             "Full" => Ok(Status::Full),
             "Charging" => Ok(Status::Charging),
             "Discharging" => Ok(Status::Discharging),
+            "Not charging" => Ok(Status::NotCharging),
             "Unknown" => Ok(Status::Unknown),
             _ => Err(anyhow!("Unknown Status: {}", s)),
         }
     }
 }
 
+/// Source of the battery information shown by [`Battery`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    /// Poll the kernel's `power_supply` class in sysfs once a minute, and
+    /// whenever the kernel reports a change such as the charger being
+    /// plugged in.
+    Sysfs,
+    /// Listen for property changes on the battery's UPower device over DBus.
+    #[cfg(feature = "upower")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "upower")))]
+    UPower,
+}
+
 /// Shows battery charge percentage
 ///
 /// This widget shows the battery's current charge percentage.
 ///
 /// When the battery has less than 10% charge remaining, the widget's text will
-/// change to the specified `warning_color`.
+/// change to the specified `warning_color`. Alternatively, the color can be
+/// picked from a [`Ramp`] of colors by charge level, and the charge can be
+/// shown with an icon (e.g. Nerd Font battery glyphs) picked from a `Ramp` of
+/// icons; see [`Battery::with_colors`] and [`Battery::with_icons`].
+///
+/// By default, battery charge information is read from
+/// [`/sys/class/power_supply/`], once a minute and whenever a power supply
+/// changes (e.g. the charger is plugged in). With the `upower` feature enabled, it
+/// can instead be received from UPower, updating as soon as it changes
+/// rather than polling; see [`Battery::with_backend`].
+///
+/// If there is more than one battery (e.g. the internal and external
+/// batteries of some ThinkPads), their charge is combined, so that the widget
+/// shows the charge and time remaining of the system as a whole.
 ///
-/// Battery charge information is read from [`/sys/class/power_supply/BAT0/`].
+/// Each [`BatteryInfo`] is also published on the [`Bus`], so that other
+/// widgets can e.g. change as the battery runs low.
 ///
-/// [`/sys/class/power_supply/BAT0/`]: https://www.kernel.org/doc/Documentation/power/power_supply_class.txt
+/// [`/sys/class/power_supply/`]: https://www.kernel.org/doc/Documentation/power/power_supply_class.txt
 pub struct Battery {
     update_interval: Duration,
-    battery: String,
+    batteries: Vec<String>,
+    backend: Backend,
     attr: Attributes,
     warning_color: Color,
+    colors: Option<Ramp<Color>>,
+    icons: Option<Ramp<String>>,
     render: Option<Box<dyn Fn(BatteryInfo) -> String>>,
+    #[cfg(feature = "upower")]
+    services: Option<Services>,
+    bus: Option<Bus>,
 }
 
 /// Represent Battery information
@@ -57,6 +119,193 @@ pub struct BatteryInfo {
     pub status: Status,
     /// Capacity in percentage
     pub capacity: u8,
+    /// Estimated time until the battery is empty (when discharging) or
+    /// full (when charging), if known.
+    pub time_remaining: Option<Duration>,
+}
+
+// What a battery's energy and power are measured in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Unit {
+    // µWh and µW.
+    Energy,
+    // µAh and µA, for a battery which reports its charge and current but
+    // not its voltage.
+    Charge,
+}
+
+// The readings of a single battery.
+#[derive(Debug)]
+struct Reading {
+    status: Status,
+    capacity: u8,
+    // The remaining and full energy, in µWh (or µAh, see `unit`), if known.
+    energy: Option<(f64, f64)>,
+    // The rate of (dis)charge, in µW (or µA, see `unit`), if known.
+    power: Option<f64>,
+    unit: Unit,
+}
+
+impl Reading {
+    // Reads the battery whose sysfs directory is `dir`.
+    fn load(dir: &Path) -> Result<Reading> {
+        // Batteries report either their energy (µWh) and power (µW), or their
+        // charge (µAh) and current (µA). Convert the latter using the voltage.
+        // If that's unknown, the charge still gives the percentage and time
+        // remaining of this battery, but can't be added to other batteries'
+        // energy.
+        let (energy, power, unit) = match (
+            load_value(dir, "energy_now"),
+            load_value(dir, "energy_full"),
+        ) {
+            (Ok(now), Ok(full)) => {
+                let power = load_value(dir, "power_now").ok();
+                (Some((now, full)), power, Unit::Energy)
+            }
+            _ => {
+                let (volts, unit) = match load_value::<f64>(dir, "voltage_now")
+                    .or_else(|_| load_value(dir, "voltage_min_design"))
+                {
+                    Ok(microvolts) => (microvolts / 1e6, Unit::Energy),
+                    Err(_) => (1.0, Unit::Charge),
+                };
+                let energy = match (
+                    load_value::<f64>(dir, "charge_now"),
+                    load_value::<f64>(dir, "charge_full"),
+                ) {
+                    (Ok(now), Ok(full)) => Some((now * volts, full * volts)),
+                    _ => None,
+                };
+                let current = load_value::<f64>(dir, "current_now").ok();
+                (energy, current.map(|current| current * volts), unit)
+            }
+        };
+
+        // Some drivers report a negative rate while discharging.
+        let power = power.map(f64::abs);
+
+        // Fall back to the battery's own idea of its capacity as a last resort.
+        let capacity = match energy {
+            Some((now, full)) if full > 0.0 => (now / full * 100.0).round().clamp(0.0, 100.0) as u8,
+            _ => load_value(dir, "capacity")?,
+        };
+
+        Ok(Reading {
+            status: load_value(dir, "status")?,
+            capacity,
+            energy,
+            power,
+            unit,
+        })
+    }
+}
+
+// Combines the readings of several batteries into one.
+fn aggregate(readings: &[Reading]) -> BatteryInfo {
+    let any = |status| readings.iter().any(|r| r.status == status);
+    let status = if any(Status::Charging) {
+        Status::Charging
+    } else if any(Status::Discharging) {
+        Status::Discharging
+    } else if readings.iter().all(|r| r.status == Status::Full) {
+        Status::Full
+    } else if any(Status::NotCharging) {
+        Status::NotCharging
+    } else {
+        Status::Unknown
+    };
+
+    // Weight each battery by its size if we can, otherwise fall back to the
+    // average of the reported percentages. Charge can't be weighed against
+    // energy, so neither can batteries which are measured in different units.
+    let same_unit = readings.windows(2).all(|pair| pair[0].unit == pair[1].unit);
+    let energy = readings
+        .iter()
+        .map(|r| r.energy)
+        .try_fold((0.0, 0.0), |(now, full), energy| {
+            energy.map(|(n, f)| (now + n, full + f))
+        })
+        .filter(|&(_, full)| same_unit && full > 0.0);
+    let capacity = match energy {
+        Some((now, full)) => (now / full * 100.0).round().clamp(0.0, 100.0) as u8,
+        None => {
+            let sum: u32 = readings.iter().map(|r| u32::from(r.capacity)).sum();
+            (sum / readings.len().max(1) as u32) as u8
+        }
+    };
+
+    let power: f64 = readings.iter().filter_map(|r| r.power).sum();
+    let time_remaining = energy.and_then(|(now, full)| {
+        let hours = match status {
+            Status::Discharging => now / power,
+            Status::Charging => (full - now) / power,
+            _ => return None,
+        };
+        if power > 0.0 && hours.is_finite() && hours >= 0.0 {
+            Some(Duration::from_secs_f64(hours * 3600.0))
+        } else {
+            None
+        }
+    });
+
+    BatteryInfo {
+        status,
+        capacity,
+        time_remaining,
+    }
+}
+
+// Returns the names of all system batteries, e.g. `["BAT0", "BAT1"]`.
+fn discover_batteries() -> Result<Vec<String>> {
+    let mut batteries = Vec::new();
+    for entry in fs::read_dir(POWER_SUPPLY)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let dir = battery_dir(&name);
+        let is_battery = load_value::<String>(&dir, "type").map_or(false, |t| t == "Battery");
+        // Skip the batteries of peripherals, such as wireless mice.
+        let is_device = load_value::<String>(&dir, "scope").map_or(false, |s| s == "Device");
+        if is_battery && !is_device {
+            batteries.push(name);
+        }
+    }
+    if batteries.is_empty() {
+        return Err(anyhow!("No batteries found in {}", POWER_SUPPLY));
+    }
+    batteries.sort();
+    Ok(batteries)
+}
+
+// Returns the sysfs directory of `battery`, e.g. `BAT0`.
+fn battery_dir(battery: &str) -> PathBuf {
+    Path::new(POWER_SUPPLY).join(battery)
+}
+
+fn load_value_inner<T>(dir: &Path, file: &str) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Into<Error>,
+{
+    let mut file = File::open(dir.join(file))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let s = FromStr::from_str(contents.trim())
+        .map_err(|e: <T as FromStr>::Err| e.into())
+        .context("Failed to parse value")?;
+    Ok(s)
+}
+
+fn load_value<T>(dir: &Path, file: &str) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Into<Error>,
+{
+    let value = load_value_inner(dir, file).with_context(|| {
+        format!(
+            "Could not load value from battery status file: {}",
+            dir.join(file).display()
+        )
+    })?;
+    Ok(value)
 }
 
 impl Battery {
@@ -67,6 +316,10 @@ impl Battery {
     ///  argument, to control the [`Color`] of the text once the battery has
     ///  less than 10% charge remaining.
     ///
+    ///  The `battery` argument selects a single battery by name (e.g.
+    ///  `BAT0`). If it is `None`, all of the system's batteries are shown
+    ///  combined.
+    ///
     ///  The [`cnx::Cnx`] instance is borrowed during construction in order to get
     ///  access to handles of its event loop. However, it is not borrowed for
     ///  the lifetime of the widget. See the [`cnx::Cnx::add_widget`] for more
@@ -106,75 +359,276 @@ impl Battery {
     ) -> Battery {
         Battery {
             update_interval: Duration::from_secs(60),
-            battery: battery.unwrap_or_else(|| "BAT0".into()),
+            batteries: battery.into_iter().collect(),
+            backend: Backend::Sysfs,
             attr,
             warning_color,
+            colors: None,
+            icons: None,
             render,
+            #[cfg(feature = "upower")]
+            services: None,
+            bus: None,
+        }
+    }
+
+    /// Combines the given batteries, e.g. `["BAT0", "BAT1"]`, rather than
+    /// using the one passed to [`Battery::new`].
+    ///
+    /// An empty list combines all of the system's batteries.
+    pub fn with_batteries(self, batteries: Vec<String>) -> Self {
+        Battery { batteries, ..self }
+    }
+
+    /// Colors the text according to the charge percentage, instead of only
+    /// using the `warning_color` when the charge is low.
+    ///
+    /// ```
+    /// # use cnx::text::{Ramp, Role};
+    /// let colors = Ramp::roles(vec![
+    ///     (10.0, Role::Crit),
+    ///     (30.0, Role::Warn),
+    ///     (100.0, Role::Ok),
+    /// ]);
+    /// ```
+    pub fn with_colors(self, colors: Ramp<Color>) -> Self {
+        Battery {
+            colors: Some(colors),
+            ..self
         }
     }
 
-    fn load_value_inner<T>(&self, file: &str) -> Result<T>
-    where
-        T: FromStr,
-        <T as FromStr>::Err: Into<Error>,
-    {
-        let path = format!("/sys/class/power_supply/{}/{}", self.battery, file);
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let s = FromStr::from_str(contents.trim())
-            .map_err(|e: <T as FromStr>::Err| e.into())
-            .context("Failed to parse value")?;
-        Ok(s)
-    }
-
-    fn load_value<T>(&self, file: &str) -> Result<T>
-    where
-        T: FromStr,
-        <T as FromStr>::Err: Into<Error>,
-    {
-        let value = self
-            .load_value_inner(file)
-            .with_context(|| format!("Could not load value from battery status file: {file}"))?;
-        Ok(value)
+    /// Shows an icon picked according to the charge percentage before the
+    /// percentage. This is ignored if a `render` closure is given.
+    ///
+    /// ```
+    /// # use cnx::text::Ramp;
+    /// let icons = Ramp::new(vec![
+    ///     (10.0, "\u{f244}".to_owned()),
+    ///     (35.0, "\u{f243}".to_owned()),
+    ///     (60.0, "\u{f242}".to_owned()),
+    ///     (85.0, "\u{f241}".to_owned()),
+    ///     (100.0, "\u{f240}".to_owned()),
+    /// ]);
+    /// ```
+    pub fn with_icons(self, icons: Ramp<String>) -> Self {
+        Battery {
+            icons: Some(icons),
+            ..self
+        }
+    }
+
+    /// Sets where the battery information is read from.
+    ///
+    /// The default is [`Backend::Sysfs`].
+    pub fn with_backend(self, backend: Backend) -> Self {
+        Battery { backend, ..self }
     }
 
     fn get_value(&self) -> Result<BatteryInfo> {
-        let capacity: u8 = self.load_value("capacity")?;
-        let status: Status = self.load_value("status")?;
-        Ok(BatteryInfo { capacity, status })
+        let batteries = if self.batteries.is_empty() {
+            discover_batteries()?
+        } else {
+            self.batteries.clone()
+        };
+        let readings = batteries
+            .iter()
+            .map(|battery| Reading::load(&battery_dir(battery)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(aggregate(&readings))
     }
 
     fn tick(&self) -> Result<Vec<Text>> {
         let battery_info = self.get_value()?;
+        Ok(self.render(battery_info))
+    }
 
-        let default_text = format!("({percentage:.0}%)", percentage = battery_info.capacity,);
+    fn render(&self, battery_info: BatteryInfo) -> Vec<Text> {
+        if let Some(bus) = &self.bus {
+            bus.publish(battery_info.clone());
+        }
+
+        let capacity = f64::from(battery_info.capacity);
+        let default_text = match self.icons.as_ref().and_then(|icons| icons.get(capacity)) {
+            Some(icon) => format!(
+                "{icon} ({percentage:.0}%)",
+                percentage = battery_info.capacity
+            ),
+            None => format!("({percentage:.0}%)", percentage = battery_info.capacity),
+        };
         let text = self
             .render
             .as_ref()
             .map_or(default_text, |x| (x)(battery_info.clone()));
 
-        // If we're discharging and have <=10% left, then render with a
-        // special warning color.
+        // Use the color ramp if there is one. Otherwise, if we're
+        // discharging and have <=10% left, then render with a special
+        // warning color.
         let mut attr = self.attr.clone();
-        if battery_info.status == Status::Discharging && battery_info.capacity <= 10 {
+        if let Some(color) = self.colors.as_ref().and_then(|colors| colors.get(capacity)) {
+            attr.fg_color = color.clone();
+        } else if battery_info.status == Status::Discharging && battery_info.capacity <= 10 {
             attr.fg_color = self.warning_color.clone()
         }
 
-        Ok(vec![Text {
+        vec![Text {
             attr,
             text,
             stretch: false,
             markup: self.render.is_some(),
-        }])
+            canvas: None,
+        }]
     }
 }
 
 impl Widget for Battery {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
-        let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        match self.backend {
+            Backend::Sysfs => {
+                // Also update as soon as e.g. the charger is plugged in,
+                // rather than waiting for the next tick.
+                let interval = time::interval(self.update_interval);
+                let ticks = IntervalStream::new(interval).map(|_| Ok(()));
+                let events: Pin<Box<dyn Stream<Item = Result<()>>>> =
+                    match uevent::power_supply_events() {
+                        Ok(events) => Box::pin(events),
+                        // Without uevents, e.g. in a sandbox, we still poll.
+                        Err(err) => {
+                            println!("Error watching for power supply events: {err}");
+                            Box::pin(stream::pending())
+                        }
+                    };
+                let stream = ticks
+                    .merge(events)
+                    .map(move |event| event.and_then(|()| self.tick()));
+
+                Ok(Box::pin(stream))
+            }
+            #[cfg(feature = "upower")]
+            Backend::UPower => {
+                // UPower's display device combines all of the batteries.
+                let device = match self.batteries.as_slice() {
+                    [battery] => format!("battery_{battery}"),
+                    _ => "DisplayDevice".to_owned(),
+                };
+                let stream = upower::battery_info_stream(device, self.services.clone())
+                    .map(move |info| info.map(|info| self.render(info)));
+
+                Ok(Box::pin(stream))
+            }
+        }
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        #[cfg(feature = "upower")]
+        {
+            self.services = Some(services.clone());
+        }
+        self.bus = Some(Bus::shared(services));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reading(status: Status, capacity: u8, energy: (f64, f64), power: f64) -> Reading {
+        Reading {
+            status,
+            capacity,
+            energy: Some(energy),
+            power: Some(power),
+            unit: Unit::Energy,
+        }
+    }
+
+    // Writes the sysfs files of a battery called `name` to a directory of its
+    // own, returning the directory.
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cnx-battery-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, value) in files {
+            fs::write(dir.join(file), format!("{value}\n")).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn aggregate_weights_by_energy() {
+        let info = aggregate(&[
+            reading(Status::Discharging, 50, (10e6, 20e6), 5e6),
+            reading(Status::Unknown, 100, (60e6, 60e6), 0.0),
+        ]);
+        assert_eq!(info.status, Status::Discharging);
+        assert_eq!(info.capacity, 88);
+        assert_eq!(info.time_remaining, Some(Duration::from_secs(14 * 3600)));
+    }
+
+    #[test]
+    fn aggregate_without_energy() {
+        let mut readings = vec![
+            reading(Status::Full, 100, (0.0, 0.0), 0.0),
+            reading(Status::NotCharging, 80, (0.0, 0.0), 0.0),
+        ];
+        for r in &mut readings {
+            r.energy = None;
+        }
+        let info = aggregate(&readings);
+        assert_eq!(info.status, Status::NotCharging);
+        assert_eq!(info.capacity, 90);
+        assert_eq!(info.time_remaining, None);
+    }
+
+    #[test]
+    fn charge_converted_to_energy() {
+        let dir = fixture(
+            "charge",
+            &[
+                ("status", "Discharging"),
+                ("charge_now", "2500000"),
+                ("charge_full", "5000000"),
+                ("current_now", "-1000000"),
+                ("voltage_now", "12000000"),
+            ],
+        );
+        let reading = Reading::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(reading.unit, Unit::Energy);
+        assert_eq!(reading.energy, Some((30e6, 60e6)));
+        assert_eq!(reading.power, Some(12e6));
+
+        let info = aggregate(&[reading]);
+        assert_eq!(info.capacity, 50);
+        assert_eq!(info.time_remaining, Some(Duration::from_secs(9000)));
+    }
+
+    #[test]
+    fn aggregate_charge_with_energy() {
+        let dir = fixture(
+            "mixed",
+            &[
+                ("status", "Discharging"),
+                ("charge_now", "4000000"),
+                ("charge_full", "5000000"),
+                ("current_now", "1000000"),
+            ],
+        );
+        let load = || Reading::load(&dir).unwrap();
+        assert_eq!(load().unit, Unit::Charge);
+        assert_eq!(load().capacity, 80);
+        // On its own, the battery's time remaining is still known.
+        assert_eq!(
+            aggregate(&[load()]).time_remaining,
+            Some(Duration::from_secs(4 * 3600))
+        );
 
-        Ok(Box::pin(stream))
+        // Without its voltage, its charge can't be weighed against another
+        // battery's energy, so their percentages are averaged instead.
+        let energy = reading(Status::Discharging, 50, (10e6, 20e6), 5e6);
+        let info = aggregate(&[energy, load()]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(info.status, Status::Discharging);
+        assert_eq!(info.capacity, 65);
+        assert_eq!(info.time_remaining, None);
     }
 }
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use async_stream::try_stream;
+use nix::sys::socket::{
+    bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag, SockProtocol,
+    SockType,
+};
+use nix::unistd::close;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use tokio::io::unix::AsyncFd;
+use tokio_stream::Stream;
+
+// The multicast group on which the kernel broadcasts uevents. udev
+// rebroadcasts them on group 2 after processing them, but udev isn't always
+// running.
+const KERNEL_GROUP: u32 = 1;
+
+// A netlink socket receiving kernel uevents, closed when dropped.
+struct UeventSocket(RawFd);
+
+impl UeventSocket {
+    fn open() -> Result<UeventSocket> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Datagram,
+            SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkKObjectUEvent,
+        )?;
+        let socket = UeventSocket(fd);
+        bind(fd, &SockAddr::Netlink(NetlinkAddr::new(0, KERNEL_GROUP)))?;
+        Ok(socket)
+    }
+}
+
+impl AsRawFd for UeventSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for UeventSocket {
+    fn drop(&mut self) {
+        let _ = close(self.0);
+    }
+}
+
+// Returns whether a uevent message is about a power supply, e.g. the AC
+// adapter being plugged in or a battery changing status.
+//
+// Messages look like `change@/devices/...\0ACTION=change\0SUBSYSTEM=...\0`.
+fn is_power_supply_event(message: &[u8]) -> bool {
+    message
+        .split(|b| *b == 0)
+        .any(|field| field == b"SUBSYSTEM=power_supply")
+}
+
+// Returns a stream which yields whenever the kernel reports a change to a
+// power supply.
+pub(crate) fn power_supply_events() -> Result<impl Stream<Item = Result<()>>> {
+    let socket = UeventSocket::open().context("Failed to open uevent socket")?;
+    let fd = AsyncFd::new(socket)?;
+
+    let stream = try_stream! {
+        let mut buf = [0; 8192];
+        loop {
+            let mut guard = fd.readable().await?;
+            let result = guard.try_io(|fd| {
+                recv(fd.as_raw_fd(), &mut buf, MsgFlags::empty()).map_err(|err| {
+                    err.as_errno()
+                        .map_or_else(|| io::Error::new(io::ErrorKind::Other, err), io::Error::from)
+                })
+            });
+            match result {
+                Ok(len) => {
+                    if is_power_supply_event(&buf[..len?]) {
+                        yield ();
+                    }
+                }
+                // Spurious wake-up, the socket has been marked as not ready.
+                Err(_would_block) => {}
+            }
+        }
+    };
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn power_supply_event() {
+        let message = b"change@/devices/LNXSYSTM:00/ACPI0003:00/power_supply/AC\0\
+                        ACTION=change\0\
+                        DEVPATH=/devices/LNXSYSTM:00/ACPI0003:00/power_supply/AC\0\
+                        SUBSYSTEM=power_supply\0\
+                        POWER_SUPPLY_NAME=AC\0\
+                        POWER_SUPPLY_ONLINE=1\0";
+        assert!(is_power_supply_event(message));
+
+        let message = b"add@/devices/virtual/net/veth0\0ACTION=add\0SUBSYSTEM=net\0";
+        assert!(!is_power_supply_event(message));
+    }
+}
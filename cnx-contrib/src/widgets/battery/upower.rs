@@ -0,0 +1,121 @@
+use super::{BatteryInfo, Status};
+use crate::dbus::{self, Bus};
+use anyhow::Result;
+use async_stream::{stream, try_stream};
+use cnx::services::Services;
+use std::time::Duration;
+use tokio_stream::{Stream, StreamExt};
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower",
+    gen_blocking = false
+)]
+trait Device {
+    #[dbus_proxy(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+
+    #[dbus_proxy(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn time_to_empty(&self) -> zbus::Result<i64>;
+
+    #[dbus_proxy(property)]
+    fn time_to_full(&self) -> zbus::Result<i64>;
+}
+
+// See the `State` property in the UPower.Device documentation.
+fn status(state: u32) -> Status {
+    match state {
+        1 => Status::Charging,
+        2 | 3 | 6 => Status::Discharging,
+        4 => Status::Full,
+        _ => Status::Unknown,
+    }
+}
+
+async fn battery_info(device: &DeviceProxy<'_>) -> Result<BatteryInfo> {
+    let percentage = device.percentage().await?;
+    let status = status(device.state().await?);
+    // UPower reports zero when it doesn't have an estimate.
+    let seconds = match status {
+        Status::Discharging => device.time_to_empty().await?,
+        Status::Charging => device.time_to_full().await?,
+        _ => 0,
+    };
+    let time_remaining = Some(seconds)
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| Duration::from_secs(seconds as u64));
+    Ok(BatteryInfo {
+        status,
+        capacity: percentage.round().clamp(0.0, 100.0) as u8,
+        time_remaining,
+    })
+}
+
+// Yields the device's current information, and then again every time UPower
+// reports that it has changed, until the connection is closed.
+fn device_info_stream(
+    connection: zbus::Connection,
+    device: String,
+) -> impl Stream<Item = Result<BatteryInfo>> {
+    try_stream! {
+        let path = format!("/org/freedesktop/UPower/devices/{device}");
+        let proxy = DeviceProxy::builder(&connection)
+            .path(path)?
+            .build()
+            .await?;
+        let mut percentage_changed = proxy.receive_percentage_changed().await;
+        let mut state_changed = proxy.receive_state_changed().await;
+        let mut time_to_empty_changed = proxy.receive_time_to_empty_changed().await;
+        let mut time_to_full_changed = proxy.receive_time_to_full_changed().await;
+
+        yield battery_info(&proxy).await?;
+        loop {
+            tokio::select! {
+                Some(_) = percentage_changed.next() => {}
+                Some(_) = state_changed.next() => {}
+                Some(_) = time_to_empty_changed.next() => {}
+                Some(_) = time_to_full_changed.next() => {}
+                else => break,
+            }
+            yield battery_info(&proxy).await?;
+        }
+    }
+}
+
+/// Yields the device's current information, and then again every time UPower
+/// reports that it has changed.
+///
+/// The `device` is the last component of its object path, e.g. `battery_BAT0`.
+pub(super) fn battery_info_stream(
+    device: String,
+    services: Option<Services>,
+) -> impl Stream<Item = Result<BatteryInfo>> {
+    let mut watches = Box::pin(dbus::pool(services.as_ref()).watch(
+        Bus::System,
+        move |connection| {
+            let infos = device_info_stream(connection, device.clone());
+            async move { Ok(Box::pin(infos)) }
+        },
+    ));
+    stream! {
+        // Property changes stop when the bus closes the connection, e.g.
+        // because dbus-daemon was restarted, so we start again with the
+        // next one.
+        while let Some(infos) = watches.next().await {
+            let mut infos = match infos {
+                Ok(infos) => infos,
+                Err(err) => {
+                    yield Err(err);
+                    continue;
+                }
+            };
+            while let Some(info) = infos.next().await {
+                yield info;
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use crate::dbus::{self, Bus};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use std::collections::HashMap;
+use tokio_stream::{self as stream, Stream, StreamExt};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{dbus_proxy, MatchRule, MessageStream, MessageType};
+
+// The properties of each of an object's interfaces, by interface name.
+type Interfaces = HashMap<String, HashMap<String, OwnedValue>>;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.DBus.ObjectManager",
+    default_service = "org.bluez",
+    default_path = "/",
+    gen_blocking = false
+)]
+trait ObjectManager {
+    fn get_managed_objects(&self) -> zbus::Result<HashMap<OwnedObjectPath, Interfaces>>;
+}
+
+#[dbus_proxy(
+    interface = "org.bluez.Adapter1",
+    default_service = "org.bluez",
+    gen_blocking = false
+)]
+trait Adapter {
+    #[dbus_proxy(property)]
+    fn set_powered(&self, powered: bool) -> zbus::Result<()>;
+}
+
+/// A connected device, as shown by [`Bluetooth`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BluetoothDevice {
+    /// The device's alias, which is its name unless it has been renamed
+    pub name: String,
+    /// The device's address, e.g. `00:1B:66:0A:2C:3D`
+    pub address: String,
+    /// The charge of the device's battery in percent, if it reports it
+    pub battery: Option<u8>,
+}
+
+/// The state of the adapter shown by [`Bluetooth`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BluetoothStatus {
+    /// The adapter's name, e.g. `hci0`
+    pub adapter: String,
+    /// Whether the adapter is powered on
+    pub powered: bool,
+    /// The devices connected through the adapter, sorted by name
+    pub devices: Vec<BluetoothDevice>,
+}
+
+type Render = Box<dyn Fn(&BluetoothStatus) -> String>;
+
+/// Shows whether a Bluetooth adapter is powered on, and which devices are
+/// connected to it along with their batteries, e.g. `WH-1000XM4 80%, MX
+/// Keys`.
+///
+/// The widget talks to BlueZ over DBus, and is updated as soon as BlueZ
+/// reports a change, e.g. as a device connects. Left clicking the widget
+/// powers the adapter on or off. The widget is hidden while there is no
+/// adapter, or BlueZ isn't running.
+pub struct Bluetooth {
+    attr: Attributes,
+    adapter: Option<String>,
+    render: Option<Render>,
+    services: Option<Services>,
+}
+
+impl Bluetooth {
+    /// Creates a new [`Bluetooth`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the adapter's [`BluetoothStatus`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::bluetooth::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|status: &BluetoothStatus| match status.powered {
+    ///     false => "BT off".to_owned(),
+    ///     true => format!("BT {}", status.devices.len()),
+    /// });
+    /// cnx.add_widget(Bluetooth::new(attr, Some(render)).with_adapter("hci1"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            adapter: None,
+            render,
+            services: None,
+        }
+    }
+
+    /// Shows the adapter called `adapter`, e.g. `hci1`. By default, the
+    /// first adapter is shown.
+    pub fn with_adapter(self, adapter: &str) -> Self {
+        Self {
+            adapter: Some(adapter.to_owned()),
+            ..self
+        }
+    }
+
+    fn render(&self, status: Option<&BluetoothStatus>) -> Vec<Text> {
+        let Some(status) = status else {
+            return Vec::new();
+        };
+        let text = match &self.render {
+            Some(render) => (render)(status),
+            None if !status.powered => i18n::tr("Bluetooth off"),
+            None if status.devices.is_empty() => i18n::tr("Bluetooth on"),
+            None => {
+                let devices: Vec<String> = status
+                    .devices
+                    .iter()
+                    .map(|device| match device.battery {
+                        Some(battery) => format!("{} {}%", device.name, battery),
+                        None => device.name.clone(),
+                    })
+                    .collect();
+                devices.join(", ")
+            }
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+
+    async fn status(
+        &self,
+        connection: &zbus::Connection,
+    ) -> Result<Option<(OwnedObjectPath, BluetoothStatus)>> {
+        let manager = ObjectManagerProxy::new(connection).await?;
+        match manager.get_managed_objects().await {
+            Ok(objects) => Ok(status(&objects, self.adapter.as_deref())),
+            // BlueZ isn't running.
+            Err(zbus::Error::MethodError(name, _, _))
+                if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn property<'a>(interfaces: &'a Interfaces, interface: &str, name: &str) -> Option<&'a OwnedValue> {
+    interfaces.get(interface)?.get(name)
+}
+
+// Returns the path and status of the adapter called `adapter`, or of the
+// first adapter, out of BlueZ's objects.
+fn status(
+    objects: &HashMap<OwnedObjectPath, Interfaces>,
+    adapter: Option<&str>,
+) -> Option<(OwnedObjectPath, BluetoothStatus)> {
+    let (path, interfaces) = objects
+        .iter()
+        .filter(|(path, interfaces)| {
+            interfaces.contains_key("org.bluez.Adapter1")
+                && adapter.map_or(true, |adapter| {
+                    path.as_str().rsplit('/').next() == Some(adapter)
+                })
+        })
+        .min_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()))?;
+    let prefix = format!("{}/", path.as_str());
+    let mut devices: Vec<BluetoothDevice> = objects
+        .iter()
+        .filter(|(device, _)| device.as_str().starts_with(&prefix))
+        .filter_map(|(_, interfaces)| {
+            let connected = property(interfaces, "org.bluez.Device1", "Connected")?;
+            if !connected.downcast_ref::<bool>().copied()? {
+                return None;
+            }
+            let address = property(interfaces, "org.bluez.Device1", "Address")?;
+            let address = address.downcast_ref::<str>()?.to_owned();
+            let name = property(interfaces, "org.bluez.Device1", "Alias")
+                .and_then(|alias| alias.downcast_ref::<str>())
+                .map_or_else(|| address.clone(), str::to_owned);
+            let battery = property(interfaces, "org.bluez.Battery1", "Percentage")
+                .and_then(|percentage| percentage.downcast_ref::<u8>().copied());
+            Some(BluetoothDevice {
+                name,
+                address,
+                battery,
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    let powered = property(interfaces, "org.bluez.Adapter1", "Powered")
+        .and_then(|powered| powered.downcast_ref::<bool>().copied())
+        .unwrap_or(false);
+    let status = BluetoothStatus {
+        adapter: path
+            .as_str()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_owned(),
+        powered,
+        devices,
+    };
+    Some((path.clone(), status))
+}
+
+// Returns a stream which yields whenever BlueZ signals a change, such as a
+// device connecting, or starts or stops, until the connection is closed.
+async fn changes(connection: &zbus::Connection) -> Result<impl Stream<Item = ()>> {
+    let bluez = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.bluez")?
+        .build();
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, "org.bluez")?
+        .build();
+    let bluez = MessageStream::for_match_rule(bluez, connection, None).await?;
+    let owner = MessageStream::for_match_rule(owner, connection, None).await?;
+    // The streams yield an error as the connection closes.
+    Ok(bluez
+        .merge(owner)
+        .take_while(|message| message.is_ok())
+        .map(drop))
+}
+
+async fn set_powered(
+    connection: &zbus::Connection,
+    adapter: &OwnedObjectPath,
+    powered: bool,
+) -> Result<()> {
+    let adapter = AdapterProxy::builder(connection)
+        .path(adapter.clone())?
+        .build()
+        .await?;
+    adapter.set_powered(powered).await?;
+    Ok(())
+}
+
+impl Widget for Bluetooth {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks.filter(|click| click.button == MouseButton::Left);
+
+        if let Err(err) = dbus::require(self.services.as_ref(), Bus::System) {
+            return Ok(capabilities::unavailable(self.attr, err));
+        }
+
+        let mut watches = Box::pin(dbus::pool(self.services.as_ref()).watch(
+            Bus::System,
+            |connection| async move {
+                let changes = Box::pin(changes(&connection).await?);
+                Ok((connection, changes))
+            },
+        ));
+
+        let stream = stream! {
+            // Signals stop when the bus closes the connection, e.g. because
+            // dbus-daemon was restarted, so we start again with the next one.
+            while let Some(watched) = watches.next().await {
+                let (connection, mut changes) = match watched {
+                    Ok(watched) => watched,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                let mut current = None;
+                loop {
+                    match self.status(&connection).await {
+                        Ok(status) => {
+                            yield Ok(self.render(status.as_ref().map(|(_, status)| status)));
+                            current = status;
+                        }
+                        Err(err) => yield Err(err),
+                    }
+                    tokio::select! {
+                        changed = changes.next() => if changed.is_none() {
+                            break;
+                        },
+                        Some(_) = clicks.next() => {
+                            if let Some((adapter, status)) = &current {
+                                let toggled = set_powered(&connection, adapter, !status.powered);
+                                if let Err(err) = toggled.await {
+                                    yield Err(err);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zbus::zvariant::{ObjectPath, Value};
+
+    fn object(interfaces: &[(&str, Vec<(&str, Value<'_>)>)]) -> Interfaces {
+        interfaces
+            .iter()
+            .map(|(interface, properties)| {
+                let properties = properties
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone().into()))
+                    .collect();
+                (interface.to_string(), properties)
+            })
+            .collect()
+    }
+
+    fn path(path: &str) -> OwnedObjectPath {
+        ObjectPath::try_from(path).unwrap().into()
+    }
+
+    #[test]
+    fn adapter_status() {
+        let device = |address: &'static str, alias: Option<&'static str>, connected: bool| {
+            let mut properties = vec![
+                ("Address", Value::from(address)),
+                ("Connected", Value::from(connected)),
+            ];
+            if let Some(alias) = alias {
+                properties.push(("Alias", Value::from(alias)));
+            }
+            properties
+        };
+        let objects: HashMap<OwnedObjectPath, Interfaces> = [
+            (
+                path("/org/bluez/hci1"),
+                object(&[("org.bluez.Adapter1", vec![("Powered", Value::from(false))])]),
+            ),
+            (
+                path("/org/bluez/hci0"),
+                object(&[("org.bluez.Adapter1", vec![("Powered", Value::from(true))])]),
+            ),
+            (
+                path("/org/bluez/hci0/dev_00_1B_66_0A_2C_3D"),
+                object(&[
+                    (
+                        "org.bluez.Device1",
+                        device("00:1B:66:0A:2C:3D", Some("WH-1000XM4"), true),
+                    ),
+                    (
+                        "org.bluez.Battery1",
+                        vec![("Percentage", Value::from(80u8))],
+                    ),
+                ]),
+            ),
+            (
+                path("/org/bluez/hci0/dev_D4_2C_44_11_22_33"),
+                object(&[("org.bluez.Device1", device("D4:2C:44:11:22:33", None, true))]),
+            ),
+            (
+                path("/org/bluez/hci0/dev_F0_99_B6_00_00_01"),
+                object(&[(
+                    "org.bluez.Device1",
+                    device("F0:99:B6:00:00:01", Some("MX"), false),
+                )]),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let (adapter, status) = status(&objects, None).unwrap();
+        assert_eq!(adapter, path("/org/bluez/hci0"));
+        assert_eq!(
+            status,
+            BluetoothStatus {
+                adapter: "hci0".to_owned(),
+                powered: true,
+                devices: vec![
+                    BluetoothDevice {
+                        name: "D4:2C:44:11:22:33".to_owned(),
+                        address: "D4:2C:44:11:22:33".to_owned(),
+                        battery: None,
+                    },
+                    BluetoothDevice {
+                        name: "WH-1000XM4".to_owned(),
+                        address: "00:1B:66:0A:2C:3D".to_owned(),
+                        battery: Some(80),
+                    },
+                ],
+            }
+        );
+
+        let (_, status) = super::status(&objects, Some("hci1")).unwrap();
+        assert!(!status.powered);
+        assert!(status.devices.is_empty());
+        assert_eq!(super::status(&objects, Some("hci2")), None);
+    }
+}
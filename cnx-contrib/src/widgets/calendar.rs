@@ -0,0 +1,630 @@
+use crate::format;
+use crate::inotify;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use nix::sys::inotify::AddWatchFlags;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::StreamExt;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+// The most occurrences of a recurring event which are looked through for
+// the next one.
+const MAX_OCCURRENCES: u32 = 1000;
+
+/// The next event in the calendars, as shown by [`Calendar`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// The event's title, e.g. `Standup`
+    pub summary: String,
+    /// Where the event is, if the calendar says
+    pub location: Option<String>,
+    /// When the event starts
+    pub start: DateTime<Local>,
+    /// How long it is until the event starts
+    pub starts_in: Duration,
+}
+
+// How often an event recurs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// When an event recurs, from its `RRULE`.
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDateTime>,
+}
+
+// An event as read from a calendar, with its start in local time.
+#[derive(Clone, Debug, PartialEq)]
+struct VEvent {
+    summary: String,
+    location: Option<String>,
+    start: NaiveDateTime,
+    all_day: bool,
+    rule: Option<Rule>,
+}
+
+// An event whose properties are still being read.
+#[derive(Default)]
+struct Draft {
+    summary: Option<String>,
+    location: Option<String>,
+    start: Option<(NaiveDateTime, bool)>,
+    rule: Option<Rule>,
+    cancelled: bool,
+}
+
+impl Draft {
+    fn finish(self) -> Option<VEvent> {
+        let (start, all_day) = self.start?;
+        if self.cancelled {
+            return None;
+        }
+        Some(VEvent {
+            summary: self.summary.unwrap_or_default(),
+            location: self.location,
+            start,
+            all_day,
+            rule: self.rule,
+        })
+    }
+}
+
+type Render = Box<dyn Fn(&Event) -> String>;
+
+/// Shows the next event in one or more iCalendar (`.ics`) files, with a
+/// countdown until it starts, e.g. `Standup in 25m`.
+///
+/// Each path is either a file, or a directory of `.ics` files, such as
+/// [vdirsyncer] keeps for each calendar it syncs. The files are read again
+/// whenever they change. The widget is shown in the theme's `Warn` color as
+/// the event approaches, and is hidden while there is no event soon enough.
+///
+/// All-day and cancelled events are skipped. Recurring events are supported
+/// as far as their frequency, interval, count and end, which covers e.g.
+/// "every other week" but not "every Monday and Wednesday", and exceptions
+/// to them aren't. Times in a time zone are taken to be local times.
+///
+/// [vdirsyncer]: https://vdirsyncer.pimutils.org/
+pub struct Calendar {
+    attr: Attributes,
+    paths: Vec<PathBuf>,
+    horizon: Duration,
+    warning: Duration,
+    render: Option<Render>,
+}
+
+impl Calendar {
+    /// Creates a new [`Calendar`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `paths` - The calendar files, or directories of them, to read.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`Event`] is the next event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::calendar::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let paths = vec!["/home/me/.calendars/work".into()];
+    /// cnx.add_widget(Calendar::new(attr, paths, None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, paths: Vec<PathBuf>, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            paths,
+            horizon: Duration::from_secs(24 * 60 * 60),
+            warning: Duration::from_secs(15 * 60),
+            render,
+        }
+    }
+
+    /// Sets how far ahead to look for the next event. The default is a day.
+    pub fn with_horizon(self, horizon: Duration) -> Self {
+        Self { horizon, ..self }
+    }
+
+    /// Sets how long before the event starts to show it in the theme's
+    /// `Warn` color. The default is 15 minutes.
+    pub fn with_warning(self, warning: Duration) -> Self {
+        Self { warning, ..self }
+    }
+
+    fn render(&self, event: Option<&Event>) -> Vec<Text> {
+        let Some(event) = event else {
+            return Vec::new();
+        };
+        let mut attr = self.attr.clone();
+        if event.starts_in <= self.warning {
+            attr.fg_color = Color::role(Role::Warn);
+        }
+        let text = match &self.render {
+            Some(render) => (render)(event),
+            None => format!(
+                "{} {}",
+                untrusted::text(&event.summary),
+                i18n::tr_with("in {}", &format::duration(countdown(event.starts_in)))
+            ),
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Rounds how long it is until an event up to whole minutes, as the
+// countdown is shown.
+fn countdown(starts_in: Duration) -> Duration {
+    let seconds = starts_in.as_secs() + u64::from(starts_in.subsec_nanos() > 0);
+    Duration::from_secs((seconds + 59) / 60 * 60)
+}
+
+// Joins the lines of a calendar which are folded onto several.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+// Splits a line into its property's name and value, e.g. `DTSTART` and
+// `20240115T093000` from `DTSTART;TZID=Europe/London:20240115T093000`. The
+// parameters after the name may be quoted, in which case they may contain
+// colons.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    let (colon, _) = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    })?;
+    let head = &line[..colon];
+    let name = head.split_once(';').map_or(head, |(name, _)| name);
+    Some((name, &line[colon + 1..]))
+}
+
+// Unescapes a text value, putting any line breaks on one line.
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => text.push(' '),
+            ('\\', Some(escaped)) => text.push(escaped),
+            (c, _) => {
+                text.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    text
+}
+
+// Parses a date and time, converting it to local time if it is in UTC.
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some(
+                Utc.from_utc_datetime(&utc)
+                    .with_timezone(&Local)
+                    .naive_local(),
+            )
+        }
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+// Parses a `DTSTART`, returning when it is and whether it is only a date,
+// for an all-day event.
+fn parse_start(value: &str) -> Option<(NaiveDateTime, bool)> {
+    match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(date) => Some((date.and_hms_opt(0, 0, 0)?, true)),
+        Err(_) => Some((parse_date_time(value)?, false)),
+    }
+}
+
+// Parses an `RRULE`, e.g. `FREQ=WEEKLY;INTERVAL=2;UNTIL=20241231T000000Z`.
+// Rules which recur more often than daily aren't supported.
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+    };
+    let mut frequency = None;
+    for part in value.split(';') {
+        let Some((name, value)) = part.split_once('=') else {
+            continue;
+        };
+        match name.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Some(Frequency::Daily),
+                    "WEEKLY" => Some(Frequency::Weekly),
+                    "MONTHLY" => Some(Frequency::Monthly),
+                    "YEARLY" => Some(Frequency::Yearly),
+                    _ => return None,
+                }
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0)?,
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            // An end date includes the whole of that day.
+            "UNTIL" => {
+                rule.until = match NaiveDate::parse_from_str(value, "%Y%m%d") {
+                    Ok(date) => date.and_hms_opt(23, 59, 59),
+                    Err(_) => Some(parse_date_time(value)?),
+                }
+            }
+            _ => {}
+        }
+    }
+    rule.frequency = frequency?;
+    Some(rule)
+}
+
+// Parses the events of a calendar, skipping any which are cancelled or
+// can't be understood.
+fn parse_calendar(text: &str) -> Vec<VEvent> {
+    let mut events = Vec::new();
+    let mut draft: Option<Draft> = None;
+    // How deep we are in components inside the event, e.g. its alarms,
+    // whose properties aren't the event's.
+    let mut nested = 0;
+    for line in unfold(text) {
+        let Some((name, value)) = split_property(&line) else {
+            continue;
+        };
+        let name = name.to_ascii_uppercase();
+        let Some(event) = &mut draft else {
+            if name == "BEGIN" && value.eq_ignore_ascii_case("VEVENT") {
+                draft = Some(Draft::default());
+            }
+            continue;
+        };
+        match name.as_str() {
+            "BEGIN" => nested += 1,
+            "END" if nested > 0 => nested -= 1,
+            "END" => events.extend(draft.take().and_then(Draft::finish)),
+            _ if nested > 0 => {}
+            "SUMMARY" => event.summary = Some(unescape(value)),
+            "LOCATION" => event.location = Some(unescape(value)),
+            "DTSTART" => event.start = parse_start(value),
+            "RRULE" => event.rule = parse_rule(value),
+            "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+    events
+}
+
+// Returns the number of whole months from `from` to `to`.
+fn months_between(from: NaiveDateTime, to: NaiveDateTime) -> i64 {
+    let months = |date: NaiveDateTime| i64::from(date.year()) * 12 + i64::from(date.month0());
+    months(to) - months(from)
+}
+
+// Returns the date `months` after `start`, or `None` if there is no such
+// date, e.g. the 31st of a shorter month.
+fn add_months(start: NaiveDateTime, months: u32) -> Option<NaiveDateTime> {
+    let months = start.month0().checked_add(months)?;
+    let year = start.year().checked_add(i32::try_from(months / 12).ok()?)?;
+    let date = NaiveDate::from_ymd_opt(year, months % 12 + 1, start.day())?;
+    Some(date.and_time(start.time()))
+}
+
+impl Rule {
+    // Returns the `n`th occurrence after the first, which is at `start`.
+    fn nth(&self, start: NaiveDateTime, n: u32) -> Option<NaiveDateTime> {
+        let steps = n.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => start.checked_add_signed(chrono::Duration::days(steps.into())),
+            Frequency::Weekly => start.checked_add_signed(chrono::Duration::weeks(steps.into())),
+            Frequency::Monthly => add_months(start, steps),
+            Frequency::Yearly => add_months(start, steps.checked_mul(12)?),
+        }
+    }
+}
+
+impl VEvent {
+    // Returns when the event next starts after `now`, if it does.
+    fn next_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let Some(rule) = &self.rule else {
+            return Some(self.start).filter(|start| *start > now);
+        };
+        // Skip to the occurrences just before `now`, rather than looking
+        // through every one since the first.
+        let interval = i64::from(rule.interval);
+        let skipped = match rule.frequency {
+            Frequency::Daily => (now - self.start).num_days() / interval,
+            Frequency::Weekly => (now - self.start).num_weeks() / interval,
+            Frequency::Monthly => months_between(self.start, now) / interval,
+            Frequency::Yearly => months_between(self.start, now) / 12 / interval,
+        };
+        let first = u32::try_from(skipped.max(1) - 1).unwrap_or(u32::MAX);
+        for n in (first..).take(MAX_OCCURRENCES as usize) {
+            if rule.count.map_or(false, |count| n >= count) {
+                return None;
+            }
+            // Some occurrences don't exist, e.g. on the 31st of February.
+            let Some(start) = rule.nth(self.start, n) else {
+                continue;
+            };
+            if rule.until.map_or(false, |until| start > until) {
+                return None;
+            }
+            if start > now {
+                return Some(start);
+            }
+        }
+        None
+    }
+}
+
+// Returns the next of `events` to start after `now`, if it starts within
+// `horizon`.
+fn next_event(events: &[VEvent], now: DateTime<Local>, horizon: Duration) -> Option<Event> {
+    let (start, event) = events
+        .iter()
+        .filter(|event| !event.all_day)
+        .filter_map(|event| Some((event.next_start(now.naive_local())?, event)))
+        .min_by_key(|(start, _)| *start)?;
+    let start = Local.from_local_datetime(&start).earliest()?;
+    let starts_in = (start - now).to_std().ok()?;
+    (starts_in <= horizon).then(|| Event {
+        summary: event.summary.clone(),
+        location: event.location.clone(),
+        start,
+        starts_in,
+    })
+}
+
+// Reads the events of each calendar file in `paths`, looking in any which
+// are directories for `.ics` files.
+fn read_calendars(paths: &[PathBuf]) -> Result<Vec<VEvent>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let entries =
+            fs::read_dir(path).with_context(|| format!("Failed to list {}", path.display()))?;
+        for entry in entries {
+            let file = entry?.path();
+            let ics = file.extension().and_then(|ext| ext.to_str());
+            if ics.map_or(false, |ext| ext.eq_ignore_ascii_case("ics")) {
+                files.push(file);
+            }
+        }
+    }
+
+    let mut events = Vec::new();
+    for file in files {
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            // A file may be removed as we read the others, e.g. while a
+            // sync is running.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", file.display()));
+            }
+        };
+        events.extend(parse_calendar(&text));
+    }
+    Ok(events)
+}
+
+impl Widget for Calendar {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        // Directories are watched rather than files, as sync tools replace
+        // files rather than writing to them.
+        let mut dirs: Vec<PathBuf> = self
+            .paths
+            .iter()
+            .map(|path| match path.is_dir() {
+                true => path.clone(),
+                false => path.parent().unwrap_or_else(|| Path::new(".")).to_owned(),
+            })
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        let flags = AddWatchFlags::IN_CLOSE_WRITE
+            | AddWatchFlags::IN_MOVED_TO
+            | AddWatchFlags::IN_MOVED_FROM
+            | AddWatchFlags::IN_DELETE;
+        let mut changes = Box::pin(inotify::watch(&dirs, flags)?);
+
+        let stream = stream! {
+            let mut events = Vec::new();
+            let mut changed = true;
+            loop {
+                if changed {
+                    match read_calendars(&self.paths) {
+                        Ok(read) => events = read,
+                        Err(err) => yield Err(err),
+                    }
+                    changed = false;
+                }
+                let next = next_event(&events, Local::now(), self.horizon);
+                yield Ok(self.render(next.as_ref()));
+
+                // Wake as the countdown ticks over to the next minute, or
+                // every minute to look for events coming within the horizon.
+                let tick = next.map_or(MINUTE, |next| {
+                    next.starts_in + MINUTE - countdown(next.starts_in)
+                });
+                tokio::select! {
+                    _ = time::sleep(tick) => {}
+                    change = changes.next() => match change {
+                        Some(Ok(())) => changed = true,
+                        Some(Err(err)) => yield Err(err),
+                        None => break,
+                    },
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        parse_date_time(value).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let calendar = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Planning\\, and a very long\r\n  title\r\n\
+            LOCATION;ALTREP=\"http://example.com:80/\":Room 1\r\n\
+            DTSTART;TZID=Europe/London:20240115T093000\r\n\
+            RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=10\r\n\
+            BEGIN:VALARM\r\n\
+            SUMMARY:Reminder\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Holiday\r\n\
+            DTSTART;VALUE=DATE:20240116\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Cancelled\r\n\
+            DTSTART:20240117T100000\r\n\
+            STATUS:CANCELLED\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let events = parse_calendar(calendar);
+        assert_eq!(
+            events,
+            [
+                VEvent {
+                    summary: "Planning, and a very long title".to_owned(),
+                    location: Some("Room 1".to_owned()),
+                    start: at("20240115T093000"),
+                    all_day: false,
+                    rule: Some(Rule {
+                        frequency: Frequency::Weekly,
+                        interval: 2,
+                        count: Some(10),
+                        until: None,
+                    }),
+                },
+                VEvent {
+                    summary: "Holiday".to_owned(),
+                    location: None,
+                    start: at("20240116T000000"),
+                    all_day: true,
+                    rule: None,
+                },
+            ]
+        );
+        assert!(parse_date_time("20240115T093000Z").is_some());
+        assert_eq!(parse_rule("FREQ=HOURLY"), None);
+    }
+
+    #[test]
+    fn recurrences() {
+        let mut event = VEvent {
+            summary: "Standup".to_owned(),
+            location: None,
+            start: at("20240115T093000"),
+            all_day: false,
+            rule: None,
+        };
+        assert_eq!(event.next_start(at("20240101T000000")), Some(event.start));
+        assert_eq!(event.next_start(at("20240115T093000")), None);
+
+        event.rule = parse_rule("FREQ=WEEKLY;INTERVAL=2;COUNT=10");
+        assert_eq!(
+            event.next_start(at("20240130T120000")),
+            Some(at("20240212T093000"))
+        );
+        // The tenth and last occurrence is 18 weeks after the first.
+        assert_eq!(
+            event.next_start(at("20240513T093000")),
+            Some(at("20240520T093000"))
+        );
+        assert_eq!(event.next_start(at("20240520T093000")), None);
+
+        // There's no 31st of February, April or June.
+        event.start = at("20240131T180000");
+        event.rule = parse_rule("FREQ=MONTHLY;UNTIL=20240630");
+        assert_eq!(
+            event.next_start(at("20240201T000000")),
+            Some(at("20240331T180000"))
+        );
+        assert_eq!(event.next_start(at("20240601T000000")), None);
+
+        event.rule = parse_rule("FREQ=DAILY");
+        assert_eq!(
+            event.next_start(at("20340601T190000")),
+            Some(at("20340602T180000"))
+        );
+    }
+
+    #[test]
+    fn countdowns() {
+        assert_eq!(countdown(Duration::from_millis(1_499_500)), 25 * MINUTE);
+        assert_eq!(countdown(25 * MINUTE), 25 * MINUTE);
+        assert_eq!(countdown(Duration::from_secs(1)), MINUTE);
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{self as stream, StreamExt};
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+const IDEAPAD: &str = "/sys/bus/platform/drivers/ideapad_acpi";
+
+/// The limit on how far the battery is charged.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    /// The battery is charged fully.
+    Off,
+    /// Charging stops at this percentage.
+    Threshold(u8),
+    /// The firmware's conservation mode is on, which stops charging at a
+    /// level chosen by the firmware, usually 60% or 80%.
+    Conservation,
+}
+
+// The sysfs attribute which controls the limit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Control {
+    // A battery's `charge_control_end_threshold`, which is supported by
+    // e.g. ThinkPads, ASUS and Huawei laptops.
+    EndThreshold(PathBuf),
+    // The `conservation_mode` of Lenovo IdeaPads.
+    ConservationMode(PathBuf),
+}
+
+impl Control {
+    fn find(battery: Option<&str>) -> Result<Self> {
+        let threshold = |battery: &str| {
+            Path::new(POWER_SUPPLY)
+                .join(battery)
+                .join("charge_control_end_threshold")
+        };
+        if let Some(battery) = battery {
+            return Ok(Control::EndThreshold(threshold(battery)));
+        }
+
+        let mut batteries = fs::read_dir(POWER_SUPPLY)
+            .with_context(|| format!("Failed to read {POWER_SUPPLY}"))?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        batteries.sort();
+        if let Some(path) = batteries
+            .iter()
+            .map(|battery| threshold(battery))
+            .find(|path| path.exists())
+        {
+            return Ok(Control::EndThreshold(path));
+        }
+
+        fs::read_dir(IDEAPAD)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path().join("conservation_mode")))
+            .find(|path| path.exists())
+            .map(Control::ConservationMode)
+            .ok_or_else(|| anyhow!("No battery charge limit control found"))
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Control::EndThreshold(path) | Control::ConservationMode(path) => path,
+        }
+    }
+
+    fn read(&self) -> Result<Limit> {
+        let path = self.path();
+        let value = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        self.parse(&value)
+            .with_context(|| format!("Invalid value in {}", path.display()))
+    }
+
+    fn parse(&self, value: &str) -> Result<Limit> {
+        let value: u8 = value.trim().parse()?;
+        Ok(match (self, value) {
+            (Control::EndThreshold(_), 100..) | (Control::ConservationMode(_), 0) => Limit::Off,
+            (Control::EndThreshold(_), threshold) => Limit::Threshold(threshold),
+            (Control::ConservationMode(_), _) => Limit::Conservation,
+        })
+    }
+
+    // Returns the value to write to toggle the limit, capping the charge
+    // at `threshold` where the control allows it.
+    fn toggled(&self, limit: Limit, threshold: u8) -> u8 {
+        match (self, limit) {
+            (Control::EndThreshold(_), Limit::Off) => threshold,
+            (Control::EndThreshold(_), _) => 100,
+            (Control::ConservationMode(_), Limit::Off) => 1,
+            (Control::ConservationMode(_), _) => 0,
+        }
+    }
+}
+
+/// Shows whether the battery's charge is limited, e.g. to 80% to prolong its
+/// life, with click to turn the limit on or off.
+///
+/// This uses the battery's `charge_control_end_threshold` in
+/// [`/sys/class/power_supply/`] where the laptop supports it (e.g. ThinkPads
+/// and ASUS laptops), or the conservation mode of Lenovo IdeaPads.
+///
+/// Left clicking the widget toggles the limit. Writing these files usually
+/// needs root, so the new value can instead be written by a privileged
+/// helper; see [`ChargeLimit::with_helper`].
+///
+/// [`/sys/class/power_supply/`]: https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-power
+pub struct ChargeLimit {
+    attr: Attributes,
+    battery: Option<String>,
+    threshold: u8,
+    helper: Vec<String>,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(Limit) -> String>>,
+}
+
+impl ChargeLimit {
+    /// Creates a new [`ChargeLimit`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`Limit`] is the current limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::charge_limit::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|limit| match limit {
+    ///     Limit::Off => "🔋 100%".to_owned(),
+    ///     Limit::Threshold(threshold) => format!("🔋 {threshold}%"),
+    ///     Limit::Conservation => "🔋 eco".to_owned(),
+    /// });
+    /// let helper = vec!["sudo".to_owned(), "/usr/local/bin/sysfs-write".to_owned()];
+    /// cnx.add_widget(ChargeLimit::new(attr, Some(render)).with_helper(helper));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(Limit) -> String>>) -> Self {
+        Self {
+            attr,
+            battery: None,
+            threshold: 80,
+            helper: Vec::new(),
+            update_interval: Duration::from_secs(60),
+            render,
+        }
+    }
+
+    /// Sets which battery in `/sys/class/power_supply/` to limit, e.g.
+    /// `BAT1`. By default, the first one which supports a charge threshold
+    /// is used.
+    pub fn with_battery(self, battery: String) -> Self {
+        Self {
+            battery: Some(battery),
+            ..self
+        }
+    }
+
+    /// Sets the percentage at which charging stops when the limit is turned
+    /// on. The default is 80%. This has no effect on conservation mode,
+    /// whose level is fixed by the firmware.
+    pub fn with_threshold(self, threshold: u8) -> Self {
+        Self {
+            threshold: threshold.clamp(1, 100),
+            ..self
+        }
+    }
+
+    /// Toggles the limit by running `helper` with the path of the sysfs file
+    /// and the new value appended to it, rather than writing the file
+    /// directly.
+    ///
+    /// For example, with `["sudo", "/usr/local/bin/sysfs-write"]` the limit
+    /// is turned on by running `sudo /usr/local/bin/sysfs-write
+    /// /sys/class/power_supply/BAT0/charge_control_end_threshold 80`.
+    pub fn with_helper(self, helper: Vec<String>) -> Self {
+        Self { helper, ..self }
+    }
+
+    async fn toggle(&self, control: &Control) -> Result<()> {
+        let value = control.toggled(control.read()?, self.threshold).to_string();
+        let path = control.path();
+        let (program, args) = match self.helper.split_first() {
+            Some(helper) => helper,
+            None => {
+                return fs::write(path, value)
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+        };
+        let status = Command::new(program)
+            .args(args)
+            .arg(path)
+            .arg(&value)
+            .status()
+            .await
+            .with_context(|| format!("Failed to run `{program}`"))?;
+        if !status.success() {
+            return Err(anyhow!("`{}` exited with {}", program, status));
+        }
+        Ok(())
+    }
+
+    fn render(&self, limit: Limit) -> Vec<Text> {
+        let text = self.render.as_ref().map_or_else(
+            || match limit {
+                Limit::Off => i18n::tr("No limit"),
+                Limit::Threshold(threshold) => i18n::tr_with("Limit {}%", &threshold.to_string()),
+                Limit::Conservation => i18n::tr("Conservation"),
+            },
+            |x| (x)(limit),
+        );
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+enum Event {
+    Tick,
+    Toggle,
+}
+
+impl Widget for ChargeLimit {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let control = Control::find(self.battery.as_deref()).context("Initialising ChargeLimit")?;
+
+        // Other programs may change the limit without the kernel telling us,
+        // so also check it periodically.
+        let ticks = IntervalStream::new(time::interval(self.update_interval)).map(|_| Event::Tick);
+        let toggles = clicks.filter_map(|click| match click.button {
+            MouseButton::Left => Some(Event::Toggle),
+            _ => None,
+        });
+        let mut events = Box::pin(ticks.merge(toggles));
+
+        let stream = stream! {
+            while let Some(event) = events.next().await {
+                if let Event::Toggle = event {
+                    if let Err(err) = self.toggle(&control).await {
+                        yield Err(err);
+                        continue;
+                    }
+                }
+                yield control.read().map(|limit| self.render(limit));
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let threshold = Control::EndThreshold(PathBuf::new());
+        assert_eq!(threshold.parse("80\n").unwrap(), Limit::Threshold(80));
+        assert_eq!(threshold.parse("100\n").unwrap(), Limit::Off);
+        assert!(threshold.parse("").is_err());
+        assert_eq!(threshold.toggled(Limit::Off, 80), 80);
+        assert_eq!(threshold.toggled(Limit::Threshold(60), 80), 100);
+
+        let conservation = Control::ConservationMode(PathBuf::new());
+        assert_eq!(conservation.parse("0\n").unwrap(), Limit::Off);
+        assert_eq!(conservation.parse("1\n").unwrap(), Limit::Conservation);
+        assert_eq!(conservation.toggled(Limit::Off, 80), 1);
+        assert_eq!(conservation.toggled(Limit::Conservation, 80), 0);
+    }
+}
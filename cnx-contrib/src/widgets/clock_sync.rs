@@ -0,0 +1,377 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use regex::Regex;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::process::Command;
+use tokio::time;
+
+// chronyd answers monitoring requests, such as `chronyc tracking`, on this
+// port without needing any privileges.
+const CHRONY_ADDRESS: &str = "127.0.0.1:323";
+
+// See `candm.h` in the chrony sources.
+const PROTO_VERSION_NUMBER: u8 = 6;
+const PKT_TYPE_CMD_REQUEST: u8 = 1;
+const PKT_TYPE_CMD_REPLY: u8 = 2;
+const REQ_TRACKING: u16 = 33;
+const RPY_TRACKING: u16 = 5;
+const STT_SUCCESS: u16 = 0;
+const LEAP_UNSYNCHRONISED: u16 = 3;
+// The length of a reply to `REQ_TRACKING`. Requests must be padded to be as
+// long as their replies.
+const TRACKING_REPLY_LEN: usize = 108;
+
+// How long to wait for chronyd to reply.
+const CHRONY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where [`ClockSync`] gets the clock's synchronization status from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// chronyd, over its command port on localhost, as `chronyc tracking`
+    /// does.
+    Chrony,
+    /// `timedatectl`, which reports on systemd-timesyncd. The offset is only
+    /// known while timesyncd is running.
+    Timedatectl,
+}
+
+/// Whether the system clock is synchronized, as shown by [`ClockSync`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncStatus {
+    /// Whether the clock is synchronized to a time source
+    pub synchronized: bool,
+    /// How far the system clock is ahead of the time source, in seconds, or
+    /// behind it if negative
+    pub offset: Option<f64>,
+}
+
+/// Shows whether the system clock is synchronized over NTP, and how far it
+/// is off, from chrony or systemd-timesyncd.
+///
+/// The status is checked every minute by default. The widget is shown in the
+/// palette's `Warn` color when the offset is more than the threshold, 100
+/// milliseconds by default, and in its `Crit` color when the clock isn't
+/// synchronized at all.
+pub struct ClockSync {
+    attr: Attributes,
+    source: Source,
+    threshold: Duration,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(SyncStatus) -> String>>,
+}
+
+impl ClockSync {
+    /// Creates a new [`ClockSync`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `source` - Where to get the synchronization status from.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`SyncStatus`] is whether the clock is
+    /// synchronized, and its offset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::clock_sync::*;
+    /// # use anyhow::Result;
+    /// # use std::time::Duration;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let sync = ClockSync::new(attr, Source::Chrony, None)
+    ///     .with_threshold(Duration::from_millis(20));
+    /// cnx.add_widget(sync);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        source: Source,
+        render: Option<Box<dyn Fn(SyncStatus) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            source,
+            threshold: Duration::from_millis(100),
+            update_interval: Duration::from_secs(60),
+            render,
+        }
+    }
+
+    /// Sets how far off the clock can be before the widget warns about it.
+    /// The default is 100 milliseconds.
+    pub fn with_threshold(self, threshold: Duration) -> Self {
+        Self { threshold, ..self }
+    }
+
+    /// Sets how often to check the status. The default is every minute.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    async fn status(&self) -> Result<SyncStatus> {
+        match self.source {
+            Source::Chrony => chrony_tracking().await,
+            Source::Timedatectl => timedatectl_status().await,
+        }
+    }
+
+    fn render(&self, status: SyncStatus) -> Vec<Text> {
+        let default_text = || match (status.synchronized, status.offset) {
+            (false, _) => i18n::tr("NTP unsynchronized"),
+            (true, Some(offset)) => format!("NTP {}", format_offset(offset)),
+            (true, None) => i18n::tr("NTP synchronized"),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(status.clone()));
+
+        let mut attr = self.attr.clone();
+        let off = status
+            .offset
+            .map_or(false, |offset| offset.abs() > self.threshold.as_secs_f64());
+        if !status.synchronized {
+            attr.fg_color = Color::role(Role::Crit);
+        } else if off {
+            attr.fg_color = Color::role(Role::Warn);
+        }
+
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Asks chronyd for its tracking report, as `chronyc tracking` does.
+async fn chrony_tracking() -> Result<SyncStatus> {
+    let socket = UdpSocket::bind("127.0.0.1:0").await?;
+    socket
+        .connect(CHRONY_ADDRESS)
+        .await
+        .context("Failed to reach chronyd")?;
+    let sequence = std::process::id();
+    socket.send(&tracking_request(sequence)).await?;
+
+    let mut reply = vec![0; 1024];
+    let len = time::timeout(CHRONY_TIMEOUT, socket.recv(&mut reply))
+        .await
+        .map_err(|_| anyhow!("No reply from chronyd"))??;
+    parse_tracking(&reply[..len], sequence)
+}
+
+fn tracking_request(sequence: u32) -> Vec<u8> {
+    let mut request = vec![0; TRACKING_REPLY_LEN];
+    request[0] = PROTO_VERSION_NUMBER;
+    request[1] = PKT_TYPE_CMD_REQUEST;
+    request[4..6].copy_from_slice(&REQ_TRACKING.to_be_bytes());
+    request[8..12].copy_from_slice(&sequence.to_be_bytes());
+    request
+}
+
+// Parses chronyd's reply to `REQ_TRACKING`.
+fn parse_tracking(reply: &[u8], sequence: u32) -> Result<SyncStatus> {
+    let u16_at = |i: usize| {
+        reply
+            .get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let u32_at = |i: usize| {
+        reply
+            .get(i..i + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if reply.len() < TRACKING_REPLY_LEN
+        || reply[1] != PKT_TYPE_CMD_REPLY
+        || u16_at(6) != Some(RPY_TRACKING)
+        || u32_at(16) != Some(sequence)
+    {
+        return Err(anyhow!("Unexpected reply from chronyd"));
+    }
+    let status = u16_at(8).unwrap_or_default();
+    if status != STT_SUCCESS {
+        return Err(anyhow!("chronyd returned error {}", status));
+    }
+    let leap_status = u16_at(54).unwrap_or(LEAP_UNSYNCHRONISED);
+    // The correction is how much the clock needs to be slewed forward, so
+    // is the opposite of the offset.
+    let correction = u32_at(68).map(chrony_float).unwrap_or_default();
+    Ok(SyncStatus {
+        synchronized: leap_status != LEAP_UNSYNCHRONISED,
+        offset: Some(-correction),
+    })
+}
+
+// Decodes chrony's 32-bit floating point format, which has a 7-bit signed
+// exponent and a 25-bit signed coefficient.
+fn chrony_float(x: u32) -> f64 {
+    const EXP_BITS: u32 = 7;
+    const COEF_BITS: u32 = 32 - EXP_BITS;
+    let mut exp = (x >> COEF_BITS) as i32;
+    if exp >= 1 << (EXP_BITS - 1) {
+        exp -= 1 << EXP_BITS;
+    }
+    let mut coef = (x % (1 << COEF_BITS)) as i32;
+    if coef >= 1 << (COEF_BITS - 1) {
+        coef -= 1 << COEF_BITS;
+    }
+    f64::from(coef) * 2f64.powi(exp - COEF_BITS as i32)
+}
+
+// Asks timedatectl whether the clock is synchronized and, if
+// systemd-timesyncd is running, by how much it was last off.
+async fn timedatectl_status() -> Result<SyncStatus> {
+    let output = Command::new("timedatectl")
+        .args(["show", "--property=NTPSynchronized", "--value"])
+        .output()
+        .await
+        .context("Failed to run timedatectl")?;
+    if !output.status.success() {
+        return Err(anyhow!("timedatectl exited with {}", output.status));
+    }
+    let synchronized = String::from_utf8_lossy(&output.stdout).trim() == "yes";
+
+    // This fails when timesyncd isn't running, e.g. because another NTP
+    // client is used.
+    let output = Command::new("timedatectl")
+        .arg("timesync-status")
+        .output()
+        .await?;
+    let offset = if output.status.success() {
+        parse_timesync_offset(&String::from_utf8_lossy(&output.stdout))
+    } else {
+        None
+    };
+    Ok(SyncStatus {
+        synchronized,
+        offset,
+    })
+}
+
+// Parses the offset from the output of `timedatectl timesync-status`, e.g.
+// `Offset: -1.503ms`, which is how far the server is ahead of the clock.
+fn parse_timesync_offset(output: &str) -> Option<f64> {
+    let re = Regex::new(r"(?m)^\s*Offset:\s*([+-]?[0-9.]+)(us|ms|s|min)\s*$").ok()?;
+    let captures = re.captures(output)?;
+    let value: f64 = captures[1].parse().ok()?;
+    let scale = match &captures[2] {
+        "us" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        _ => 60.0,
+    };
+    Some(-value * scale)
+}
+
+// Formats an offset in seconds, in whichever unit suits it, e.g. `+1.2ms`.
+fn format_offset(offset: f64) -> String {
+    let (value, unit) = match offset.abs() {
+        x if x < 1e-3 => (offset * 1e6, "µs"),
+        x if x < 1.0 => (offset * 1e3, "ms"),
+        _ => (offset, "s"),
+    };
+    format!("{value:+.1}{unit}")
+}
+
+impl Widget for ClockSync {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                yield self.status().await.map(|status| self.render(status));
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn floats() {
+        assert_eq!(chrony_float(0x0480_0000), 1.0);
+        assert_eq!(chrony_float(0x0380_0000), -0.5);
+        assert_eq!(chrony_float(0xfe80_0000), 0.125);
+        assert_eq!(chrony_float(0), 0.0);
+    }
+
+    #[test]
+    fn tracking() {
+        let mut reply = vec![0; TRACKING_REPLY_LEN];
+        reply[0] = PROTO_VERSION_NUMBER;
+        reply[1] = PKT_TYPE_CMD_REPLY;
+        reply[4..6].copy_from_slice(&REQ_TRACKING.to_be_bytes());
+        reply[6..8].copy_from_slice(&RPY_TRACKING.to_be_bytes());
+        reply[16..20].copy_from_slice(&42u32.to_be_bytes());
+        // The clock is half a second fast.
+        reply[68..72].copy_from_slice(&0x0380_0000u32.to_be_bytes());
+        assert_eq!(
+            parse_tracking(&reply, 42).unwrap(),
+            SyncStatus {
+                synchronized: true,
+                offset: Some(0.5),
+            }
+        );
+        assert!(parse_tracking(&reply, 43).is_err());
+
+        reply[54..56].copy_from_slice(&LEAP_UNSYNCHRONISED.to_be_bytes());
+        assert!(!parse_tracking(&reply, 42).unwrap().synchronized);
+        reply[8..10].copy_from_slice(&3u16.to_be_bytes());
+        assert!(parse_tracking(&reply, 42).is_err());
+        assert!(parse_tracking(&reply[..28], 42).is_err());
+    }
+
+    #[test]
+    fn timesync_offset() {
+        let output = "       Server: 192.168.1.1 (_gateway)\n\
+                      Poll interval: 34min 8s (min: 32s; max 34min 8s)\n\
+                               Leap: normal\n\
+                             Offset: -1.503ms\n\
+                              Delay: 1.213ms\n";
+        let close_to = |offset: Option<f64>, expected: f64| {
+            offset.map_or(false, |offset| (offset - expected).abs() < 1e-12)
+        };
+        assert!(close_to(parse_timesync_offset(output), 1.503e-3));
+        assert!(close_to(parse_timesync_offset("Offset: +312us"), -312e-6));
+        assert_eq!(parse_timesync_offset("Server: n/a"), None);
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(format_offset(0.0012), "+1.2ms");
+        assert_eq!(format_offset(-0.000_25), "-250.0µs");
+        assert_eq!(format_offset(2.5), "+2.5s");
+    }
+}
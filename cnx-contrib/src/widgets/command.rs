@@ -1,16 +1,42 @@
-use anyhow::Result;
-use cnx::text::{Attributes, Text};
+use crate::sandbox::Sandbox;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::capabilities::Capabilities;
+use cnx::services::Services;
+use cnx::text::{truncate, Attributes, Color, Role, Text};
+use cnx::untrusted;
 use cnx::widgets::{Widget, WidgetStream};
-use std::process::Command as Process;
+use std::pin::Pin;
 use std::time::Duration;
+use tokio::process::Command as Process;
 use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
+use tokio_stream::{self as stream, Stream, StreamExt};
 
+/// Shows the output of a shell command, run every `update_interval`, e.g. a
+/// script printing the number of unread messages. This is the escape hatch
+/// for anything no other widget shows.
+///
+/// Only the first line of the output is shown, and it is taken to be Pango
+/// markup unless [`Command::with_markup()`] says otherwise. If the command
+/// exits with a non-zero status, it is shown in the theme's `Warn` color, or
+/// in the color given for that status with [`Command::with_exit_color()`].
+///
+/// With [`Command::with_signal()`], the command is also run whenever Cnx is
+/// sent a real-time signal, as in i3blocks, e.g. `pkill -RTMIN+1 cnx` from a
+/// script which has just changed what the command reports.
+///
+/// The command runs in a [`Sandbox`], which by default only caps how much of
+/// its output is read; see [`Command::with_sandbox()`] to confine it further.
 pub struct Command {
     attr: Attributes,
     command: String,
     update_interval: Duration,
+    max_width: Option<usize>,
+    markup: bool,
+    signal: Option<i32>,
+    exit_colors: Vec<(i32, Color)>,
+    sandbox: Sandbox,
+    services: Option<Services>,
 }
 
 impl Command {
@@ -46,7 +72,9 @@ impl Command {
     /// };
     ///
     /// let mut cnx = Cnx::new(Position::Top);
-    /// cnx.add_widget(Command::new(attr, "echo foo".into(), Duration::from_secs(10)));
+    /// let command = Command::new(attr, "echo foo".into(), Duration::from_secs(10))
+    ///     .with_exit_color(1, Color::role(Role::Crit));
+    /// cnx.add_widget(command);
     /// Ok(())
     /// }
     /// fn main() { run().unwrap(); }
@@ -56,32 +84,189 @@ impl Command {
             attr,
             command,
             update_interval,
+            max_width: None,
+            markup: true,
+            signal: None,
+            exit_colors: Vec::new(),
+            sandbox: Sandbox::default(),
+            services: None,
+        }
+    }
+
+    /// Truncates the output to at most `max_width` columns, as measured by
+    /// [`truncate()`].
+    ///
+    /// Markup can't be cut safely, so the truncated output is shown as plain
+    /// text.
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self {
+            max_width: Some(max_width),
+            ..self
         }
     }
 
-    fn tick(&self) -> Vec<Text> {
-        let output = Process::new("sh")
-            .arg("-c")
-            .arg(self.command.clone())
-            .output()
-            .expect("failed to execute process");
+    /// Sets whether the output is Pango markup, or plain text to be shown
+    /// as it is. The default is markup.
+    pub fn with_markup(self, markup: bool) -> Self {
+        Self { markup, ..self }
+    }
 
-        let texts = vec![Text {
-            attr: self.attr.clone(),
-            text: String::from_utf8(output.stdout).unwrap_or_else(|_| "error".into()),
-            stretch: false,
-            markup: true,
-        }];
+    /// Also runs the command whenever Cnx is sent the real-time signal
+    /// `SIGRTMIN+offset`, and then again after `update_interval`.
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+    pub fn with_signal(self, offset: i32) -> Self {
+        Self {
+            signal: Some(offset),
+            ..self
+        }
+    }
+
+    /// Shows the output in `color` when the command exits with `code`. This
+    /// can be used for a successful exit too, with a `code` of zero.
+    pub fn with_exit_color(mut self, code: i32, color: Color) -> Self {
+        self.exit_colors.retain(|(other, _)| *other != code);
+        self.exit_colors.push((code, color));
+        self
+    }
+
+    /// Runs the command in `sandbox`, e.g. with a timeout so that a command
+    /// which hangs doesn't stop the widget updating.
+    pub fn with_sandbox(self, sandbox: Sandbox) -> Self {
+        Self { sandbox, ..self }
+    }
+
+    // Returns the color to show the output in, if it isn't the widget's
+    // own, for the exit `code` of the command.
+    fn exit_color(&self, code: Option<i32>) -> Option<Color> {
+        let chosen = self
+            .exit_colors
+            .iter()
+            .find(|(other, _)| Some(*other) == code);
+        match (chosen, code) {
+            (Some((_, color)), _) => Some(color.clone()),
+            (None, Some(0)) => None,
+            // Including a command killed by a signal, which has no exit code.
+            (None, _) => Some(Color::role(Role::Warn)),
+        }
+    }
 
-        texts
+    async fn tick(&self, capabilities: &Capabilities) -> Result<Vec<Text>> {
+        let mut process = Process::new("sh");
+        process.arg("-c").arg(&self.command);
+        let output = self
+            .sandbox
+            .output(process, capabilities)
+            .await
+            .with_context(|| format!("Failed to run {}", self.command))?;
+
+        let line = first_line(&output.stdout);
+        let (text, markup) = match self.max_width {
+            Some(max_width) => (truncate(&line, max_width), false),
+            None if self.markup => (line, true),
+            None => (untrusted::text(&line), false),
+        };
+        let mut attr = self.attr.clone();
+        if let Some(color) = self.exit_color(output.status.code()) {
+            attr.fg_color = color;
+        }
+        Ok(vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup,
+            canvas: None,
+        }])
     }
 }
 
+// Returns the first line of the command's output, without its line ending.
+fn first_line(stdout: &[u8]) -> String {
+    let stdout = String::from_utf8_lossy(stdout);
+    stdout
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_owned()
+}
+
+// Returns a stream which yields whenever Cnx is sent `SIGRTMIN+offset`.
+#[cfg(target_os = "linux")]
+fn realtime_signals(offset: i32) -> Result<impl Stream<Item = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let number = libc::SIGRTMIN() + offset;
+    anyhow::ensure!(
+        (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&number),
+        "SIGRTMIN+{offset} isn't a real-time signal"
+    );
+    let mut signals = signal(SignalKind::from_raw(number))
+        .with_context(|| format!("Failed to listen for SIGRTMIN+{offset}"))?;
+    Ok(stream! {
+        while signals.recv().await.is_some() {
+            yield ();
+        }
+    })
+}
+
 impl Widget for Command {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
-        let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+        let mut signals: Pin<Box<dyn Stream<Item = ()>>> = match self.signal {
+            #[cfg(target_os = "linux")]
+            Some(offset) => Box::pin(realtime_signals(offset)?),
+            _ => Box::pin(stream::pending()),
+        };
+
+        let capabilities = self
+            .services
+            .as_ref()
+            .map_or_else(Capabilities::default, Capabilities::shared);
+
+        let stream = stream! {
+            let mut interval = time::interval(self.update_interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    Some(()) = signals.next() => interval.reset(),
+                }
+                yield self.tick(&capabilities).await;
+            }
+        };
 
         Ok(Box::pin(stream))
     }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cnx::text::{Font, Padding};
+
+    #[test]
+    fn outputs() {
+        assert_eq!(first_line(b"<b>3</b> unread\nmore\n"), "<b>3</b> unread");
+        assert_eq!(first_line(b"42 \r\n"), "42");
+        assert_eq!(first_line(b""), "");
+        assert_eq!(first_line(b"caf\xc3\xa9 \xff"), "caf\u{e9} \u{fffd}");
+
+        let attr = Attributes {
+            font: Font::new("SourceCodePro 16"),
+            fg_color: Color::white(),
+            bg_color: None,
+            padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+        };
+        let command = Command::new(attr, "true".into(), Duration::from_secs(1))
+            .with_exit_color(33, Color::red())
+            .with_exit_color(0, Color::white())
+            .with_exit_color(33, Color::role(Role::Crit));
+        assert_eq!(command.exit_color(Some(0)), Some(Color::white()));
+        assert_eq!(command.exit_color(Some(33)), Some(Color::role(Role::Crit)));
+        assert_eq!(command.exit_color(Some(1)), Some(Color::role(Role::Warn)));
+        assert_eq!(command.exit_color(None), Some(Color::role(Role::Warn)));
+    }
 }
@@ -1,9 +1,8 @@
-use anyhow::{anyhow, Result};
-use cnx::text::{Attributes, Text};
+use crate::history::History;
+use crate::sysinfo::{self, CpuTimes, SysInfo};
+use anyhow::Result;
+use cnx::text::{Attributes, Canvas, Text};
 use cnx::widgets::{Widget, WidgetStream};
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
 use std::time::Duration;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
@@ -12,8 +11,13 @@ use tokio_stream::StreamExt;
 /// Represents CPU widget used to show current CPU consumptiong
 pub struct Cpu {
     attr: Attributes,
-    cpu_data: CpuData,
+    cpu_times: CpuTimes,
     render: Option<Box<dyn Fn(u64) -> String>>,
+    history: Option<(History<f64>, f64)>,
+    // The times of each logical CPU at the last tick, and the width of
+    // their bars.
+    cores: Option<(Vec<CpuTimes>, f64)>,
+    sysinfo: Option<SysInfo>,
 }
 
 impl Cpu {
@@ -54,99 +58,136 @@ impl Cpu {
     /// # fn main() { run().unwrap(); }
     /// ```
     pub fn new(attr: Attributes, render: Option<Box<dyn Fn(u64) -> String>>) -> Result<Self> {
-        let cpu_data = CpuData::get_values()?;
+        let cpu_times = sysinfo::cpu_times()?;
         Ok(Cpu {
             attr,
-            cpu_data,
+            cpu_times,
             render,
+            history: None,
+            cores: None,
+            sysinfo: None,
         })
     }
 
-    fn tick(&mut self) -> Result<Vec<Text>> {
-        let cpu_data = CpuData::get_values()?;
+    /// Takes the CPU usage from a shared [`SysInfo`] sampler, rather than
+    /// reading it every ten seconds.
+    pub fn with_sysinfo(self, sysinfo: SysInfo) -> Self {
+        Cpu {
+            sysinfo: Some(sysinfo),
+            ..self
+        }
+    }
 
-        // https://github.com/jaor/xmobar/blob/61d075d3c275366c3344d59c058d7dd0baf21ef2/src/Xmobar/Plugins/Monitors/Cpu.hs#L128
-        let previous = &self.cpu_data;
-        let current = cpu_data;
-        let diff_total = (current.user_time - previous.user_time)
-            + (current.nice_time - previous.nice_time)
-            + (current.system_time - previous.system_time)
-            + (current.idle_time - previous.idle_time)
-            + (current.iowait_time - previous.iowait_time);
-        let percentage = match diff_total {
-            0 => 0.0,
-            _ => (current.total_time - previous.total_time) as f64 / diff_total as f64,
-        };
+    /// Shows a histogram of the last `samples` readings after the text,
+    /// `width` pixels wide. The CPU usage is read every ten seconds (unless
+    /// using [`Cpu::with_sysinfo`]), so e.g. 30 samples show the last five
+    /// minutes.
+    pub fn with_history(self, samples: usize, width: f64) -> Self {
+        Cpu {
+            history: Some((History::new(samples), width)),
+            ..self
+        }
+    }
+
+    /// Shows a small vertical bar for each logical CPU after the text, each
+    /// `bar_width` pixels wide, like the header of `htop`. This shows at a
+    /// glance when the load is uneven, e.g. a single-threaded task keeping
+    /// one core busy.
+    pub fn with_cores(self, bar_width: f64) -> Result<Self> {
+        let cores = sysinfo::core_times()?;
+        Ok(Cpu {
+            cores: Some((cores, bar_width)),
+            ..self
+        })
+    }
 
-        let cpu_usage = (percentage * 100.0) as u64;
+    fn tick(&mut self, current: CpuTimes, cores: &[CpuTimes]) -> Vec<Text> {
+        // https://github.com/jaor/xmobar/blob/61d075d3c275366c3344d59c058d7dd0baf21ef2/src/Xmobar/Plugins/Monitors/Cpu.hs#L128
+        let percentage = current.usage_since(&self.cpu_times);
+        let cpu_usage = percentage as u64;
         let text = self
             .render
             .as_ref()
             .map_or(format!("{cpu_usage} %"), |x| (x)(cpu_usage));
-        self.cpu_data = current;
-        let texts = vec![Text {
+        self.cpu_times = current;
+        let mut texts = vec![Text {
             attr: self.attr.clone(),
             text,
             stretch: false,
             markup: true,
+            canvas: None,
         }];
-        Ok(texts)
+
+        if let Some((history, width)) = &mut self.history {
+            history.push(percentage);
+            texts.push(Text {
+                attr: self.attr.clone(),
+                text: String::new(),
+                stretch: false,
+                markup: false,
+                canvas: Some(history.histogram(*width, 100.0)),
+            });
+        }
+
+        if let Some((previous, bar_width)) = &mut self.cores {
+            // Cores which have just come online have nothing to compare
+            // with, so are left out until the next tick.
+            let usages = cores
+                .iter()
+                .zip(previous.iter())
+                .map(|(current, previous)| current.usage_since(previous))
+                .collect();
+            *previous = cores.to_vec();
+            texts.push(Text {
+                attr: self.attr.clone(),
+                text: String::new(),
+                stretch: false,
+                markup: false,
+                canvas: Some(core_bars(usages, *bar_width)),
+            });
+        }
+        texts
     }
 }
 
-struct CpuData {
-    user_time: i64,
-    nice_time: i64,
-    system_time: i64,
-    idle_time: i64,
-    total_time: i64,
-    iowait_time: i64,
-}
+// The gap between the bars of each core, in pixels.
+const CORE_GAP: f64 = 1.0;
 
-impl CpuData {
-    fn get_values() -> Result<CpuData> {
-        // https://www.kernel.org/doc/Documentation/filesystems/proc.txt
-        let file = File::open("/proc/stat")?;
-        let mut cpu_line = String::new();
-        let mut reader = BufReader::new(file);
-        reader.read_line(&mut cpu_line)?;
-        let val: Vec<&str> = cpu_line
-            .split(' ')
-            .filter(|item| item != &"cpu" && !item.is_empty())
-            .collect();
-        let mut cpu_data = CpuData {
-            user_time: 0,
-            nice_time: 0,
-            system_time: 0,
-            idle_time: 0,
-            total_time: 0,
-            iowait_time: 0,
-        };
-        match val[..] {
-            [user, nice, system, idle, iowait, ..] => {
-                let user_time = user.parse()?;
-                let nice_time = nice.parse()?;
-                let system_time = system.parse()?;
-                let idle_time = idle.parse()?;
-                let iowait_time = iowait.parse()?;
-                cpu_data.user_time = user_time;
-                cpu_data.nice_time = nice_time;
-                cpu_data.system_time = system_time;
-                cpu_data.idle_time = idle_time;
-                cpu_data.iowait_time = iowait_time;
-                cpu_data.total_time = user_time + nice_time + system_time;
-            }
-            _ => return Err(anyhow!("Missing data in /proc/stat")),
+// Draws a bar for the usage of each core, with every bar at least a pixel
+// high so that idle cores can still be counted.
+fn core_bars(usages: Vec<f64>, bar_width: f64) -> Canvas {
+    let count = usages.len() as f64;
+    let width = (bar_width * count + CORE_GAP * (count - 1.0)).max(0.0);
+    Canvas::new(width, 0.0, move |cr, _, height| {
+        for (i, usage) in usages.iter().enumerate() {
+            let bar_height = (usage / 100.0).clamp(0.0, 1.0) * height;
+            let bar_height = bar_height.max(1.0);
+            let x = i as f64 * (bar_width + CORE_GAP);
+            cr.rectangle(x, height - bar_height, bar_width, bar_height);
         }
-        Ok(cpu_data)
-    }
+        let _ = cr.fill();
+    })
 }
 
 impl Widget for Cpu {
     fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        if let Some(sysinfo) = &self.sysinfo {
+            let stream = sysinfo.subscribe().map(move |sample| {
+                let sample = sample?;
+                Ok(self.tick(sample.cpu, &sample.cores))
+            });
+            return Ok(Box::pin(stream));
+        }
+
         let ten_seconds = Duration::from_secs(10);
         let interval = time::interval(ten_seconds);
-        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        let stream = IntervalStream::new(interval).map(move |_| {
+            let cores = match self.cores {
+                Some(_) => sysinfo::core_times()?,
+                None => Vec::new(),
+            };
+            Ok(self.tick(sysinfo::cpu_times()?, &cores))
+        });
         Ok(Box::pin(stream))
     }
 }
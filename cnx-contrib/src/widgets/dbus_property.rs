@@ -0,0 +1,333 @@
+use crate::dbus::{self, Bus};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities;
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use std::collections::HashMap;
+use tokio_stream::{Stream, StreamExt};
+use zbus::fdo::{self, PropertiesProxy};
+use zbus::names::InterfaceName;
+use zbus::zvariant::OwnedValue;
+use zbus::{CacheProperties, MatchRule, MessageStream, MessageType};
+
+pub use zbus::zvariant::Value;
+
+// The body of a PropertiesChanged signal: the interface, its changed
+// properties with their new values, and its invalidated properties.
+type PropertiesChanged = (String, HashMap<String, OwnedValue>, Vec<String>);
+
+// What a signal tells us about the watched property.
+#[derive(Debug, PartialEq)]
+enum Change {
+    // The property's new value.
+    Value(OwnedValue),
+    // The property's value has to be read again, as it was invalidated, or
+    // the service started or stopped.
+    Stale,
+}
+
+type Render = Box<dyn Fn(&Value) -> String>;
+
+/// Shows a property of an object on DBus, e.g. NetworkManager's
+/// connectivity or a daemon's status, so that the many daemons which expose
+/// their state over DBus can be shown without a widget of their own.
+///
+/// The widget is updated as soon as the service signals that the property
+/// has changed, with `PropertiesChanged`, and reads it again whenever the
+/// service starts or stops. By default, the value is shown as plain text,
+/// e.g. `42`, `online` or `a, b` for an array. The widget is hidden while
+/// the service isn't running.
+pub struct DbusProperty {
+    attr: Attributes,
+    bus: Bus,
+    service: String,
+    path: String,
+    interface: String,
+    property: String,
+    render: Option<Render>,
+    services: Option<Services>,
+}
+
+impl DbusProperty {
+    /// Creates a new [`DbusProperty`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `bus` - The bus the service is on.
+    ///
+    /// * `service` - The name of the service, e.g.
+    /// `org.freedesktop.NetworkManager`.
+    ///
+    /// * `path` - The path of the object, e.g. `/org/freedesktop/NetworkManager`.
+    ///
+    /// * `interface` - The interface the property belongs to.
+    ///
+    /// * `property` - The name of the property to show.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the property's value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::dbus::Bus;
+    /// # use cnx_contrib::widgets::dbus_property::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|value: &Value| match value {
+    ///     Value::U32(4) => "Online".to_owned(),
+    ///     _ => "Offline".to_owned(),
+    /// });
+    /// let connectivity = DbusProperty::new(
+    ///     attr,
+    ///     Bus::System,
+    ///     "org.freedesktop.NetworkManager".into(),
+    ///     "/org/freedesktop/NetworkManager".into(),
+    ///     "org.freedesktop.NetworkManager".into(),
+    ///     "Connectivity".into(),
+    ///     Some(render),
+    /// );
+    /// cnx.add_widget(connectivity);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        bus: Bus,
+        service: String,
+        path: String,
+        interface: String,
+        property: String,
+        render: Option<Render>,
+    ) -> Self {
+        Self {
+            attr,
+            bus,
+            service,
+            path,
+            interface,
+            property,
+            render,
+            services: None,
+        }
+    }
+
+    // Returns the property's value, or `None` if the service isn't running.
+    async fn get(&self, properties: &PropertiesProxy<'_>) -> Result<Option<OwnedValue>> {
+        let interface = InterfaceName::try_from(self.interface.as_str())?;
+        match properties.get(interface, &self.property).await {
+            Ok(value) => Ok(Some(value)),
+            Err(fdo::Error::ServiceUnknown(_) | fdo::Error::NameHasNoOwner(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Returns a stream of the changes to the property, until the connection
+    // is closed.
+    async fn changes(&self, connection: &zbus::Connection) -> Result<impl Stream<Item = Change>> {
+        let properties = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(self.service.as_str())?
+            .path(self.path.as_str())?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .arg(0, self.interface.as_str())?
+            .build();
+        let owner = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender("org.freedesktop.DBus")?
+            .interface("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .arg(0, self.service.as_str())?
+            .build();
+        let properties = MessageStream::for_match_rule(properties, connection, None).await?;
+        let owner = MessageStream::for_match_rule(owner, connection, None).await?;
+        let property = self.property.clone();
+        // The streams yield an error as the connection closes.
+        let properties =
+            properties
+                .take_while(|message| message.is_ok())
+                .filter_map(move |message| {
+                    let body: PropertiesChanged = message.ok()?.body().ok()?;
+                    changed(body, &property)
+                });
+        let owner = owner
+            .take_while(|message| message.is_ok())
+            .map(|_| Change::Stale);
+        Ok(properties.merge(owner))
+    }
+
+    fn render(&self, value: Option<&Value>) -> Vec<Text> {
+        let Some(value) = value else {
+            return Vec::new();
+        };
+        let text = match &self.render {
+            Some(render) => (render)(value),
+            None => untrusted::text(&display(value)),
+        };
+        if text.is_empty() {
+            return Vec::new();
+        }
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns what a PropertiesChanged signal with `body` says about `property`,
+// if it is among the properties which changed.
+fn changed(body: PropertiesChanged, property: &str) -> Option<Change> {
+    let (_, mut values, invalidated) = body;
+    if let Some(value) = values.remove(property) {
+        Some(Change::Value(value))
+    } else if invalidated.iter().any(|name| name == property) {
+        Some(Change::Stale)
+    } else {
+        None
+    }
+}
+
+// Returns `value` as plain text, e.g. `42`, `online` or `a, b`.
+fn display(value: &Value) -> String {
+    match value {
+        Value::U8(x) => x.to_string(),
+        Value::Bool(x) => x.to_string(),
+        Value::I16(x) => x.to_string(),
+        Value::U16(x) => x.to_string(),
+        Value::I32(x) => x.to_string(),
+        Value::U32(x) => x.to_string(),
+        Value::I64(x) => x.to_string(),
+        Value::U64(x) => x.to_string(),
+        Value::F64(x) => x.to_string(),
+        Value::Str(x) => x.as_str().to_owned(),
+        Value::ObjectPath(x) => x.as_str().to_owned(),
+        Value::Value(x) => display(x),
+        Value::Array(x) => x.get().iter().map(display).collect::<Vec<_>>().join(", "),
+        value => format!("{value:?}"),
+    }
+}
+
+impl Widget for DbusProperty {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        if let Err(err) = dbus::require(self.services.as_ref(), self.bus) {
+            return Ok(capabilities::unavailable(self.attr, err));
+        }
+
+        let pool = dbus::pool(self.services.as_ref());
+
+        let stream = stream! {
+            let widget = &self;
+            let mut watches = Box::pin(pool.watch(self.bus, |connection| async move {
+                let properties = PropertiesProxy::builder(&connection)
+                    .destination(widget.service.as_str())?
+                    .path(widget.path.as_str())?
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?;
+                Ok((properties, Box::pin(widget.changes(&connection).await?)))
+            }));
+            // Signals stop when the bus closes the connection, e.g. because
+            // dbus-daemon was restarted, so we start again with the next one.
+            while let Some(watched) = watches.next().await {
+                let (properties, mut changes) = match watched {
+                    Ok(watched) => watched,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                let mut change = Change::Stale;
+                loop {
+                    let value = match change {
+                        Change::Value(value) => Ok(Some(value)),
+                        Change::Stale => self.get(&properties).await,
+                    };
+                    match value {
+                        Ok(value) => yield Ok(self.render(value.as_deref())),
+                        Err(err) => yield Err(err),
+                    }
+                    let Some(next) = changes.next().await else {
+                        break;
+                    };
+                    change = next;
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let values = HashMap::from([
+            (
+                "Connectivity".to_owned(),
+                OwnedValue::from(Value::from(4u32)),
+            ),
+            ("State".to_owned(), OwnedValue::from(Value::from(70u32))),
+        ]);
+        let body = || {
+            (
+                "org.freedesktop.NetworkManager".to_owned(),
+                values.clone(),
+                Vec::new(),
+            )
+        };
+        assert_eq!(
+            changed(body(), "Connectivity"),
+            Some(Change::Value(OwnedValue::from(Value::from(4u32))))
+        );
+        assert_eq!(changed(body(), "Metered"), None);
+
+        let invalidated = vec!["Metered".to_owned()];
+        let body = (
+            "org.freedesktop.NetworkManager".to_owned(),
+            HashMap::new(),
+            invalidated,
+        );
+        assert_eq!(changed(body, "Metered"), Some(Change::Stale));
+    }
+
+    #[test]
+    fn displays() {
+        assert_eq!(display(&Value::from(42u32)), "42");
+        assert_eq!(display(&Value::from("online")), "online");
+        assert_eq!(display(&Value::from(true)), "true");
+        assert_eq!(display(&Value::from(vec!["a", "b"])), "a, b");
+        assert_eq!(display(&Value::new(Value::from(-1i32))), "-1");
+    }
+}
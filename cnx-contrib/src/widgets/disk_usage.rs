@@ -1,6 +1,8 @@
+use crate::format::{ByteUnits, NumberFormat};
 use anyhow::Result;
-use byte_unit::{Byte, ByteUnit};
-use cnx::text::{Attributes, Text};
+use byte_unit::Byte;
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Ramp, Text};
 use cnx::widgets::{Widget, WidgetStream};
 use nix::sys::statvfs::statvfs;
 use std::time::Duration;
@@ -11,6 +13,8 @@ use tokio_stream::StreamExt;
 /// Represent Information about the mounted filesystem
 #[derive(Debug)]
 pub struct DiskInfo {
+    /// The label of the mount point, e.g. `Disk` or `/home`
+    pub label: String,
     /// Total size of the filesystem
     pub total: Byte,
     /// Total used space of the filesystem
@@ -20,7 +24,7 @@ pub struct DiskInfo {
 }
 
 impl DiskInfo {
-    fn new(path: &str) -> Result<Self> {
+    fn new(label: &str, path: &str) -> Result<Self> {
         let stat = statvfs(path)?;
         let total_size = stat.blocks() * stat.fragment_size();
         let used = (stat.blocks() - stat.blocks_free()) * stat.fragment_size();
@@ -29,16 +33,35 @@ impl DiskInfo {
         let used = byte_unit::Byte::from_bytes(used as u128);
         let free: Byte = byte_unit::Byte::from_bytes(available as u128);
 
-        let disk_info = DiskInfo { total, used, free };
+        let disk_info = DiskInfo {
+            label: label.to_owned(),
+            total,
+            used,
+            free,
+        };
         Ok(disk_info)
     }
+
+    /// Returns the percentage of the filesystem which is used.
+    pub fn used_percentage(&self) -> f64 {
+        match self.total.get_bytes() {
+            0 => 0.0,
+            total => self.used.get_bytes() as f64 * 100.0 / total as f64,
+        }
+    }
 }
 
 /// Disk usage widget to show current usage and remaining free space
 /// in the mounted filesystem.
+///
+/// Several filesystems can be shown at once with [`DiskUsage::with_mounts`],
+/// each as its own text, and colored as they fill up with
+/// [`DiskUsage::with_colors`].
 pub struct DiskUsage {
     attr: Attributes,
-    path: String,
+    // The label and path of each mount point.
+    mounts: Vec<(String, String)>,
+    colors: Option<Ramp<Color>>,
     render: Option<Box<dyn Fn(DiskInfo) -> String>>,
 }
 
@@ -86,28 +109,75 @@ impl DiskUsage {
         path: String,
         render: Option<Box<dyn Fn(DiskInfo) -> String>>,
     ) -> Self {
-        Self { attr, render, path }
+        Self {
+            attr,
+            mounts: vec![("Disk".to_owned(), path)],
+            colors: None,
+            render,
+        }
+    }
+
+    /// Shows each of the given mount points, as `(label, path)` pairs, e.g.
+    /// `("/".into(), "/".into())` and `("Home".into(), "/home".into())`,
+    /// rather than the one passed to [`DiskUsage::new`].
+    pub fn with_mounts(self, mounts: Vec<(String, String)>) -> Self {
+        Self { mounts, ..self }
+    }
+
+    /// Colors each mount point's text according to the percentage of it
+    /// which is used, e.g. to warn when a filesystem is nearly full.
+    ///
+    /// ```
+    /// # use cnx::text::{Color, Ramp, Role};
+    /// let colors = Ramp::new(vec![
+    ///     (80.0, Color::white()),
+    ///     (90.0, Role::Warn.into()),
+    ///     (100.0, Role::Crit.into()),
+    /// ]);
+    /// ```
+    pub fn with_colors(self, colors: Ramp<Color>) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
     }
 
     fn tick(&self) -> Result<Vec<Text>> {
-        let disk_info = DiskInfo::new(self.path.as_ref())?;
+        self.mounts
+            .iter()
+            .map(|(label, path)| Ok(self.render(DiskInfo::new(&i18n::tr(label), path)?)))
+            .collect()
+    }
+
+    fn render(&self, disk_info: DiskInfo) -> Text {
+        let mut attr = self.attr.clone();
+        if let Some(color) = self
+            .colors
+            .as_ref()
+            .and_then(|colors| colors.get(disk_info.used_percentage()))
+        {
+            attr.fg_color = color.clone();
+        }
+
+        let format = NumberFormat::from_locale();
         let disk_default_str = format!(
-            "Disk: {}/{}",
-            disk_info.used.get_adjusted_unit(ByteUnit::GiB).format(0),
-            disk_info.total.get_adjusted_unit(ByteUnit::GiB).format(0)
+            "{}: {}/{}",
+            disk_info.label,
+            format.bytes(disk_info.used.get_bytes() as u64, ByteUnits::Iec),
+            format.bytes(disk_info.total.get_bytes() as u64, ByteUnits::Iec)
         );
 
         let text: String = self
             .render
             .as_ref()
             .map_or(disk_default_str, |disk| (disk)(disk_info));
-        let texts = vec![Text {
-            attr: self.attr.clone(),
+        Text {
+            attr,
             text,
             stretch: false,
             markup: true,
-        }];
-        Ok(texts)
+            canvas: None,
+        }
     }
 }
 
@@ -0,0 +1,249 @@
+use crate::dbus::{self, Bus};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use tokio_stream::{self as stream, Stream, StreamExt};
+use zbus::{dbus_proxy, CacheProperties, MatchRule, MessageStream, MessageType};
+
+#[dbus_proxy(
+    interface = "org.dunstproject.cmd0",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    gen_blocking = false
+)]
+trait Dunst {
+    #[dbus_proxy(property, name = "paused")]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property, name = "paused")]
+    fn set_paused(&self, paused: bool) -> zbus::Result<()>;
+
+    #[dbus_proxy(property, name = "displayedLength")]
+    fn displayed_length(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property, name = "waitingLength")]
+    fn waiting_length(&self) -> zbus::Result<u32>;
+}
+
+/// The state of dunst, as shown by [`DoNotDisturb`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DunstStatus {
+    /// Whether notifications are paused, i.e. do not disturb is on
+    pub paused: bool,
+    /// The number of notifications which are waiting to be shown until
+    /// notifications are unpaused
+    pub waiting: u32,
+    /// The number of notifications which are being shown
+    pub displayed: u32,
+}
+
+type Render = Box<dyn Fn(&DunstStatus) -> String>;
+
+/// Shows whether dunst's notifications are paused, and how many are waiting
+/// to be shown, e.g. `DND 3`, and left clicking it pauses or unpauses them,
+/// e.g. before sharing the screen.
+///
+/// The widget talks to dunst over the session bus, and is updated as soon as
+/// dunst reports a change, including one made with `dunstctl`. It is shown
+/// in the theme's `Warn` color while notifications are paused, and is hidden
+/// while dunst isn't running.
+pub struct DoNotDisturb {
+    attr: Attributes,
+    render: Option<Render>,
+    services: Option<Services>,
+}
+
+impl DoNotDisturb {
+    /// Creates a new [`DoNotDisturb`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given dunst's [`DunstStatus`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::dunst::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|status: &DunstStatus| match status.paused {
+    ///     true => format!("🔕 {}", status.waiting),
+    ///     false => "🔔".to_owned(),
+    /// });
+    /// cnx.add_widget(DoNotDisturb::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            render,
+            services: None,
+        }
+    }
+
+    fn render(&self, status: Option<DunstStatus>) -> Vec<Text> {
+        let Some(status) = status else {
+            return Vec::new();
+        };
+        let mut attr = self.attr.clone();
+        if status.paused {
+            attr.fg_color = Color::role(Role::Warn);
+        }
+        let text = match &self.render {
+            Some(render) => (render)(&status),
+            None if !status.paused => i18n::tr("Notifications"),
+            None if status.waiting == 0 => i18n::tr("DND"),
+            None => format!("{} {}", i18n::tr("DND"), status.waiting),
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns dunst's status, or `None` if it isn't running.
+async fn status(dunst: &DunstProxy<'_>) -> Result<Option<DunstStatus>> {
+    let paused = match dunst.paused().await {
+        Ok(paused) => paused,
+        // Another notification daemon is running, or none.
+        Err(zbus::Error::MethodError(name, _, _))
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown"
+                || name.as_str() == "org.freedesktop.DBus.Error.UnknownInterface" =>
+        {
+            return Ok(None)
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok(Some(DunstStatus {
+        paused,
+        waiting: dunst.waiting_length().await?,
+        displayed: dunst.displayed_length().await?,
+    }))
+}
+
+// Returns a stream which yields whenever dunst's properties change, or
+// dunst starts or stops, until the connection is closed.
+async fn changes(connection: &zbus::Connection) -> Result<impl Stream<Item = ()>> {
+    let properties = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.Notifications")?
+        .path("/org/freedesktop/Notifications")?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .arg(0, "org.dunstproject.cmd0")?
+        .build();
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, "org.freedesktop.Notifications")?
+        .build();
+    let properties = MessageStream::for_match_rule(properties, connection, None).await?;
+    let owner = MessageStream::for_match_rule(owner, connection, None).await?;
+    // The streams yield an error as the connection closes.
+    Ok(properties
+        .merge(owner)
+        .take_while(|message| message.is_ok())
+        .map(drop))
+}
+
+impl Widget for DoNotDisturb {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks.filter(|click| click.button == MouseButton::Left);
+
+        if let Err(err) = dbus::require(self.services.as_ref(), Bus::Session) {
+            return Ok(capabilities::unavailable(self.attr, err));
+        }
+
+        let mut watches = Box::pin(dbus::pool(self.services.as_ref()).watch(
+            Bus::Session,
+            |connection| async move {
+                // The properties are read again whenever dunst signals a
+                // change, so there is no need to cache them.
+                let dunst = DunstProxy::builder(&connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?;
+                Ok((dunst, Box::pin(changes(&connection).await?)))
+            },
+        ));
+
+        let stream = stream! {
+            // Signals stop when the bus closes the connection, e.g. because
+            // dbus-daemon was restarted, so we start again with the next one.
+            while let Some(watched) = watches.next().await {
+                let (dunst, mut changes) = match watched {
+                    Ok(watched) => watched,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                let mut current = None;
+                loop {
+                    match status(&dunst).await {
+                        Ok(status) => {
+                            current = status;
+                            yield Ok(self.render(status));
+                        }
+                        Err(err) => yield Err(err),
+                    }
+                    tokio::select! {
+                        changed = changes.next() => if changed.is_none() {
+                            break;
+                        },
+                        // The change shows once dunst reports it.
+                        Some(_) = clicks.next() => {
+                            let Some(status) = current else {
+                                continue;
+                            };
+                            if let Err(err) = dunst.set_paused(!status.paused).await {
+                                yield Err(err.into());
+                            }
+                            continue;
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
@@ -0,0 +1,251 @@
+use crate::procfs;
+use anyhow::Result;
+use async_stream::stream;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::time::Duration;
+use tokio::task;
+use tokio::time;
+
+/// How many file descriptors and inotify watches are in use, and their
+/// limits, as shown by [`FdUsage`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DescriptorUsage {
+    /// The number of inotify watches the user's processes have added
+    pub inotify_watches: usize,
+    /// The most inotify watches the user may add, from
+    /// `fs.inotify.max_user_watches`
+    pub max_inotify_watches: Option<u64>,
+    /// The number of inotify instances the user's processes have open
+    pub inotify_instances: usize,
+    /// The most inotify instances the user may have, from
+    /// `fs.inotify.max_user_instances`
+    pub max_inotify_instances: Option<u64>,
+    /// The number of files the watched process has open
+    pub fds: Option<usize>,
+    /// The most files the watched process may open, unless that is unlimited
+    pub max_fds: Option<u64>,
+}
+
+impl DescriptorUsage {
+    /// Returns the largest fraction of any of the limits which is used, from
+    /// 0 to 1.
+    pub fn highest(&self) -> f64 {
+        [
+            fraction(Some(self.inotify_watches), self.max_inotify_watches),
+            fraction(Some(self.inotify_instances), self.max_inotify_instances),
+            fraction(self.fds, self.max_fds),
+        ]
+        .into_iter()
+        .fold(0.0, f64::max)
+    }
+}
+
+fn fraction(used: Option<usize>, max: Option<u64>) -> f64 {
+    match (used, max) {
+        (Some(used), Some(max)) if max > 0 => used as f64 / max as f64,
+        _ => 0.0,
+    }
+}
+
+type Render = Box<dyn Fn(&DescriptorUsage) -> String>;
+
+/// Shows how close the user is to running out of inotify watches or
+/// instances, or a process to running out of file descriptors, e.g.
+/// `inotify 7950/8192`, while any of them is nearly used up.
+///
+/// Widgets such as [`Mail`] and [`Kubernetes`] watch files with inotify,
+/// and stop updating once the user's watches run out, which editors and
+/// file sync tools can use up by themselves. The file descriptors counted
+/// are Cnx's own, unless another process is watched with
+/// [`FdUsage::with_process()`].
+///
+/// The widget is shown in the theme's `Warn` color once 80% of a limit is
+/// used, by default, or `Crit` once all of it is, and is hidden otherwise.
+/// It is updated every 30 seconds by default.
+///
+/// [`Mail`]: super::mail::Mail
+/// [`Kubernetes`]: super::kubernetes::Kubernetes
+pub struct FdUsage {
+    attr: Attributes,
+    pid: u32,
+    threshold: f64,
+    update_interval: Duration,
+    render: Option<Render>,
+}
+
+impl FdUsage {
+    /// Creates a new [`FdUsage`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`DescriptorUsage`], once a
+    /// limit is nearly used up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::fd_usage::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|usage: &DescriptorUsage| {
+    ///     format!("⚠ {:.0}%", usage.highest() * 100.0)
+    /// });
+    /// cnx.add_widget(FdUsage::new(attr, Some(render)).with_threshold(90.0));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            pid: std::process::id(),
+            threshold: 80.0,
+            update_interval: Duration::from_secs(30),
+            render,
+        }
+    }
+
+    /// Counts the file descriptors of the process `pid`, which must be the
+    /// user's own, rather than Cnx's.
+    pub fn with_process(self, pid: u32) -> Self {
+        Self { pid, ..self }
+    }
+
+    /// Sets the percentage of a limit above which the widget is shown. The
+    /// default is 80.
+    pub fn with_threshold(self, threshold: f64) -> Self {
+        Self { threshold, ..self }
+    }
+
+    /// Sets how often to count the descriptors. The default is every 30
+    /// seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, usage: &DescriptorUsage) -> Vec<Text> {
+        let highest = usage.highest();
+        if highest * 100.0 < self.threshold {
+            return Vec::new();
+        }
+        let mut attr = self.attr.clone();
+        attr.fg_color = match highest >= 1.0 {
+            true => Color::role(Role::Crit),
+            false => Color::role(Role::Warn),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| default_text(usage, self.threshold), |x| (x)(usage));
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Lists the limits of which at least `threshold` percent is used.
+fn default_text(usage: &DescriptorUsage, threshold: f64) -> String {
+    let counts = [
+        (
+            "inotify",
+            Some(usage.inotify_watches),
+            usage.max_inotify_watches,
+        ),
+        (
+            "inotify instances",
+            Some(usage.inotify_instances),
+            usage.max_inotify_instances,
+        ),
+        ("fds", usage.fds, usage.max_fds),
+    ];
+    let near: Vec<String> = counts
+        .into_iter()
+        .filter(|(_, used, max)| fraction(*used, *max) * 100.0 >= threshold)
+        .filter_map(|(label, used, max)| Some(format!("{} {}/{}", label, used?, max?)))
+        .collect();
+    near.join(" ")
+}
+
+fn descriptor_usage(pid: u32) -> DescriptorUsage {
+    let (inotify_instances, inotify_watches) = procfs::inotify_usage();
+    DescriptorUsage {
+        inotify_watches,
+        max_inotify_watches: procfs::sysctl("fs/inotify/max_user_watches"),
+        inotify_instances,
+        max_inotify_instances: procfs::sysctl("fs/inotify/max_user_instances"),
+        fds: procfs::open_fds(pid),
+        max_fds: procfs::fd_limit(pid),
+    }
+}
+
+impl Widget for FdUsage {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                // Every file descriptor of every process is looked at.
+                let pid = self.pid;
+                let usage = task::spawn_blocking(move || descriptor_usage(pid)).await;
+                yield usage
+                    .map(|usage| self.render(&usage))
+                    .map_err(anyhow::Error::from);
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn near_limits() {
+        let usage = DescriptorUsage {
+            inotify_watches: 7950,
+            max_inotify_watches: Some(8192),
+            inotify_instances: 12,
+            max_inotify_instances: Some(128),
+            fds: Some(40),
+            max_fds: None,
+        };
+        assert!((usage.highest() - 7950.0 / 8192.0).abs() < 1e-9);
+        assert_eq!(default_text(&usage, 80.0), "inotify 7950/8192");
+        assert_eq!(
+            default_text(&usage, 5.0),
+            "inotify 7950/8192 inotify instances 12/128"
+        );
+        assert_eq!(DescriptorUsage::default().highest(), 0.0);
+
+        let usage = descriptor_usage(std::process::id());
+        assert!(usage.fds.unwrap() > 0);
+        assert!(usage.max_inotify_watches.is_some());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::http::{HttpClient, HttpConfig};
+use crate::state;
+use anyhow::Result;
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use regex::Regex;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::{self as stream, StreamExt};
+
+// The file in which the IDs of seen items are kept between runs.
+const SEEN_STATE: &str = "feeds-seen";
+// How many seen items to remember, which must be more than the feeds have
+// at once for them not to be shown again.
+const MAX_SEEN: usize = 1000;
+
+// An item of a feed.
+#[derive(Clone, Debug, PartialEq)]
+struct Item {
+    // The item's GUID or ID, or else its link or title.
+    id: String,
+    title: String,
+}
+
+/// Shows the newest headline you haven't seen yet from one or more RSS or
+/// Atom feeds, or how many there are.
+///
+/// Left clicking the widget marks the headline shown as seen, showing the
+/// next one, and right clicking it marks all of them as seen. When it shows
+/// the count, either marks all of them as seen. Seen headlines are kept in
+/// `$XDG_STATE_HOME/cnx` between runs. While there are none, the widget is
+/// hidden.
+///
+/// The feeds are fetched every 15 minutes by default. Feeds list their
+/// newest items first, so the headline shown is the first unseen item of the
+/// first feed which has any, in the order the feeds were given.
+pub struct Feeds {
+    attr: Attributes,
+    urls: Vec<String>,
+    update_interval: Duration,
+    max_length: usize,
+    count: bool,
+    http: HttpConfig,
+}
+
+impl Feeds {
+    /// Creates a new [`Feeds`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `urls` - The URLs of the RSS or Atom feeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::feeds::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let urls = vec![
+    ///     "https://blog.rust-lang.org/feed.xml".to_owned(),
+    ///     "https://this-week-in-rust.org/rss.xml".to_owned(),
+    /// ];
+    /// cnx.add_widget(Feeds::new(attr, urls).with_max_length(40));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, urls: Vec<String>) -> Self {
+        Self {
+            attr,
+            urls,
+            update_interval: Duration::from_secs(15 * 60),
+            max_length: 60,
+            count: false,
+            http: HttpConfig::default(),
+        }
+    }
+
+    /// Sets how often to fetch the feeds. The default is every 15 minutes.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets how many characters of the headline to show, after which it is
+    /// cut off with `…`. The default is 60.
+    pub fn with_max_length(self, max_length: usize) -> Self {
+        Self { max_length, ..self }
+    }
+
+    /// Shows how many headlines haven't been seen, rather than the newest of
+    /// them.
+    pub fn with_count(self) -> Self {
+        Self {
+            count: true,
+            ..self
+        }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to fetch the
+    /// feeds.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    // Fetches each feed, keeping the items it had last time if it can't be
+    // fetched, and returns the errors.
+    async fn fetch(&self, client: &HttpClient, feeds: &mut [Vec<Item>]) -> Vec<anyhow::Error> {
+        let mut errors = Vec::new();
+        for (url, items) in self.urls.iter().zip(feeds) {
+            match client.get_text(url).await {
+                Ok(body) => *items = parse_feed(&body),
+                Err(err) => errors.push(err),
+            }
+        }
+        errors
+    }
+
+    fn render(&self, feeds: &[Vec<Item>], seen: &[String]) -> Vec<Text> {
+        let mut unseen = unseen(feeds, seen);
+        let text = match (unseen.next(), self.count) {
+            (None, _) => return Vec::new(),
+            (Some(_), true) => i18n::tr_with("{} new", &(unseen.count() + 1).to_string()),
+            (Some(item), false) => truncate(&item.title, self.max_length),
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the items which haven't been seen, newest first.
+fn unseen<'a>(feeds: &'a [Vec<Item>], seen: &'a [String]) -> impl Iterator<Item = &'a Item> {
+    feeds
+        .iter()
+        .flatten()
+        .filter(move |item| !seen.contains(&item.id))
+}
+
+// Reads the IDs of the items seen in earlier runs, oldest first.
+fn load_seen() -> Vec<String> {
+    state::read(SEEN_STATE)
+        .map(|seen| seen.lines().map(str::to_owned).collect())
+        .unwrap_or_default()
+}
+
+// Adds `ids` to the seen items, forgetting the oldest once there are too
+// many, and saves them.
+fn mark_seen(seen: &mut Vec<String>, ids: Vec<String>) -> Result<()> {
+    // Another bar may have seen items since they were loaded.
+    for id in load_seen().into_iter().chain(ids) {
+        if !seen.contains(&id) {
+            seen.push(id);
+        }
+    }
+    let excess = seen.len().saturating_sub(MAX_SEEN);
+    seen.drain(..excess);
+    state::write(SEEN_STATE, &seen.join("\n"))
+}
+
+// Parses the items of an RSS or Atom feed. Items without a title are
+// skipped.
+fn parse_feed(body: &str) -> Vec<Item> {
+    let item = Regex::new(r"(?s)<(item|entry)[\s>].*?</(item|entry)>").unwrap();
+    let title = Regex::new(r"(?s)<title[^>]*>(.*?)</title>").unwrap();
+    let id = Regex::new(r"(?s)<(guid|id)[^>]*>(.*?)</(guid|id)>").unwrap();
+    // RSS links are text, and Atom links are attributes.
+    let link = Regex::new(r#"(?s)<link>(.*?)</link>|<link[^>]*href="([^"]*)""#).unwrap();
+
+    item.find_iter(body)
+        .filter_map(|item| {
+            let item = item.as_str();
+            let title = unescape(title.captures(item)?.get(1)?.as_str());
+            let id = id
+                .captures(item)
+                .and_then(|c| c.get(2))
+                .or_else(|| {
+                    link.captures(item)
+                        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+                })
+                .map_or_else(|| title.clone(), |id| unescape(id.as_str()));
+            Some(Item { id, title })
+        })
+        .collect()
+}
+
+// Unwraps CDATA and decodes the entities in the text of an element, and
+// collapses any whitespace.
+fn unescape(text: &str) -> String {
+    let text = text.trim();
+    let text = match text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        Some(text) => text.to_owned(),
+        None => {
+            let entity = Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-z]+);").unwrap();
+            entity
+                .replace_all(text, |captures: &regex::Captures| {
+                    let name = &captures[1];
+                    let code = match name.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    match (name, code.and_then(char::from_u32)) {
+                        (_, Some(c)) => c.to_string(),
+                        ("amp", _) => "&".to_owned(),
+                        ("lt", _) => "<".to_owned(),
+                        ("gt", _) => ">".to_owned(),
+                        ("quot", _) => "\"".to_owned(),
+                        ("apos", _) => "'".to_owned(),
+                        _ => captures[0].to_owned(),
+                    }
+                })
+                .into_owned()
+        }
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Cuts `text` off after `max_length` characters, marking that it has been.
+fn truncate(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_owned(),
+    }
+}
+
+impl Widget for Feeds {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let client = self.http.client()?;
+        let mut clicks = clicks.filter(|click| {
+            click.button == MouseButton::Left || click.button == MouseButton::Right
+        });
+        let stream = stream! {
+            let mut seen = load_seen();
+            let mut feeds = vec![Vec::new(); self.urls.len()];
+            loop {
+                for err in self.fetch(&client, &mut feeds).await {
+                    yield Err(err);
+                }
+                yield Ok(self.render(&feeds, &seen));
+
+                let sleep = time::sleep(self.update_interval);
+                tokio::pin!(sleep);
+                loop {
+                    tokio::select! {
+                        _ = &mut sleep => break,
+                        Some(click) = clicks.next() => {
+                            let unseen = unseen(&feeds, &seen).map(|item| item.id.clone());
+                            let ids: Vec<String> = match click.button {
+                                MouseButton::Left if !self.count => unseen.take(1).collect(),
+                                _ => unseen.collect(),
+                            };
+                            if let Err(err) = mark_seen(&mut seen, ids) {
+                                yield Err(err);
+                            }
+                            yield Ok(self.render(&feeds, &seen));
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// Parses `body` as a feed, for the `http_body` fuzz target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(body: &str) {
+    parse_feed(body);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rss() {
+        let body = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel>
+  <title>Example</title>
+  <link>https://example.com/</link>
+  <item>
+    <title>Rust 1.75 &amp; async fn in traits</title>
+    <link>https://example.com/rust-1.75</link>
+    <guid isPermaLink="false">tag:example.com,2023:75</guid>
+  </item>
+  <item>
+    <title><![CDATA[Generics <explained>]]></title>
+    <link>https://example.com/generics</link>
+  </item>
+</channel></rss>"#;
+        assert_eq!(
+            parse_feed(body),
+            vec![
+                Item {
+                    id: "tag:example.com,2023:75".to_owned(),
+                    title: "Rust 1.75 & async fn in traits".to_owned(),
+                },
+                Item {
+                    id: "https://example.com/generics".to_owned(),
+                    title: "Generics <explained>".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn atom() {
+        let body = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Example</title>
+  <id>urn:uuid:feed</id>
+  <entry>
+    <title type="html">It&#39;s
+      here</title>
+    <link rel="alternate" href="https://example.com/here"/>
+    <id>urn:uuid:1</id>
+  </entry>
+  <entry>
+    <title>No ID</title>
+    <link href="https://example.com/no-id"/>
+  </entry>
+</feed>"#;
+        assert_eq!(
+            parse_feed(body),
+            vec![
+                Item {
+                    id: "urn:uuid:1".to_owned(),
+                    title: "It's here".to_owned(),
+                },
+                Item {
+                    id: "https://example.com/no-id".to_owned(),
+                    title: "No ID".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unseen_items() {
+        let item = |id: &str| Item {
+            id: id.to_owned(),
+            title: id.to_uppercase(),
+        };
+        let feeds = vec![vec![item("a"), item("b")], vec![item("c")]];
+        let seen = vec!["a".to_owned(), "c".to_owned()];
+        let unseen: Vec<_> = unseen(&feeds, &seen).collect();
+        assert_eq!(unseen, vec![&item("b")]);
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(truncate("Short", 10), "Short");
+        assert_eq!(truncate("Café au lait", 5), "Café…");
+        assert_eq!(truncate("A long headline", 6), "A long…");
+    }
+}
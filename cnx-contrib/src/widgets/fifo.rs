@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Context as _, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use nix::sys::stat::Mode;
+use nix::unistd::mkfifo;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, BufReader, ReadBuf};
+use tokio_stream::StreamExt;
+
+// The read end of a FIFO, which is also opened for writing, so that it never
+// reaches the end of its input as writers come and go.
+struct Pipe(AsyncFd<File>);
+
+impl AsyncRead for Pipe {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            match guard.try_io(|fd| fd.get_ref().read(buf.initialize_unfilled())) {
+                Ok(read) => {
+                    buf.advance(read?);
+                    return Poll::Ready(Ok(()));
+                }
+                // Spurious wake-up, the pipe has been marked as not ready.
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+type Render = Box<dyn Fn(&str) -> String>;
+
+/// Shows the last line written to a named pipe (FIFO), so that scripts can
+/// push text into the bar, e.g. `echo "Backing up" > ~/.cache/cnx.fifo`.
+///
+/// The FIFO is created if it doesn't exist yet, readable and writable only
+/// by the user. Each line written to it replaces the one shown, and an empty
+/// line hides the widget, which is also hidden until the first line is
+/// written.
+pub struct Fifo {
+    attr: Attributes,
+    path: PathBuf,
+    render: Option<Render>,
+}
+
+impl Fifo {
+    /// Creates a new [`Fifo`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `path` - The FIFO to read lines from.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the last line written, without its
+    /// line ending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::fifo::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Fifo::new(attr, "/home/me/.cache/cnx.fifo".into(), None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, path: PathBuf, render: Option<Render>) -> Self {
+        Self { attr, path, render }
+    }
+
+    fn render(&self, line: &str) -> Vec<Text> {
+        if line.is_empty() {
+            return Vec::new();
+        }
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| untrusted::text(line), |x| (x)(line));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Opens the FIFO at `path`, creating it first if need be.
+fn open(path: &Path) -> Result<Pipe> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => {}
+        Ok(_) => return Err(anyhow!("{} isn't a FIFO", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(Pipe(AsyncFd::new(file)?))
+}
+
+impl Widget for Fifo {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let pipe = open(&self.path)?;
+        let mut lines = Box::pin(untrusted::lines(BufReader::new(pipe), untrusted::MAX_LINE));
+
+        let stream = stream! {
+            yield Ok(Vec::new());
+            while let Some(line) = lines.next().await {
+                match line {
+                    Ok(line) => yield Ok(self.render(&line)),
+                    Err(err) => yield Err(err.into()),
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn pushed_lines() {
+        let dir = std::env::temp_dir().join(format!("cnx-fifo-{}", std::process::id()));
+        let path = dir.join("cnx.fifo");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let pipe = open(&path).unwrap();
+            assert!(fs::metadata(&path).unwrap().file_type().is_fifo());
+            let mut lines = Box::pin(untrusted::lines(BufReader::new(pipe), 16));
+
+            // Lines from one writer and then another are read, as the pipe
+            // is never left without a writer.
+            for line in ["Backing up\n", "Done\n"] {
+                let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+                writer.write_all(line.as_bytes()).unwrap();
+            }
+            assert_eq!(lines.next().await.unwrap().unwrap(), "Backing up");
+            assert_eq!(lines.next().await.unwrap().unwrap(), "Done");
+        });
+
+        // A file which isn't a FIFO is left alone.
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, "").unwrap();
+        assert!(open(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use crate::format::{ByteUnits, NumberFormat};
+use crate::procfs::{self, Process};
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use cnx::xcb::xcb_properties_stream;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::StreamExt;
+use xcb_util::ewmh;
+
+/// The resources used by the focused window's process, as shown by
+/// [`FocusedProcess`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessUsage {
+    /// The name of the process's executable, e.g. `firefox`
+    pub name: String,
+    /// The process's ID
+    pub pid: u32,
+    /// The percentage of one CPU the process and its descendants have used
+    /// since the last update, which is unknown at first
+    pub cpu: Option<f64>,
+    /// The resident memory of the process and its descendants, in bytes
+    pub rss: u64,
+    /// How many processes there are, counting the process itself
+    pub processes: usize,
+}
+
+type Render = Box<dyn Fn(&ProcessUsage) -> String>;
+
+/// Shows the CPU and memory used by the focused window's process, e.g.
+/// `firefox 112% 2.3 GiB`, to find out which window is making the fans
+/// spin.
+///
+/// The process is found from the window's `_NET_WM_PID`, and its usage
+/// includes that of all of its descendants, such as a browser's content
+/// processes. Memory shared between the processes is counted more than
+/// once. As with `top`, a process busy on more than one CPU uses more than
+/// 100%.
+///
+/// The widget is updated when the focus changes, and every 3 seconds by
+/// default. It is hidden while the focused window doesn't give its process
+/// ID, or there is none.
+pub struct FocusedProcess {
+    attr: Attributes,
+    update_interval: Duration,
+    render: Option<Render>,
+}
+
+impl FocusedProcess {
+    /// Creates a new [`FocusedProcess`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`ProcessUsage`] of the focused
+    /// window's process.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::focused_process::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|usage: &ProcessUsage| match usage.cpu {
+    ///     Some(cpu) if cpu >= 50.0 => format!("{} {:.0}%", usage.name, cpu),
+    ///     _ => String::new(),
+    /// });
+    /// cnx.add_widget(FocusedProcess::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            update_interval: Duration::from_secs(3),
+            render,
+        }
+    }
+
+    /// Sets how often to update the usage while the focus doesn't change.
+    /// The default is every 3 seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, usage: Option<ProcessUsage>) -> Vec<Text> {
+        let Some(usage) = usage else {
+            return Vec::new();
+        };
+        let default_text = || {
+            let format = NumberFormat::from_locale();
+            let rss = format.bytes(usage.rss, ByteUnits::Iec);
+            match usage.cpu {
+                Some(cpu) => format!("{} {}% {}", usage.name, format.decimal(cpu, 0), rss),
+                None => format!("{} {}", usage.name, rss),
+            }
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(&usage));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the process ID of the focused window, if it gives one.
+fn focused_pid(conn: &ewmh::Connection, screen_idx: i32) -> Option<u32> {
+    let window = ewmh::get_active_window(conn, screen_idx).get_reply().ok()?;
+    ewmh::get_wm_pid(conn, window).get_reply().ok()
+}
+
+// The CPU times of every process, as of when they were read.
+struct Sample {
+    taken: Instant,
+    cpu_ticks: HashMap<u32, u64>,
+}
+
+// Sums the usage of the process `pid` and its descendants. Their CPU usage
+// is measured since `previous`, skipping any which have started since.
+fn usage(
+    processes: &[Process],
+    pid: u32,
+    previous: Option<&Sample>,
+    now: Instant,
+) -> Option<ProcessUsage> {
+    let tree = procfs::tree(processes, pid);
+    let process = tree.first()?;
+    let cpu = previous.and_then(|previous| {
+        let elapsed = now.duration_since(previous.taken).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let ticks: u64 = tree
+            .iter()
+            .filter_map(|p| Some(p.cpu_ticks.saturating_sub(*previous.cpu_ticks.get(&p.pid)?)))
+            .sum();
+        Some(ticks as f64 / procfs::clock_ticks() as f64 / elapsed * 100.0)
+    });
+    Some(ProcessUsage {
+        name: process.name.clone(),
+        pid,
+        cpu,
+        rss: tree.iter().map(|p| p.rss_pages).sum::<u64>() * procfs::page_size(),
+        processes: tree.len(),
+    })
+}
+
+impl Widget for FocusedProcess {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let screen_idx = 0; // XXX assume
+        let (conn, focus) = xcb_properties_stream(&["_NET_ACTIVE_WINDOW"])
+            .context("Initialising FocusedProcess")?;
+        let ticks = IntervalStream::new(time::interval(self.update_interval)).map(drop);
+        let mut updates = Box::pin(focus.merge(ticks));
+
+        let stream = stream! {
+            let mut previous = None;
+            while updates.next().await.is_some() {
+                let processes = procfs::processes();
+                let now = Instant::now();
+                let usage = focused_pid(&conn, screen_idx)
+                    .and_then(|pid| usage(&processes, pid, previous.as_ref(), now));
+                previous = Some(Sample {
+                    taken: now,
+                    cpu_ticks: processes.iter().map(|p| (p.pid, p.cpu_ticks)).collect(),
+                });
+                yield Ok(self.render(usage));
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn usage_of_tree() {
+        let process = |pid, parent, cpu_ticks| Process {
+            pid,
+            name: format!("p{pid}"),
+            parent,
+            state: 'S',
+            cpu_ticks,
+            rss_pages: 10,
+        };
+        let then = Instant::now();
+        let previous = Sample {
+            taken: then,
+            cpu_ticks: [(10, 1000), (11, 500)].into_iter().collect(),
+        };
+        let ticks = procfs::clock_ticks();
+        // The first process used half a CPU, the second a whole one, and the
+        // third has just started.
+        let processes = [
+            process(10, 1, 1000 + ticks),
+            process(11, 10, 500 + 2 * ticks),
+            process(12, 11, 50),
+            process(20, 1, 0),
+        ];
+        let now = then + Duration::from_secs(2);
+
+        let usage = usage(&processes, 10, Some(&previous), now).unwrap();
+        assert_eq!(usage.name, "p10");
+        assert_eq!(usage.processes, 3);
+        assert_eq!(usage.rss, 30 * procfs::page_size());
+        assert!((usage.cpu.unwrap() - 150.0).abs() < 0.001);
+
+        assert_eq!(super::usage(&processes, 10, None, now).unwrap().cpu, None);
+        assert_eq!(super::usage(&processes, 30, None, now), None);
+    }
+}
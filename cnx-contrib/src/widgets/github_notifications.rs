@@ -0,0 +1,352 @@
+use crate::http::{self, HttpClient, HttpConfig};
+use crate::secret::Secret;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use regex::Regex;
+use reqwest::header::{IF_MODIFIED_SINCE, LAST_MODIFIED, LINK};
+use reqwest::StatusCode;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+use tokio_stream::{self as stream, StreamExt};
+
+// GitHub allows at most 50 notifications per page.
+const NOTIFICATIONS_URL: &str = "https://api.github.com/notifications?per_page=50";
+const NOTIFICATIONS_PAGE: &str = "https://github.com/notifications";
+
+// How long to wait between polls until GitHub says otherwise with the
+// `X-Poll-Interval` header.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The unread GitHub notifications, as shown by [`GithubNotifications`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Notifications {
+    /// How many notifications are unread
+    pub unread: usize,
+    /// How many are unread for each reason, such as `mention` or
+    /// `review_requested`, most first
+    pub reasons: Vec<(String, usize)>,
+}
+
+impl Notifications {
+    fn from_reasons(reasons: impl IntoIterator<Item = String>) -> Self {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for reason in reasons {
+            match counts.iter_mut().find(|(r, _)| *r == reason) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((reason, 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Notifications {
+            unread: counts.iter().map(|(_, count)| count).sum(),
+            reasons: counts,
+        }
+    }
+}
+
+/// Shows how many GitHub notifications are unread, optionally broken down
+/// by why you got them.
+///
+/// The notifications are fetched with a personal access token with the
+/// `notifications` scope (or, for a fine-grained token, no particular
+/// permission). GitHub is polled as often as its `X-Poll-Interval` header
+/// allows, usually once a minute, and only counts against your rate limit
+/// when something has changed.
+///
+/// Left clicking the widget opens <https://github.com/notifications>, with
+/// `xdg-open` by default.
+pub struct GithubNotifications {
+    attr: Attributes,
+    token: Secret,
+    breakdown: bool,
+    open_command: String,
+    http: HttpConfig,
+    render: Option<Box<dyn Fn(Notifications) -> String>>,
+}
+
+impl GithubNotifications {
+    /// Creates a new [`GithubNotifications`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `token` - A GitHub personal access token. Use a [`Secret`] to look
+    /// it up e.g. in an environment variable, rather than writing it in your
+    /// configuration.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`Notifications`] is the count of unread
+    /// notifications.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::secret::Secret;
+    /// # use cnx_contrib::widgets::github_notifications::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let token = Secret::Env("GITHUB_TOKEN".into());
+    /// cnx.add_widget(GithubNotifications::new(attr, token, None).with_breakdown());
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        token: impl Into<Secret>,
+        render: Option<Box<dyn Fn(Notifications) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            token: token.into(),
+            breakdown: false,
+            open_command: "xdg-open".to_owned(),
+            http: HttpConfig::default(),
+            render,
+        }
+    }
+
+    /// Shows how many notifications are unread for each reason, e.g.
+    /// `GitHub 3: 2 review requested, 1 mention`, rather than just the
+    /// total.
+    pub fn with_breakdown(self) -> Self {
+        Self {
+            breakdown: true,
+            ..self
+        }
+    }
+
+    /// Sets the program run to open the notifications page, which is given
+    /// its URL as its argument. The default is `xdg-open`.
+    pub fn with_open_command(self, open_command: String) -> Self {
+        Self {
+            open_command,
+            ..self
+        }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to reach
+    /// GitHub.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    // Fetches the unread notifications, unless none have changed since
+    // `last_modified`, and returns them with how long to wait before polling
+    // again.
+    async fn poll(
+        &self,
+        client: &HttpClient,
+        last_modified: &mut Option<String>,
+    ) -> Result<(Option<Notifications>, Duration)> {
+        let token = format!("Bearer {}", self.token.get().await?);
+        let headers = [
+            ("Authorization", token.as_str()),
+            ("Accept", "application/vnd.github+json"),
+            ("X-GitHub-Api-Version", "2022-11-28"),
+        ];
+
+        let mut first = headers.to_vec();
+        if let Some(last_modified) = last_modified.as_deref() {
+            first.push((IF_MODIFIED_SINCE.as_str(), last_modified));
+        }
+        let response = client.get(NOTIFICATIONS_URL, &first).await?;
+        let interval = response
+            .headers()
+            .get("X-Poll-Interval")
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .map_or(DEFAULT_POLL_INTERVAL, Duration::from_secs);
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok((None, interval));
+        }
+        *last_modified = response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        let mut reasons = Vec::new();
+        let mut response = response;
+        loop {
+            let next = response
+                .headers()
+                .get(LINK)
+                .and_then(|value| next_page(value.to_str().ok()?));
+            let body = http::text(response).await?;
+            reasons.extend(parse_reasons(&body)?);
+            match next {
+                Some(next) => response = client.get(&next, &headers).await?,
+                None => break,
+            }
+        }
+        Ok((Some(Notifications::from_reasons(reasons)), interval))
+    }
+
+    async fn open(&self) -> Result<()> {
+        Command::new(&self.open_command)
+            .arg(NOTIFICATIONS_PAGE)
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {}", self.open_command))?;
+        Ok(())
+    }
+
+    fn render(&self, notifications: Notifications) -> Vec<Text> {
+        let default_text = || {
+            let total = i18n::tr_with("GitHub {}", &notifications.unread.to_string());
+            if !self.breakdown || notifications.reasons.is_empty() {
+                return total;
+            }
+            let reasons = notifications
+                .reasons
+                .iter()
+                .map(|(reason, count)| format!("{count} {}", i18n::tr(&reason.replace('_', " "))))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{total}: {reasons}")
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(notifications.clone()));
+
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the reason for each notification in a page of them, e.g.
+// `[{"id":"1","unread":true,"reason":"mention",...},...]`.
+fn parse_reasons(body: &str) -> Result<Vec<String>> {
+    let re = Regex::new(r#""reason"\s*:\s*"(\w+)""#)?;
+    Ok(re
+        .captures_iter(body)
+        .map(|captures| captures[1].to_owned())
+        .collect())
+}
+
+// Returns the URL of the next page from a `Link` header, e.g.
+// `<https://api.github.com/notifications?page=2>; rel="next", <...>; rel="last"`.
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|link| {
+        let (url, rel) = link.split_once(';')?;
+        (rel.trim() == r#"rel="next""#).then(|| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_owned()
+        })
+    })
+}
+
+impl Widget for GithubNotifications {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let client = self.http.client()?;
+        let mut clicks = clicks.filter(|click| click.button == MouseButton::Left);
+        let stream = stream! {
+            let mut last_modified = None;
+            let mut interval = DEFAULT_POLL_INTERVAL;
+            loop {
+                match self.poll(&client, &mut last_modified).await {
+                    Ok((notifications, next)) => {
+                        interval = next;
+                        if let Some(notifications) = notifications {
+                            yield Ok(self.render(notifications));
+                        }
+                    }
+                    Err(err) => yield Err(err),
+                }
+
+                let sleep = time::sleep(interval);
+                tokio::pin!(sleep);
+                loop {
+                    tokio::select! {
+                        _ = &mut sleep => break,
+                        Some(_) = clicks.next() => {
+                            if let Err(err) = self.open().await {
+                                yield Err(err);
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// Parses `body` as a page of notifications, and as a `Link` header, for the
+// `http_body` fuzz target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(body: &str) {
+    let _ = parse_reasons(body);
+    next_page(body);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn count_reasons() {
+        let body = r#"[
+            {"id":"1","unread":true,"reason":"mention","subject":{"title":"Fix","type":"PullRequest"}},
+            {"id":"2","unread":true,"reason":"review_requested","subject":{"title":"Add","type":"PullRequest"}},
+            {"id":"3","unread":true,"reason":"review_requested","subject":{"title":"Bump","type":"PullRequest"}}
+        ]"#;
+        let notifications = Notifications::from_reasons(parse_reasons(body).unwrap());
+        assert_eq!(notifications.unread, 3);
+        assert_eq!(
+            notifications.reasons,
+            vec![
+                ("review_requested".to_owned(), 2),
+                ("mention".to_owned(), 1)
+            ]
+        );
+        assert_eq!(
+            Notifications::from_reasons(parse_reasons("[]").unwrap()),
+            Notifications::default()
+        );
+    }
+
+    #[test]
+    fn link_header() {
+        let link = r#"<https://api.github.com/notifications?per_page=50&page=2>; rel="next", <https://api.github.com/notifications?per_page=50&page=3>; rel="last""#;
+        assert_eq!(
+            next_page(link).as_deref(),
+            Some("https://api.github.com/notifications?per_page=50&page=2")
+        );
+        let link = r#"<https://api.github.com/notifications?per_page=50&page=1>; rel="prev""#;
+        assert_eq!(next_page(link), None);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::format::{ByteUnits, NumberFormat};
+use anyhow::{Context, Result};
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::units::Units;
+use cnx::widgets::{Widget, WidgetStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::StreamExt;
+
+mod amdgpu;
+mod nvml;
+
+/// Where [`Gpu`] reads the GPU's statistics from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Backend {
+    /// NVIDIA's management library, NVML, for the GPU at the given index, as
+    /// numbered by `nvidia-smi`. NVML (`libnvidia-ml.so.1`) is installed with
+    /// NVIDIA's driver, and is loaded when the widget starts.
+    Nvml(u32),
+    /// The files of the amdgpu driver in sysfs, for the given card, e.g.
+    /// `card0` for `/sys/class/drm/card0`.
+    Amdgpu(String),
+}
+
+/// The statistics of a GPU, as shown by [`Gpu`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuInfo {
+    /// The percentage of time the GPU was busy
+    pub utilization: f64,
+    /// The video memory in use, in bytes
+    pub vram_used: u64,
+    /// The total video memory, in bytes
+    pub vram_total: u64,
+    /// The temperature of the GPU in °C, if it reports one
+    pub temperature: Option<f64>,
+}
+
+// An opened backend.
+enum Source {
+    Nvml(nvml::Device),
+    Amdgpu(PathBuf),
+}
+
+impl Source {
+    fn read(&self) -> Result<GpuInfo> {
+        match self {
+            Source::Nvml(device) => device.read(),
+            Source::Amdgpu(device) => amdgpu::read(device),
+        }
+    }
+}
+
+/// Shows the utilization, video memory and temperature of a GPU.
+///
+/// NVIDIA GPUs are read through NVML and AMD GPUs through sysfs; see
+/// [`Backend`]. The statistics are updated every 2 seconds by default. The
+/// temperature is shown in the temperature unit of the bar's [`Units`].
+pub struct Gpu {
+    attr: Attributes,
+    backend: Backend,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(GpuInfo) -> String>>,
+    services: Option<Services>,
+}
+
+impl Gpu {
+    /// Creates a new [`Gpu`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `backend` - The GPU to show and where its statistics are read
+    /// from, e.g. `Backend::Nvml(0)` for the first NVIDIA GPU.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`GpuInfo`] is the GPU's latest statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::gpu::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|info: GpuInfo| {
+    ///     format!(
+    ///         "<span foreground=\"#808080\">GPU</span> {:.0}%",
+    ///         info.utilization
+    ///     )
+    /// });
+    /// cnx.add_widget(Gpu::new(attr, Backend::Amdgpu("card0".to_owned()), Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        backend: Backend,
+        render: Option<Box<dyn Fn(GpuInfo) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            backend,
+            update_interval: Duration::from_secs(2),
+            render,
+            services: None,
+        }
+    }
+
+    /// Sets how often the statistics are updated. The default is every 2
+    /// seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn tick(&self, source: &Source, units: &Units) -> Result<Vec<Text>> {
+        let info = source.read()?;
+        let default_text = || {
+            let format = NumberFormat::from_locale();
+            let text = format!(
+                "GPU {:.0}% {}/{}",
+                info.utilization,
+                format.bytes(info.vram_used, ByteUnits::Iec),
+                format.bytes(info.vram_total, ByteUnits::Iec)
+            );
+            match info.temperature {
+                Some(temperature) => format!(
+                    "{text} {:.0}{}",
+                    units.temperature(temperature),
+                    units.temperature_symbol()
+                ),
+                None => text,
+            }
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(info));
+
+        Ok(vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }])
+    }
+}
+
+impl Widget for Gpu {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let source = match &self.backend {
+            Backend::Nvml(index) => {
+                Source::Nvml(nvml::Device::open(*index).context("Initialising Gpu")?)
+            }
+            Backend::Amdgpu(card) => {
+                Source::Amdgpu(PathBuf::from("/sys/class/drm").join(card).join("device"))
+            }
+        };
+
+        let interval = time::interval(self.update_interval);
+        let units = self
+            .services
+            .as_ref()
+            .map(Units::shared)
+            .unwrap_or_default();
+        let stream = IntervalStream::new(interval).map(move |_| self.tick(&source, &units));
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
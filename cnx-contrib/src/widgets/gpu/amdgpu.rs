@@ -0,0 +1,36 @@
+use super::GpuInfo;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+// Returns the temperature of the GPU's edge, from its hwmon chip, in °C.
+fn temperature(hwmon: &Path) -> Option<f64> {
+    fs::read_dir(hwmon)
+        .ok()?
+        .flatten()
+        .find_map(|chip| {
+            let millidegrees = fs::read_to_string(chip.path().join("temp1_input")).ok()?;
+            millidegrees.trim().parse::<f64>().ok()
+        })
+        .map(|millidegrees| millidegrees / 1000.0)
+}
+
+/// Reads the statistics of the GPU whose device directory is `device`, e.g.
+/// `/sys/class/drm/card0/device`.
+pub fn read(device: &Path) -> Result<GpuInfo> {
+    let value = |file: &str| -> Result<u64> {
+        let path = device.join(file);
+        let value = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid value in {}", path.display()))
+    };
+    Ok(GpuInfo {
+        utilization: value("gpu_busy_percent")? as f64,
+        vram_used: value("mem_info_vram_used")?,
+        vram_total: value("mem_info_vram_total")?,
+        temperature: temperature(&device.join("hwmon")),
+    })
+}
@@ -0,0 +1,121 @@
+use super::GpuInfo;
+use crate::dylib::Library;
+use anyhow::{anyhow, Result};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::ptr;
+
+// See `nvml.h` in NVIDIA's CUDA toolkit.
+type Return = c_int;
+type DeviceHandle = *mut c_void;
+const NVML_SUCCESS: Return = 0;
+const NVML_TEMPERATURE_GPU: c_int = 0;
+
+#[repr(C)]
+#[derive(Default)]
+struct Utilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Memory {
+    total: u64,
+    free: u64,
+    used: u64,
+}
+
+type ErrorString = unsafe extern "C" fn(Return) -> *const c_char;
+
+// Returns NVML's description of the error `ret`.
+fn message(error_string: ErrorString, ret: Return) -> String {
+    unsafe { CStr::from_ptr(error_string(ret)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A GPU, queried through NVML.
+pub struct Device {
+    handle: DeviceHandle,
+    error_string: ErrorString,
+    utilization_rates: unsafe extern "C" fn(DeviceHandle, *mut Utilization) -> Return,
+    memory_info: unsafe extern "C" fn(DeviceHandle, *mut Memory) -> Return,
+    temperature: unsafe extern "C" fn(DeviceHandle, c_int, *mut c_uint) -> Return,
+    shutdown: unsafe extern "C" fn() -> Return,
+    // Dropped, and so unloaded, after NVML is shut down.
+    _library: Library,
+}
+
+impl Device {
+    /// Opens the GPU at `index`, as numbered by `nvidia-smi`.
+    pub fn open(index: u32) -> Result<Device> {
+        let library = Library::open("libnvidia-ml.so.1", "is installed with NVIDIA's driver")?;
+        unsafe {
+            let init: unsafe extern "C" fn() -> Return = library.symbol(b"nvmlInit_v2\0")?;
+            let handle_by_index: unsafe extern "C" fn(c_uint, *mut DeviceHandle) -> Return =
+                library.symbol(b"nvmlDeviceGetHandleByIndex_v2\0")?;
+            let error_string: ErrorString = library.symbol(b"nvmlErrorString\0")?;
+            let utilization_rates = library.symbol(b"nvmlDeviceGetUtilizationRates\0")?;
+            let memory_info = library.symbol(b"nvmlDeviceGetMemoryInfo\0")?;
+            let temperature = library.symbol(b"nvmlDeviceGetTemperature\0")?;
+            let shutdown = library.symbol(b"nvmlShutdown\0")?;
+
+            let ret = init();
+            if ret != NVML_SUCCESS {
+                return Err(anyhow!(
+                    "Failed to initialise NVML: {}",
+                    message(error_string, ret)
+                ));
+            }
+            // From here on, dropping the device shuts NVML down.
+            let mut device = Device {
+                handle: ptr::null_mut(),
+                error_string,
+                utilization_rates,
+                memory_info,
+                temperature,
+                shutdown,
+                _library: library,
+            };
+            let ret = handle_by_index(index, &mut device.handle);
+            device.check(ret)?;
+            Ok(device)
+        }
+    }
+
+    fn check(&self, ret: Return) -> Result<()> {
+        match ret {
+            NVML_SUCCESS => Ok(()),
+            _ => Err(anyhow!("NVML error: {}", message(self.error_string, ret))),
+        }
+    }
+
+    /// Reads the GPU's current statistics.
+    pub fn read(&self) -> Result<GpuInfo> {
+        let mut utilization = Utilization::default();
+        let mut memory = Memory::default();
+        let mut temperature = 0;
+        unsafe {
+            self.check((self.utilization_rates)(self.handle, &mut utilization))?;
+            self.check((self.memory_info)(self.handle, &mut memory))?;
+            self.check((self.temperature)(
+                self.handle,
+                NVML_TEMPERATURE_GPU,
+                &mut temperature,
+            ))?;
+        }
+        Ok(GpuInfo {
+            utilization: f64::from(utilization.gpu),
+            vram_used: memory.used,
+            vram_total: memory.total,
+            temperature: Some(f64::from(temperature)),
+        })
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe { (self.shutdown)() };
+    }
+}
@@ -0,0 +1,161 @@
+use crate::sandbox::Sandbox;
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities::Capabilities;
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+
+/// Shows a greeting for a while after the bar starts, and then hides itself.
+///
+/// The greeting can be fixed text, or the output of a command such as
+/// `fortune -s` for a different quote each time.
+pub struct Greeting {
+    attr: Attributes,
+    greeting: String,
+    command: Option<String>,
+    duration: Duration,
+    sandbox: Option<Sandbox>,
+    services: Option<Services>,
+}
+
+impl Greeting {
+    /// Creates a new [`Greeting`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `greeting` - The text to show, which may contain Pango markup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::greeting::*;
+    /// # use anyhow::Result;
+    /// # use std::time::Duration;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let greeting = Greeting::new(attr, "Hello!".into())
+    ///     .with_command("fortune -s -n 80".into())
+    ///     .with_duration(Duration::from_secs(30));
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(greeting);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, greeting: String) -> Self {
+        Self {
+            attr,
+            greeting,
+            command: None,
+            duration: Duration::from_secs(10),
+            sandbox: None,
+            services: None,
+        }
+    }
+
+    /// Shows the output of `command`, run with `sh -c`, instead of the
+    /// greeting. The greeting is still shown if the command fails or has no
+    /// output.
+    pub fn with_command(self, command: String) -> Self {
+        Self {
+            command: Some(command),
+            ..self
+        }
+    }
+
+    /// Sets how long the greeting is shown for. The default is ten seconds.
+    pub fn with_duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Runs the command in `sandbox`. By default, it runs in a [`Sandbox`]
+    /// which kills it if it is still running when the greeting is hidden.
+    pub fn with_sandbox(self, sandbox: Sandbox) -> Self {
+        Self {
+            sandbox: Some(sandbox),
+            ..self
+        }
+    }
+
+    // Runs the command, if any, returning its output if it succeeded.
+    async fn command_output(&self) -> Result<Option<String>> {
+        let command = match &self.command {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        let sandbox = match &self.sandbox {
+            Some(sandbox) => sandbox.clone(),
+            None => Sandbox::default().with_timeout(self.duration),
+        };
+        let capabilities = self
+            .services
+            .as_ref()
+            .map_or_else(Capabilities::default, Capabilities::shared);
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        let output = sandbox.output(process, &capabilities).await?;
+        // Fortunes are often several lines long, but the bar only has room
+        // for one.
+        let text = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok((output.status.success() && !text.is_empty()).then_some(text))
+    }
+}
+
+impl Widget for Greeting {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            let output = match self.command_output().await {
+                Ok(output) => output,
+                Err(err) => {
+                    yield Err(err);
+                    None
+                }
+            };
+            // Only the greeting is markup, as a command's output is unlikely
+            // to be.
+            let (text, markup) = match output {
+                Some(output) => (output, false),
+                None => (self.greeting.clone(), true),
+            };
+            yield Ok(vec![Text {
+                attr: self.attr.clone(),
+                text,
+                stretch: false,
+                markup,
+                canvas: None,
+            }]);
+
+            time::sleep(self.duration).await;
+            yield Ok(Vec::new());
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::io;
+use tokio_stream as stream;
+
+/// How [`HostBadge`] shows a host: with a label such as `prod-jumpbox`
+/// rather than its hostname, in a color of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Badge {
+    /// The text to show, or `None` for the hostname
+    pub label: Option<String>,
+    /// The color of the badge's background
+    pub color: Color,
+}
+
+/// Shows the hostname, or a label for the host's role, on a background
+/// color of the host's own.
+///
+/// This is meant for sharing one configuration between several machines, so
+/// that it is obvious at a glance, even in a screen share, which one you are
+/// looking at. Hosts are matched by their hostname against patterns, in
+/// which `*` matches anything, e.g. `prod-*`. The first pattern to match
+/// gives the host's [`Badge`]; a host which matches none is shown with the
+/// widget's own attributes.
+///
+/// A badge's color may be a [`Color::role()`], e.g. `Role::Crit` for
+/// production hosts, in which case it follows the bar's [`Theme`].
+///
+/// [`Theme`]: cnx::text::Theme
+pub struct HostBadge {
+    attr: Attributes,
+    hosts: Vec<(String, Badge)>,
+    short: bool,
+}
+
+impl HostBadge {
+    /// Creates a new [`HostBadge`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::host_badge::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let badge = HostBadge::new(attr)
+    ///     .with_host("prod-*", Some("prod-jumpbox".into()), Color::role(Role::Crit))
+    ///     .with_host("staging-*", None, Color::role(Role::Warn))
+    ///     .with_host("*", None, Color::from_hex("#1e1e2e"));
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(badge);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes) -> Self {
+        Self {
+            attr,
+            hosts: Vec::new(),
+            short: false,
+        }
+    }
+
+    /// Shows hosts whose hostname matches `pattern` with `label` (or their
+    /// hostname, if it is `None`) on a background of `color`. Patterns are
+    /// tried in the order they were added.
+    pub fn with_host(mut self, pattern: &str, label: Option<String>, color: Color) -> Self {
+        self.hosts
+            .push((pattern.to_owned(), Badge { label, color }));
+        self
+    }
+
+    /// Shows only the first part of the hostname, e.g. `web1` rather than
+    /// `web1.example.com`. Patterns are still matched against the whole
+    /// hostname.
+    pub fn with_short_hostname(self) -> Self {
+        Self {
+            short: true,
+            ..self
+        }
+    }
+
+    fn render(&self, hostname: &str) -> Vec<Text> {
+        let badge = self
+            .hosts
+            .iter()
+            .find(|(pattern, _)| matches(pattern, hostname))
+            .map(|(_, badge)| badge);
+        let name = match self.short {
+            true => hostname.split('.').next().unwrap_or(hostname),
+            false => hostname,
+        };
+        let mut attr = self.attr.clone();
+        let text = match badge {
+            Some(badge) => {
+                attr.bg_color = Some(badge.color.clone());
+                badge.label.clone().unwrap_or_else(|| name.to_owned())
+            }
+            None => name.to_owned(),
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+// Returns whether `hostname` matches `pattern`, in which `*` matches any
+// number of characters.
+fn matches(pattern: &str, hostname: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == hostname,
+        Some((prefix, rest)) => {
+            let Some(hostname) = hostname.strip_prefix(prefix) else {
+                return false;
+            };
+            // Try each place the rest of the pattern could start.
+            hostname
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([hostname.len()])
+                .any(|i| matches(rest, &hostname[i..]))
+        }
+    }
+}
+
+fn hostname() -> Result<String> {
+    let mut buf = [0u8; 256];
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    // The name may be truncated without a terminating NUL.
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+impl Widget for HostBadge {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let text = self.render(&hostname()?);
+        Ok(Box::pin(stream::once(Ok(text))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        assert!(matches("web1", "web1"));
+        assert!(!matches("web1", "web10"));
+        assert!(matches("prod-*", "prod-db1"));
+        assert!(!matches("prod-*", "staging-db1"));
+        assert!(matches("*.example.com", "web1.example.com"));
+        assert!(matches("db*-*", "db1-eu"));
+        assert!(!matches("db*-*", "db1"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn badges() {
+        let attr = Attributes {
+            font: cnx::text::Font::new("Sans 10"),
+            fg_color: Color::white(),
+            bg_color: None,
+            padding: cnx::text::Padding::new(0.0, 0.0, 0.0, 0.0),
+        };
+        let badge = HostBadge::new(attr)
+            .with_host("prod-*", Some("prod-jumpbox".into()), Color::red())
+            .with_host("*", None, Color::blue())
+            .with_short_hostname();
+
+        let texts = badge.render("prod-jump1.example.com");
+        assert_eq!(texts[0].text, "prod-jumpbox");
+        assert_eq!(texts[0].attr.bg_color, Some(Color::red()));
+        let texts = badge.render("laptop.example.com");
+        assert_eq!(texts[0].text, "laptop");
+        assert_eq!(texts[0].attr.bg_color, Some(Color::blue()));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::http::{self, HttpClient, HttpConfig};
+use crate::secret::Secret;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use std::time::Duration;
+use tokio::time;
+
+pub use serde_json::Value;
+
+type Render = Box<dyn Fn(&Value) -> String>;
+
+/// Shows a value from a JSON API, polled on an interval, e.g. a temperature
+/// from a home automation hub or the status of the latest CI build. This
+/// covers one-off web integrations which don't need a widget of their own.
+///
+/// The value is picked out of the response with a [JSON Pointer], e.g.
+/// `/data/0/status` for the `status` of the first item of the `data` array.
+/// By default, a string is shown as it is, and anything else as JSON, and the
+/// widget is hidden while the value is `null`.
+///
+/// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+pub struct HttpJson {
+    attr: Attributes,
+    url: String,
+    pointer: String,
+    headers: Vec<(String, Secret)>,
+    update_interval: Duration,
+    http: HttpConfig,
+    render: Option<Render>,
+}
+
+impl HttpJson {
+    /// Creates a new [`HttpJson`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `url` - The URL to fetch the JSON from.
+    ///
+    /// * `pointer` - The JSON Pointer of the value to show, or `""` for the
+    /// whole response.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the value the pointer refers to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::secret::Secret;
+    /// # use cnx_contrib::widgets::http_json::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|value: &Value| match value.as_f64() {
+    ///     Some(celsius) => format!("Living room {celsius:.1}°C"),
+    ///     None => "Living room ?".to_owned(),
+    /// });
+    /// let temperature = HttpJson::new(
+    ///     attr,
+    ///     "http://homeassistant.local:8123/api/states/sensor.living_room".into(),
+    ///     "/state".into(),
+    ///     Some(render),
+    /// )
+    /// .with_header("Authorization", Secret::Env("HASS_AUTHORIZATION".into()));
+    /// cnx.add_widget(temperature);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, url: String, pointer: String, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            url,
+            pointer,
+            headers: Vec::new(),
+            update_interval: Duration::from_secs(5 * 60),
+            http: HttpConfig::default(),
+            render,
+        }
+    }
+
+    /// Sends the header `name` with each request, e.g. `Authorization` with
+    /// an API token. The value is looked up before each request.
+    pub fn with_header(mut self, name: &str, value: impl Into<Secret>) -> Self {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Sets how often to fetch the value. The default is every 5 minutes.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to fetch the
+    /// JSON.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    async fn fetch(&self, client: &HttpClient) -> Result<Value> {
+        let mut values = Vec::with_capacity(self.headers.len());
+        for (_, secret) in &self.headers {
+            values.push(secret.get().await?);
+        }
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .zip(&values)
+            .map(|((name, _), value)| (name.as_str(), value.as_str()))
+            .collect();
+        let response = client.get(&self.url, &headers).await?;
+        let body = http::text(response).await?;
+        extract(&body, &self.pointer).with_context(|| format!("Invalid response from {}", self.url))
+    }
+
+    fn render(&self, value: &Value) -> Vec<Text> {
+        let text = match (&self.render, value) {
+            (Some(render), _) => (render)(value),
+            (None, Value::Null) => return Vec::new(),
+            (None, Value::String(text)) => untrusted::text(text),
+            (None, value) => untrusted::text(&value.to_string()),
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Parses `body` as JSON and returns the value `pointer` refers to.
+fn extract(body: &str, pointer: &str) -> Result<Value> {
+    let mut json: Value = serde_json::from_str(body)?;
+    json.pointer_mut(pointer)
+        .map(Value::take)
+        .ok_or_else(|| anyhow!("No value at {}", pointer))
+}
+
+// Parses `body` as a response, for the `http_body` fuzz target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(body: &str) {
+    let _ = extract(body, "/data/0/status");
+}
+
+impl Widget for HttpJson {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let client = self.http.client()?;
+        let stream = stream! {
+            loop {
+                match self.fetch(&client).await {
+                    Ok(value) => yield Ok(self.render(&value)),
+                    Err(err) => yield Err(err),
+                }
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extracts() {
+        let body = r#"{"data":[{"status":"passed","duration":93.5}],"next":null}"#;
+        assert_eq!(
+            extract(body, "/data/0/status").unwrap(),
+            Value::from("passed")
+        );
+        assert_eq!(
+            extract(body, "/data/0/duration").unwrap(),
+            Value::from(93.5)
+        );
+        assert_eq!(extract(body, "/next").unwrap(), Value::Null);
+        assert_eq!(extract("42", "").unwrap(), Value::from(42));
+        assert!(extract(body, "/data/1/status").is_err());
+        assert!(extract("<html>", "").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use super::network_link::{
+    parse_address, IFADDRMSG_LEN, RTM_GETADDR, RTNLGRP_IPV4_IFADDR, RTNLGRP_IPV6_IFADDR,
+};
+use crate::netlink::{attr, Socket};
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use nix::net::if_::if_nametoindex;
+use std::net::IpAddr;
+use tokio_stream::{self as stream, StreamExt};
+
+// See `include/uapi/linux/rtnetlink.h` in the kernel sources.
+const RTM_GETROUTE: u16 = 26;
+const RTNLGRP_IPV4_ROUTE: u32 = 7;
+const RTNLGRP_IPV6_ROUTE: u32 = 11;
+const RTA_OIF: u16 = 4;
+const RTA_PRIORITY: u16 = 6;
+const RTA_TABLE: u16 = 15;
+const RT_TABLE_MAIN: u8 = 254;
+const RTN_UNICAST: u8 = 1;
+
+// The size of `struct rtmsg`.
+const RTMSG_LEN: usize = 12;
+
+/// Which of an interface's addresses [`IpAddress`] shows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Family {
+    /// Its IPv4 address
+    Ipv4,
+    /// Its IPv6 address
+    Ipv6,
+    /// Its IPv4 address, or its IPv6 address if it has no IPv4 address
+    Any,
+}
+
+/// Shows the IP address of a network interface, or of the interface the
+/// default route goes through.
+///
+/// This asks the kernel over rtnetlink, and is updated as soon as an address
+/// is added or removed, or the default route changes, e.g. when switching
+/// from Ethernet to Wi-Fi.
+///
+/// Only global addresses are shown. With [`IpAddress::with_obfuscation`], the
+/// end of the address is hidden, e.g. `192.168.*.*`, so that it can be
+/// shown while streaming or sharing the screen.
+pub struct IpAddress {
+    attr: Attributes,
+    interface: Option<String>,
+    family: Family,
+    obfuscate: bool,
+}
+
+impl IpAddress {
+    /// Creates a new [`IpAddress`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `interface` - The name of the network interface, e.g. `enp3s0`, or
+    /// `None` for whichever the default route goes through.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::ip_address::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(IpAddress::new(attr.clone(), None));
+    /// cnx.add_widget(IpAddress::new(attr, Some("wg0".into())).with_family(Family::Ipv6));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, interface: Option<String>) -> Self {
+        Self {
+            attr,
+            interface,
+            family: Family::Any,
+            obfuscate: false,
+        }
+    }
+
+    /// Sets which of the interface's addresses to show. The default is its
+    /// IPv4 address, or its IPv6 address if it has no IPv4 address.
+    pub fn with_family(self, family: Family) -> Self {
+        Self { family, ..self }
+    }
+
+    /// Hides the end of the address: all but the first two parts of an IPv4
+    /// address, and all but the first two groups of an IPv6 address.
+    pub fn with_obfuscation(self) -> Self {
+        Self {
+            obfuscate: true,
+            ..self
+        }
+    }
+
+    // Asks the kernel for the address to show, if the interface has one.
+    fn address(&self, socket: &mut Socket) -> Result<Option<IpAddr>> {
+        let index = match &self.interface {
+            Some(interface) => match if_nametoindex(interface.as_str()) {
+                Ok(index) => index,
+                // The interface doesn't exist, at least not yet.
+                Err(_) => return Ok(None),
+            },
+            None => match default_route(socket)? {
+                Some(index) => index,
+                None => return Ok(None),
+            },
+        };
+
+        // The kernel doesn't filter address dumps by interface.
+        let ifaddrmsg = vec![0; IFADDRMSG_LEN];
+        let addresses: Vec<IpAddr> = socket
+            .request(RTM_GETADDR, libc::NLM_F_DUMP as u16, &ifaddrmsg)?
+            .iter()
+            .filter_map(|reply| parse_address(reply, index))
+            .collect();
+        let ipv4 = || addresses.iter().find(|address| address.is_ipv4());
+        let ipv6 = || addresses.iter().find(|address| address.is_ipv6());
+        Ok(match self.family {
+            Family::Ipv4 => ipv4(),
+            Family::Ipv6 => ipv6(),
+            Family::Any => ipv4().or_else(ipv6),
+        }
+        .copied())
+    }
+
+    fn render(&self, address: Option<IpAddr>) -> Vec<Text> {
+        let text = match address {
+            Some(address) if self.obfuscate => obfuscate(address),
+            Some(address) => address.to_string(),
+            None => i18n::tr("No address"),
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the index of the interface the default route goes through,
+// preferring IPv4 routes, and then those with the lowest metric.
+fn default_route(socket: &mut Socket) -> Result<Option<u32>> {
+    let rtmsg = vec![0; RTMSG_LEN];
+    let routes = socket.request(RTM_GETROUTE, libc::NLM_F_DUMP as u16, &rtmsg)?;
+    Ok(routes
+        .iter()
+        .filter_map(|reply| parse_default_route(reply))
+        .min_by_key(|route| (route.family != libc::AF_INET, route.priority))
+        .map(|route| route.interface))
+}
+
+// A default route, as found by `parse_default_route`.
+#[derive(Debug, PartialEq)]
+struct DefaultRoute {
+    family: i32,
+    priority: u32,
+    interface: u32,
+}
+
+// Returns the route in a reply to `RTM_GETROUTE`, if it is a default route
+// in the main table.
+fn parse_default_route(reply: &[u8]) -> Option<DefaultRoute> {
+    let family = i32::from(*reply.first()?);
+    let dst_len = *reply.get(1)?;
+    let route_type = *reply.get(7)?;
+    let attributes = reply.get(RTMSG_LEN..)?;
+    let u32_attr = |kind| Some(u32::from_ne_bytes(attr(attributes, kind)?.try_into().ok()?));
+    // Tables above 255 are only given by `RTA_TABLE`.
+    let table = u32_attr(RTA_TABLE).unwrap_or_else(|| u32::from(reply[4]));
+    if dst_len != 0 || route_type != RTN_UNICAST || table != u32::from(RT_TABLE_MAIN) {
+        return None;
+    }
+    Some(DefaultRoute {
+        family,
+        priority: u32_attr(RTA_PRIORITY).unwrap_or(0),
+        interface: u32_attr(RTA_OIF)?,
+    })
+}
+
+// Hides all but the start of an address, e.g. `192.168.*.*` or
+// `2001:db8:*`.
+fn obfuscate(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, _, _] = address.octets();
+            format!("{a}.{b}.*.*")
+        }
+        IpAddr::V6(address) => {
+            let [a, b, ..] = address.segments();
+            format!("{a:x}:{b:x}:*")
+        }
+    }
+}
+
+impl Widget for IpAddress {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let mut socket =
+            Socket::open(libc::NETLINK_ROUTE, false).context("Initialising IpAddress")?;
+        let events = Socket::open(libc::NETLINK_ROUTE, true).context("Initialising IpAddress")?;
+        for group in [
+            RTNLGRP_IPV4_IFADDR,
+            RTNLGRP_IPV6_IFADDR,
+            RTNLGRP_IPV4_ROUTE,
+            RTNLGRP_IPV6_ROUTE,
+        ] {
+            events.subscribe(group)?;
+        }
+
+        let mut changes = Box::pin(stream::once(Ok(())).chain(events.events()?));
+        let stream = stream! {
+            // Changes to other interfaces and routes are reported too, so
+            // only yield when the address shown changes.
+            let mut shown = None;
+            while let Some(change) = changes.next().await {
+                if let Err(err) = change {
+                    yield Err(err);
+                    continue;
+                }
+                match self.address(&mut socket) {
+                    Ok(address) if shown == Some(address) => {}
+                    Ok(address) => {
+                        shown = Some(address);
+                        yield Ok(self.render(address));
+                    }
+                    Err(err) => yield Err(err),
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::netlink::put_attr;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn default_routes() {
+        let rtmsg = |family: i32, dst_len: u8, attributes: &[(u16, u32)]| {
+            let mut reply = vec![
+                family as u8,
+                dst_len,
+                0,
+                0,
+                RT_TABLE_MAIN,
+                0,
+                0,
+                RTN_UNICAST,
+            ];
+            reply.extend_from_slice(&0u32.to_ne_bytes());
+            for (kind, value) in attributes {
+                put_attr(&mut reply, *kind, &value.to_ne_bytes());
+            }
+            reply
+        };
+        let reply = rtmsg(libc::AF_INET, 0, &[(RTA_OIF, 3), (RTA_PRIORITY, 600)]);
+        assert_eq!(
+            parse_default_route(&reply),
+            Some(DefaultRoute {
+                family: libc::AF_INET,
+                priority: 600,
+                interface: 3,
+            })
+        );
+        // A route to a subnet, rather than a default route.
+        assert_eq!(
+            parse_default_route(&rtmsg(libc::AF_INET, 24, &[(RTA_OIF, 3)])),
+            None
+        );
+        // A default route in another table, e.g. a VPN's.
+        let reply = rtmsg(libc::AF_INET6, 0, &[(RTA_OIF, 5), (RTA_TABLE, 51820)]);
+        assert_eq!(parse_default_route(&reply), None);
+    }
+
+    #[test]
+    fn obfuscated() {
+        assert_eq!(obfuscate(IpAddr::from([192, 168, 1, 20])), "192.168.*.*");
+        let ipv6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        assert_eq!(obfuscate(IpAddr::V6(ipv6)), "2001:db8:*");
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::capabilities::{self, Capabilities, Capability};
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickEvent, ClickStream, MouseButton, Widget, WidgetStream};
+use cnx::xcb::XcbEventStream;
+use std::rc::Rc;
+use tokio_stream::{self as stream, StreamExt};
+use xcb::xkb;
+use xcb::xproto::{PropertyNotifyEvent, PROPERTY_NOTIFY};
+use xcb_util::ewmh;
+
+// The root window property in which `setxkbmap` stores the keyboard's
+// configuration, including its layouts.
+const RULES_NAMES: &str = "_XKB_RULES_NAMES";
+
+/// A keyboard layout, as configured with e.g. `setxkbmap -layout us,de`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layout {
+    /// The name of the layout, e.g. `us`
+    pub name: String,
+    /// The variant of the layout, e.g. `dvorak`, if any
+    pub variant: Option<String>,
+}
+
+enum Event {
+    Xcb(xcb::GenericEvent),
+    Click(ClickEvent),
+}
+
+/// Shows the active keyboard layout, e.g. `us` or `de`.
+///
+/// This listens for XKB state changes, so it is updated as soon as the layout
+/// is switched, whether by a keyboard shortcut or another program. Left
+/// clicking or scrolling up switches to the next layout, and right clicking
+/// or scrolling down switches to the previous one.
+///
+/// The layouts are read from the root window's `_XKB_RULES_NAMES` property,
+/// as set by `setxkbmap`. The widget is hidden if there is only one layout,
+/// and shows `n/a` if the X server doesn't have the XKB extension.
+pub struct KeyboardLayout {
+    attr: Attributes,
+    render: Option<Box<dyn Fn(Layout) -> String>>,
+    services: Option<Services>,
+}
+
+impl KeyboardLayout {
+    /// Creates a new [`KeyboardLayout`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`Layout`] is the active layout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::keyboard_layout::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|layout: Layout| layout.name.to_uppercase());
+    /// cnx.add_widget(KeyboardLayout::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(Layout) -> String>>) -> Self {
+        Self {
+            attr,
+            render,
+            services: None,
+        }
+    }
+
+    fn on_change(&self, layouts: &[Layout], group: u8) -> Vec<Text> {
+        let layout = match layouts.get(group as usize) {
+            Some(layout) if layouts.len() > 1 => layout,
+            _ => return Vec::new(),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| layout.name.clone(), |x| (x)(layout.clone()));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Parses the layouts from the value of `_XKB_RULES_NAMES`, which holds the
+// rules, model, layouts, variants and options separated by nulls, e.g.
+// `evdev\0pc105\0us,de\0,nodeadkeys\0grp:alt_shift_toggle\0`.
+fn parse_rules_names(value: &[u8]) -> Vec<Layout> {
+    let value = String::from_utf8_lossy(value);
+    let mut fields = value.split('\0').skip(2);
+    let layouts = fields.next().unwrap_or_default();
+    let mut variants = fields.next().unwrap_or_default().split(',');
+    layouts
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| Layout {
+            name: name.to_owned(),
+            variant: variants
+                .next()
+                .filter(|variant| !variant.is_empty())
+                .map(str::to_owned),
+        })
+        .collect()
+}
+
+// An X connection on which XKB state changes and changes to the layouts
+// are reported.
+struct Keyboard {
+    conn: Rc<ewmh::Connection>,
+    root: xcb::Window,
+    rules_names: xcb::Atom,
+    // The first event number of the XKB extension, which is the response
+    // type of all of its events.
+    first_event: u8,
+}
+
+impl Keyboard {
+    fn connect() -> Result<Self> {
+        let (conn, screen_idx) =
+            xcb::Connection::connect(None).context("Failed to connect to X server")?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx as usize)
+            .ok_or_else(|| anyhow!("Invalid screen"))?
+            .root();
+
+        let reply = xkb::use_extension(&conn, 1, 0)
+            .get_reply()
+            .context("Failed to enable the XKB extension")?;
+        if !reply.supported() {
+            return Err(anyhow!("The X server does not support XKB"));
+        }
+        let first_event = conn
+            .get_extension_data(xkb::id())
+            .filter(|data| data.present())
+            .ok_or_else(|| anyhow!("The X server does not support XKB"))?
+            .first_event();
+        let state_notify = xkb::EVENT_TYPE_STATE_NOTIFY as u16;
+        xkb::select_events(
+            &conn,
+            xkb::ID_USE_CORE_KBD as xkb::DeviceSpec,
+            state_notify,
+            0,
+            state_notify,
+            0,
+            0,
+            None,
+        );
+
+        let rules_names = xcb::intern_atom(&conn, false, RULES_NAMES)
+            .get_reply()?
+            .atom();
+        let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)];
+        xcb::change_window_attributes(&conn, root, &attributes);
+        conn.flush();
+
+        let conn = ewmh::Connection::connect(conn)
+            .map_err(|(e, _)| e)
+            .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
+        Ok(Keyboard {
+            conn: Rc::new(conn),
+            root,
+            rules_names,
+            first_event,
+        })
+    }
+
+    fn layouts(&self) -> Result<Vec<Layout>> {
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            self.root,
+            self.rules_names,
+            xcb::ATOM_STRING,
+            0,
+            1024,
+        )
+        .get_reply()?;
+        Ok(parse_rules_names(reply.value()))
+    }
+
+    fn group(&self) -> Result<u8> {
+        let reply =
+            xkb::get_state(&self.conn, xkb::ID_USE_CORE_KBD as xkb::DeviceSpec).get_reply()?;
+        Ok(reply.group())
+    }
+
+    // Locks the keyboard to `group`. We'll receive a state change once it
+    // has been switched.
+    fn lock_group(&self, group: u8) {
+        xkb::latch_lock_state(
+            &self.conn,
+            xkb::ID_USE_CORE_KBD as xkb::DeviceSpec,
+            0,
+            0,
+            true,
+            group,
+            0,
+            false,
+            0,
+        );
+        self.conn.flush();
+    }
+}
+
+impl Widget for KeyboardLayout {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let capabilities = self
+            .services
+            .as_ref()
+            .map_or_else(Capabilities::default, Capabilities::shared);
+        if let Err(err) = capabilities.require(Capability::Xkb) {
+            return Ok(capabilities::unavailable(self.attr, err));
+        }
+
+        let keyboard = Keyboard::connect().context("Initialising KeyboardLayout")?;
+        let mut layouts = keyboard.layouts()?;
+        let mut group = keyboard.group()?;
+        let initial = self.on_change(&layouts, group);
+
+        let events = XcbEventStream::new(keyboard.conn.clone())?
+            .map(Event::Xcb)
+            .merge(clicks.map(Event::Click));
+        let stream = events.filter_map(move |event| match event {
+            Event::Xcb(event) => {
+                let response_type = event.response_type() & !0x80;
+                if response_type == keyboard.first_event {
+                    let event: &xkb::StateNotifyEvent = unsafe { xcb::cast_event(&event) };
+                    let changed = event.changed() as xkb::StatePart;
+                    if event.xkb_type() != xkb::STATE_NOTIFY
+                        || changed & xkb::STATE_PART_GROUP_STATE == 0
+                    {
+                        return None;
+                    }
+                    group = event.group();
+                } else if response_type == PROPERTY_NOTIFY {
+                    let event: &PropertyNotifyEvent = unsafe { xcb::cast_event(&event) };
+                    if event.atom() != keyboard.rules_names {
+                        return None;
+                    }
+                    layouts = match keyboard.layouts() {
+                        Ok(layouts) => layouts,
+                        Err(err) => return Some(Err(err)),
+                    };
+                } else {
+                    return None;
+                }
+                Some(Ok(self.on_change(&layouts, group)))
+            }
+            Event::Click(click) => {
+                let count = layouts.len();
+                let next = match click.button {
+                    MouseButton::Left | MouseButton::ScrollUp => Some(group as usize + 1),
+                    MouseButton::Right | MouseButton::ScrollDown => {
+                        Some(group as usize + count.saturating_sub(1))
+                    }
+                    _ => None,
+                };
+                if let Some(next) = next.filter(|_| count > 1) {
+                    keyboard.lock_group((next % count) as u8);
+                }
+                None
+            }
+        });
+
+        Ok(Box::pin(stream::once(Ok(initial)).chain(stream)))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn layout(name: &str, variant: Option<&str>) -> Layout {
+        Layout {
+            name: name.to_owned(),
+            variant: variant.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn rules_names() {
+        let value = b"evdev\0pc105\0us,de\0,nodeadkeys\0grp:alt_shift_toggle\0";
+        assert_eq!(
+            parse_rules_names(value),
+            vec![layout("us", None), layout("de", Some("nodeadkeys"))]
+        );
+
+        let value = b"evdev\0pc105\0gb\0\0\0";
+        assert_eq!(parse_rules_names(value), vec![layout("gb", None)]);
+
+        assert_eq!(parse_rules_names(b""), Vec::new());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::inotify;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use nix::sys::inotify::AddWatchFlags;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio_stream::StreamExt;
+
+/// The context kubectl is using, as shown by [`Kubernetes`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KubeContext {
+    /// The name of the current context, e.g. `prod-eu`
+    pub name: String,
+    /// The namespace the context uses, which is `default` unless it sets one
+    pub namespace: String,
+    /// Whether the context's name matches the production pattern
+    pub production: bool,
+}
+
+type Render = Box<dyn Fn(&KubeContext) -> String>;
+
+/// Shows kubectl's current context and namespace, e.g. `⎈ prod-eu/web`, so
+/// that you know which cluster a `kubectl delete` is about to delete from.
+///
+/// The kubeconfig is read from the files in `$KUBECONFIG`, or from
+/// `~/.kube/config`, and their directories are watched, so that the widget
+/// changes as soon as e.g. `kubectl config use-context` or `kubens` does.
+/// Contexts whose names match the production pattern, which is `prod`
+/// ignoring case by default, are shown in the theme's `Crit` color. The
+/// widget is hidden while there is no current context.
+///
+/// Only the block style YAML which kubectl writes is understood.
+pub struct Kubernetes {
+    attr: Attributes,
+    production: Regex,
+    render: Option<Render>,
+}
+
+impl Kubernetes {
+    /// Creates a new [`Kubernetes`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the current [`KubeContext`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::kubernetes::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let kubernetes = Kubernetes::new(attr, None).with_production("^(prd|live)-")?;
+    /// cnx.add_widget(kubernetes);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            production: Regex::new("(?i)prod").unwrap(),
+            render,
+        }
+    }
+
+    /// Sets the regular expression which matches the names of production
+    /// contexts. The default is `(?i)prod`.
+    pub fn with_production(self, pattern: &str) -> Result<Self> {
+        let production = Regex::new(pattern)
+            .with_context(|| format!("Invalid production pattern: {pattern}"))?;
+        Ok(Self { production, ..self })
+    }
+
+    fn render(&self, context: Option<(String, Option<String>)>) -> Vec<Text> {
+        let Some((name, namespace)) = context else {
+            return Vec::new();
+        };
+        let context = KubeContext {
+            production: self.production.is_match(&name),
+            namespace: namespace.unwrap_or_else(|| "default".to_owned()),
+            name,
+        };
+        let mut attr = self.attr.clone();
+        if context.production {
+            attr.fg_color = Color::role(Role::Crit);
+        }
+        let text = self.render.as_ref().map_or_else(
+            || format!("⎈ {}/{}", context.name, context.namespace),
+            |x| (x)(&context),
+        );
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the kubeconfig files, as kubectl finds them.
+fn kubeconfigs() -> Result<Vec<PathBuf>> {
+    if let Some(paths) = env::var_os("KUBECONFIG").filter(|paths| !paths.is_empty()) {
+        return Ok(env::split_paths(&paths).collect());
+    }
+    let home = env::var_os("HOME").ok_or_else(|| anyhow!("Neither KUBECONFIG nor HOME is set"))?;
+    Ok(vec![PathBuf::from(home).join(".kube/config")])
+}
+
+// The parts of a kubeconfig file the widget needs.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct Kubeconfig {
+    current_context: Option<String>,
+    // The name and namespace of each context.
+    contexts: Vec<(String, Option<String>)>,
+}
+
+// Returns the current context and its namespace, out of `configs`. As with
+// kubectl, the first file to set a value wins.
+fn current_context(configs: &[Kubeconfig]) -> Option<(String, Option<String>)> {
+    let name = configs
+        .iter()
+        .find_map(|config| config.current_context.clone())?;
+    let namespace = configs
+        .iter()
+        .flat_map(|config| &config.contexts)
+        .find(|(context, _)| *context == name)
+        .and_then(|(_, namespace)| namespace.clone());
+    Some((name, namespace))
+}
+
+fn read_kubeconfig(path: &Path) -> Result<Kubeconfig> {
+    match fs::read_to_string(path) {
+        Ok(config) => Ok(parse_kubeconfig(&config)),
+        // kubectl ignores missing files, as may happen while the file is
+        // being replaced.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Kubeconfig::default()),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn scalar(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+// A context being parsed, and the indentation of its keys.
+struct ContextEntry {
+    indent: usize,
+    name: Option<String>,
+    namespace: Option<String>,
+}
+
+fn finish(contexts: &mut Vec<(String, Option<String>)>, context: Option<ContextEntry>) {
+    if let Some(ContextEntry {
+        name: Some(name),
+        namespace,
+        ..
+    }) = context
+    {
+        contexts.push((name, namespace));
+    }
+}
+
+// Parses a kubeconfig as kubectl writes it, e.g.
+//
+// ```yaml
+// contexts:
+// - context:
+//     cluster: eu-1
+//     namespace: web
+//     user: admin
+//   name: prod-eu
+// current-context: prod-eu
+// ```
+fn parse_kubeconfig(config: &str) -> Kubeconfig {
+    let mut kubeconfig = Kubeconfig::default();
+    let mut in_contexts = false;
+    let mut context: Option<ContextEntry> = None;
+    for line in config.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut indent = line.len() - trimmed.len();
+        // A top-level key.
+        if indent == 0 && !line.starts_with('-') {
+            finish(&mut kubeconfig.contexts, context.take());
+            in_contexts = line.trim_end() == "contexts:";
+            if let Some(name) = line.strip_prefix("current-context:") {
+                kubeconfig.current_context = scalar(name);
+            }
+            continue;
+        }
+        if !in_contexts {
+            continue;
+        }
+        // kubectl starts the list at the same indentation as its key, while
+        // other tools indent it.
+        let mut entry = trimmed;
+        if let Some(rest) = trimmed.strip_prefix("- ") {
+            if indent <= 2 {
+                finish(&mut kubeconfig.contexts, context.take());
+                indent += 2;
+                context = Some(ContextEntry {
+                    indent,
+                    name: None,
+                    namespace: None,
+                });
+                entry = rest;
+            }
+        }
+        let (Some(context), Some((key, value))) = (&mut context, entry.split_once(':')) else {
+            continue;
+        };
+        // Other lists in the context, such as minikube's extensions, have
+        // names of their own.
+        match key {
+            "name" if indent == context.indent => context.name = scalar(value),
+            "namespace" if indent > context.indent => context.namespace = scalar(value),
+            _ => {}
+        }
+    }
+    finish(&mut kubeconfig.contexts, context);
+    kubeconfig
+}
+
+impl Widget for Kubernetes {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let paths = kubeconfigs()?;
+        // The directories are watched rather than the files, as tools such as
+        // kubectx replace the file rather than writing to it.
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for path in &paths {
+            let dir = path.parent().unwrap_or_else(|| Path::new("."));
+            if !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_owned());
+            }
+        }
+        let flags =
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_DELETE;
+        let mut changes = Box::pin(inotify::watch(&dirs, flags)?);
+
+        let stream = stream! {
+            loop {
+                let configs: Result<Vec<Kubeconfig>> =
+                    paths.iter().map(|path| read_kubeconfig(path)).collect();
+                yield configs.map(|configs| self.render(current_context(&configs)));
+                match changes.next().await {
+                    Some(Ok(())) => {}
+                    Some(Err(err)) => yield Err(err),
+                    None => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config = "\
+apiVersion: v1
+clusters:
+- cluster:
+    server: https://eu-1.example.com
+  name: eu-1
+contexts:
+- context:
+    cluster: eu-1
+    namespace: web
+    user: admin
+  name: prod-eu
+- context:
+    cluster: kind
+    extensions:
+    - extension:
+        provider: kind.sigs.k8s.io
+      name: context_info
+    user: kind
+  name: \"kind-dev\"
+current-context: prod-eu
+kind: Config
+users:
+- name: admin
+  user:
+    token: secret
+";
+        let kubeconfig = parse_kubeconfig(config);
+        assert_eq!(
+            kubeconfig,
+            Kubeconfig {
+                current_context: Some("prod-eu".to_owned()),
+                contexts: vec![
+                    ("prod-eu".to_owned(), Some("web".to_owned())),
+                    ("kind-dev".to_owned(), None),
+                ],
+            }
+        );
+        assert_eq!(
+            current_context(&[kubeconfig]),
+            Some(("prod-eu".to_owned(), Some("web".to_owned())))
+        );
+    }
+
+    #[test]
+    fn merged() {
+        let overrides = parse_kubeconfig("current-context: \"kind-dev\"\n");
+        let base = parse_kubeconfig(
+            "\
+contexts:
+  - name: kind-dev
+    context:
+      namespace: test
+current-context: prod-eu
+",
+        );
+        assert_eq!(
+            current_context(&[overrides, base]),
+            Some(("kind-dev".to_owned(), Some("test".to_owned())))
+        );
+        assert_eq!(
+            current_context(&[parse_kubeconfig("current-context: \"\"\n")]),
+            None
+        );
+    }
+}
@@ -134,6 +134,7 @@ impl LeftWM {
                         text: t.name.clone(),
                         stretch: false,
                         markup: true,
+                        canvas: None,
                     }
                 })
                 .collect();
@@ -0,0 +1,222 @@
+use crate::inotify;
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use nix::sys::inotify::AddWatchFlags;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+use tokio_stream::StreamExt;
+
+/// Where [`Mail`] counts unread mail.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Backend {
+    /// The number of messages matching a [notmuch] query, such as
+    /// `tag:inbox and tag:unread`, as given by `notmuch count`.
+    ///
+    /// [notmuch]: https://notmuchmail.org
+    Notmuch(String),
+    /// The number of messages in the `new/` directories of these Maildirs,
+    /// i.e. those which no mail client has seen yet.
+    Maildir(Vec<PathBuf>),
+}
+
+/// Shows how many unread messages there are in local mail, for those who
+/// sync their mail with e.g. mbsync or offlineimap rather than having Cnx
+/// talk IMAP.
+///
+/// A Maildir's count is updated as soon as a message arrives in, or leaves,
+/// its `new/` directory. A notmuch query is counted every minute by default,
+/// as notmuch has no way of telling when its result changes.
+pub struct Mail {
+    attr: Attributes,
+    backend: Backend,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(usize) -> String>>,
+}
+
+impl Mail {
+    /// Creates a new [`Mail`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `backend` - Where to count unread mail.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the number of unread messages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::mail::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let query = Backend::Notmuch("tag:inbox and tag:unread".into());
+    /// cnx.add_widget(Mail::new(attr.clone(), query, None));
+    /// let maildirs = Backend::Maildir(vec!["/home/me/Mail/work/INBOX".into()]);
+    /// let render = Box::new(|unread| match unread {
+    ///     0 => String::new(),
+    ///     n => format!("✉ {n}"),
+    /// });
+    /// cnx.add_widget(Mail::new(attr, maildirs, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        backend: Backend,
+        render: Option<Box<dyn Fn(usize) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            backend,
+            update_interval: Duration::from_secs(60),
+            render,
+        }
+    }
+
+    /// Sets how often to count the messages matching a notmuch query. The
+    /// default is every minute. Maildirs are watched instead.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    async fn count(&self) -> Result<usize> {
+        match &self.backend {
+            Backend::Notmuch(query) => notmuch_count(query).await,
+            Backend::Maildir(maildirs) => maildirs.iter().map(|maildir| count_new(maildir)).sum(),
+        }
+    }
+
+    fn render(&self, unread: usize) -> Vec<Text> {
+        let text = self.render.as_ref().map_or_else(
+            || i18n::tr_with("Mail {}", &unread.to_string()),
+            |x| (x)(unread),
+        );
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+async fn notmuch_count(query: &str) -> Result<usize> {
+    let output = Command::new("notmuch")
+        .args(["count", "--", query])
+        .output()
+        .await
+        .context("Failed to run notmuch")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`notmuch count` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let count = String::from_utf8_lossy(&output.stdout);
+    count
+        .trim()
+        .parse()
+        .with_context(|| format!("Unexpected output from notmuch: {count}"))
+}
+
+// Counts the messages in a Maildir's `new/` directory, skipping any hidden
+// files, which Maildir readers ignore.
+fn count_new(maildir: &Path) -> Result<usize> {
+    let new = maildir.join("new");
+    let mut count = 0;
+    let entries =
+        fs::read_dir(&new).with_context(|| format!("Failed to read {}", new.display()))?;
+    for entry in entries {
+        if !entry?.file_name().to_string_lossy().starts_with('.') {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+impl Widget for Mail {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let mut changes = match &self.backend {
+            Backend::Notmuch(_) => None,
+            Backend::Maildir(maildirs) => {
+                let dirs: Vec<PathBuf> =
+                    maildirs.iter().map(|maildir| maildir.join("new")).collect();
+                // Messages are delivered to `tmp/` and then moved into `new/`,
+                // and are moved from `new/` to `cur/` once they have been seen.
+                let flags = AddWatchFlags::IN_CREATE
+                    | AddWatchFlags::IN_DELETE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_MOVED_FROM;
+                Some(Box::pin(inotify::watch(&dirs, flags)?))
+            }
+        };
+
+        let stream = stream! {
+            loop {
+                yield self.count().await.map(|unread| self.render(unread));
+                match &mut changes {
+                    Some(changes) => match changes.next().await {
+                        Some(Ok(())) => {}
+                        Some(Err(err)) => yield Err(err),
+                        None => break,
+                    },
+                    None => time::sleep(self.update_interval).await,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn maildir() {
+        let maildir = env::temp_dir().join(format!("cnx-mail-test-{}", std::process::id()));
+        for dir in ["new", "cur", "tmp"] {
+            fs::create_dir_all(maildir.join(dir)).unwrap();
+        }
+        fs::write(maildir.join("new/1705316400.1234_1.host"), "").unwrap();
+        fs::write(maildir.join("new/1705316401.1234_2.host"), "").unwrap();
+        fs::write(maildir.join("new/.hidden"), "").unwrap();
+        fs::write(maildir.join("cur/1705316000.1234_0.host:2,S"), "").unwrap();
+        let count = count_new(&maildir);
+        fs::remove_dir_all(&maildir).unwrap();
+        assert_eq!(count.unwrap(), 2);
+
+        assert!(count_new(&maildir).is_err());
+    }
+}
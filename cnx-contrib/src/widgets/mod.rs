@@ -1,22 +1,172 @@
+/// ACPI widget to show the lid and AC adapter state and special button presses
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod acpi;
+/// Air quality widget to show the AQI near you from WAQI or OpenAQ
+pub mod air_quality;
+/// App volume widget to show and scroll to change the volume of the focused window's audio
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod app_volume;
+/// Backlight widget to show and scroll to change the screen's brightness
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod backlight;
 /// Battery widget to shows the current capacity
 pub mod battery;
+/// Bluetooth widget to show the adapter's power and connected devices, with click to toggle power
+#[cfg(feature = "bluetooth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetooth")))]
+pub mod bluetooth;
+/// Calendar widget to show the next event in iCalendar files, with a countdown
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod calendar;
+/// Charge limit widget to show and toggle the battery's charge threshold
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod charge_limit;
+/// Clock sync widget to show whether the clock is synchronized over NTP
+pub mod clock_sync;
 /// Command widget to show output of a CLI command
 pub mod command;
 /// CPU widget to show the current CPU consumption
 pub mod cpu;
+/// DBus property widget to show any property of an object on DBus as it changes
+#[cfg(feature = "dbus-property")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dbus-property")))]
+pub mod dbus_property;
 /// Disk usage widget to show current usage and remaining free space
 pub mod disk_usage;
+/// Do not disturb widget to show whether dunst's notifications are paused, with click to toggle
+#[cfg(feature = "dunst")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dunst")))]
+pub mod dunst;
+/// File descriptor usage widget to warn when inotify watches or a process's file descriptors run low
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod fd_usage;
+/// Feeds widget to show the newest unseen headline from RSS or Atom feeds
+pub mod feeds;
+/// FIFO widget to show the last line scripts wrote to a named pipe
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod fifo;
+/// Focused process widget to show the CPU and memory used by the focused window's process
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod focused_process;
+/// GitHub notifications widget to show how many notifications are unread
+pub mod github_notifications;
+/// GPU widget to show the utilization, video memory and temperature of an NVIDIA or AMD GPU
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod gpu;
+/// Greeting widget to show a greeting or quote for a while after the bar starts
+pub mod greeting;
+/// Host badge widget to show the hostname or role in a per-host color
+pub mod host_badge;
+/// HTTP JSON widget to show a value polled from a JSON API
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub mod http_json;
+/// IP address widget to show the address of an interface or the default route
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod ip_address;
+/// Keyboard layout widget to show the active XKB layout, with click to switch it
+#[cfg(feature = "xkb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "xkb")))]
+pub mod keyboard_layout;
+/// Kubernetes widget to show kubectl's current context and namespace
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod kubernetes;
 /// LeftWM widget that subscribes to leftwm-state and streams the monitors and tags upfate
 #[cfg(feature = "leftwm")]
 #[cfg_attr(docsrs, doc(cfg(feature = "leftwm")))]
 pub mod leftwm;
-/// Sensor widget to periodically parses and displays the output of the sensors provided by the system.
+/// Mail widget to show how many messages are unread in notmuch or Maildirs
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod mail;
+/// MPD widget to show the song that MPD is playing
+pub mod mpd;
+/// Network link widget to show whether an interface is up and plugged in, and its address
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod network_link;
+/// Package updates widget to show how many updates pacman, apt or dnf have pending
+pub mod package_updates;
+/// Ping widget to show the round-trip time to a host and the recent packet loss
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod ping;
+/// PipeWire widget to show the quantum and sample rate of the running graph
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod pipewire;
+/// Pomodoro widget to time work and breaks, optionally starting a Taskwarrior task
+pub mod pomodoro;
+/// Ring gauge widget to show a percentage as a ring
+pub mod ring_gauge;
+/// Scratchpad widget to show the number of scratchpad or iconified windows
+#[cfg(feature = "wm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wm")))]
+pub mod scratchpad;
+/// Sensor widget to periodically show the temperatures of the sensors provided by the system.
 pub mod sensors;
+/// Login session widget to show the seat, other sessions and lock state, with click to lock
+#[cfg(feature = "logind")]
+#[cfg_attr(docsrs, doc(cfg(feature = "logind")))]
+pub mod session;
+/// Stdin widget to show the last line piped into Cnx
+pub mod stdin;
+/// Stopwatch widget to show the time since it was last reset, by a click or a timestamp file
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod stopwatch;
+/// Systemd units widget to show which of the units it watches have failed or are inactive
+#[cfg(feature = "systemd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "systemd")))]
+pub mod systemd_units;
+/// Timer widget to count down for a duration or to a time, started by clicks or the control socket
+pub mod timer;
+/// Transport widget to show whether JACK's or Ardour's transport is rolling, and its tempo
+pub mod transport;
+/// Update check widget to show when a newer version of Cnx is available
+pub mod update_check;
 /// Volume widget to show the current volume/mute status of the default output device.
 pub mod volume;
+/// VPN widget to show a lock and the names of the VPN tunnels which are up
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod vpn;
 /// Weather widget to show temperature of your location
 pub mod weather;
-/// Wireless widget to show wireless strength of your SSID
-#[cfg(feature = "wireless")]
-#[cfg_attr(docsrs, doc(cfg(feature = "wireless")))]
+/// Weather report widget to show the weather from wttr.in or a METAR report, without an API key
+pub mod weather_report;
+/// WebSocket widget to show the messages received over a WebSocket
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+pub mod websocket;
+/// Window count widget to show the number of tiled and floating windows on the current workspace
+#[cfg(feature = "wm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wm")))]
+pub mod window_count;
+/// Wireless widget to show the SSID and signal strength of your network
+#[cfg(all(feature = "wireless", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wireless", target_os = "linux"))))]
 pub mod wireless;
+/// Window manager layout widget to show the layout of the focused workspace
+#[cfg(feature = "wm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wm")))]
+pub mod wm_layout;
+/// Window manager mode widget to show the active keybinding mode or submap
+#[cfg(feature = "wm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wm")))]
+pub mod wm_mode;
+/// Zombies widget to show how many zombie processes there are, and whose they are
+#[cfg(target_os = "linux")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "linux")))]
+pub mod zombies;
@@ -0,0 +1,734 @@
+use crate::secret::Secret;
+use anyhow::Result;
+use async_stream::stream;
+#[cfg(feature = "album-art")]
+use cairo::{Format, ImageSurface};
+use cnx::i18n;
+use cnx::teardown::{self, AbortOnDrop};
+use cnx::text::{truncate, Attributes, Canvas, Color, Text};
+use cnx::widgets::{ClickEvent, ClickStream, MouseButton, Widget, WidgetStream};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{self as stream, Stream, StreamExt};
+
+mod client;
+
+#[cfg(fuzzing)]
+pub(crate) use client::fuzz;
+use client::{split_password, Client};
+
+// The subsystems whose changes affect what we show.
+const SUBSYSTEMS: &[&str] = &["player", "mixer", "options"];
+
+// The bounds of the delay between attempts to reconnect to MPD.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// How much each scroll changes the volume by, in percent.
+const VOLUME_STEP: u8 = 5;
+
+enum Event {
+    // New information from MPD and the current song's cover, or `None` if we
+    // lost the connection.
+    Info(Result<Option<(MpdInfo, Option<Canvas>)>>),
+    // Time to advance the progress highlight.
+    Tick,
+    Click(ClickEvent),
+}
+
+/// The playback state of MPD.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum State {
+    Play,
+    Pause,
+    Stop,
+}
+
+/// The song that MPD is playing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Song {
+    /// The path of the song, relative to MPD's music directory
+    pub file: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+}
+
+/// The state of MPD, passed to the `render` closure of [`Mpd`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MpdInfo {
+    pub state: State,
+    /// The current song, if any
+    pub song: Option<Song>,
+    /// The volume as a percentage, if MPD has a mixer
+    pub volume: Option<u8>,
+    /// How far into the current song playback is
+    pub elapsed: Option<Duration>,
+    /// The length of the current song
+    pub duration: Option<Duration>,
+}
+
+fn find<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn seconds(value: Option<&str>) -> Option<Duration> {
+    value
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs_f64)
+}
+
+impl MpdInfo {
+    // Builds an `MpdInfo` from the responses to `status` and `currentsong`.
+    fn from_responses(status: &[(String, String)], song: &[(String, String)]) -> MpdInfo {
+        let state = match find(status, "state") {
+            Some("play") => State::Play,
+            Some("pause") => State::Pause,
+            _ => State::Stop,
+        };
+        let song = find(song, "file").map(|file| Song {
+            file: file.to_owned(),
+            artist: find(song, "Artist").map(str::to_owned),
+            album: find(song, "Album").map(str::to_owned),
+            title: find(song, "Title").map(str::to_owned),
+        });
+        MpdInfo {
+            state,
+            song,
+            // MPD reports a volume of -1 if there is no mixer.
+            volume: find(status, "volume").and_then(|volume| volume.parse().ok()),
+            elapsed: seconds(find(status, "elapsed")),
+            duration: seconds(find(status, "duration")),
+        }
+    }
+
+    // Returns the info as of `since` after it was received from MPD.
+    //
+    // MPD only reports the elapsed time when asked, so we count it ourselves
+    // in between. It only advances while playing.
+    fn advance(&self, since: Duration) -> MpdInfo {
+        let mut info = self.clone();
+        if info.state == State::Play {
+            info.elapsed = match (info.elapsed, info.duration) {
+                (Some(elapsed), Some(duration)) => Some((elapsed + since).min(duration)),
+                (elapsed, _) => elapsed.map(|elapsed| elapsed + since),
+            };
+        }
+        info
+    }
+}
+
+/// Shows the song that [MPD] is playing.
+///
+/// Clicking the widget controls playback: a left click plays or pauses, a
+/// middle click skips to the next song, and scrolling up or down changes the
+/// volume.
+///
+/// With the `album-art` feature, the cover of the current song can be shown
+/// too, with `Mpd::with_album_art()`.
+///
+/// The widget waits for MPD to report changes rather than polling. If MPD
+/// isn't running, or the connection to it is lost (e.g. because MPD is
+/// restarted), the widget shows a placeholder and keeps trying to reconnect,
+/// waiting longer between each attempt up to a minute.
+///
+/// [MPD]: https://www.musicpd.org/
+pub struct Mpd {
+    attr: Attributes,
+    socket: String,
+    password: Option<Secret>,
+    disconnected_text: String,
+    progress_color: Option<Color>,
+    album_art_size: Option<f64>,
+    max_width: Option<usize>,
+    render: Option<Box<dyn Fn(MpdInfo) -> String>>,
+}
+
+impl Mpd {
+    /// Creates a new [`Mpd`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `socket` - The address of MPD: either a host and port (e.g.
+    /// `localhost:6600`) or the path of a Unix socket (e.g.
+    /// `/run/mpd/socket`). As with the `MPD_HOST` environment variable, this
+    /// may start with a password and `@`, e.g. `secret@localhost:6600`.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`MpdInfo`] contains the playback state and
+    /// current song. The closure may capture variables, e.g. to build it
+    /// from your configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::mpd::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let separator = " — ".to_owned();
+    /// let render = Box::new(move |info: MpdInfo| match info.song {
+    ///     Some(Song { artist: Some(artist), title: Some(title), .. }) => {
+    ///         format!("{artist}{separator}{title}")
+    ///     }
+    ///     Some(song) => song.title.unwrap_or(song.file),
+    ///     None => String::new(),
+    /// });
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Mpd::new(attr, "localhost:6600".to_owned(), Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        socket: String,
+        render: Option<Box<dyn Fn(MpdInfo) -> String>>,
+    ) -> Self {
+        let (password, socket) = split_password(&socket);
+        Self {
+            attr,
+            password: password.map(|password| Secret::Value(password.to_owned())),
+            socket: socket.to_owned(),
+            disconnected_text: "MPD disconnected".to_owned(),
+            progress_color: None,
+            album_art_size: None,
+            max_width: None,
+            render,
+        }
+    }
+
+    /// Sets the password used to log in to MPD, overriding any given in the
+    /// `socket` argument of [`Mpd::new`]. This may be a `String`, or a
+    /// [`Secret`] to look it up elsewhere, e.g. in an environment variable.
+    pub fn with_password(self, password: impl Into<Secret>) -> Self {
+        Self {
+            password: Some(password.into()),
+            ..self
+        }
+    }
+
+    /// Sets the text shown while the widget can't connect to MPD. It is
+    /// hidden if this is empty.
+    pub fn with_disconnected_text(self, disconnected_text: String) -> Self {
+        Self {
+            disconnected_text,
+            ..self
+        }
+    }
+
+    /// Highlights the text with a background of `progress_color` in
+    /// proportion to how much of the song has been played, like a progress
+    /// bar. This is updated every second while playing.
+    ///
+    /// Only the default text is highlighted, as the output of a `render`
+    /// closure may contain markup.
+    pub fn with_progress(self, progress_color: Color) -> Self {
+        Self {
+            progress_color: Some(progress_color),
+            ..self
+        }
+    }
+
+    /// Shows the cover of the current song before the text, `size` pixels
+    /// wide and scaled to fit the height of the bar.
+    ///
+    /// The cover is the picture embedded in the song or, failing that, a
+    /// `cover.*` file in the song's directory. This needs MPD 0.21 or later,
+    /// and 0.22 or later for embedded pictures.
+    #[cfg(feature = "album-art")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "album-art")))]
+    pub fn with_album_art(self, size: f64) -> Self {
+        Self {
+            album_art_size: Some(size),
+            ..self
+        }
+    }
+
+    /// Truncates the text to at most `max_width` columns, as measured by
+    /// [`truncate()`], e.g. so that long titles don't push other widgets
+    /// aside.
+    ///
+    /// As with [`Mpd::with_progress`], only the default text is truncated.
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self {
+            max_width: Some(max_width),
+            ..self
+        }
+    }
+
+    async fn query(client: &mut Client) -> Result<MpdInfo> {
+        let status = client.command("status").await?;
+        let song = client.command("currentsong").await?;
+        Ok(MpdInfo::from_responses(&status, &song))
+    }
+
+    fn on_change(&self, info: MpdInfo, cover: Option<&Canvas>) -> Vec<Text> {
+        let progress = match (info.elapsed, info.duration) {
+            (Some(elapsed), Some(duration)) if !duration.is_zero() => {
+                Some(elapsed.as_secs_f64() / duration.as_secs_f64())
+            }
+            _ => None,
+        };
+        let default_text = || {
+            let text = default_text(&info);
+            match self.max_width {
+                Some(max_width) => truncate(&text, max_width),
+                None => text,
+            }
+        };
+        let (text, markup) = match (&self.render, &self.progress_color, progress) {
+            (Some(render), _, _) => ((render)(info), true),
+            (None, Some(color), Some(progress)) => {
+                (highlight(&default_text(), progress, color), true)
+            }
+            (None, _, _) => (default_text(), false),
+        };
+        if text.is_empty() {
+            return Vec::new();
+        }
+        let cover = cover.map(|cover| Text {
+            attr: self.attr.clone(),
+            text: String::new(),
+            stretch: false,
+            markup: false,
+            canvas: Some(cover.clone()),
+        });
+        let text = Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup,
+            canvas: None,
+        };
+        cover.into_iter().chain(Some(text)).collect()
+    }
+
+    fn on_disconnect(&self) -> Vec<Text> {
+        if self.disconnected_text.is_empty() {
+            return Vec::new();
+        }
+        vec![Text {
+            attr: self.attr.clone(),
+            text: i18n::tr(&self.disconnected_text),
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+// Shows `Artist - Title` (or the file name), hiding the widget while stopped.
+fn default_text(info: &MpdInfo) -> String {
+    let song = match (&info.state, &info.song) {
+        (State::Stop, _) | (_, None) => return String::new(),
+        (_, Some(song)) => song,
+    };
+    let title = song.title.as_deref().unwrap_or_else(|| {
+        let name = song.file.rsplit('/').next().unwrap_or(&song.file);
+        name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+    });
+    let text = match &song.artist {
+        Some(artist) => format!("{artist} - {title}"),
+        None => title.to_owned(),
+    };
+    match info.state {
+        State::Pause => format!("{text} ({})", i18n::tr("paused")),
+        _ => text,
+    }
+}
+
+// Escapes `text` for use in Pango markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Returns Pango markup highlighting the first `fraction` of the characters of
+// `text` with a background of `color`.
+fn highlight(text: &str, fraction: f64, color: &Color) -> String {
+    let split = (text.chars().count() as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+    let split = text
+        .char_indices()
+        .nth(split)
+        .map_or(text.len(), |(i, _)| i);
+    let (played, rest) = text.split_at(split);
+    format!(
+        "<span background=\"{}\">{}</span>{}",
+        color.to_hex(),
+        escape(played),
+        escape(rest)
+    )
+}
+
+// Converts RGBA pixels to Cairo's ARGB32 format, which has premultiplied
+// alpha and is stored in native-endian `u32`s.
+#[cfg(feature = "album-art")]
+fn to_argb32(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = u32::from(pixel[3]);
+            let premultiply = |channel: u8| u32::from(channel) * alpha / 255;
+            let argb = alpha << 24
+                | premultiply(pixel[0]) << 16
+                | premultiply(pixel[1]) << 8
+                | premultiply(pixel[2]);
+            argb.to_ne_bytes()
+        })
+        .collect()
+}
+
+// Decodes a picture from MPD into a `Canvas` `size` pixels wide, filling the
+// height of the bar.
+#[cfg(feature = "album-art")]
+fn cover_canvas(picture: &[u8], size: f64) -> Result<Canvas> {
+    let pixels = size.ceil() as u32;
+    let image = image::load_from_memory(picture)?
+        .thumbnail(pixels, pixels)
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let stride = Format::ARgb32.stride_for_width(width)?;
+    // ARGB32 rows are always a multiple of four bytes, so need no padding.
+    let surface = ImageSurface::create_for_data(
+        to_argb32(image.as_raw()),
+        Format::ARgb32,
+        width as i32,
+        height as i32,
+        stride,
+    )?;
+    Ok(Canvas::image(surface, size, 0.0))
+}
+
+// Fetches the cover of the song `file`, if it has one.
+#[cfg(feature = "album-art")]
+async fn fetch_cover(client: &mut Client, file: &str, size: f64) -> Result<Option<Canvas>> {
+    // `readpicture` fails on MPD older than 0.22, and `albumart` fails if
+    // there is no cover file, so we treat errors as there being no picture.
+    // If the connection itself has failed, the next command will tell us.
+    let mut picture = client.picture("readpicture", file).await.unwrap_or(None);
+    if picture.is_none() {
+        picture = client.picture("albumart", file).await.unwrap_or(None);
+    }
+    picture
+        .map(|picture| cover_canvas(&picture, size))
+        .transpose()
+}
+
+// Returns the cover of the current song, only fetching it if the song has
+// changed since `cover`, the last song we fetched a cover for, and its cover.
+#[cfg(feature = "album-art")]
+async fn current_cover(
+    client: &mut Client,
+    info: &MpdInfo,
+    size: f64,
+    cover: &mut Option<(String, Option<Canvas>)>,
+) -> Result<Option<Canvas>> {
+    let file = match &info.song {
+        Some(song) => &song.file,
+        None => return Ok(None),
+    };
+    if cover.as_ref().map(|(cover_file, _)| cover_file) != Some(file) {
+        // Don't try again for the same song if this fails.
+        *cover = Some((file.clone(), None));
+        let canvas = fetch_cover(client, file, size).await?;
+        *cover = Some((file.clone(), canvas));
+    }
+    Ok(cover.as_ref().and_then(|(_, canvas)| canvas.clone()))
+}
+
+// Returns a stream of the information from MPD at `socket`, along with the
+// current song's cover if `album_art_size` is set, yielding `None` whenever
+// the connection is lost.
+//
+// We keep trying to reconnect, waiting longer between each attempt.
+fn info_stream(
+    socket: String,
+    password: Option<Secret>,
+    album_art_size: Option<f64>,
+) -> impl Stream<Item = Result<Option<(MpdInfo, Option<Canvas>)>>> {
+    stream! {
+        let mut backoff = MIN_BACKOFF;
+        #[cfg(feature = "album-art")]
+        let mut cover = None;
+        loop {
+            match Client::connect(&socket, password.as_ref()).await {
+                Ok(mut client) => {
+                    backoff = MIN_BACKOFF;
+                    loop {
+                        match Mpd::query(&mut client).await {
+                            Ok(info) => {
+                                let canvas = match album_art_size {
+                                    #[cfg(feature = "album-art")]
+                                    Some(size) => {
+                                        current_cover(&mut client, &info, size, &mut cover).await
+                                    }
+                                    _ => Ok(None),
+                                };
+                                let canvas = match canvas {
+                                    Ok(canvas) => canvas,
+                                    Err(err) => {
+                                        yield Err(err);
+                                        None
+                                    }
+                                };
+                                yield Ok(Some((info, canvas)));
+                            }
+                            Err(err) => {
+                                yield Err(err);
+                                break;
+                            }
+                        }
+                        if let Err(err) = client.idle(SUBSYSTEMS).await {
+                            yield Err(err);
+                            break;
+                        }
+                    }
+                }
+                Err(err) => yield Err(err),
+            }
+
+            yield Ok(None);
+            time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+// Returns the command to send to MPD for a click, if any.
+fn click_command(click: &ClickEvent, info: &MpdInfo) -> Option<String> {
+    match click.button {
+        MouseButton::Left if info.state == State::Play => Some("pause 1".to_owned()),
+        MouseButton::Left => Some("play".to_owned()),
+        MouseButton::Middle => Some("next".to_owned()),
+        MouseButton::ScrollUp => info
+            .volume
+            .map(|volume| format!("setvol {}", volume.saturating_add(VOLUME_STEP).min(100))),
+        MouseButton::ScrollDown => info
+            .volume
+            .map(|volume| format!("setvol {}", volume.saturating_sub(VOLUME_STEP))),
+        _ => None,
+    }
+}
+
+// Spawns a task which sends the commands it is given to MPD, returning the
+// sender to give it commands with, and the task, which is aborted once it is
+// dropped along with the widget's stream.
+//
+// This uses a separate connection from the one waiting in `idle`, so that
+// clicks neither have to wait for nor interrupt it. The connection is only
+// opened when needed, and reopened if it has been closed, e.g. by MPD after
+// it has been inactive for a while.
+fn spawn_commander(
+    socket: String,
+    password: Option<Secret>,
+) -> (mpsc::UnboundedSender<String>, AbortOnDrop<()>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let task = teardown::spawn_local(async move {
+        let mut client = None;
+        while let Some(command) = receiver.recv().await {
+            // Retry once, in case the connection went stale.
+            for retry in [false, true] {
+                if client.is_none() {
+                    match Client::connect(&socket, password.as_ref()).await {
+                        Ok(connected) => client = Some(connected),
+                        Err(err) => {
+                            println!("Error sending MPD command {command:?}: {err}");
+                            break;
+                        }
+                    }
+                }
+                if let Some(connected) = &mut client {
+                    match connected.command(&command).await {
+                        Ok(_) => break,
+                        Err(err) => {
+                            client = None;
+                            if retry {
+                                println!("Error sending MPD command {command:?}: {err}");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    (sender, task)
+}
+
+impl Widget for Mpd {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let (commands, commander) = spawn_commander(self.socket.clone(), self.password.clone());
+        let infos = Box::pin(info_stream(
+            self.socket.clone(),
+            self.password.clone(),
+            self.album_art_size,
+        ))
+        .map(Event::Info);
+        let ticks: Pin<Box<dyn Stream<Item = Event>>> = match self.progress_color {
+            Some(_) => {
+                let interval = time::interval(Duration::from_secs(1));
+                Box::pin(IntervalStream::new(interval).map(|_| Event::Tick))
+            }
+            None => Box::pin(stream::pending()),
+        };
+
+        // The latest info and cover, and when we received them.
+        let mut latest: Option<(MpdInfo, Option<Canvas>, Instant)> = None;
+        let events = infos.merge(ticks).merge(clicks.map(Event::Click));
+        let stream = events.filter_map(move |event| {
+            // Keep the commander running for as long as the stream is.
+            let _commander = &commander;
+            match event {
+                Event::Info(Ok(Some((info, cover)))) => {
+                    let texts = self.on_change(info.clone(), cover.as_ref());
+                    latest = Some((info, cover, Instant::now()));
+                    Some(Ok(texts))
+                }
+                Event::Info(Ok(None)) => {
+                    latest = None;
+                    Some(Ok(self.on_disconnect()))
+                }
+                Event::Info(Err(err)) => Some(Err(err)),
+                // The highlight is frozen while paused or stopped.
+                Event::Tick => match &latest {
+                    Some((info, cover, received)) if info.state == State::Play => Some(Ok(
+                        self.on_change(info.advance(received.elapsed()), cover.as_ref())
+                    )),
+                    _ => None,
+                },
+                Event::Click(click) => {
+                    let command = latest
+                        .as_ref()
+                        .and_then(|(info, _, _)| click_command(&click, info));
+                    if let Some(command) = command {
+                        let _ = commands.send(command);
+                    }
+                    None
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn info_from_responses() {
+        let status = pairs(&[
+            ("volume", "-1"),
+            ("state", "pause"),
+            ("elapsed", "12.500"),
+            ("duration", "180.000"),
+        ]);
+        let song = pairs(&[("file", "Beatles/Help.flac"), ("Artist", "The Beatles")]);
+        let info = MpdInfo::from_responses(&status, &song);
+        assert_eq!(info.state, State::Pause);
+        assert_eq!(info.volume, None);
+        assert_eq!(info.elapsed, Some(Duration::from_millis(12500)));
+        assert_eq!(info.duration, Some(Duration::from_secs(180)));
+        assert_eq!(default_text(&info), "The Beatles - Help (paused)");
+
+        let playing = MpdInfo {
+            state: State::Play,
+            ..info.clone()
+        };
+        let later = Duration::from_secs(10);
+        assert_eq!(
+            info.advance(later).elapsed,
+            Some(Duration::from_millis(12500))
+        );
+        assert_eq!(
+            playing.advance(later).elapsed,
+            Some(Duration::from_millis(22500))
+        );
+
+        let info = MpdInfo::from_responses(&pairs(&[("state", "stop")]), &[]);
+        assert_eq!(info.song, None);
+        assert_eq!(default_text(&info), "");
+    }
+
+    #[test]
+    fn click_commands() {
+        let click = |button| ClickEvent {
+            button,
+            index: 0,
+            x: 0,
+            y: 0,
+        };
+        let info = MpdInfo {
+            state: State::Play,
+            song: None,
+            volume: Some(98),
+            elapsed: None,
+            duration: None,
+        };
+        let command = |button| click_command(&click(button), &info);
+        assert_eq!(command(MouseButton::Left).as_deref(), Some("pause 1"));
+        assert_eq!(
+            command(MouseButton::ScrollUp).as_deref(),
+            Some("setvol 100")
+        );
+        assert_eq!(
+            command(MouseButton::ScrollDown).as_deref(),
+            Some("setvol 93")
+        );
+        assert_eq!(command(MouseButton::Right), None);
+    }
+
+    #[cfg(feature = "album-art")]
+    #[test]
+    fn argb32_pixels() {
+        let argb = to_argb32(&[0xff, 0x80, 0x00, 0xff, 0xff, 0xff, 0xff, 0x80]);
+        assert_eq!(argb[..4], 0xffff8000u32.to_ne_bytes());
+        assert_eq!(argb[4..], 0x80808080u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn highlight_progress() {
+        let color = Color::from_hex("#336699");
+        assert_eq!(
+            highlight("Abbey <Road>", 0.5, &color),
+            "<span background=\"#336699\">Abbey </span>&lt;Road&gt;"
+        );
+        assert_eq!(
+            highlight("Él", 1.0, &color),
+            "<span background=\"#336699\">Él</span>"
+        );
+    }
+}
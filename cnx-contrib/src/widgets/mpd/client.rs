@@ -0,0 +1,228 @@
+use crate::secret::Secret;
+use anyhow::{anyhow, bail, Context, Result};
+use cnx::untrusted;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, UnixStream};
+
+// A line of a response from MPD.
+#[derive(Debug, PartialEq)]
+enum Line {
+    // The end of a successful response.
+    Ok,
+    // A `key: value` pair.
+    Pair(String, String),
+}
+
+// Parses a line of a response, turning an `ACK` into an error.
+//
+// https://mpd.readthedocs.io/en/latest/protocol.html#responses
+fn parse_line(line: &str) -> Result<Line> {
+    if line == "OK" {
+        Ok(Line::Ok)
+    } else if let Some(error) = line.strip_prefix("ACK ") {
+        Err(anyhow!("MPD error: {}", error))
+    } else if let Some((key, value)) = line.split_once(": ") {
+        Ok(Line::Pair(key.to_owned(), value.to_owned()))
+    } else {
+        Err(anyhow!("Unexpected response from MPD: {}", line))
+    }
+}
+
+// Quotes an argument of a command.
+//
+// https://mpd.readthedocs.io/en/latest/protocol.html#escaping-string-values
+fn quote(argument: &str) -> String {
+    let escaped = argument.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+// Splits an optional password off the front of `socket`, as in
+// `password@localhost:6600`, like the `MPD_HOST` environment variable. The
+// password may contain `@` itself, so it ends at the last one.
+pub(crate) fn split_password(socket: &str) -> (Option<&str>, &str) {
+    match socket.rsplit_once('@') {
+        Some((password, socket)) => (Some(password), socket),
+        None => (None, socket),
+    }
+}
+
+// A connection to MPD.
+//
+// This implements just enough of the protocol to send commands and read
+// their responses as `key: value` pairs.
+pub(crate) struct Client {
+    reader: BufReader<Box<dyn AsyncRead + Unpin>>,
+    writer: Box<dyn AsyncWrite + Unpin>,
+}
+
+impl Client {
+    // Connects to MPD at `socket`, which is either the path of a Unix socket
+    // (e.g. `/run/mpd/socket`) or a host and port (e.g. `localhost:6600`),
+    // and logs in with `password` if given.
+    pub async fn connect(socket: &str, password: Option<&Secret>) -> Result<Client> {
+        let context = || format!("Failed to connect to MPD at {socket}");
+        let (reader, writer): (Box<dyn AsyncRead + Unpin>, Box<dyn AsyncWrite + Unpin>) =
+            if socket.starts_with('/') {
+                let (reader, writer) = UnixStream::connect(socket)
+                    .await
+                    .with_context(context)?
+                    .into_split();
+                (Box::new(reader), Box::new(writer))
+            } else {
+                let (reader, writer) = TcpStream::connect(socket)
+                    .await
+                    .with_context(context)?
+                    .into_split();
+                (Box::new(reader), Box::new(writer))
+            };
+        let mut client = Client {
+            reader: BufReader::new(reader),
+            writer,
+        };
+
+        let greeting = client.read_line().await?;
+        if !greeting.starts_with("OK MPD ") {
+            bail!("Unexpected greeting from MPD: {}", greeting);
+        }
+        if let Some(password) = password {
+            let password = password.get().await?;
+            client
+                .command(&format!("password {}", quote(&password)))
+                .await
+                .context("Failed to log in to MPD")?;
+        }
+        Ok(client)
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if untrusted::read_line(&mut self.reader, &mut line, untrusted::MAX_LINE).await? == 0 {
+            bail!("MPD closed the connection");
+        }
+        Ok(line.trim_end_matches('\n').to_owned())
+    }
+
+    // Sends a command, returning the `key: value` pairs of its response.
+    pub async fn command(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        let (pairs, _) = self.binary_command(command).await?;
+        Ok(pairs)
+    }
+
+    // Sends a command whose response may include binary data, e.g.
+    // `albumart`, returning its `key: value` pairs and the data.
+    //
+    // https://mpd.readthedocs.io/en/latest/protocol.html#binary-responses
+    async fn binary_command(&mut self, command: &str) -> Result<(Vec<(String, String)>, Vec<u8>)> {
+        self.writer
+            .write_all(format!("{command}\n").as_bytes())
+            .await?;
+
+        let mut pairs = Vec::new();
+        let mut data = Vec::new();
+        // How much of the response has been read, which is limited so that
+        // a response which never ends can't use up all of our memory.
+        let mut read = 0;
+        loop {
+            let line = self.read_line().await?;
+            read = untrusted::check_len(read + line.len(), untrusted::MAX_MESSAGE)?;
+            match parse_line(&line)? {
+                Line::Ok => return Ok((pairs, data)),
+                Line::Pair(key, value) if key == "binary" => {
+                    let length: usize = value.parse()?;
+                    read = untrusted::check_len(read + length, untrusted::MAX_MESSAGE)?;
+                    data.resize(length, 0);
+                    self.reader.read_exact(&mut data).await?;
+                    // The data is followed by a newline.
+                    self.reader.read_u8().await?;
+                }
+                Line::Pair(key, value) => pairs.push((key, value)),
+            }
+        }
+    }
+
+    // Reads the whole of a picture of the song `uri` with `command`, which
+    // is either `readpicture` (for a picture embedded in the song) or
+    // `albumart` (for a `cover.*` file next to it). MPD sends pictures in
+    // chunks, so this asks for each in turn.
+    //
+    // Returns `None` if there is no picture.
+    #[cfg(feature = "album-art")]
+    pub async fn picture(&mut self, command: &str, uri: &str) -> Result<Option<Vec<u8>>> {
+        let mut picture = Vec::new();
+        loop {
+            let (pairs, chunk) = self
+                .binary_command(&format!("{command} {} {}", quote(uri), picture.len()))
+                .await?;
+            let size: usize = match pairs.iter().find(|(key, _)| key == "size") {
+                Some((_, size)) => untrusted::check_len(size.parse()?, untrusted::MAX_MESSAGE)?,
+                None => return Ok(None),
+            };
+            if chunk.is_empty() {
+                bail!("MPD sent an empty chunk of {}", uri);
+            }
+            picture.extend(chunk);
+            if picture.len() >= size {
+                return Ok(Some(picture));
+            }
+        }
+    }
+
+    // Waits until there is a change in one of the given `subsystems`.
+    pub async fn idle(&mut self, subsystems: &[&str]) -> Result<()> {
+        self.command(&format!("idle {}", subsystems.join(" ")))
+            .await?;
+        Ok(())
+    }
+}
+
+// Reads `response` as MPD's replies to commands, for the `mpd_response` fuzz
+// target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(response: &[u8]) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let reader: Box<dyn AsyncRead + Unpin> = Box::new(std::io::Cursor::new(response.to_vec()));
+    let mut client = Client {
+        reader: BufReader::new(reader),
+        writer: Box::new(tokio::io::sink()),
+    };
+    runtime.block_on(async {
+        let _ = client.command("status").await;
+        let _ = client.picture("albumart", "song.flac").await;
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(parse_line("OK").unwrap(), Line::Ok);
+        assert_eq!(
+            parse_line("Title: Hey: Jude").unwrap(),
+            Line::Pair("Title".to_owned(), "Hey: Jude".to_owned())
+        );
+        assert!(parse_line("ACK [50@0] {play} No such song").is_err());
+        assert!(parse_line("garbage").is_err());
+    }
+
+    #[test]
+    fn quote_arguments() {
+        assert_eq!(quote(r#"pa"ss\word"#), r#""pa\"ss\\word""#);
+    }
+
+    #[test]
+    fn split_passwords() {
+        assert_eq!(split_password("localhost:6600"), (None, "localhost:6600"));
+        assert_eq!(
+            split_password("secret@/run/mpd/socket"),
+            (Some("secret"), "/run/mpd/socket")
+        );
+        assert_eq!(
+            split_password("p@ss@localhost:6600"),
+            (Some("p@ss"), "localhost:6600")
+        );
+    }
+}
@@ -0,0 +1,595 @@
+use crate::history::History;
+use crate::netlink::{attr, Socket};
+use crate::state;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use nix::net::if_::if_nametoindex;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{self as stream, Stream, StreamExt};
+
+// See `include/uapi/linux/rtnetlink.h`, `if_link.h` and `if_addr.h` in the
+// kernel sources.
+const RTM_GETLINK: u16 = 18;
+pub(crate) const RTM_GETADDR: u16 = 22;
+const RTNLGRP_LINK: u32 = 1;
+pub(crate) const RTNLGRP_IPV4_IFADDR: u32 = 5;
+pub(crate) const RTNLGRP_IPV6_IFADDR: u32 = 9;
+const IFLA_CARRIER: u16 = 33;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const RT_SCOPE_UNIVERSE: u8 = 0;
+
+// The sizes of `struct ifinfomsg` and `struct ifaddrmsg`.
+const IFINFOMSG_LEN: usize = 16;
+pub(crate) const IFADDRMSG_LEN: usize = 8;
+
+/// The state of a network interface's link.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkState {
+    /// The interface doesn't exist, e.g. a USB adapter isn't plugged in.
+    Absent,
+    /// The interface has been brought down.
+    Down,
+    /// The interface is up, but has no carrier, e.g. the cable is unplugged.
+    NoCarrier,
+    /// The interface is up and has a carrier.
+    Up,
+}
+
+/// The state and addresses of a network interface, as shown by
+/// [`NetworkLink`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkInfo {
+    /// The name of the interface, e.g. `enp3s0`
+    pub interface: String,
+    pub state: LinkState,
+    /// The interface's first global IPv4 address
+    pub ipv4: Option<Ipv4Addr>,
+    /// The interface's first global IPv6 address
+    pub ipv6: Option<Ipv6Addr>,
+    /// All of the interface's global addresses, IPv4 and IPv6
+    pub addresses: Vec<IpAddr>,
+}
+
+impl LinkInfo {
+    fn absent(interface: &str) -> Self {
+        LinkInfo {
+            interface: interface.to_owned(),
+            state: LinkState::Absent,
+            ipv4: None,
+            ipv6: None,
+            addresses: Vec::new(),
+        }
+    }
+
+    // Returns the text of the popup listing the interface's addresses.
+    fn popup_body(&self) -> String {
+        if self.addresses.is_empty() {
+            return i18n::tr("No addresses");
+        }
+        self.addresses
+            .iter()
+            .map(|address| match address {
+                IpAddr::V4(address) => format!("IPv4 {address}"),
+                IpAddr::V6(address) => format!("IPv6 {address}"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Returns the state of the link in the reply to `RTM_GETLINK`.
+fn parse_link(reply: &[u8]) -> LinkState {
+    let flags = reply
+        .get(8..12)
+        .and_then(|flags| Some(u32::from_ne_bytes(flags.try_into().ok()?)))
+        .unwrap_or_default();
+    let attributes = reply.get(IFINFOMSG_LEN..).unwrap_or_default();
+    // `IFLA_CARRIER` is missing on old kernels, where `IFF_LOWER_UP` says
+    // the same.
+    let carrier = match attr(attributes, IFLA_CARRIER) {
+        Some(carrier) => carrier.first() == Some(&1),
+        None => flags & libc::IFF_LOWER_UP as u32 != 0,
+    };
+    match (flags & libc::IFF_UP as u32 != 0, carrier) {
+        (false, _) => LinkState::Down,
+        (true, false) => LinkState::NoCarrier,
+        (true, true) => LinkState::Up,
+    }
+}
+
+// Returns the address in a reply to `RTM_GETADDR`, if it is a global address
+// of the interface with the given index.
+pub(crate) fn parse_address(reply: &[u8], index: u32) -> Option<IpAddr> {
+    let family = i32::from(*reply.first()?);
+    let scope = *reply.get(3)?;
+    let reply_index = u32::from_ne_bytes(reply.get(4..8)?.try_into().ok()?);
+    if reply_index != index || scope != RT_SCOPE_UNIVERSE {
+        return None;
+    }
+    // On point-to-point links, `IFA_ADDRESS` is the peer's address.
+    let attributes = reply.get(IFADDRMSG_LEN..)?;
+    let address = attr(attributes, IFA_LOCAL).or_else(|| attr(attributes, IFA_ADDRESS))?;
+    match family {
+        libc::AF_INET => Some(IpAddr::from(<[u8; 4]>::try_from(address).ok()?)),
+        libc::AF_INET6 => Some(IpAddr::from(<[u8; 16]>::try_from(address).ok()?)),
+        _ => None,
+    }
+}
+
+// Asks the kernel over rtnetlink for the state and addresses of `interface`.
+fn link_info(socket: &mut Socket, interface: &str) -> Result<LinkInfo> {
+    let index = match if_nametoindex(interface) {
+        Ok(index) => index,
+        Err(_) => return Ok(LinkInfo::absent(interface)),
+    };
+
+    let mut ifinfomsg = vec![0; IFINFOMSG_LEN];
+    ifinfomsg[4..8].copy_from_slice(&index.to_ne_bytes());
+    let state = socket
+        .request(RTM_GETLINK, 0, &ifinfomsg)?
+        .first()
+        .map_or(LinkState::Absent, |reply| parse_link(reply));
+
+    // The kernel doesn't filter address dumps by interface.
+    let ifaddrmsg = vec![0; IFADDRMSG_LEN];
+    let addresses = socket.request(RTM_GETADDR, libc::NLM_F_DUMP as u16, &ifaddrmsg)?;
+    let mut info = LinkInfo {
+        state,
+        ..LinkInfo::absent(interface)
+    };
+    for address in addresses
+        .iter()
+        .filter_map(|reply| parse_address(reply, index))
+    {
+        match address {
+            IpAddr::V4(address) => {
+                info.ipv4.get_or_insert(address);
+            }
+            IpAddr::V6(address) => {
+                info.ipv6.get_or_insert(address);
+            }
+        }
+        info.addresses.push(address);
+    }
+    Ok(info)
+}
+
+// Returns the number of bytes `interface` has received and sent, or `None`
+// if it doesn't exist.
+fn transferred_bytes(interface: &str) -> Option<u64> {
+    let read = |name: &str| -> Option<u64> {
+        let path = format!("/sys/class/net/{interface}/statistics/{name}");
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    Some(read("rx_bytes")? + read("tx_bytes")?)
+}
+
+// The throughput of the shown interface over the last samples, and what it
+// is measured against.
+struct Throughput {
+    history: History<f64>,
+    width: f64,
+    // The interface, its byte count, and when it was read.
+    previous: Option<(String, u64, Instant)>,
+}
+
+impl Throughput {
+    // Adds the bytes per second transferred by `interface` since the last
+    // sample. The history starts again when the shown interface changes.
+    fn sample(&mut self, interface: &str, bytes: Option<u64>, now: Instant) {
+        let previous = self.previous.take();
+        let Some(bytes) = bytes else {
+            return;
+        };
+        match previous {
+            Some((previous_interface, previous_bytes, then)) if previous_interface == interface => {
+                let seconds = now.duration_since(then).as_secs_f64();
+                // The counters are reset when a driver is reloaded.
+                if seconds > 0.0 && bytes >= previous_bytes {
+                    self.history.push((bytes - previous_bytes) as f64 / seconds);
+                }
+            }
+            _ => self.history = History::new(self.history.capacity()),
+        }
+        self.previous = Some((interface.to_owned(), bytes, now));
+    }
+
+    // Returns a histogram of the samples, scaled to the largest one.
+    fn text(&self, attr: &Attributes) -> Text {
+        let max = self.history.iter().copied().fold(1.0, f64::max);
+        Text {
+            attr: attr.clone(),
+            text: String::new(),
+            stretch: false,
+            markup: false,
+            canvas: Some(self.history.histogram(self.width, max)),
+        }
+    }
+}
+
+// Returns the index of the interface after (or before) `selected`.
+fn cycle(selected: usize, len: usize, forward: bool) -> usize {
+    match forward {
+        true => (selected + 1) % len,
+        false => (selected + len - 1) % len,
+    }
+}
+
+/// Shows whether a network interface (e.g. an Ethernet port) is up and has a
+/// carrier, and its IP address.
+///
+/// This asks the kernel over rtnetlink, and is updated as soon as the
+/// interface goes up or down, a cable is plugged in or unplugged, or an
+/// address is added or removed.
+///
+/// The widget can be given several interfaces with
+/// [`NetworkLink::with_interfaces`], e.g. `wlan0`, `eth0` and `tun0`, and
+/// scrolling over it cycles through which one is shown. The selection is kept
+/// in `$XDG_STATE_HOME/cnx` between runs. Left clicking the widget opens a
+/// popup listing all of the shown interface's addresses, with `notify-send`
+/// by default.
+///
+/// With [`NetworkLink::with_history`], the widget also draws a histogram of
+/// the shown interface's recent throughput.
+pub struct NetworkLink {
+    attr: Attributes,
+    interfaces: Vec<String>,
+    down_color: Option<Color>,
+    popup_command: String,
+    history: Option<(usize, f64)>,
+    render: Option<Box<dyn Fn(LinkInfo) -> String>>,
+}
+
+impl NetworkLink {
+    /// Creates a new [`NetworkLink`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `interface` - The name of the network interface, e.g. `enp3s0`. You
+    /// can find it using the `ip link` command.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`LinkInfo`] is the interface's state and
+    /// addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::network_link::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|info: LinkInfo| match (info.state, info.ipv4) {
+    ///     (LinkState::Up, Some(ipv4)) => format!("🖧 {ipv4}"),
+    ///     (LinkState::Up, None) => "🖧".to_owned(),
+    ///     _ => "🖧 ✗".to_owned(),
+    /// });
+    /// cnx.add_widget(NetworkLink::new(attr, "enp3s0".to_owned(), Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        interface: String,
+        render: Option<Box<dyn Fn(LinkInfo) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            interfaces: vec![interface],
+            down_color: None,
+            popup_command: "notify-send".to_owned(),
+            history: None,
+            render,
+        }
+    }
+
+    /// Adds more interfaces, after the one given to [`NetworkLink::new`],
+    /// which scrolling over the widget cycles through.
+    pub fn with_interfaces(mut self, interfaces: Vec<String>) -> Self {
+        self.interfaces.extend(interfaces);
+        self
+    }
+
+    /// Sets the program run to open the popup listing the interface's
+    /// addresses, which is given the title and the text of the popup as its
+    /// arguments. The default is `notify-send`.
+    pub fn with_popup_command(self, popup_command: String) -> Self {
+        Self {
+            popup_command,
+            ..self
+        }
+    }
+
+    /// Shows the widget in `down_color` while the link isn't up.
+    pub fn with_down_color(self, down_color: Color) -> Self {
+        Self {
+            down_color: Some(down_color),
+            ..self
+        }
+    }
+
+    /// Draws a histogram of the interface's throughput, received and sent,
+    /// over the last `samples` seconds after the text. The histogram is
+    /// `width` pixels wide and scaled to the busiest second shown.
+    pub fn with_history(self, samples: usize, width: f64) -> Self {
+        Self {
+            history: Some((samples, width)),
+            ..self
+        }
+    }
+
+    // Returns the index of the interface selected in an earlier run, or the
+    // first.
+    fn load_selected(&self) -> usize {
+        state::read(&self.state_name())
+            .and_then(|selected| {
+                self.interfaces
+                    .iter()
+                    .position(|interface| *interface == selected.trim())
+            })
+            .unwrap_or(0)
+    }
+
+    fn save_selected(&self, selected: usize) -> Result<()> {
+        state::write(&self.state_name(), &self.interfaces[selected])
+    }
+
+    // The name of the file in which the interface selected by scrolling is
+    // kept between runs, after the widget's first interface.
+    fn state_name(&self) -> String {
+        format!("network-link-{}", self.interfaces[0])
+    }
+
+    async fn popup(&self, info: &LinkInfo) -> Result<()> {
+        Command::new(&self.popup_command)
+            .arg(&info.interface)
+            .arg(info.popup_body())
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {}", self.popup_command))?;
+        Ok(())
+    }
+
+    fn render(&self, info: LinkInfo, throughput: Option<&Throughput>) -> Vec<Text> {
+        let interface = &info.interface;
+        let default_text = || match (info.state, info.ipv4, info.ipv6) {
+            (LinkState::Absent, _, _) => format!("{interface} {}", i18n::tr("absent")),
+            (LinkState::Down, _, _) => format!("{interface} {}", i18n::tr("down")),
+            (LinkState::NoCarrier, _, _) => format!("{interface} {}", i18n::tr("unplugged")),
+            (LinkState::Up, Some(ipv4), _) => format!("{interface} {ipv4}"),
+            (LinkState::Up, None, Some(ipv6)) => format!("{interface} {ipv6}"),
+            (LinkState::Up, None, None) => format!("{interface} {}", i18n::tr("up")),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(info.clone()));
+
+        let mut attr = self.attr.clone();
+        if let (Some(color), false) = (&self.down_color, info.state == LinkState::Up) {
+            attr.fg_color = color.clone();
+        }
+
+        let mut texts = vec![Text {
+            attr: attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }];
+        if let Some(throughput) = throughput {
+            texts.push(throughput.text(&attr));
+        }
+        texts
+    }
+}
+
+enum Event {
+    Changed(Result<()>),
+    Tick,
+    Scroll { forward: bool },
+    Click,
+}
+
+impl Widget for NetworkLink {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut socket =
+            Socket::open(libc::NETLINK_ROUTE, false).context("Initialising NetworkLink")?;
+        let events = Socket::open(libc::NETLINK_ROUTE, true).context("Initialising NetworkLink")?;
+        for group in [RTNLGRP_LINK, RTNLGRP_IPV4_IFADDR, RTNLGRP_IPV6_IFADDR] {
+            events.subscribe(group)?;
+        }
+
+        // Changes to other interfaces are reported too, but they are rare
+        // enough that we don't bother filtering them out.
+        let changes = stream::once(Ok(()))
+            .chain(events.events()?)
+            .map(Event::Changed);
+        let clicks = clicks.filter_map(|click| match click.button {
+            MouseButton::ScrollUp => Some(Event::Scroll { forward: true }),
+            MouseButton::ScrollDown => Some(Event::Scroll { forward: false }),
+            MouseButton::Left => Some(Event::Click),
+            _ => None,
+        });
+        // The throughput is only sampled if it is drawn.
+        let ticks: Pin<Box<dyn Stream<Item = Event>>> = match self.history {
+            Some(_) => {
+                let interval = time::interval(Duration::from_secs(1));
+                Box::pin(IntervalStream::new(interval).map(|_| Event::Tick))
+            }
+            None => Box::pin(stream::empty()),
+        };
+        let mut events = Box::pin(changes.merge(clicks).merge(ticks));
+
+        let stream = stream! {
+            let mut selected = self.load_selected();
+            let mut throughput = self.history.map(|(samples, width)| Throughput {
+                history: History::new(samples),
+                width,
+                previous: None,
+            });
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Changed(Err(err)) => {
+                        yield Err(err);
+                        continue;
+                    }
+                    Event::Changed(Ok(())) => {}
+                    Event::Tick => {
+                        if let Some(throughput) = &mut throughput {
+                            let interface = &self.interfaces[selected];
+                            throughput.sample(interface, transferred_bytes(interface), Instant::now());
+                        }
+                    }
+                    Event::Scroll { forward } => {
+                        // There's nothing to cycle through.
+                        if self.interfaces.len() == 1 {
+                            continue;
+                        }
+                        selected = cycle(selected, self.interfaces.len(), forward);
+                        if let Err(err) = self.save_selected(selected) {
+                            yield Err(err);
+                        }
+                    }
+                    Event::Click => {
+                        let result = match link_info(&mut socket, &self.interfaces[selected]) {
+                            Ok(info) => self.popup(&info).await,
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = result {
+                            yield Err(err);
+                        }
+                        continue;
+                    }
+                }
+                yield link_info(&mut socket, &self.interfaces[selected])
+                    .map(|info| self.render(info, throughput.as_ref()));
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::netlink::put_attr;
+
+    #[test]
+    fn link() {
+        let ifinfomsg = |flags: i32, carrier: Option<u8>| {
+            let mut reply = vec![0; IFINFOMSG_LEN];
+            reply[8..12].copy_from_slice(&(flags as u32).to_ne_bytes());
+            if let Some(carrier) = carrier {
+                put_attr(&mut reply, IFLA_CARRIER, &[carrier]);
+            }
+            reply
+        };
+        let up = libc::IFF_UP | libc::IFF_RUNNING;
+        assert_eq!(parse_link(&ifinfomsg(0, Some(0))), LinkState::Down);
+        assert_eq!(parse_link(&ifinfomsg(up, Some(0))), LinkState::NoCarrier);
+        assert_eq!(parse_link(&ifinfomsg(up, Some(1))), LinkState::Up);
+        let lower_up = up | libc::IFF_LOWER_UP;
+        assert_eq!(parse_link(&ifinfomsg(lower_up, None)), LinkState::Up);
+    }
+
+    #[test]
+    fn cycle_interfaces() {
+        assert_eq!(cycle(0, 3, true), 1);
+        assert_eq!(cycle(2, 3, true), 0);
+        assert_eq!(cycle(0, 3, false), 2);
+        assert_eq!(cycle(0, 1, false), 0);
+    }
+
+    #[test]
+    fn throughput() {
+        let mut throughput = Throughput {
+            history: History::new(4),
+            width: 40.0,
+            previous: None,
+        };
+        let start = Instant::now();
+        let after = |seconds| start + Duration::from_secs(seconds);
+        throughput.sample("wlan0", Some(1000), start);
+        throughput.sample("wlan0", Some(3000), after(2));
+        throughput.sample("wlan0", Some(3500), after(3));
+        let samples =
+            |throughput: &Throughput| throughput.history.iter().copied().collect::<Vec<_>>();
+        assert_eq!(samples(&throughput), vec![1000.0, 500.0]);
+
+        // Switching to another interface starts again.
+        throughput.sample("eth0", Some(100), after(4));
+        assert!(throughput.history.is_empty());
+        // A reset counter is skipped rather than shown as a huge spike.
+        throughput.sample("eth0", Some(50), after(5));
+        throughput.sample("eth0", Some(150), after(6));
+        assert_eq!(samples(&throughput), vec![100.0]);
+    }
+
+    #[test]
+    fn popup() {
+        let mut info = LinkInfo::absent("wlan0");
+        assert_eq!(info.popup_body(), "No addresses");
+        let ipv6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        info.addresses = vec![IpAddr::from([192, 168, 1, 20]), IpAddr::V6(ipv6)];
+        assert_eq!(info.popup_body(), "IPv4 192.168.1.20\nIPv6 2001:db8::1");
+    }
+
+    #[test]
+    fn address() {
+        let ifaddrmsg = |family: i32, scope: u8, index: u32, address: &[u8]| {
+            let mut reply = vec![family as u8, 24, 0, scope];
+            reply.extend_from_slice(&index.to_ne_bytes());
+            put_attr(&mut reply, IFA_ADDRESS, address);
+            put_attr(&mut reply, IFA_LOCAL, address);
+            reply
+        };
+        let ipv4 = ifaddrmsg(libc::AF_INET, 0, 2, &[192, 168, 1, 20]);
+        assert_eq!(
+            parse_address(&ipv4, 2),
+            Some(IpAddr::from([192, 168, 1, 20]))
+        );
+        assert_eq!(parse_address(&ipv4, 3), None);
+
+        let ipv6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let reply = ifaddrmsg(libc::AF_INET6, 0, 2, &ipv6.octets());
+        assert_eq!(parse_address(&reply, 2), Some(IpAddr::V6(ipv6)));
+
+        // Link-local addresses aren't shown.
+        let link_local = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let reply = ifaddrmsg(libc::AF_INET6, 253, 2, &link_local.octets());
+        assert_eq!(parse_address(&reply, 2), None);
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::process::Command;
+use std::time::Duration;
+use tokio::time;
+
+/// The package manager [`PackageUpdates`] asks for pending updates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Manager {
+    /// pacman, through `checkupdates` from pacman-contrib, which syncs a copy
+    /// of the package databases so that it doesn't need root
+    Pacman,
+    /// apt, through `apt list --upgradable`, which only knows of the updates
+    /// in the package lists as they were last updated, e.g. by
+    /// `unattended-upgrades` or a timer running `apt update`
+    Apt,
+    /// dnf, through `dnf check-update`
+    Dnf,
+}
+
+impl Manager {
+    fn command(self) -> Command {
+        let (program, args): (_, &[_]) = match self {
+            Manager::Pacman => ("checkupdates", &[]),
+            Manager::Apt => ("apt", &["list", "--upgradable"]),
+            Manager::Dnf => ("dnf", &["--quiet", "check-update"]),
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+
+    // Returns whether the command succeeded, from its exit code. Both
+    // `checkupdates` and `dnf check-update` use one to say whether there are
+    // any updates.
+    fn succeeded(self, code: Option<i32>) -> bool {
+        match self {
+            Manager::Pacman => matches!(code, Some(0) | Some(2)),
+            Manager::Apt => code == Some(0),
+            Manager::Dnf => matches!(code, Some(0) | Some(100)),
+        }
+    }
+}
+
+/// Shows how many package updates are pending, by running the package
+/// manager's check, hourly by default. The widget is hidden while there are
+/// none.
+///
+/// The check runs without blocking the bar, as it can take a while, e.g.
+/// while `checkupdates` syncs its databases, and is killed if the widget is
+/// dropped, e.g. as Cnx stops, before it finishes.
+pub struct PackageUpdates {
+    attr: Attributes,
+    manager: Manager,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(usize) -> String>>,
+}
+
+impl PackageUpdates {
+    /// Creates a new [`PackageUpdates`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `manager` - The package manager to ask.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the number of pending updates,
+    /// which is never zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::package_updates::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|updates| format!("⬆ {updates}"));
+    /// cnx.add_widget(PackageUpdates::new(attr, Manager::Pacman, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        manager: Manager,
+        render: Option<Box<dyn Fn(usize) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            manager,
+            update_interval: Duration::from_secs(60 * 60),
+            render,
+        }
+    }
+
+    /// Sets how often to check for updates. The default is every hour.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, updates: usize) -> Vec<Text> {
+        if updates == 0 {
+            return Vec::new();
+        }
+        let text = self.render.as_ref().map_or_else(
+            || i18n::tr_with("Updates {}", &updates.to_string()),
+            |x| (x)(updates),
+        );
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Runs the package manager's check and counts the updates it lists.
+async fn pending_updates(manager: Manager) -> Result<usize> {
+    let command = manager.command();
+    let description = format!("{command:?}");
+    // The check may take a while, e.g. to sync the package databases, so
+    // it's killed if the widget is dropped before it finishes.
+    let output = tokio::process::Command::from(command)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {description}"))?;
+    if !manager.succeeded(output.status.code()) {
+        return Err(anyhow!(
+            "{} exited with {}: {}",
+            description,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(count_updates(
+        manager,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+// Counts the updates listed in the output of a package manager's check.
+fn count_updates(manager: Manager, output: &str) -> usize {
+    match manager {
+        // e.g. `linux 6.6.1.arch1-1 -> 6.6.2.arch1-1`
+        Manager::Pacman => output.lines().filter(|line| line.contains(" -> ")).count(),
+        // e.g. `curl/stable 7.88.1-10+deb12u5 amd64 [upgradable from: 7.88.1-10+deb12u4]`
+        Manager::Apt => output
+            .lines()
+            .filter(|line| line.contains("[upgradable from:"))
+            .count(),
+        // e.g. `curl.x86_64    8.2.1-3.fc39    updates`, followed by any
+        // packages that are obsoleted, which are already counted.
+        Manager::Dnf => output
+            .lines()
+            .take_while(|line| !line.starts_with("Obsoleting"))
+            .filter(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                fields.len() == 3 && fields[0].contains('.')
+            })
+            .count(),
+    }
+}
+
+impl Widget for PackageUpdates {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                let updates = pending_updates(self.manager).await;
+                yield updates.map(|updates| self.render(updates));
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pacman() {
+        let output = "\
+linux 6.6.1.arch1-1 -> 6.6.2.arch1-1
+firefox 120.0-1 -> 120.0.1-1
+";
+        assert_eq!(count_updates(Manager::Pacman, output), 2);
+        assert_eq!(count_updates(Manager::Pacman, ""), 0);
+    }
+
+    #[test]
+    fn apt() {
+        let output = "\
+Listing...
+curl/stable 7.88.1-10+deb12u5 amd64 [upgradable from: 7.88.1-10+deb12u4]
+libcurl4/stable 7.88.1-10+deb12u5 amd64 [upgradable from: 7.88.1-10+deb12u4]
+";
+        assert_eq!(count_updates(Manager::Apt, output), 2);
+        assert_eq!(count_updates(Manager::Apt, "Listing...\n"), 0);
+    }
+
+    #[test]
+    fn dnf() {
+        let output = "
+curl.x86_64                       8.2.1-3.fc39                  updates
+kernel.x86_64                     6.6.2-201.fc39                updates
+libcurl.x86_64                    8.2.1-3.fc39                  updates
+Obsoleting Packages
+grub2-tools.x86_64                1:2.06-108.fc39               updates
+    grub2-tools.x86_64            1:2.06-100.fc39               @updates
+";
+        assert_eq!(count_updates(Manager::Dnf, output), 3);
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert!(Manager::Pacman.succeeded(Some(2)));
+        assert!(!Manager::Pacman.succeeded(Some(1)));
+        assert!(Manager::Dnf.succeeded(Some(100)));
+        assert!(!Manager::Apt.succeeded(None));
+    }
+}
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Color, Ramp, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, Instant};
+use tokio::net::{self, TcpStream, UdpSocket};
+use tokio::time;
+
+// The types of ICMP and ICMPv6 echo requests and replies.
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+// How many of the latest probes the packet loss is computed over.
+const LOSS_WINDOW: usize = 10;
+
+/// How [`Ping`] probes the host.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Probe {
+    /// Send ICMP echo requests, as `ping` does.
+    ///
+    /// These are sent on an unprivileged ICMP socket, which is only allowed
+    /// if the user's group is in the range of the `net.ipv4.ping_group_range`
+    /// sysctl, as it is by default on most distributions.
+    Icmp,
+    /// Connect to a TCP port, e.g. 443, for hosts or networks which drop
+    /// ICMP. A refused connection counts as a reply.
+    Tcp(u16),
+}
+
+/// The round-trip time to a host and the recent packet loss, as shown by
+/// [`Ping`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PingInfo {
+    /// The round-trip time of the latest probe, or `None` if it got no reply
+    pub rtt: Option<Duration>,
+    /// The percentage of the last 10 probes which got no reply
+    pub loss: f64,
+}
+
+// Returns the percentage of `replies` which are `false`.
+fn loss(replies: &VecDeque<bool>) -> f64 {
+    match replies.len() {
+        0 => 0.0,
+        len => replies.iter().filter(|reply| !**reply).count() as f64 / len as f64 * 100.0,
+    }
+}
+
+// Returns an ICMP or ICMPv6 echo request with sequence number `seq`. The
+// kernel fills in the identifier and checksum.
+fn echo_request(ipv6: bool, seq: u16) -> Vec<u8> {
+    let kind = if ipv6 {
+        ICMPV6_ECHO_REQUEST
+    } else {
+        ICMP_ECHO_REQUEST
+    };
+    let mut request = vec![kind, 0, 0, 0, 0, 0];
+    request.extend_from_slice(&seq.to_be_bytes());
+    request.extend_from_slice(b"cnx ping");
+    request
+}
+
+// Returns whether `reply` is the echo reply to the request with sequence
+// number `seq`.
+fn is_echo_reply(reply: &[u8], ipv6: bool, seq: u16) -> bool {
+    let kind = if ipv6 {
+        ICMPV6_ECHO_REPLY
+    } else {
+        ICMP_ECHO_REPLY
+    };
+    reply.first() == Some(&kind) && reply.get(6..8) == Some(&seq.to_be_bytes()[..])
+}
+
+// Opens an unprivileged ICMP socket for the family of `addr`.
+fn icmp_socket(addr: &SocketAddr) -> Result<UdpSocket> {
+    let (domain, protocol) = match addr {
+        SocketAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+        SocketAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+    };
+    let kind = libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+    let fd = unsafe { libc::socket(domain, kind, protocol) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error()).context(
+            "Failed to open ICMP socket. Allow it with the net.ipv4.ping_group_range sysctl, \
+             or use Probe::Tcp",
+        );
+    }
+    // Ping sockets are datagram sockets, so they can be used as UDP ones.
+    let socket = unsafe { StdUdpSocket::from_raw_fd(fd) };
+    Ok(UdpSocket::from_std(socket)?)
+}
+
+/// Shows the round-trip time to a host, and how many of the recent probes
+/// got no reply.
+///
+/// The host is probed every 5 seconds by default, with ICMP echo requests or
+/// by connecting to a TCP port; see [`Probe`]. The color of the text can be
+/// picked from a [`Ramp`] of colors by round-trip time in milliseconds, with
+/// probes which got no reply using the last color; see
+/// [`Ping::with_colors`].
+pub struct Ping {
+    attr: Attributes,
+    host: String,
+    probe: Probe,
+    update_interval: Duration,
+    timeout: Duration,
+    colors: Option<Ramp<Color>>,
+    render: Option<Box<dyn Fn(PingInfo) -> String>>,
+}
+
+impl Ping {
+    /// Creates a new [`Ping`] widget, which sends ICMP echo requests.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `host` - The host name or IP address to probe, e.g. `1.1.1.1` or
+    /// your router's address.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`PingInfo`] is the latest round-trip time and
+    /// the recent packet loss.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::ping::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let colors = Ramp::roles(vec![
+    ///     (50.0, Role::Ok),
+    ///     (200.0, Role::Warn),
+    ///     (f64::INFINITY, Role::Crit),
+    /// ]);
+    /// let ping = Ping::new(attr, "1.1.1.1".to_owned(), None)
+    ///     .with_probe(Probe::Tcp(443))
+    ///     .with_colors(colors);
+    /// cnx.add_widget(ping);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        host: String,
+        render: Option<Box<dyn Fn(PingInfo) -> String>>,
+    ) -> Self {
+        Self {
+            attr,
+            host,
+            probe: Probe::Icmp,
+            update_interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            colors: None,
+            render,
+        }
+    }
+
+    /// Sets how the host is probed. The default is [`Probe::Icmp`].
+    pub fn with_probe(self, probe: Probe) -> Self {
+        Self { probe, ..self }
+    }
+
+    /// Sets how often the host is probed. The default is every 5 seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for a reply before counting a probe as lost.
+    /// The default is 2 seconds.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Colors the text with a color picked according to the round-trip time
+    /// in milliseconds.
+    pub fn with_colors(self, colors: Ramp<Color>) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    // Probes the host, returning the round-trip time, or `None` if there was
+    // no reply, e.g. because the network is down.
+    async fn probe(&self, seq: u16) -> Result<Option<Duration>> {
+        let port = match self.probe {
+            Probe::Icmp => 0,
+            Probe::Tcp(port) => port,
+        };
+        let addr = match net::lookup_host((self.host.as_str(), port)).await {
+            Ok(mut addrs) => addrs.next(),
+            Err(_) => None,
+        };
+        let addr = match addr {
+            Some(addr) => addr,
+            None => return Ok(None),
+        };
+
+        match self.probe {
+            Probe::Icmp => {
+                let socket = icmp_socket(&addr)?;
+                let ipv6 = addr.is_ipv6();
+                let start = Instant::now();
+                let reply = time::timeout(self.timeout, async {
+                    socket.connect(addr).await?;
+                    socket.send(&echo_request(ipv6, seq)).await?;
+                    let mut buf = [0; 64];
+                    loop {
+                        let len = socket.recv(&mut buf).await?;
+                        if is_echo_reply(&buf[..len], ipv6, seq) {
+                            return Ok::<_, io::Error>(());
+                        }
+                    }
+                })
+                .await;
+                Ok(matches!(reply, Ok(Ok(()))).then(|| start.elapsed()))
+            }
+            Probe::Tcp(_) => {
+                let start = Instant::now();
+                let reply = time::timeout(self.timeout, TcpStream::connect(addr)).await;
+                let replied = match reply {
+                    Ok(Ok(_)) => true,
+                    Ok(Err(err)) => err.kind() == io::ErrorKind::ConnectionRefused,
+                    Err(_) => false,
+                };
+                Ok(replied.then(|| start.elapsed()))
+            }
+        }
+    }
+
+    fn render(&self, info: PingInfo) -> Vec<Text> {
+        let default_text = || {
+            let rtt = match info.rtt {
+                Some(rtt) => format!("{} {:.0} ms", self.host, rtt.as_secs_f64() * 1000.0),
+                None => format!("{} {}", self.host, i18n::tr("timeout")),
+            };
+            match info.loss {
+                loss if loss > 0.0 => format!("{rtt} ({loss:.0}% {})", i18n::tr("loss")),
+                _ => rtt,
+            }
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(info));
+
+        let mut attr = self.attr.clone();
+        let ms = info
+            .rtt
+            .map_or(f64::INFINITY, |rtt| rtt.as_secs_f64() * 1000.0);
+        if let Some(color) = self.colors.as_ref().and_then(|colors| colors.get(ms)) {
+            attr.fg_color = color.clone();
+        }
+
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for Ping {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        if self.host.is_empty() {
+            return Err(anyhow!("Ping needs a host"));
+        }
+
+        let mut interval = time::interval(self.update_interval);
+        let stream = stream! {
+            let mut replies = VecDeque::with_capacity(LOSS_WINDOW);
+            let mut seq = 0u16;
+            loop {
+                interval.tick().await;
+                seq = seq.wrapping_add(1);
+                let rtt = match self.probe(seq).await {
+                    Ok(rtt) => rtt,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                if replies.len() == LOSS_WINDOW {
+                    replies.pop_front();
+                }
+                replies.push_back(rtt.is_some());
+                yield Ok(self.render(PingInfo { rtt, loss: loss(&replies) }));
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn echo() {
+        let request = echo_request(false, 0x1234);
+        assert_eq!(&request[..8], &[8, 0, 0, 0, 0, 0, 0x12, 0x34]);
+
+        // The kernel fills in the identifier and checksum of the reply too.
+        let mut reply = request.clone();
+        reply[0] = ICMP_ECHO_REPLY;
+        reply[2..6].copy_from_slice(&[0xab, 0xcd, 0x00, 0x07]);
+        assert!(is_echo_reply(&reply, false, 0x1234));
+        assert!(!is_echo_reply(&reply, false, 0x1235));
+        assert!(!is_echo_reply(&reply, true, 0x1234));
+        assert!(!is_echo_reply(&request, false, 0x1234));
+
+        let mut reply = echo_request(true, 7);
+        reply[0] = ICMPV6_ECHO_REPLY;
+        assert!(is_echo_reply(&reply, true, 7));
+    }
+
+    #[test]
+    fn packet_loss() {
+        assert_eq!(loss(&VecDeque::new()), 0.0);
+        let replies = VecDeque::from(vec![true, false, true, true]);
+        assert_eq!(loss(&replies), 25.0);
+    }
+}
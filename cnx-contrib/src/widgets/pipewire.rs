@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+
+/// The quantum and sample rate the PipeWire graph is running at, as shown by
+/// [`PipeWire`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Graph {
+    /// The name of the node driving the graph, e.g. a sound card's output
+    pub driver: String,
+    /// The number of samples processed in each cycle
+    pub quantum: u32,
+    /// The sample rate, in Hz
+    pub rate: u32,
+}
+
+impl Graph {
+    /// The latency of one cycle, i.e. the quantum's length in time.
+    pub fn latency(&self) -> Duration {
+        Duration::from_secs_f64(f64::from(self.quantum) / f64::from(self.rate))
+    }
+}
+
+/// Shows the quantum and sample rate the PipeWire graph is running at, e.g.
+/// `64/48kHz 1.3ms`, to confirm that it is running at the latency you
+/// expect, e.g. after setting `clock.force-quantum`.
+///
+/// This runs `pw-top` in batch mode, which takes a couple of seconds, every
+/// 10 seconds by default. While nothing is playing or recording, the graph
+/// isn't running, and `Audio idle` is shown.
+pub struct PipeWire {
+    attr: Attributes,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(Option<Graph>) -> String>>,
+}
+
+impl PipeWire {
+    /// Creates a new [`PipeWire`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`Graph`] that is running, if
+    /// any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::pipewire::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|graph: Option<Graph>| match graph {
+    ///     Some(graph) => format!("{:.1}ms", graph.latency().as_secs_f64() * 1000.0),
+    ///     None => String::new(),
+    /// });
+    /// cnx.add_widget(PipeWire::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(Option<Graph>) -> String>>) -> Self {
+        Self {
+            attr,
+            update_interval: Duration::from_secs(10),
+            render,
+        }
+    }
+
+    /// Sets how often to check the graph. The default is every 10 seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, graph: Option<Graph>) -> Vec<Text> {
+        let default_text = || match &graph {
+            Some(graph) => format!(
+                "{}/{} {:.1}ms",
+                graph.quantum,
+                format_rate(graph.rate),
+                graph.latency().as_secs_f64() * 1000.0
+            ),
+            None => i18n::tr("Audio idle"),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(graph.clone()));
+
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Asks `pw-top` for the graph that is running. Its first iteration may not
+// have measured anything yet, so it is run for two.
+async fn running_graph() -> Result<Option<Graph>> {
+    let output = Command::new("pw-top")
+        .args(["--batch-mode", "--iterations", "2"])
+        .output()
+        .await
+        .context("Failed to run pw-top")?;
+    if !output.status.success() {
+        return Err(anyhow!("pw-top exited with {}", output.status));
+    }
+    Ok(parse_pw_top(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Returns the first running driver in the last iteration of `pw-top`'s
+// output, e.g.
+//
+// ```text
+// S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+// S   28      0      0    ---     ---   ---   ---     0                  Dummy-Driver
+// R   39    256  48000  64.3us  17.2us  0.01  0.00    0    S16LE 2 48000 alsa_output.pci-0000_00_1f.3.analog-stereo
+// R   63    256  48000  37.9us  24.5us  0.01  0.01    0    F32LE 2 48000  + Firefox
+// ```
+//
+// where the nodes driven by a driver follow it, marked with `+`.
+fn parse_pw_top(output: &str) -> Option<Graph> {
+    let lines: Vec<&str> = output.lines().collect();
+    let iteration = lines
+        .iter()
+        .rposition(|line| line.split_whitespace().nth(1) == Some("ID"))
+        .map_or(&lines[..], |header| &lines[header + 1..]);
+    iteration.iter().find_map(|line| {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.first() != Some(&"R") || line.contains(" + ") {
+            return None;
+        }
+        let quantum: u32 = columns.get(2)?.parse().ok()?;
+        let rate: u32 = columns.get(3)?.parse().ok()?;
+        if quantum == 0 || rate == 0 {
+            return None;
+        }
+        Some(Graph {
+            driver: columns.last()?.to_string(),
+            quantum,
+            rate,
+        })
+    })
+}
+
+// Formats a sample rate, e.g. `48kHz` or `44.1kHz`.
+fn format_rate(rate: u32) -> String {
+    if rate % 1000 == 0 {
+        format!("{}kHz", rate / 1000)
+    } else {
+        format!("{:.1}kHz", f64::from(rate) / 1000.0)
+    }
+}
+
+impl Widget for PipeWire {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                yield running_graph().await.map(|graph| self.render(graph));
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pw_top() {
+        let output = "\
+S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+S   28      0      0    ---     ---   ---   ---     0                  Dummy-Driver
+R   39   1024  48000    ---     ---   ---   ---     0    S16LE 2 48000 alsa_output.analog-stereo
+S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+S   28      0      0    ---     ---   ---   ---     0                  Dummy-Driver
+R   63    256  44100  37.9us  24.5us  0.01  0.01    0    F32LE 2 44100  + Firefox
+R   39    256  44100  64.3us  17.2us  0.01  0.00    0    S16LE 2 44100 alsa_output.analog-stereo
+";
+        let graph = parse_pw_top(output).unwrap();
+        assert_eq!(
+            graph,
+            Graph {
+                driver: "alsa_output.analog-stereo".to_owned(),
+                quantum: 256,
+                rate: 44100,
+            }
+        );
+        assert_eq!(format_rate(graph.rate), "44.1kHz");
+        assert_eq!(format_rate(48000), "48kHz");
+
+        let idle = "\
+S   ID  QUANT   RATE    WAIT    BUSY   W/Q   B/Q  ERR FORMAT           NAME
+S   28      0      0    ---     ---   ---   ---     0                  Dummy-Driver
+";
+        assert_eq!(parse_pw_top(idle), None);
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::{self, Instant};
+use tokio_stream::{self as stream, StreamExt};
+
+/// The phases a [`Pomodoro`] session goes through.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// Waiting to be started
+    Idle,
+    /// Working on something, 25 minutes by default
+    Work,
+    /// A short break after working, 5 minutes by default
+    ShortBreak,
+    /// A long break after every fourth time working, 15 minutes by default
+    LongBreak,
+}
+
+/// A Taskwarrior task, which is started while working on it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Task {
+    /// The task's UUID
+    pub uuid: String,
+    /// What the task is
+    pub description: String,
+}
+
+/// The state of a [`Pomodoro`] session, as given to its render closure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Session {
+    /// The phase the session is in
+    pub phase: Phase,
+    /// How long is left of the phase, which is zero while idle
+    pub remaining: Duration,
+    /// The task being worked on, if Taskwarrior is used
+    pub task: Option<Task>,
+    /// How many times the work phase has been completed
+    pub completed: u32,
+}
+
+type Render = Box<dyn Fn(&Session) -> String>;
+
+/// A Pomodoro timer, which alternates working with short breaks, and a long
+/// break after every fourth time working, e.g. `Work 24:59` or
+/// `Break 04:59`.
+///
+/// Left clicking the widget starts working, or stops the session if it has
+/// already started. Right clicking it skips to the next phase, without
+/// counting the work phase as completed. After a break, the widget waits to
+/// be started again.
+///
+/// With the `json` feature and `Pomodoro::with_taskwarrior()`, starting to
+/// work also runs `task start` on the most urgent task matching a filter,
+/// which is shown while working, and `task stop` is run when the work phase
+/// ends, so that Taskwarrior tracks the time spent on it. A task is left
+/// started if Cnx exits while working.
+pub struct Pomodoro {
+    attr: Attributes,
+    work: Duration,
+    short_break: Duration,
+    long_break: Duration,
+    #[cfg(feature = "json")]
+    task_filter: Option<String>,
+    render: Option<Render>,
+}
+
+impl Pomodoro {
+    /// Creates a new [`Pomodoro`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`Session`]'s state, every
+    /// second while a phase is running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::pomodoro::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Pomodoro::new(attr, None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            #[cfg(feature = "json")]
+            task_filter: None,
+            render,
+        }
+    }
+
+    /// Sets how long to work, and how long the short and long breaks are.
+    /// The defaults are 25, 5 and 15 minutes.
+    pub fn with_durations(
+        self,
+        work: Duration,
+        short_break: Duration,
+        long_break: Duration,
+    ) -> Self {
+        Self {
+            work,
+            short_break,
+            long_break,
+            ..self
+        }
+    }
+
+    /// Starts the most urgent pending task matching `filter`, a Taskwarrior
+    /// filter such as `+next` or `project:home`, while working, and shows
+    /// what it is. The filter is split into arguments at whitespace.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn with_taskwarrior(self, filter: &str) -> Self {
+        Self {
+            task_filter: Some(filter.to_owned()),
+            ..self
+        }
+    }
+
+    fn length(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Idle => Duration::ZERO,
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        }
+    }
+
+    // The break to take after completing the work phase `completed` times.
+    fn break_after(&self, completed: u32) -> Phase {
+        if completed > 0 && completed % 4 == 0 {
+            Phase::LongBreak
+        } else {
+            Phase::ShortBreak
+        }
+    }
+
+    // Moves the session on to `phase`, stopping the task when the work phase
+    // ends and starting one when it begins.
+    async fn enter(&self, session: &mut Session, phase: Phase) -> Result<()> {
+        let mut result = Ok(());
+        if let Some(task) = session.task.take() {
+            result = taskwarrior(&[task.uuid.as_str(), "stop"]).await.map(drop);
+        }
+        session.phase = phase;
+        session.remaining = self.length(phase);
+        #[cfg(feature = "json")]
+        if let (Phase::Work, Some(filter)) = (phase, &self.task_filter) {
+            match start_task(filter).await {
+                Ok(task) => session.task = task,
+                Err(err) => result = Err(err),
+            }
+        }
+        result
+    }
+
+    fn render(&self, session: &Session) -> Vec<Text> {
+        let text = match &self.render {
+            Some(render) => (render)(session),
+            None => {
+                let remaining = format_remaining(session.remaining);
+                match (session.phase, &session.task) {
+                    (Phase::Idle, _) => i18n::tr("Pomodoro"),
+                    (Phase::Work, Some(task)) => format!("{} {}", remaining, task.description),
+                    (Phase::Work, None) => i18n::tr_with("Work {}", &remaining),
+                    (Phase::ShortBreak | Phase::LongBreak, _) => {
+                        i18n::tr_with("Break {}", &remaining)
+                    }
+                }
+            }
+        };
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Formats the time left of a phase as minutes and seconds, rounding up so
+// that it reaches `00:00` as the phase ends.
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+async fn taskwarrior(args: &[&str]) -> Result<String> {
+    let output = Command::new("task")
+        .args(["rc.verbose=nothing", "rc.confirmation=off"])
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run task")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`task {}` exited with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Starts the most urgent pending task matching `filter`, if there is one.
+#[cfg(feature = "json")]
+async fn start_task(filter: &str) -> Result<Option<Task>> {
+    let mut args: Vec<&str> = filter.split_whitespace().collect();
+    args.extend(["status:pending", "export"]);
+    let Some(task) = most_urgent(&taskwarrior(&args).await?)? else {
+        return Ok(None);
+    };
+    taskwarrior(&[task.uuid.as_str(), "start"]).await?;
+    Ok(Some(task))
+}
+
+// Finds the most urgent task in the output of `task export`, a JSON array of
+// tasks, e.g.
+//
+// ```text
+// [
+// {"id":3,"description":"Write \"docs\"","status":"pending","uuid":"…","urgency":4.2},
+// ...
+// ]
+// ```
+#[cfg(feature = "json")]
+fn most_urgent(export: &str) -> Result<Option<Task>> {
+    let tasks: Vec<serde_json::Value> =
+        serde_json::from_str(export).context("Failed to parse `task export`")?;
+    let most = tasks
+        .iter()
+        .filter_map(|task| {
+            let urgency = task["urgency"].as_f64().unwrap_or(0.0);
+            let task = Task {
+                uuid: task["uuid"].as_str()?.to_owned(),
+                description: task["description"].as_str()?.to_owned(),
+            };
+            Some((task, urgency))
+        })
+        .fold(
+            None,
+            |most: Option<(Task, f64)>, (task, urgency)| match most {
+                Some(most) if most.1 >= urgency => Some(most),
+                _ => Some((task, urgency)),
+            },
+        );
+    Ok(most.map(|(task, _)| task))
+}
+
+impl Widget for Pomodoro {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks;
+        let stream = stream! {
+            let mut session = Session {
+                phase: Phase::Idle,
+                remaining: Duration::ZERO,
+                task: None,
+                completed: 0,
+            };
+            let mut ends = Instant::now();
+            loop {
+                if session.phase != Phase::Idle {
+                    session.remaining = ends.saturating_duration_since(Instant::now());
+                }
+                yield Ok(self.render(&session));
+
+                // Wake as the time left reaches a whole second, to show it.
+                let tick = match session.remaining.subsec_nanos() {
+                    0 => Duration::from_secs(1),
+                    nanos => Duration::from_nanos(nanos.into()),
+                };
+                let next = tokio::select! {
+                    _ = time::sleep(tick), if session.phase != Phase::Idle => {
+                        if Instant::now() < ends {
+                            continue;
+                        }
+                        match session.phase {
+                            Phase::Work => {
+                                session.completed += 1;
+                                self.break_after(session.completed)
+                            }
+                            _ => Phase::Idle,
+                        }
+                    }
+                    Some(click) = clicks.next() => match (click.button, session.phase) {
+                        (MouseButton::Left, Phase::Idle) => Phase::Work,
+                        (MouseButton::Left, _) => Phase::Idle,
+                        (MouseButton::Right, Phase::Work) => self.break_after(session.completed),
+                        (MouseButton::Right, _) => Phase::Idle,
+                        _ => continue,
+                    },
+                    // Nothing can start the session without clicks.
+                    else => break,
+                };
+                if let Err(err) = self.enter(&mut session, next).await {
+                    yield Err(err);
+                }
+                ends = Instant::now() + session.remaining;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remaining() {
+        assert_eq!(format_remaining(Duration::from_secs(25 * 60)), "25:00");
+        assert_eq!(format_remaining(Duration::from_millis(299_001)), "05:00");
+        assert_eq!(format_remaining(Duration::from_millis(298_999)), "04:59");
+        assert_eq!(format_remaining(Duration::ZERO), "00:00");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn export() {
+        let export = r#"[
+{"id":1,"description":"Reply to \"urgent\" mail","status":"pending","uuid":"uuid-1","urgency":1.9},
+{"id":2,"description":"Write docs","status":"pending","uuid":"uuid-2","urgency":6.25},
+{"id":3,"description":"Tidy up","status":"pending","uuid":"uuid-3","urgency":-1}
+]"#;
+        assert_eq!(
+            most_urgent(export).unwrap(),
+            Some(Task {
+                uuid: "uuid-2".to_owned(),
+                description: "Write docs".to_owned(),
+            })
+        );
+        let export = r#"[{"description":"Reply to \"urgent\" mail","uuid":"uuid-1"}]"#;
+        assert_eq!(
+            most_urgent(export).unwrap().unwrap().description,
+            r#"Reply to "urgent" mail"#
+        );
+        assert_eq!(most_urgent("[\n]").unwrap(), None);
+        assert!(most_urgent("Not JSON").is_err());
+    }
+}
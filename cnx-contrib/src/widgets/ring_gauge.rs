@@ -0,0 +1,136 @@
+use anyhow::Result;
+use cnx::text::{Attributes, Canvas, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::time::Duration;
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::StreamExt;
+
+/// Shows a percentage as a ring, filled clockwise from the top.
+///
+/// The percentage is read periodically from a closure, so this can show
+/// anything from the battery's charge to the volume or disk usage. The ring
+/// is drawn in the foreground color, over an optional track showing the
+/// unfilled part.
+pub struct RingGauge {
+    attr: Attributes,
+    size: f64,
+    value: Box<dyn Fn() -> Result<f64>>,
+    update_interval: Duration,
+    track_color: Option<Color>,
+}
+
+impl RingGauge {
+    /// Creates a new [`RingGauge`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `size` - The width and height of the ring, in pixels.
+    ///
+    /// * `value` - The closure called to get the percentage to show, between
+    /// 0 and 100.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::ring_gauge::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let battery = Box::new(|| {
+    ///     let capacity = std::fs::read_to_string("/sys/class/power_supply/BAT0/capacity")?;
+    ///     Ok(capacity.trim().parse()?)
+    /// });
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(RingGauge::new(attr, 16.0, battery).with_track_color(Color::from_hex("#444444")));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, size: f64, value: Box<dyn Fn() -> Result<f64>>) -> Self {
+        Self {
+            attr,
+            size,
+            value,
+            update_interval: Duration::from_secs(10),
+            track_color: None,
+        }
+    }
+
+    /// Sets how often the percentage is read. The default is every ten
+    /// seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Draws the unfilled part of the ring in `track_color`.
+    pub fn with_track_color(self, track_color: Color) -> Self {
+        Self {
+            track_color: Some(track_color),
+            ..self
+        }
+    }
+
+    fn tick(&self) -> Result<Vec<Text>> {
+        let fraction = ((self.value)()? / 100.0).clamp(0.0, 1.0);
+        let fg_color = self.attr.fg_color.clone();
+        let track_color = self.track_color.clone();
+
+        let canvas = Canvas::new(self.size, self.size, move |cr, width, height| {
+            let line_width = (width.min(height) / 8.0).max(1.0);
+            let radius = (width.min(height) - line_width) / 2.0;
+            let (x, y) = (width / 2.0, height / 2.0);
+            let start = -FRAC_PI_2;
+            cr.set_line_width(line_width);
+
+            if let Some(track_color) = &track_color {
+                track_color.apply_to_context(cr);
+                cr.arc(x, y, radius, 0.0, TAU);
+                let _ = cr.stroke();
+            }
+
+            if fraction > 0.0 {
+                fg_color.apply_to_context(cr);
+                cr.arc(x, y, radius, start, start + fraction * TAU);
+                let _ = cr.stroke();
+            }
+        });
+
+        Ok(vec![Text {
+            attr: self.attr.clone(),
+            text: String::new(),
+            stretch: false,
+            markup: false,
+            canvas: Some(canvas),
+        }])
+    }
+}
+
+impl Widget for RingGauge {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let interval = time::interval(self.update_interval);
+        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+
+        Ok(Box::pin(stream))
+    }
+}
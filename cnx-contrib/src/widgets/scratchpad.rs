@@ -0,0 +1,192 @@
+use crate::wm::{i3, WindowManager};
+use anyhow::{anyhow, Context, Result};
+use async_stream::try_stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickEvent, ClickStream, MouseButton, Widget, WidgetStream};
+use cnx::xcb::{watch_properties, xcb_properties_stream};
+use tokio_stream::{self as stream, StreamExt};
+use xcb_util::ewmh;
+
+enum Event {
+    Changed,
+    Click(ClickEvent),
+}
+
+/// Shows the number of windows in the scratchpad.
+///
+/// With [`WindowManager::I3`], this counts the windows in the i3/sway
+/// scratchpad, and a left click runs `scratchpad show` to cycle through them.
+///
+/// Without a window manager, this counts the iconified (minimized) windows
+/// on any [`EWMH`]-compliant window manager, and each left click restores one
+/// of them.
+///
+/// The widget is hidden while the count is zero.
+///
+/// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+pub struct Scratchpad {
+    attr: Attributes,
+    wm: Option<WindowManager>,
+    render: Option<Box<dyn Fn(usize) -> String>>,
+}
+
+impl Scratchpad {
+    /// Creates a new [`Scratchpad`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `wm` - The [`WindowManager`] whose scratchpad to show, or `None`
+    /// to count iconified windows using EWMH. Only [`WindowManager::I3`]
+    /// is supported.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. The `usize` is the number of windows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::scratchpad::*;
+    /// # use cnx_contrib::wm::WindowManager;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Scratchpad::new(attr, Some(WindowManager::I3), None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        wm: Option<WindowManager>,
+        render: Option<Box<dyn Fn(usize) -> String>>,
+    ) -> Self {
+        Self { attr, wm, render }
+    }
+
+    fn on_change(&self, count: usize) -> Vec<Text> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| format!("[{count}]"), |x| (x)(count));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+
+    fn i3_stream(self, clicks: ClickStream) -> WidgetStream {
+        let events = i3::events(&["window"]).map(|event| event.map(|_| Event::Changed));
+        let mut events = Box::pin(events.merge(clicks.map(|click| Ok(Event::Click(click)))));
+
+        let stream = try_stream! {
+            let mut conn = i3::Connection::connect().await?;
+            yield self.on_change(conn.get_tree().await?.scratchpad_len());
+
+            while let Some(event) = events.next().await {
+                match event? {
+                    Event::Changed => yield self.on_change(conn.get_tree().await?.scratchpad_len()),
+                    Event::Click(click) if click.button == MouseButton::Left => {
+                        conn.run_command("scratchpad show").await?;
+                    }
+                    Event::Click(_) => {}
+                }
+            }
+        };
+        Box::pin(stream)
+    }
+
+    fn ewmh_stream(self, clicks: ClickStream) -> Result<WidgetStream> {
+        let screen_idx = 0; // XXX assume
+        let (conn, changes) = xcb_properties_stream(&["_NET_CLIENT_LIST", "_NET_WM_STATE"])
+            .context("Initialising Scratchpad")?;
+        let events = changes
+            .map(|()| Event::Changed)
+            .merge(clicks.map(Event::Click));
+
+        let mut iconified = Vec::new();
+        let stream = events.filter_map(move |event| match event {
+            Event::Changed => {
+                iconified = iconified_windows(&conn, screen_idx);
+                Some(Ok(self.on_change(iconified.len())))
+            }
+            Event::Click(click) if click.button == MouseButton::Left => {
+                // The resulting _NET_WM_STATE change will update the count.
+                if let Some(window) = iconified.last() {
+                    ewmh::request_change_active_window(
+                        &conn,
+                        screen_idx,
+                        *window,
+                        ewmh::CLIENT_SOURCE_TYPE_OTHER,
+                        xcb::CURRENT_TIME,
+                        xcb::NONE,
+                    );
+                    conn.flush();
+                }
+                None
+            }
+            Event::Click(_) => None,
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+// Returns the iconified windows, in the order the window manager lists them
+// in _NET_CLIENT_LIST.
+//
+// This also registers for PROPERTY_CHANGE events on every client window, so
+// that we're notified when any of them is iconified or restored.
+fn iconified_windows(conn: &ewmh::Connection, screen_idx: i32) -> Vec<xcb::Window> {
+    let clients = match ewmh::get_client_list(conn, screen_idx).get_reply() {
+        Ok(reply) => reply.windows().to_vec(),
+        Err(_) => return Vec::new(),
+    };
+
+    watch_properties(conn, &clients);
+
+    clients
+        .into_iter()
+        .filter(
+            |window| match ewmh::get_wm_state(conn, *window).get_reply() {
+                Ok(state) => state.atoms().contains(&conn.WM_STATE_HIDDEN()),
+                Err(_) => false,
+            },
+        )
+        .collect()
+}
+
+impl Widget for Scratchpad {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        match self.wm {
+            Some(WindowManager::I3) => Ok(self.i3_stream(clicks)),
+            None => self.ewmh_stream(clicks),
+            Some(wm) => Err(anyhow!("Scratchpad does not support {:?}", wm)),
+        }
+    }
+}
@@ -5,4 +5,4 @@ mod sensors_linux;
 #[cfg(feature = "openbsd")]
 pub use sensors_bsd::Sensors;
 #[cfg(target_os = "linux")]
-pub use sensors_linux::Sensors;
+pub use sensors_linux::{Backend, Sensors};
@@ -120,6 +120,7 @@ impl Sensors {
                     text,
                     stretch: false,
                     markup: false,
+                    canvas: None,
                 }
             })
             .collect();
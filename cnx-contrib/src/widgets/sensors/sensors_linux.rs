@@ -1,6 +1,9 @@
+use crate::sysinfo::{self, Temperature};
 use anyhow::{anyhow, Context, Result};
 #[cfg(target_os = "linux")]
-use cnx::text::{Attributes, Text};
+use cnx::services::Services;
+use cnx::text::{Attributes, Color, Ramp, Role, Text};
+use cnx::units::Units;
 use cnx::widgets::{Widget, WidgetStream};
 use regex::Regex;
 use std::collections::HashMap;
@@ -39,19 +42,59 @@ fn parse_sensors_output(output: &str) -> Result<HashMap<&str, Value<'_>>> {
     Ok(map)
 }
 
+/// Where [`Sensors`] reads the temperatures from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    /// Read the kernel's hwmon class in sysfs, `/sys/class/hwmon`, which
+    /// needs nothing installed and reports each sensor's critical
+    /// temperature.
+    Hwmon,
+    /// Run the `sensors` command from [`lm_sensors`], which applies the
+    /// labels and corrections from its configuration. It expects the
+    /// `sensors` executable to be available in the `PATH`.
+    ///
+    /// [`lm_sensors`]: https://wiki.archlinux.org/index.php/lm_sensors
+    LmSensors,
+}
+
+// A temperature shown by the widget, in the units it is reported in.
+struct Reading {
+    value: f64,
+    units: char,
+    critical: Option<f64>,
+}
+
+// Returns the temperature selected by `sensor`, which is either a label such
+// as `Core 0`, or a chip and a label such as `coretemp: Package id 0`.
+fn find<'a>(temperatures: &'a [Temperature], sensor: &str) -> Option<&'a Temperature> {
+    let chip_label = sensor
+        .split_once(':')
+        .map(|(chip, label)| (chip.trim(), label.trim()));
+    temperatures.iter().find(|temperature| {
+        temperature.label == sensor
+            || chip_label == Some((temperature.chip.as_str(), temperature.label.as_str()))
+    })
+}
+
 /// Shows the temperature from one or more sensors.
 ///
-/// This widget shows the temperature reported by one or more sensors from the
-/// output of the `sensors` command, which is part of the [`lm_sensors`]
-/// package.
+/// This widget shows the temperature reported by one or more sensors, read
+/// from the kernel's hwmon class by default; see [`Backend`].
 ///
-/// It expects the `sensors` executable to be available in the `PATH`.
+/// Each sensor is shown in red once it reaches the critical temperature
+/// reported by its chip, and its color can otherwise be picked from a
+/// [`Ramp`] of colors by temperature; see [`Sensors::with_colors`].
 ///
-/// [`lm_sensors`]: https://wiki.archlinux.org/index.php/lm_sensors
+/// Temperatures reported in Celsius are shown in the temperature unit of the
+/// bar's [`Units`].
 pub struct Sensors {
     update_interval: Duration,
     attr: Attributes,
     sensors: Vec<String>,
+    backend: Backend,
+    colors: HashMap<String, Ramp<Color>>,
+    critical_color: Option<Color>,
+    services: Option<Services>,
 }
 
 impl Sensors {
@@ -60,8 +103,12 @@ impl Sensors {
     /// Creates a new `Sensors` widget, whose text will be displayed with the
     /// given [`Attributes`].
     ///
-    /// A list of sensor names should be passed as the `sensors` argument. (You
-    /// can discover the names by running the `sensors` utility in a terminal).
+    /// A list of sensor names should be passed as the `sensors` argument.
+    /// Each is either the label of a sensor, e.g. `Core 0`, or the name of
+    /// its chip and its label, e.g. `coretemp: Package id 0`, for when
+    /// several chips have sensors with the same label. (You can discover the
+    /// names by running the `sensors` utility in a terminal, or by reading
+    /// the `name` and `temp*_label` files in `/sys/class/hwmon/*`).
     ///
     /// The [`cnx::Cnx`] instance is borrowed during construction in order to get
     /// access to handles of its event loop. However, it is not borrowed for the
@@ -89,8 +136,14 @@ impl Sensors {
     /// };
     ///
     /// let mut cnx = Cnx::new(Position::Top);
+    /// let colors = Ramp::new(vec![
+    ///     (60.0, Color::white()),
+    ///     (80.0, Role::Warn.into()),
+    ///     (f64::INFINITY, Role::Crit.into()),
+    /// ]);
     /// cnx.add_widget(
-    ///     Sensors::new(attr.clone(), vec!["Core 0", "Core 1"])
+    ///     Sensors::new(attr.clone(), vec!["coretemp: Package id 0", "Core 1"])
+    ///         .with_colors("coretemp: Package id 0", colors),
     /// );
     /// # Ok(())
     /// # }
@@ -101,46 +154,146 @@ impl Sensors {
             update_interval: Duration::from_secs(60),
             attr,
             sensors: sensors.into_iter().map(Into::into).collect(),
+            backend: Backend::Hwmon,
+            colors: HashMap::new(),
+            critical_color: Some(Color::role(Role::Crit)),
+            services: None,
+        }
+    }
+
+    /// Sets where the temperatures are read from.
+    ///
+    /// The default is [`Backend::Hwmon`].
+    pub fn with_backend(self, backend: Backend) -> Self {
+        Sensors { backend, ..self }
+    }
+
+    /// Colors `sensor`, named as in [`Sensors::new`], with a color picked
+    /// according to its temperature, in the units it is reported in, i.e.
+    /// always Celsius with [`Backend::Hwmon`].
+    pub fn with_colors(mut self, sensor: &str, colors: Ramp<Color>) -> Self {
+        self.colors.insert(sensor.to_owned(), colors);
+        self
+    }
+
+    /// Sets the color of sensors which have reached their critical
+    /// temperature, or `None` to not color them. The default is red.
+    ///
+    /// This takes precedence over [`Sensors::with_colors`]. Only
+    /// [`Backend::Hwmon`] reports critical temperatures.
+    pub fn with_critical_color(self, critical_color: Option<Color>) -> Self {
+        Sensors {
+            critical_color,
+            ..self
+        }
+    }
+
+    // Returns the reading of each sensor, in order.
+    fn read(&self) -> Result<Vec<Option<Reading>>> {
+        match self.backend {
+            Backend::Hwmon => {
+                let temperatures = sysinfo::temperatures();
+                Ok(self
+                    .sensors
+                    .iter()
+                    .map(|sensor| {
+                        find(&temperatures, sensor).map(|temperature| Reading {
+                            value: temperature.celsius,
+                            units: 'C',
+                            critical: temperature.critical,
+                        })
+                    })
+                    .collect())
+            }
+            Backend::LmSensors => {
+                let output = Command::new("sensors")
+                    .output()
+                    .context("Failed to run `sensors`")?;
+                let string =
+                    String::from_utf8(output.stdout).context("Invalid UTF-8 in sensors output")?;
+                let parsed =
+                    parse_sensors_output(&string).context("Failed to parse `sensors` output")?;
+                Ok(self
+                    .sensors
+                    .iter()
+                    .map(|sensor| {
+                        let Value { temp, units } = parsed.get::<str>(sensor)?;
+                        Some(Reading {
+                            value: temp.parse().ok()?,
+                            units: units.chars().next()?,
+                            critical: None,
+                        })
+                    })
+                    .collect())
+            }
         }
     }
 
-    fn tick(&self) -> Result<Vec<Text>> {
-        let output = Command::new("sensors")
-            .output()
-            .context("Failed to run `sensors`")?;
-        let string = String::from_utf8(output.stdout).context("Invalid UTF-8 in sensors output")?;
-        let parsed = parse_sensors_output(&string).context("Failed to parse `sensors` output")?;
-        self.sensors
+    fn tick(&self, units: &Units) -> Result<Vec<Text>> {
+        let readings = self.read()?;
+        Ok(self
+            .sensors
             .iter()
-            .map(|sensor_name| {
-                let text = parsed
-                    .get::<str>(sensor_name)
-                    .map_or("Invalid".to_owned(), |&Value { temp, units }| {
-                        format!("{temp}°{units}")
-                    });
-                Ok(Text {
-                    attr: self.attr.clone(),
+            .zip(readings)
+            .map(|(sensor, reading)| {
+                let mut attr = self.attr.clone();
+                let text = match reading {
+                    Some(Reading {
+                        value,
+                        units: reading_units,
+                        critical,
+                    }) => {
+                        let color = match (&self.critical_color, critical) {
+                            (Some(color), Some(critical)) if value >= critical => Some(color),
+                            _ => self.colors.get(sensor).and_then(|colors| colors.get(value)),
+                        };
+                        if let Some(color) = color {
+                            attr.fg_color = color.clone();
+                        }
+                        match reading_units {
+                            'C' => format!(
+                                "{:.1}{}",
+                                units.temperature(value),
+                                units.temperature_symbol()
+                            ),
+                            _ => format!("{value:.1}°{reading_units}"),
+                        }
+                    }
+                    None => "Invalid".to_owned(),
+                };
+                Text {
+                    attr,
                     text,
                     stretch: false,
                     markup: false,
-                })
+                    canvas: None,
+                }
             })
-            .collect()
+            .collect())
     }
 }
 
 impl Widget for Sensors {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
         let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| self.tick());
+        let units = self
+            .services
+            .as_ref()
+            .map(Units::shared)
+            .unwrap_or_default();
+        let stream = IntervalStream::new(interval).map(move |_| self.tick(&units));
 
         Ok(Box::pin(stream))
     }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{parse_sensors_output, Value};
+    use super::{find, parse_sensors_output, Temperature, Value};
 
     #[test]
     fn works() {
@@ -189,4 +342,32 @@ Core 1:        +58.0 C  (high = +105.0 C, crit = +105.0 C)
 
         assert_eq!(parsed.len(), 5);
     }
+
+    #[test]
+    fn select() {
+        let temperature = |chip: &str, label: &str| Temperature {
+            chip: chip.to_owned(),
+            label: label.to_owned(),
+            celsius: 50.0,
+            critical: None,
+        };
+        let temperatures = vec![
+            temperature("acpitz", "temp1"),
+            temperature("coretemp", "Package id 0"),
+            temperature("amdgpu", "edge"),
+            temperature("nvme", "Composite"),
+            temperature("nvme", "Sensor 1: NAND"),
+            temperature("coretemp", "temp1"),
+        ];
+        let found = |sensor| find(&temperatures, sensor).map(|t| (&*t.chip, &*t.label));
+
+        assert_eq!(found("edge"), Some(("amdgpu", "edge")));
+        assert_eq!(found("Package id 0"), Some(("coretemp", "Package id 0")));
+        assert_eq!(found("temp1"), Some(("acpitz", "temp1")));
+        assert_eq!(found("coretemp: temp1"), Some(("coretemp", "temp1")));
+        assert_eq!(found("coretemp:temp1"), Some(("coretemp", "temp1")));
+        assert_eq!(found("Sensor 1: NAND"), Some(("nvme", "Sensor 1: NAND")));
+        assert_eq!(found("amdgpu: temp1"), None);
+        assert_eq!(found("Core 0"), None);
+    }
 }
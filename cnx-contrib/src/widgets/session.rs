@@ -0,0 +1,334 @@
+use crate::dbus::{self, Bus};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use tokio_stream::{self as stream, StreamExt};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::{dbus_proxy, CacheProperties};
+
+// A session as listed by `ListSessions`: its ID, user ID, user name, seat
+// and object path.
+type SessionEntry = (String, u32, String, String, OwnedObjectPath);
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1",
+    gen_blocking = false
+)]
+trait Manager {
+    fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn list_sessions(&self) -> zbus::Result<Vec<SessionEntry>>;
+
+    fn lock_session(&self, session_id: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn session_new(&self, session_id: &str, object_path: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn session_removed(&self, session_id: &str, object_path: ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto",
+    gen_blocking = false
+)]
+trait Session {
+    #[dbus_proxy(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn seat(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+
+    #[dbus_proxy(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn active(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn state(&self) -> zbus::Result<String>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Seat",
+    default_service = "org.freedesktop.login1",
+    gen_blocking = false
+)]
+trait Seat {
+    #[dbus_proxy(property)]
+    fn active_session(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
+
+/// Another user's (or the same user's) login session.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OtherSession {
+    /// The name of the session's user
+    pub user: String,
+    /// The seat the session is on, e.g. `seat0`, unless it is remote
+    pub seat: Option<String>,
+    /// Whether the session is in the foreground of its seat
+    pub active: bool,
+}
+
+/// The state of the current login session, as shown by [`LoginSession`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SessionInfo {
+    /// The seat the current session is on, e.g. `seat0`
+    pub seat: Option<String>,
+    /// Whether the current session is locked
+    pub locked: bool,
+    /// The other sessions which are logged in
+    pub others: Vec<OtherSession>,
+}
+
+/// Shows the seat of the current login session, how many other sessions
+/// are logged in, and whether the session is locked.
+///
+/// This asks systemd-logind over DBus, and is updated whenever a session
+/// starts or ends, another session is switched to, or the session is locked
+/// or unlocked. Left clicking the widget locks the session, as with
+/// `loginctl lock-session`, which the screen locker started by e.g.
+/// `xss-lock` reacts to.
+pub struct LoginSession {
+    attr: Attributes,
+    render: Option<Box<dyn Fn(SessionInfo) -> String>>,
+    services: Option<Services>,
+}
+
+impl LoginSession {
+    /// Creates a new [`LoginSession`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`SessionInfo`] is the current session's state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::session::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|info: SessionInfo| match info.others.len() {
+    ///     0 => "👤".to_owned(),
+    ///     others => format!("👥 {}", others + 1),
+    /// });
+    /// cnx.add_widget(LoginSession::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(SessionInfo) -> String>>) -> Self {
+        Self {
+            attr,
+            render,
+            services: None,
+        }
+    }
+
+    fn render(&self, info: SessionInfo) -> Vec<Text> {
+        let default_text = || {
+            let mut text = info.seat.clone().unwrap_or_default();
+            if !info.others.is_empty() {
+                text.push_str(&format!(" +{}", info.others.len()));
+            }
+            if info.locked {
+                text.push_str(&format!(" ({})", i18n::tr("locked")));
+            }
+            text
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(info.clone()));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// The current session, on a connection to logind.
+struct Logind {
+    connection: zbus::Connection,
+    manager: ManagerProxy<'static>,
+    session: SessionProxy<'static>,
+    id: String,
+}
+
+impl Logind {
+    async fn connect(connection: zbus::Connection) -> Result<Self> {
+        let manager = ManagerProxy::new(&connection).await?;
+        let id = SessionProxy::new(&connection).await?.id().await?;
+        // Changes are signalled on the session's own path, rather than the
+        // `auto` alias.
+        let session = SessionProxy::builder(&connection)
+            .path(manager.get_session(&id).await?)?
+            .build()
+            .await?;
+        Ok(Logind {
+            connection,
+            manager,
+            session,
+            id,
+        })
+    }
+
+    // Returns the current session's seat, if it has one.
+    async fn seat(&self) -> Result<Option<SeatProxy<'static>>> {
+        let (seat, path) = self.session.seat().await?;
+        if seat.is_empty() {
+            return Ok(None);
+        }
+        let seat = SeatProxy::builder(&self.connection)
+            .path(path)?
+            .build()
+            .await?;
+        Ok(Some(seat))
+    }
+
+    async fn info(&self) -> Result<SessionInfo> {
+        let (seat, _) = self.session.seat().await?;
+        let mut others = Vec::new();
+        for (id, _, user, seat, path) in self.manager.list_sessions().await? {
+            if id == self.id {
+                continue;
+            }
+            let session = SessionProxy::builder(&self.connection)
+                .path(path)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            // Sessions linger while their processes exit.
+            if session.state().await? == "closing" {
+                continue;
+            }
+            others.push(OtherSession {
+                user,
+                seat: Some(seat).filter(|seat| !seat.is_empty()),
+                active: session.active().await?,
+            });
+        }
+        Ok(SessionInfo {
+            seat: Some(seat).filter(|seat| !seat.is_empty()),
+            locked: self.session.locked_hint().await?,
+            others,
+        })
+    }
+
+    async fn lock(&self) -> Result<()> {
+        self.manager.lock_session(&self.id).await?;
+        Ok(())
+    }
+}
+
+impl Widget for LoginSession {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks.filter(|click| click.button == MouseButton::Left);
+
+        if let Err(err) = dbus::require(self.services.as_ref(), Bus::System) {
+            return Ok(capabilities::unavailable(self.attr, err));
+        }
+
+        let mut watches = Box::pin(dbus::pool(self.services.as_ref()).watch(
+            Bus::System,
+            |connection| async move {
+                let logind = Logind::connect(connection).await?;
+                let seat = logind.seat().await?;
+                let active_session_changed = match &seat {
+                    Some(seat) => Some(seat.receive_active_session_changed().await),
+                    None => None,
+                };
+                let streams = (
+                    logind.manager.receive_session_new().await?,
+                    logind.manager.receive_session_removed().await?,
+                    logind.session.receive_locked_hint_changed().await,
+                    active_session_changed,
+                );
+                Ok((logind, streams))
+            },
+        ));
+
+        let stream = stream! {
+            // Changes stop when the bus closes the connection, e.g. because
+            // dbus-daemon was restarted, so we start again with the next one.
+            while let Some(watched) = watches.next().await {
+                let (logind, streams) = match watched {
+                    Ok(watched) => watched,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                let (mut session_new, mut session_removed, mut locked_changed, mut active_changed) =
+                    streams;
+
+                yield logind.info().await.map(|info| self.render(info));
+                loop {
+                    tokio::select! {
+                        // Signals stop when the connection is closed.
+                        new = session_new.next() => if new.is_none() {
+                            break;
+                        },
+                        Some(_) = session_removed.next() => {}
+                        Some(_) = locked_changed.next() => {}
+                        // Sessions without a seat never switch.
+                        Some(_) = async {
+                            match &mut active_changed {
+                                Some(changes) => changes.next().await,
+                                None => None,
+                            }
+                        } => {}
+                        // The lock shows once logind reports it.
+                        Some(_) = clicks.next() => {
+                            if let Err(err) = logind.lock().await {
+                                yield Err(err);
+                            }
+                            continue;
+                        }
+                        else => break,
+                    }
+                    yield logind.info().await.map(|info| self.render(info));
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use std::future;
+use tokio::io::{self, BufReader};
+use tokio_stream::StreamExt;
+
+type Render = Box<dyn Fn(&str) -> String>;
+
+/// Shows the last line read from Cnx's standard input, so that the output of
+/// another program can be piped into the bar, e.g. `my-status-script |
+/// cnx`, or that of an existing status generator such as `i3status` in its
+/// plain text mode.
+///
+/// Each line replaces the one shown, and an empty line hides the widget,
+/// which is also hidden until the first line is read. Once the input ends,
+/// the last line stays shown. There is only one standard input, so only one
+/// of these widgets should be added to the bar.
+pub struct Stdin {
+    attr: Attributes,
+    render: Option<Render>,
+}
+
+impl Stdin {
+    /// Creates a new [`Stdin`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the last line read, without its
+    /// line ending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::stdin::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Stdin::new(attr, None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self { attr, render }
+    }
+
+    fn render(&self, line: &str) -> Vec<Text> {
+        if line.is_empty() {
+            return Vec::new();
+        }
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| untrusted::text(line), |x| (x)(line));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for Stdin {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let reader = BufReader::new(io::stdin());
+        let mut lines = Box::pin(untrusted::lines(reader, untrusted::MAX_LINE));
+
+        let stream = stream! {
+            yield Ok(Vec::new());
+            while let Some(line) = lines.next().await {
+                match line {
+                    Ok(line) => yield Ok(self.render(&line)),
+                    Err(err) => yield Err(err.into()),
+                }
+            }
+            // The input has ended, but the widget hasn't: the last line
+            // stays shown.
+            future::pending::<()>().await;
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
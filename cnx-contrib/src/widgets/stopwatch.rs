@@ -0,0 +1,261 @@
+use crate::format;
+use crate::inotify;
+use crate::state;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use nix::sys::inotify::AddWatchFlags;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time;
+use tokio_stream::{self as stream, Stream, StreamExt};
+
+/// Shows how long it has been since something happened, e.g. `1h 23m`
+/// since the last break or the last backup, and left clicking it resets it.
+///
+/// When it was last reset is kept in a file, which holds the time as a Unix
+/// timestamp in seconds, or else is taken to be the time the file was last
+/// modified. By default, this is a file in Cnx's state directory, so that
+/// the stopwatch carries on counting across restarts, and until it is first
+/// reset it counts from when Cnx started.
+///
+/// With [`Stopwatch::with_file()`], another program can reset it, e.g. a
+/// backup script running `date +%s > ~/.last-backup` or `touch
+/// ~/.last-backup`. The widget is hidden while that file doesn't exist.
+pub struct Stopwatch {
+    attr: Attributes,
+    name: String,
+    file: Option<PathBuf>,
+    update_interval: Duration,
+    render: Option<Box<dyn Fn(Duration) -> String>>,
+}
+
+impl Stopwatch {
+    /// Creates a new [`Stopwatch`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given how long it has been since the
+    /// stopwatch was last reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::format;
+    /// # use cnx_contrib::widgets::stopwatch::*;
+    /// # use anyhow::Result;
+    /// # use std::time::Duration;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|since: Duration| format!("Backup {} ago", format::duration(since)));
+    /// cnx.add_widget(Stopwatch::new(attr, Some(render)).with_file("/home/me/.last-backup"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(Duration) -> String>>) -> Self {
+        Self {
+            attr,
+            name: "stopwatch".to_owned(),
+            file: None,
+            update_interval: Duration::from_secs(1),
+            render,
+        }
+    }
+
+    /// Sets the name of the file in Cnx's state directory in which the
+    /// time of the last reset is kept, e.g. to have more than one stopwatch.
+    /// The default is `stopwatch`.
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..self
+        }
+    }
+
+    /// Keeps the time of the last reset in `path` instead, so that other
+    /// programs can reset the stopwatch, and hides the widget while the file
+    /// doesn't exist. Clicking the widget writes the current time to it.
+    pub fn with_file<P: Into<PathBuf>>(self, path: P) -> Self {
+        Self {
+            file: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Sets how often to update the time shown. The default is every
+    /// second.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, since: Option<SystemTime>) -> Vec<Text> {
+        let Some(since) = since else {
+            return Vec::new();
+        };
+        // A reset in the future, e.g. from another clock, counts as now.
+        let elapsed = SystemTime::now().duration_since(since).unwrap_or_default();
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| format::duration(elapsed), |x| (x)(elapsed));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Parses a Unix timestamp in seconds.
+fn parse_timestamp(contents: &str) -> Option<SystemTime> {
+    let seconds: u64 = contents.trim().parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+// Reads when the stopwatch was last reset from `path`, or returns `None` if
+// the file doesn't exist.
+fn read_reset(path: &Path) -> Result<Option<SystemTime>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    match parse_timestamp(&contents) {
+        Some(reset) => Ok(Some(reset)),
+        None => Ok(Some(fs::metadata(path)?.modified()?)),
+    }
+}
+
+fn write_reset(path: &Path, reset: SystemTime) -> Result<()> {
+    let seconds = reset.duration_since(UNIX_EPOCH)?.as_secs();
+    fs::write(path, format!("{seconds}\n"))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+impl Widget for Stopwatch {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks.filter(|click| click.button == MouseButton::Left);
+
+        // Without a file of its own, the stopwatch counts from now until it
+        // is first reset.
+        let started = SystemTime::now();
+        let (path, fallback) = match &self.file {
+            Some(file) => (Some(file.clone()), None),
+            None => (state::path(&self.name), Some(started)),
+        };
+        // The directory is watched rather than the file, as the file may
+        // not exist yet, or be replaced rather than written to.
+        let mut changes: Pin<Box<dyn Stream<Item = Result<()>>>> = match &path {
+            Some(path) => {
+                let dir = path.parent().unwrap_or_else(|| Path::new("."));
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                let flags = AddWatchFlags::IN_CLOSE_WRITE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_DELETE
+                    | AddWatchFlags::IN_ATTRIB;
+                Box::pin(inotify::watch(&[dir.to_owned()], flags)?)
+            }
+            None => Box::pin(stream::pending()),
+        };
+
+        let stream = stream! {
+            let mut reset = fallback;
+            let mut changed = true;
+            loop {
+                if let (true, Some(path)) = (changed, &path) {
+                    match read_reset(path) {
+                        Ok(read) => reset = read.or(fallback),
+                        Err(err) => yield Err(err),
+                    }
+                }
+                changed = false;
+                yield Ok(self.render(reset));
+
+                tokio::select! {
+                    _ = time::sleep(self.update_interval), if reset.is_some() => {}
+                    change = changes.next() => match change {
+                        Some(Ok(())) => changed = true,
+                        Some(Err(err)) => yield Err(err),
+                        None => break,
+                    },
+                    Some(_) = clicks.next() => {
+                        let now = SystemTime::now();
+                        reset = Some(now);
+                        if let Some(path) = &path {
+                            if let Err(err) = write_reset(path, now) {
+                                yield Err(err);
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resets() {
+        assert_eq!(
+            parse_timestamp("1700000000\n"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+
+        let dir = std::env::temp_dir().join(format!("cnx-stopwatch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("reset");
+        assert_eq!(read_reset(&path).unwrap(), None);
+
+        let reset = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        write_reset(&path, reset).unwrap();
+        assert_eq!(read_reset(&path).unwrap(), Some(reset));
+
+        // A file which doesn't hold a timestamp was reset when it was
+        // modified.
+        fs::write(&path, "").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(read_reset(&path).unwrap(), Some(modified));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use crate::dbus::{self, Bus, DbusPool};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::capabilities;
+use cnx::services::Services;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt, StreamMap};
+use zbus::dbus_proxy;
+use zbus::zvariant::OwnedObjectPath;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1",
+    gen_blocking = false
+)]
+trait Manager {
+    fn load_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn subscribe(&self) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.systemd1.Unit",
+    default_service = "org.freedesktop.systemd1",
+    gen_blocking = false
+)]
+trait Unit {
+    #[dbus_proxy(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+}
+
+/// A unit watched by [`SystemdUnits`], and the state it is in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnitStatus {
+    /// The unit's name, e.g. `syncthing.service`
+    pub name: String,
+    /// [`Bus::System`] for a system unit, or [`Bus::Session`] for a user
+    /// unit
+    pub bus: Bus,
+    /// The unit's active state, e.g. `active`, `inactive` or `failed`
+    pub state: String,
+}
+
+impl UnitStatus {
+    // Returns whether the unit has stopped, rather than running or changing
+    // state.
+    fn is_down(&self) -> bool {
+        self.state == "failed" || self.state == "inactive"
+    }
+}
+
+type Render = Box<dyn Fn(&[UnitStatus]) -> String>;
+
+/// Shows the systemd units, out of those it watches, which have failed or
+/// are inactive, e.g. `✗ syncthing.service`. The widget is hidden while all
+/// of them are running.
+///
+/// The units may be system units or user units, and are watched over DBus,
+/// so that a unit which stops is shown as soon as it does. The widget is
+/// shown in the theme's `Crit` color if any unit has failed, or its `Warn`
+/// color if they are only inactive.
+pub struct SystemdUnits {
+    attr: Attributes,
+    system_units: Vec<String>,
+    user_units: Vec<String>,
+    render: Option<Render>,
+    services: Option<Services>,
+}
+
+impl SystemdUnits {
+    /// Creates a new [`SystemdUnits`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the units which have failed or are
+    /// inactive, of which there is always at least one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::systemd_units::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let units = SystemdUnits::new(attr, None)
+    ///     .with_system_unit("sshd.service")
+    ///     .with_user_unit("syncthing.service");
+    /// cnx.add_widget(units);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            system_units: Vec::new(),
+            user_units: Vec::new(),
+            render,
+            services: None,
+        }
+    }
+
+    /// Watches the system unit called `name`, e.g. `sshd.service`.
+    pub fn with_system_unit(mut self, name: &str) -> Self {
+        self.system_units.push(name.to_owned());
+        self
+    }
+
+    /// Watches the user unit called `name`, e.g. `syncthing.service`, of the
+    /// user's own service manager.
+    pub fn with_user_unit(mut self, name: &str) -> Self {
+        self.user_units.push(name.to_owned());
+        self
+    }
+
+    fn render(&self, down: &[UnitStatus]) -> Vec<Text> {
+        if down.is_empty() {
+            return Vec::new();
+        }
+        let mut attr = self.attr.clone();
+        let text = match &self.render {
+            Some(render) => (render)(down),
+            None => {
+                let names: Vec<&str> = down.iter().map(|unit| unit.name.as_str()).collect();
+                format!("✗ {}", names.join(", "))
+            }
+        };
+        attr.fg_color = match down.iter().any(|unit| unit.state == "failed") {
+            true => Color::role(Role::Crit),
+            false => Color::role(Role::Warn),
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+type Changes = StreamMap<usize, Pin<Box<dyn Stream<Item = ()>>>>;
+
+// Loads the units called `names` on a connection to their service manager,
+// and subscribes to changes of their states.
+async fn watch(
+    connection: &zbus::Connection,
+    names: &[String],
+) -> Result<(Vec<UnitProxy<'static>>, Changes)> {
+    let manager = ManagerProxy::new(connection).await?;
+    // The manager only signals changes while a client is subscribed.
+    manager.subscribe().await?;
+    let mut units = Vec::new();
+    let mut changes = StreamMap::new();
+    for (i, name) in names.iter().enumerate() {
+        let unit = UnitProxy::builder(connection)
+            .path(manager.load_unit(name).await?)?
+            .build()
+            .await?;
+        let changed = unit.receive_active_state_changed().await.map(drop);
+        changes.insert(i, Box::pin(changed) as Pin<Box<dyn Stream<Item = ()>>>);
+        units.push(unit);
+    }
+    Ok((units, changes))
+}
+
+async fn statuses(bus: Bus, names: &[String], units: &[UnitProxy<'_>]) -> Result<Vec<UnitStatus>> {
+    let mut statuses = Vec::new();
+    for (name, unit) in names.iter().zip(units) {
+        statuses.push(UnitStatus {
+            name: name.clone(),
+            bus,
+            state: unit.active_state().await?,
+        });
+    }
+    Ok(statuses)
+}
+
+// Yields the states of the units called `names` on `bus`, and then again
+// whenever one of them changes.
+fn unit_statuses(
+    pool: DbusPool,
+    bus: Bus,
+    names: Vec<String>,
+) -> impl Stream<Item = (Bus, Result<Vec<UnitStatus>>)> {
+    stream! {
+        if names.is_empty() {
+            return;
+        }
+        let mut watches = Box::pin(pool.watch(bus, |connection| {
+            let names = names.clone();
+            async move { watch(&connection, &names).await }
+        }));
+        // Changes stop when the bus closes the connection, e.g. because
+        // dbus-daemon was restarted, so we start again with the next one.
+        while let Some(watched) = watches.next().await {
+            let (units, mut changes) = match watched {
+                Ok(watched) => watched,
+                Err(err) => {
+                    yield (bus, Err(err));
+                    continue;
+                }
+            };
+            yield (bus, statuses(bus, &names, &units).await);
+            while changes.next().await.is_some() {
+                yield (bus, statuses(bus, &names, &units).await);
+            }
+        }
+    }
+}
+
+impl Widget for SystemdUnits {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        // Only the buses with units to watch are needed.
+        let buses = [
+            (Bus::System, !self.system_units.is_empty()),
+            (Bus::Session, !self.user_units.is_empty()),
+        ];
+        for (bus, _) in buses.into_iter().filter(|(_, needed)| *needed) {
+            if let Err(err) = dbus::require(self.services.as_ref(), bus) {
+                return Ok(capabilities::unavailable(self.attr, err));
+            }
+        }
+
+        let pool = dbus::pool(self.services.as_ref());
+        let system = unit_statuses(pool.clone(), Bus::System, self.system_units.clone());
+        let user = unit_statuses(pool, Bus::Session, self.user_units.clone());
+        let mut updates = Box::pin(system.merge(user));
+
+        let stream = stream! {
+            let mut system = Vec::new();
+            let mut user = Vec::new();
+            while let Some((bus, statuses)) = updates.next().await {
+                match (bus, statuses) {
+                    (Bus::System, Ok(statuses)) => system = statuses,
+                    (Bus::Session, Ok(statuses)) => user = statuses,
+                    (_, Err(err)) => {
+                        yield Err(err);
+                        continue;
+                    }
+                }
+                let down: Vec<UnitStatus> = system
+                    .iter()
+                    .chain(&user)
+                    .filter(|unit| unit.is_down())
+                    .cloned()
+                    .collect();
+                yield Ok(self.render(&down));
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
@@ -0,0 +1,324 @@
+use crate::format;
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use chrono::{Local, NaiveTime};
+use cnx::commands::Commands;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{ClickStream, MouseButton, Widget, WidgetStream};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+use tokio_stream::{self as stream, StreamExt};
+
+// How long the widget flashes for once the countdown reaches zero, and how
+// often it switches between its colors while it does.
+const FLASH_FOR: Duration = Duration::from_secs(30);
+const FLASH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A countdown, as shown by [`Timer`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Countdown {
+    /// What the countdown is for, e.g. `tea`, if it was given a label
+    pub label: Option<String>,
+    /// How long is left, which is zero once the countdown has finished
+    pub remaining: Duration,
+}
+
+// A request sent on the control socket.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Request {
+    Start {
+        duration: Duration,
+        label: Option<String>,
+    },
+    Stop,
+}
+
+type Render = Box<dyn Fn(Option<&Countdown>) -> String>;
+
+/// A countdown timer, e.g. `tea 04:59`, which flashes in the theme's `Crit`
+/// color for 30 seconds once it reaches zero.
+///
+/// Left clicking the widget starts a countdown, of 5 minutes by default, or
+/// stops it if one is running or flashing. Countdowns can also be started
+/// with the control socket (see [`Cnx::with_control_socket()`]), for a
+/// duration or until a time of day, with an optional label, and stopped:
+///
+/// ```sh
+/// echo "timer start 25m tea" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/cnx.sock
+/// echo "timer start 17:30 standup" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/cnx.sock
+/// echo "timer stop" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/cnx.sock
+/// ```
+///
+/// Durations are written as e.g. `90s`, `25m` or `1h30m`. A time of day
+/// which has already passed today is taken to be tomorrow's.
+///
+/// [`Cnx::with_control_socket()`]: cnx::Cnx::with_control_socket
+pub struct Timer {
+    attr: Attributes,
+    command: String,
+    duration: Duration,
+    render: Option<Render>,
+    services: Option<Services>,
+}
+
+impl Timer {
+    /// Creates a new [`Timer`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`Countdown`] which is running,
+    /// every second, or `None` while there isn't one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::timer::*;
+    /// # use anyhow::Result;
+    /// # use std::time::Duration;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let timer = Timer::new(attr, None).with_duration(Duration::from_secs(3 * 60));
+    /// cnx.add_widget(timer);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            command: "timer".to_owned(),
+            duration: Duration::from_secs(5 * 60),
+            render,
+            services: None,
+        }
+    }
+
+    /// Sets how long the countdowns started by clicking the widget last. The
+    /// default is 5 minutes.
+    pub fn with_duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Sets the name of the command which starts and stops countdowns on the
+    /// control socket, e.g. to have more than one timer. The default is
+    /// `timer`.
+    pub fn with_command(self, command: &str) -> Self {
+        Self {
+            command: command.to_owned(),
+            ..self
+        }
+    }
+
+    fn render(&self, countdown: Option<&Countdown>, highlight: bool) -> Vec<Text> {
+        let mut attr = self.attr.clone();
+        if highlight {
+            attr.bg_color = Some(Color::role(Role::Crit));
+        }
+        let text = match (&self.render, countdown) {
+            (Some(render), _) => (render)(countdown),
+            (None, None) => i18n::tr("Timer"),
+            (None, Some(countdown)) => match &countdown.label {
+                Some(label) => format!("{} {}", label, format_remaining(countdown.remaining)),
+                None => format_remaining(countdown.remaining),
+            },
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Formats the time left as minutes and seconds, with hours if there are
+// any, rounding up so that it reaches `00:00` as the countdown finishes.
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    match seconds / 3600 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+// Parses the arguments of a command sent on the control socket, taking any
+// time of day to be the next one after `now`.
+fn parse_request(args: &[&str], now: NaiveTime) -> Result<Request> {
+    match args {
+        ["start", target, label @ ..] => Ok(Request::Start {
+            duration: parse_target(target, now)?,
+            label: (!label.is_empty()).then(|| label.join(" ")),
+        }),
+        ["stop"] => Ok(Request::Stop),
+        _ => Err(anyhow!("Expected start <duration|HH:MM> [label], or stop")),
+    }
+}
+
+// Parses how long to count down for, or the time of day to count down to.
+fn parse_target(target: &str, now: NaiveTime) -> Result<Duration> {
+    let Ok(time) = NaiveTime::parse_from_str(target, "%H:%M") else {
+        return format::parse_duration(target);
+    };
+    let until = time.signed_duration_since(now);
+    let until = match until > chrono::Duration::zero() {
+        true => until,
+        false => until + chrono::Duration::days(1),
+    };
+    Ok(until.to_std()?)
+}
+
+impl Widget for Timer {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        let mut clicks = clicks;
+
+        // Commands are parsed as they are received, so that mistakes are
+        // replied to, and then passed on to the stream.
+        let (sender, mut requests) = mpsc::unbounded_channel();
+        let registration = self.services.as_ref().map(|services| {
+            Commands::shared(services).register(&self.command, move |args| {
+                let request = parse_request(args, Local::now().time())?;
+                sender
+                    .send(request)
+                    .map_err(|_| anyhow!("The timer has stopped"))?;
+                Ok(String::new())
+            })
+        });
+
+        let stream = stream! {
+            // The command is handled for as long as the stream is running.
+            let _registration = registration;
+            let mut countdown: Option<Countdown> = None;
+            let mut ends = Instant::now();
+            loop {
+                let now = Instant::now();
+                if let Some(countdown) = &mut countdown {
+                    countdown.remaining = ends.saturating_duration_since(now);
+                }
+                // How long the widget has been flashing for, once the
+                // countdown has finished, until it stops.
+                let flashing = match &countdown {
+                    Some(_) if now >= ends => Some(now - ends).filter(|since| *since < FLASH_FOR),
+                    _ => None,
+                };
+                if countdown.is_some() && now >= ends && flashing.is_none() {
+                    countdown = None;
+                }
+                let highlight = flashing.map_or(false, |since| {
+                    since.as_millis() / FLASH_INTERVAL.as_millis() % 2 == 0
+                });
+                yield Ok(self.render(countdown.as_ref(), highlight));
+
+                // Wake as the time left reaches a whole second, to show it,
+                // or to switch colors while flashing.
+                let tick = match (&countdown, flashing) {
+                    (_, Some(since)) => {
+                        let into = since.as_nanos() % FLASH_INTERVAL.as_nanos();
+                        FLASH_INTERVAL - Duration::from_nanos(into as u64)
+                    }
+                    (Some(countdown), None) if countdown.remaining.subsec_nanos() > 0 => {
+                        Duration::from_nanos(countdown.remaining.subsec_nanos().into())
+                    }
+                    _ => Duration::from_secs(1),
+                };
+                tokio::select! {
+                    _ = time::sleep(tick), if countdown.is_some() => {}
+                    Some(request) = requests.recv() => match request {
+                        Request::Start { duration, label } => {
+                            ends = Instant::now() + duration;
+                            countdown = Some(Countdown { label, remaining: duration });
+                        }
+                        Request::Stop => countdown = None,
+                    },
+                    Some(click) = clicks.next() => match click.button {
+                        MouseButton::Left if countdown.is_some() => countdown = None,
+                        MouseButton::Left => {
+                            ends = Instant::now() + self.duration;
+                            countdown = Some(Countdown {
+                                label: None,
+                                remaining: self.duration,
+                            });
+                        }
+                        _ => continue,
+                    },
+                    // Nothing can start a countdown without clicks or
+                    // commands.
+                    else => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        let now = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        assert_eq!(
+            parse_request(&["start", "25m", "green", "tea"], now).unwrap(),
+            Request::Start {
+                duration: Duration::from_secs(25 * 60),
+                label: Some("green tea".to_owned()),
+            }
+        );
+        assert_eq!(
+            parse_request(&["start", "17:30"], now).unwrap(),
+            Request::Start {
+                duration: Duration::from_secs(30 * 60),
+                label: None,
+            }
+        );
+        // A time which has passed is tomorrow's.
+        assert_eq!(
+            parse_target("16:00", now).unwrap(),
+            Duration::from_secs(23 * 3600)
+        );
+        assert_eq!(parse_request(&["stop"], now).unwrap(), Request::Stop);
+        assert!(parse_request(&["start"], now).is_err());
+        assert!(parse_request(&["start", "soon"], now).is_err());
+        assert!(parse_request(&[], now).is_err());
+    }
+
+    #[test]
+    fn remaining() {
+        assert_eq!(format_remaining(Duration::from_millis(299_001)), "05:00");
+        assert_eq!(
+            format_remaining(Duration::from_secs(3 * 3600 + 62)),
+            "3:01:02"
+        );
+        assert_eq!(format_remaining(Duration::ZERO), "00:00");
+    }
+}
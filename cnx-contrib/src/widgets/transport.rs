@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time;
+
+mod jack;
+
+// How long to wait for Ardour to answer.
+const ARDOUR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where [`Transport`] gets the transport's state from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The JACK server's transport, which JACK applications, and DAWs using
+    /// JACK, share. `libjack.so.0` is loaded when the widget starts, so
+    /// PipeWire's JACK support works too.
+    Jack,
+    /// Ardour's transport, asked for over OSC, which must be enabled in
+    /// Ardour's preferences. It listens on port 3819 by default. Ardour
+    /// doesn't tell OSC clients its tempo, so there is no BPM.
+    Ardour(SocketAddr),
+}
+
+/// The state a transport can be in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum State {
+    /// Stopped
+    Stopped,
+    /// About to roll, while JACK clients get ready
+    Starting,
+    /// Playing
+    Rolling,
+    /// Playing and recording, which only Ardour tells
+    Recording,
+}
+
+/// The transport's state and position, as shown by [`Transport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    /// Whether the transport is rolling
+    pub state: State,
+    /// The tempo, in beats per minute, if the timebase master gives it
+    pub bpm: Option<f64>,
+    /// The bar and beat, counting from 1, if the timebase master gives them
+    pub bar_beat: Option<(i32, i32)>,
+}
+
+type Render = Box<dyn Fn(&Status) -> String>;
+
+/// Shows whether JACK's or Ardour's transport is rolling, and its tempo and
+/// position, e.g. `▶ 120 BPM 12|3`, so that you can see it without the DAW
+/// focused. While recording, it is shown in the theme's `Crit` color.
+///
+/// The transport is checked every half a second by default, and the widget
+/// is updated when it has changed.
+pub struct Transport {
+    attr: Attributes,
+    source: Source,
+    update_interval: Duration,
+    render: Option<Render>,
+}
+
+impl Transport {
+    /// Creates a new [`Transport`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `source` - Which transport to show.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the transport's [`Status`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::transport::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(Transport::new(attr.clone(), Source::Jack, None));
+    /// let ardour = Source::Ardour("127.0.0.1:3819".parse()?);
+    /// let render = Box::new(|status: &Status| match status.state {
+    ///     State::Recording => "REC".to_owned(),
+    ///     _ => String::new(),
+    /// });
+    /// cnx.add_widget(Transport::new(attr, ardour, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, source: Source, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            source,
+            update_interval: Duration::from_millis(500),
+            render,
+        }
+    }
+
+    /// Sets how often to check the transport. The default is every half a
+    /// second.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    // Asks for the transport's status, connecting to JACK if it isn't
+    // already, or if the server has gone away since.
+    async fn status(&self, client: &mut Option<jack::Client>) -> Result<Status> {
+        match self.source {
+            Source::Jack => {
+                let jack = match client.take() {
+                    Some(jack) if !jack.is_shut_down() => jack,
+                    _ => jack::Client::open()?,
+                };
+                let status = jack.query();
+                *client = Some(jack);
+                Ok(status)
+            }
+            Source::Ardour(address) => ardour_status(address).await,
+        }
+    }
+
+    fn render(&self, status: &Status) -> Vec<Text> {
+        let mut attr = self.attr.clone();
+        let text = match &self.render {
+            Some(render) => (render)(status),
+            None => {
+                let mut text = match status.state {
+                    State::Stopped => "■",
+                    State::Starting | State::Rolling => "▶",
+                    State::Recording => {
+                        attr.fg_color = Color::role(Role::Crit);
+                        "●"
+                    }
+                }
+                .to_owned();
+                if let Some(bpm) = status.bpm {
+                    text.push_str(&format!(" {bpm:.0} BPM"));
+                }
+                if let Some((bar, beat)) = status.bar_beat {
+                    text.push_str(&format!(" {bar}|{beat}"));
+                }
+                text
+            }
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Asks Ardour whether its transport is rolling and whether it is recording,
+// which it answers with messages of the same addresses.
+async fn ardour_status(address: SocketAddr) -> Result<Status> {
+    let local = match address {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(address).await?;
+    socket.send(&osc_message("/transport_speed")).await?;
+    socket.send(&osc_message("/record_enabled")).await?;
+
+    let mut speed = None;
+    let mut record = None;
+    let mut packet = [0; 1024];
+    let replies = async {
+        while speed.is_none() || record.is_none() {
+            let len = socket.recv(&mut packet).await?;
+            match parse_osc(&packet[..len]) {
+                Some(("/transport_speed", value)) => speed = Some(value),
+                Some(("/record_enabled", value)) => record = Some(value),
+                // Ardour may also send feedback we haven't asked for.
+                _ => {}
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    time::timeout(ARDOUR_TIMEOUT, replies)
+        .await
+        .map_err(|_| anyhow!("No reply from Ardour at {}", address))??;
+
+    let state = match (speed, record) {
+        (Some(speed), _) if speed == 0.0 => State::Stopped,
+        (_, Some(record)) if record != 0.0 => State::Recording,
+        _ => State::Rolling,
+    };
+    Ok(Status {
+        state,
+        bpm: None,
+        bar_beat: None,
+    })
+}
+
+// Encodes an OSC message without arguments.
+fn osc_message(address: &str) -> Vec<u8> {
+    let mut message = osc_string(address);
+    message.extend(osc_string(","));
+    message
+}
+
+// Encodes an OSC string, which is nul terminated and padded to a multiple of
+// four bytes.
+fn osc_string(string: &str) -> Vec<u8> {
+    let mut bytes = string.as_bytes().to_vec();
+    bytes.resize((bytes.len() + 4) & !3, 0);
+    bytes
+}
+
+// Splits an OSC string off the front of `bytes`.
+fn read_osc_string(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let len = bytes.iter().position(|&b| b == 0)?;
+    let string = std::str::from_utf8(&bytes[..len]).ok()?;
+    Some((string, bytes.get((len + 4) & !3..)?))
+}
+
+// Decodes an OSC message with a single numeric argument, returning its
+// address and argument.
+fn parse_osc(packet: &[u8]) -> Option<(&str, f64)> {
+    let (address, rest) = read_osc_string(packet)?;
+    let (tags, argument) = read_osc_string(rest)?;
+    let value = match tags {
+        ",i" => f64::from(i32::from_be_bytes(argument.get(..4)?.try_into().ok()?)),
+        ",f" => f64::from(f32::from_be_bytes(argument.get(..4)?.try_into().ok()?)),
+        ",h" => i64::from_be_bytes(argument.get(..8)?.try_into().ok()?) as f64,
+        ",d" => f64::from_be_bytes(argument.get(..8)?.try_into().ok()?),
+        _ => return None,
+    };
+    Some((address, value))
+}
+
+impl Widget for Transport {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            let mut client = None;
+            let mut last = None;
+            loop {
+                match self.status(&mut client).await {
+                    Ok(status) => {
+                        if last.as_ref() != Some(&status) {
+                            yield Ok(self.render(&status));
+                            last = Some(status);
+                        }
+                    }
+                    Err(err) => {
+                        last = None;
+                        yield Err(err);
+                    }
+                }
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn osc() {
+        assert_eq!(
+            osc_message("/transport_speed"),
+            b"/transport_speed\0\0\0\0,\0\0\0"
+        );
+        assert_eq!(osc_message("/abc"), b"/abc\0\0\0\0,\0\0\0");
+
+        let mut reply = b"/transport_speed\0\0\0\0,f\0\0".to_vec();
+        reply.extend(1.5f32.to_be_bytes());
+        assert_eq!(parse_osc(&reply), Some(("/transport_speed", 1.5)));
+
+        let mut reply = b"/record_enabled\0,i\0\0".to_vec();
+        reply.extend(1i32.to_be_bytes());
+        assert_eq!(parse_osc(&reply), Some(("/record_enabled", 1.0)));
+
+        assert_eq!(parse_osc(b"/strip/name\0,s\0\0Bass\0\0\0\0"), None);
+        assert_eq!(parse_osc(b"/record_enabled\0,i\0\0\0\0"), None);
+    }
+}
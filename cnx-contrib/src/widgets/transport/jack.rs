@@ -0,0 +1,173 @@
+use super::{State, Status};
+use crate::dylib::Library;
+use anyhow::{anyhow, Result};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// See `jack/types.h` and `jack/transport.h`.
+type JackClient = *mut c_void;
+const JACK_NO_START_SERVER: c_int = 0x01;
+const JACK_TRANSPORT_ROLLING: c_int = 1;
+const JACK_TRANSPORT_STARTING: c_int = 3;
+const JACK_POSITION_BBT: u32 = 0x10;
+
+// `jack_position_t` is packed, so rather than declaring it, its fields are
+// read from their offsets.
+const POSITION_SIZE: usize = 136;
+const VALID_OFFSET: usize = 24;
+const BAR_OFFSET: usize = 28;
+const BEAT_OFFSET: usize = 32;
+const BEATS_PER_MINUTE_OFFSET: usize = 64;
+
+// Called by JACK, on a thread of its own, if the server goes away.
+extern "C" fn on_shutdown(shut_down: *mut c_void) {
+    unsafe { (*(shut_down as *const AtomicBool)).store(true, Ordering::Relaxed) };
+}
+
+/// A client of the JACK server, which only asks it about the transport.
+pub struct Client {
+    client: JackClient,
+    transport_query: unsafe extern "C" fn(JackClient, *mut u8) -> c_int,
+    client_close: unsafe extern "C" fn(JackClient) -> c_int,
+    // Set once the server has gone away. It is boxed so that it stays where
+    // JACK was told it is.
+    shut_down: Box<AtomicBool>,
+    // Dropped, and so unloaded, after the client is closed.
+    _library: Library,
+}
+
+impl Client {
+    /// Connects to the JACK server that is running, without starting one.
+    pub fn open() -> Result<Client> {
+        let library = Library::open("libjack.so.0", "is installed with JACK or pipewire-jack")?;
+        unsafe {
+            let client_open: unsafe extern "C" fn(
+                *const c_char,
+                c_int,
+                *mut c_int,
+                ...
+            ) -> JackClient = library.symbol(b"jack_client_open\0")?;
+            let on_shutdown_fn: unsafe extern "C" fn(
+                JackClient,
+                extern "C" fn(*mut c_void),
+                *mut c_void,
+            ) = library.symbol(b"jack_on_shutdown\0")?;
+            let activate: unsafe extern "C" fn(JackClient) -> c_int =
+                library.symbol(b"jack_activate\0")?;
+            let transport_query = library.symbol(b"jack_transport_query\0")?;
+            let client_close = library.symbol(b"jack_client_close\0")?;
+
+            let name = b"cnx\0";
+            let mut status = 0;
+            let client = client_open(
+                name.as_ptr() as *const c_char,
+                JACK_NO_START_SERVER,
+                &mut status,
+            );
+            if client.is_null() {
+                return Err(anyhow!(
+                    "Failed to connect to the JACK server (status {:#x})",
+                    status
+                ));
+            }
+            // From here on, dropping the client closes it.
+            let client = Client {
+                client,
+                transport_query,
+                client_close,
+                shut_down: Box::new(AtomicBool::new(false)),
+                _library: library,
+            };
+            let shut_down = &*client.shut_down as *const AtomicBool as *mut c_void;
+            on_shutdown_fn(client.client, on_shutdown, shut_down);
+            if activate(client.client) != 0 {
+                return Err(anyhow!("Failed to activate the JACK client"));
+            }
+            Ok(client)
+        }
+    }
+
+    /// Returns whether the JACK server has gone away, after which the client
+    /// must be opened again.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::Relaxed)
+    }
+
+    /// Asks the server for the transport's state and position.
+    pub fn query(&self) -> Status {
+        let mut position = [0u8; POSITION_SIZE];
+        let state = unsafe { (self.transport_query)(self.client, position.as_mut_ptr()) };
+        parse_position(state, &position)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        unsafe { (self.client_close)(self.client) };
+    }
+}
+
+// Reads the tempo and the bar and beat from a `jack_position_t`, which only
+// has them if the timebase master, e.g. a DAW, gives them.
+fn parse_position(state: c_int, position: &[u8; POSITION_SIZE]) -> Status {
+    let state = match state {
+        JACK_TRANSPORT_ROLLING => State::Rolling,
+        JACK_TRANSPORT_STARTING => State::Starting,
+        _ => State::Stopped,
+    };
+    let valid = u32::from_ne_bytes(field(position, VALID_OFFSET));
+    if valid & JACK_POSITION_BBT == 0 {
+        return Status {
+            state,
+            bpm: None,
+            bar_beat: None,
+        };
+    }
+    let bpm = f64::from_ne_bytes(field(position, BEATS_PER_MINUTE_OFFSET));
+    let bar = i32::from_ne_bytes(field(position, BAR_OFFSET));
+    let beat = i32::from_ne_bytes(field(position, BEAT_OFFSET));
+    Status {
+        state,
+        bpm: Some(bpm),
+        bar_beat: Some((bar, beat)),
+    }
+}
+
+// Copies the `N` bytes of the field at `offset` out of a `jack_position_t`.
+fn field<const N: usize>(position: &[u8; POSITION_SIZE], offset: usize) -> [u8; N] {
+    let mut field = [0; N];
+    field.copy_from_slice(&position[offset..offset + N]);
+    field
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let mut position = [0u8; POSITION_SIZE];
+        assert_eq!(
+            parse_position(0, &position),
+            Status {
+                state: State::Stopped,
+                bpm: None,
+                bar_beat: None,
+            }
+        );
+
+        position[VALID_OFFSET..VALID_OFFSET + 4].copy_from_slice(&0x10u32.to_ne_bytes());
+        position[BAR_OFFSET..BAR_OFFSET + 4].copy_from_slice(&12i32.to_ne_bytes());
+        position[BEAT_OFFSET..BEAT_OFFSET + 4].copy_from_slice(&3i32.to_ne_bytes());
+        position[BEATS_PER_MINUTE_OFFSET..BEATS_PER_MINUTE_OFFSET + 8]
+            .copy_from_slice(&128.0f64.to_ne_bytes());
+        assert_eq!(
+            parse_position(JACK_TRANSPORT_ROLLING, &position),
+            Status {
+                state: State::Rolling,
+                bpm: Some(128.0),
+                bar_beat: Some((12, 3)),
+            }
+        );
+    }
+}
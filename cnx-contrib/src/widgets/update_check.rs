@@ -0,0 +1,187 @@
+use crate::http::{HttpClient, HttpConfig};
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::env;
+use std::time::Duration;
+use tokio::time;
+
+const CRATES_IO_URL: &str = "https://crates.io/api/v1/crates/cnx";
+
+/// The environment variable which, if set, stops [`UpdateCheck`] from ever
+/// checking for a new version.
+pub const OPT_OUT_VAR: &str = "CNX_NO_UPDATE_CHECK";
+
+/// Shows a badge when a newer version of Cnx is available on crates.io.
+///
+/// The widget is hidden until a newer version is found. It checks once at
+/// startup and then at long intervals, daily by default. Setting the
+/// `CNX_NO_UPDATE_CHECK` environment variable disables the check altogether,
+/// so no requests are ever made.
+pub struct UpdateCheck {
+    attr: Attributes,
+    current_version: String,
+    update_interval: Duration,
+    http: HttpConfig,
+    render: Option<Box<dyn Fn(String) -> String>>,
+}
+
+impl UpdateCheck {
+    /// Creates a new [`UpdateCheck`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the newest version available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::update_check::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(UpdateCheck::new(attr, None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(String) -> String>>) -> Self {
+        Self {
+            attr,
+            current_version: cnx::VERSION.to_owned(),
+            update_interval: Duration::from_secs(24 * 60 * 60),
+            http: HttpConfig::default(),
+            render,
+        }
+    }
+
+    /// Sets how often to check for a new version. The default is once a
+    /// day.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to reach
+    /// crates.io.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    fn render(&self, latest: &str) -> Vec<Text> {
+        let text = self.render.as_ref().map_or_else(
+            || i18n::tr_with("Cnx {} available", latest),
+            |x| (x)(latest.to_owned()),
+        );
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Fetches the newest stable version of Cnx from crates.io.
+async fn latest_version(client: &HttpClient) -> Result<String> {
+    let body = client.get_text(CRATES_IO_URL).await?;
+    parse_max_stable_version(&body)
+}
+
+fn parse_max_stable_version(body: &str) -> Result<String> {
+    let re = regex::Regex::new(r#""max_stable_version"\s*:\s*"([^"]+)""#)?;
+    re.captures(body)
+        .map(|captures| captures[1].to_owned())
+        .ok_or_else(|| anyhow!("No version in response from crates.io"))
+}
+
+// Compares the `major.minor.patch` parts of two versions. Any pre-release or
+// build metadata is ignored.
+fn is_newer(version: &str, than: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    parts(version) > parts(than)
+}
+
+impl Widget for UpdateCheck {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        if env::var_os(OPT_OUT_VAR).is_some() {
+            return Ok(Box::pin(tokio_stream::empty()));
+        }
+
+        let client = self.http.client()?;
+        let mut interval = time::interval(self.update_interval);
+        let stream = stream! {
+            loop {
+                interval.tick().await;
+                match latest_version(&client).await {
+                    Ok(latest) if is_newer(&latest, &self.current_version) => {
+                        yield Ok(self.render(&latest));
+                    }
+                    Ok(_) => yield Ok(Vec::new()),
+                    Err(err) => yield Err(err),
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// Parses `body` as a response from crates.io, for the `http_body` fuzz
+// target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(body: &str) {
+    let _ = parse_max_stable_version(body);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_response() {
+        let body =
+            r#"{"crate":{"id":"cnx","max_version":"0.4.0-beta","max_stable_version":"0.3.1"}}"#;
+        assert_eq!(parse_max_stable_version(body).unwrap(), "0.3.1");
+        assert!(parse_max_stable_version("{}").is_err());
+    }
+
+    #[test]
+    fn compare_versions() {
+        assert!(is_newer("0.3.1", "0.3.0"));
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(!is_newer("0.3.0", "0.3.0"));
+        assert!(!is_newer("0.3.0-beta", "0.3.0"));
+        assert!(!is_newer("0.2.9", "0.3.0"));
+    }
+}
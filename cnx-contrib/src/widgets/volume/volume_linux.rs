@@ -105,6 +105,7 @@ impl Widget for Volume {
                 text,
                 stretch: false,
                 markup: true,
+                canvas: None,
             }])
         });
 
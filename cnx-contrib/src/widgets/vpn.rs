@@ -0,0 +1,223 @@
+use crate::netlink::{attr, Socket};
+use anyhow::{Context, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::{self as stream, StreamExt};
+
+// See `include/uapi/linux/rtnetlink.h` and `if_link.h` in the kernel
+// sources.
+const RTM_GETLINK: u16 = 18;
+const RTNLGRP_LINK: u32 = 1;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+
+// The size of `struct ifinfomsg`.
+const IFINFOMSG_LEN: usize = 16;
+
+/// The kind of a VPN tunnel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TunnelKind {
+    /// A WireGuard interface, e.g. as set up by `wg-quick`.
+    WireGuard,
+    /// A TUN or TAP interface, as used by e.g. OpenVPN and NetworkManager's
+    /// VPN plugins.
+    Tun,
+}
+
+/// A VPN tunnel which is up, as shown by [`Vpn`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tunnel {
+    /// The name of the tunnel's interface, e.g. `wg0`
+    pub name: String,
+    pub kind: TunnelKind,
+}
+
+// Returns a string attribute's value, without its trailing nul.
+fn string(payload: &[u8]) -> String {
+    let payload = payload.strip_suffix(&[0]).unwrap_or(payload);
+    String::from_utf8_lossy(payload).into_owned()
+}
+
+// Returns the tunnel described by a reply to `RTM_GETLINK`, if the interface
+// is a tunnel and is up.
+fn parse_tunnel(reply: &[u8]) -> Option<Tunnel> {
+    let flags = u32::from_ne_bytes(reply.get(8..12)?.try_into().ok()?);
+    if flags & libc::IFF_UP as u32 == 0 {
+        return None;
+    }
+    let attributes = reply.get(IFINFOMSG_LEN..)?;
+    let kind = attr(attr(attributes, IFLA_LINKINFO)?, IFLA_INFO_KIND)?;
+    let kind = match string(kind).as_str() {
+        "wireguard" => TunnelKind::WireGuard,
+        "tun" => TunnelKind::Tun,
+        _ => return None,
+    };
+    let name = string(attr(attributes, IFLA_IFNAME)?);
+    Some(Tunnel { name, kind })
+}
+
+// Asks the kernel over rtnetlink for the tunnels which are up.
+fn tunnels(socket: &mut Socket) -> Result<Vec<Tunnel>> {
+    let ifinfomsg = vec![0; IFINFOMSG_LEN];
+    let links = socket.request(RTM_GETLINK, libc::NLM_F_DUMP as u16, &ifinfomsg)?;
+    Ok(links
+        .iter()
+        .filter_map(|reply| parse_tunnel(reply))
+        .collect())
+}
+
+/// Shows a lock and the names of the VPN tunnels which are up, if any.
+///
+/// WireGuard interfaces and TUN/TAP interfaces, which most other VPNs use,
+/// are counted as tunnels. This asks the kernel over rtnetlink, and is
+/// updated as soon as a tunnel goes up or down.
+///
+/// By default, the widget is hidden while there are no tunnels; see
+/// [`Vpn::with_inactive_text`].
+pub struct Vpn {
+    attr: Attributes,
+    inactive_text: Option<String>,
+    render: Option<Box<dyn Fn(Vec<Tunnel>) -> String>>,
+}
+
+impl Vpn {
+    /// Creates a new [`Vpn`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the [`Tunnel`]s which are up, and is
+    /// called even when there are none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::vpn::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|tunnels: Vec<Tunnel>| match tunnels.first() {
+    ///     Some(tunnel) => format!("<span foreground=\"#00ff00\">🔒 {}</span>", tunnel.name),
+    ///     None => "<span foreground=\"#ff0000\">🔓</span>".to_owned(),
+    /// });
+    /// cnx.add_widget(Vpn::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Box<dyn Fn(Vec<Tunnel>) -> String>>) -> Self {
+        Self {
+            attr,
+            inactive_text: None,
+            render,
+        }
+    }
+
+    /// Shows `inactive_text`, e.g. `🔓`, while there are no tunnels, rather
+    /// than hiding the widget.
+    pub fn with_inactive_text(self, inactive_text: String) -> Self {
+        Self {
+            inactive_text: Some(inactive_text),
+            ..self
+        }
+    }
+
+    fn render(&self, tunnels: Vec<Tunnel>) -> Vec<Text> {
+        let text = match (&self.render, &self.inactive_text) {
+            (Some(render), _) => (render)(tunnels),
+            (None, _) if !tunnels.is_empty() => {
+                let names: Vec<_> = tunnels.iter().map(|tunnel| tunnel.name.as_str()).collect();
+                format!("🔒 {}", names.join(", "))
+            }
+            (None, Some(inactive_text)) => inactive_text.clone(),
+            (None, None) => return Vec::new(),
+        };
+
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for Vpn {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let mut socket = Socket::open(libc::NETLINK_ROUTE, false).context("Initialising Vpn")?;
+        let events = Socket::open(libc::NETLINK_ROUTE, true).context("Initialising Vpn")?;
+        events.subscribe(RTNLGRP_LINK)?;
+
+        let stream = stream::once(Ok(()))
+            .chain(events.events()?)
+            .map(move |event| {
+                event?;
+                Ok(self.render(tunnels(&mut socket)?))
+            });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::netlink::put_attr;
+
+    fn ifinfomsg(name: &str, flags: i32, kind: Option<&str>) -> Vec<u8> {
+        let mut reply = vec![0; IFINFOMSG_LEN];
+        reply[8..12].copy_from_slice(&(flags as u32).to_ne_bytes());
+        put_attr(&mut reply, IFLA_IFNAME, format!("{name}\0").as_bytes());
+        if let Some(kind) = kind {
+            let mut linkinfo = Vec::new();
+            put_attr(
+                &mut linkinfo,
+                IFLA_INFO_KIND,
+                format!("{kind}\0").as_bytes(),
+            );
+            // Nested attributes are flagged in the top bit of their type.
+            put_attr(&mut reply, IFLA_LINKINFO | 0x8000, &linkinfo);
+        }
+        reply
+    }
+
+    #[test]
+    fn tunnel() {
+        let up = libc::IFF_UP | libc::IFF_RUNNING;
+        assert_eq!(
+            parse_tunnel(&ifinfomsg("wg0", up, Some("wireguard"))),
+            Some(Tunnel {
+                name: "wg0".to_owned(),
+                kind: TunnelKind::WireGuard
+            })
+        );
+        assert_eq!(
+            parse_tunnel(&ifinfomsg("tun0", up, Some("tun"))),
+            Some(Tunnel {
+                name: "tun0".to_owned(),
+                kind: TunnelKind::Tun
+            })
+        );
+        assert_eq!(parse_tunnel(&ifinfomsg("wg0", 0, Some("wireguard"))), None);
+        assert_eq!(parse_tunnel(&ifinfomsg("enp3s0", up, None)), None);
+        assert_eq!(parse_tunnel(&ifinfomsg("veth0", up, Some("veth"))), None);
+    }
+}
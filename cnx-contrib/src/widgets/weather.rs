@@ -1,15 +1,22 @@
 use anyhow::Result;
 use async_stream::try_stream;
+use cnx::i18n;
+use cnx::services::Services;
 use cnx::text::{Attributes, Text};
+use cnx::units::Units;
 use cnx::widgets::{Widget, WidgetStream};
 use std::time::Duration;
 use weathernoaa::weather::*;
 
 /// Represents Weather widget used to show current weather information.
+///
+/// Without a `render` closure, the temperature is shown in the temperature
+/// unit of the bar's [`Units`].
 pub struct Weather {
     attr: Attributes,
     station_code: String,
     render: Option<Box<dyn Fn(WeatherInfo) -> String>>,
+    services: Option<Services>,
 }
 
 impl Weather {
@@ -66,21 +73,29 @@ impl Weather {
             attr,
             station_code,
             render,
+            services: None,
         }
     }
 }
 
 impl Widget for Weather {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let units = self
+            .services
+            .as_ref()
+            .map(Units::shared)
+            .unwrap_or_default();
         let stream = try_stream! {
             loop {
                 let weather = get_weather(self.station_code.clone()).await?;
-                let text = self.render.as_ref().map_or(format!("Temp: {}°C", weather.temperature.celsius), |x| (x)(weather));
+                let temperature = units.temperature(f64::from(weather.temperature.celsius));
+                let text = self.render.as_ref().map_or(format!("{}: {:.0}{}", i18n::tr("Temp"), temperature, units.temperature_symbol()), |x| (x)(weather));
                 let texts = vec![Text {
                     attr: self.attr.clone(),
                     text,
                     stretch: false,
                     markup: true,
+                    canvas: None,
                 }];
                 yield texts;
 
@@ -91,4 +106,8 @@ impl Widget for Weather {
         };
         Ok(Box::pin(stream))
     }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
 }
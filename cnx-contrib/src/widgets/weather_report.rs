@@ -0,0 +1,424 @@
+use crate::http::{HttpClient, HttpConfig};
+use anyhow::{anyhow, Result};
+use async_stream::stream;
+use cnx::i18n;
+use cnx::services::Services;
+use cnx::text::{Attributes, Text};
+use cnx::units::{Speed, Temperature, Units};
+use cnx::widgets::{Widget, WidgetStream};
+use reqwest::Url;
+use std::time::{Duration, Instant};
+use tokio::time;
+
+const WTTR_URL: &str = "https://wttr.in/";
+const METAR_URL: &str = "https://tgftp.nws.noaa.gov/data/observations/metar/stations/";
+
+// How long to wait before trying again after a report couldn't be fetched.
+const RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Where [`WeatherReport`] gets the weather from. Neither needs an account
+/// or an API key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// [wttr.in](https://wttr.in), for a location such as `London`,
+    /// `Eiffel Tower` or an airport code such as `muc`. If it is empty,
+    /// wttr.in guesses the location from your IP address.
+    Wttr(String),
+    /// The latest METAR report from NOAA for the weather station at an
+    /// airport with the given ICAO code, such as `EGLL`.
+    Metar(String),
+}
+
+/// Shows the current weather from wttr.in or a METAR report, without needing
+/// an account or an API key.
+///
+/// The text is given by a format string using wttr.in's
+/// [format specifiers](https://github.com/chubin/wttr.in#one-line-output),
+/// `%c %t` (the conditions as an emoji, and the temperature) by default. For
+/// [`Source::Wttr`], the format is passed to wttr.in as it is. For
+/// [`Source::Metar`], only these are filled in, and `%c` is left empty:
+///
+/// * `%t` - the temperature, e.g. `+12°C`
+/// * `%w` - the wind direction and speed, e.g. `SW 19km/h`
+/// * `%h` - the relative humidity, e.g. `72%`
+/// * `%P` - the pressure, e.g. `1013hPa`
+/// * `%l` - the station's ICAO code
+///
+/// Temperatures and speeds are shown in the bar's [`Units`]. wttr.in can
+/// only show them in Celsius and km/h or m/s, or in Fahrenheit and mph, so
+/// its speeds are in mph whenever its temperatures are in Fahrenheit.
+///
+/// The weather is fetched every 30 minutes by default. If it can't be
+/// fetched, e.g. because you are offline, the last report keeps being shown
+/// and the fetch is tried again after 5 minutes, until the last report is
+/// more than 2 hours old, when the offline text is shown instead.
+pub struct WeatherReport {
+    attr: Attributes,
+    source: Source,
+    format: String,
+    update_interval: Duration,
+    max_age: Duration,
+    offline_text: String,
+    http: HttpConfig,
+    services: Option<Services>,
+}
+
+impl WeatherReport {
+    /// Creates a new [`WeatherReport`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `source` - Where to get the weather from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::weather_report::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(WeatherReport::new(attr.clone(), Source::Wttr("Bangalore".into())));
+    /// cnx.add_widget(WeatherReport::new(attr, Source::Metar("VOBL".into())).with_format("%t %w"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, source: Source) -> Self {
+        Self {
+            attr,
+            source,
+            format: "%c %t".to_owned(),
+            update_interval: Duration::from_secs(30 * 60),
+            max_age: Duration::from_secs(2 * 60 * 60),
+            offline_text: i18n::tr("Weather unavailable"),
+            http: HttpConfig::default(),
+            services: None,
+        }
+    }
+
+    /// Sets the format of the text, using wttr.in's format specifiers. The
+    /// default is `%c %t`.
+    pub fn with_format(self, format: &str) -> Self {
+        Self {
+            format: format.to_owned(),
+            ..self
+        }
+    }
+
+    /// Sets how often to fetch the weather. The default is every 30 minutes.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Sets how long to keep showing the last report while the weather can't
+    /// be fetched, and what to show after that. The default is 2 hours, and
+    /// then `Weather unavailable`.
+    pub fn with_offline(self, max_age: Duration, offline_text: String) -> Self {
+        Self {
+            max_age,
+            offline_text,
+            ..self
+        }
+    }
+
+    /// Sets the proxy, certificates, timeout and retries used to fetch the
+    /// weather.
+    pub fn with_http(self, http: HttpConfig) -> Self {
+        Self { http, ..self }
+    }
+
+    // Fetches the weather, and returns it in `self.format`.
+    async fn fetch(&self, client: &HttpClient, units: &Units) -> Result<String> {
+        match &self.source {
+            Source::Wttr(location) => {
+                let mut url = Url::parse(WTTR_URL)?;
+                if !location.is_empty() {
+                    url.path_segments_mut()
+                        .map_err(|_| anyhow!("Invalid wttr.in URL"))?
+                        .pop_if_empty()
+                        .push(location);
+                }
+                let query = match (units.temperature, units.speed) {
+                    (Temperature::Fahrenheit, _) => "u",
+                    (_, Speed::MetersPerSecond) => "M",
+                    _ => "m",
+                };
+                url.set_query(Some(query));
+                url.query_pairs_mut().append_pair("format", &self.format);
+                let body = client.get_text(url.as_str()).await?;
+                Ok(body.trim().to_owned())
+            }
+            Source::Metar(station) => {
+                let url = format!("{METAR_URL}{}.TXT", station.to_uppercase());
+                let body = client.get_text(&url).await?;
+                let metar = Metar::parse(&body)?;
+                Ok(metar.format(&self.format, units))
+            }
+        }
+    }
+
+    fn render(&self, text: String) -> Vec<Text> {
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for WeatherReport {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let client = self.http.client()?;
+        let units = self
+            .services
+            .as_ref()
+            .map(Units::shared)
+            .unwrap_or_default();
+        let stream = stream! {
+            let mut fetched: Option<Instant> = None;
+            loop {
+                match self.fetch(&client, &units).await {
+                    Ok(text) => {
+                        fetched = Some(Instant::now());
+                        yield Ok(self.render(text));
+                        time::sleep(self.update_interval).await;
+                        continue;
+                    }
+                    // The last report is still shown, as the bar keeps a
+                    // widget's text when it yields an error.
+                    Err(err) if fetched.map_or(false, |at| at.elapsed() < self.max_age) => {
+                        yield Err(err);
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        yield Ok(self.render(self.offline_text.clone()));
+                    }
+                }
+                time::sleep(RETRY_DELAY.min(self.update_interval)).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+// The parts of a METAR report which `WeatherReport` shows.
+#[derive(Debug, Default, PartialEq)]
+struct Metar {
+    station: String,
+    // In degrees Celsius.
+    temperature: Option<i32>,
+    dew_point: Option<i32>,
+    // The direction the wind comes from, in degrees, or `None` if it varies.
+    wind_direction: Option<u32>,
+    // In km/h.
+    wind_speed: Option<f64>,
+    // In hPa.
+    pressure: Option<f64>,
+}
+
+impl Metar {
+    // Parses the report in a station's file from NOAA, whose first line is
+    // the time it was observed, and whose second is the report itself, e.g.
+    // `EGLL 151150Z 24012KT 9999 FEW030 12/07 Q1013`.
+    fn parse(body: &str) -> Result<Metar> {
+        let report = body
+            .lines()
+            .map(str::trim)
+            .find(|line| {
+                line.split_whitespace()
+                    .nth(1)
+                    .map_or(false, |time| time.ends_with('Z'))
+            })
+            .ok_or_else(|| anyhow!("No METAR report in response"))?;
+
+        let mut groups = report.split_whitespace();
+        let mut metar = Metar {
+            station: groups.next().unwrap_or_default().to_owned(),
+            ..Metar::default()
+        };
+        for group in groups {
+            // The remarks are free-form, and may contain anything.
+            if group == "RMK" {
+                break;
+            }
+            if let Some((direction, speed)) = parse_wind(group) {
+                metar.wind_direction = direction;
+                metar.wind_speed = Some(speed);
+            } else if let Some((temperature, dew_point)) = parse_temperatures(group) {
+                metar.temperature = Some(temperature);
+                metar.dew_point = dew_point;
+            } else if let Some(pressure) = parse_pressure(group) {
+                metar.pressure = Some(pressure);
+            }
+        }
+        Ok(metar)
+    }
+
+    // The relative humidity as a percentage, from the temperature and dew
+    // point by the Magnus formula.
+    fn humidity(&self) -> Option<f64> {
+        let magnus = |t: f64| (17.625 * t / (243.04 + t)).exp();
+        let (temperature, dew_point) = (self.temperature?, self.dew_point?);
+        Some(100.0 * magnus(f64::from(dew_point)) / magnus(f64::from(temperature)))
+    }
+
+    // Fills in the specifiers in `format` which a METAR report has values
+    // for, in `units`.
+    fn format(&self, format: &str, units: &Units) -> String {
+        let temperature = self
+            .temperature
+            .map(|t| {
+                let t = units.temperature(f64::from(t)).round();
+                format!("{t:+}{}", units.temperature_symbol())
+            })
+            .unwrap_or_default();
+        let speed = self
+            .wind_speed
+            .map(|speed| format!("{:.0}{}", units.speed(speed), units.speed_symbol()));
+        let wind = match (self.wind_direction, speed) {
+            (Some(direction), Some(speed)) => format!("{} {speed}", compass(direction)),
+            (None, Some(speed)) => speed,
+            _ => String::new(),
+        };
+        let humidity = self
+            .humidity()
+            .map(|h| format!("{h:.0}%"))
+            .unwrap_or_default();
+        let pressure = self
+            .pressure
+            .map(|p| format!("{p:.0}hPa"))
+            .unwrap_or_default();
+        format
+            .replace("%t", &temperature)
+            .replace("%w", &wind)
+            .replace("%h", &humidity)
+            .replace("%P", &pressure)
+            .replace("%l", &self.station)
+            .replace("%c", "")
+            .trim()
+            .to_owned()
+    }
+}
+
+// Parses a wind group, e.g. `24012KT`, `VRB03KT`, `31015G25KT` or `24005MPS`,
+// into the direction in degrees (if it doesn't vary) and speed in km/h.
+fn parse_wind(group: &str) -> Option<(Option<u32>, f64)> {
+    let (group, factor) = if let Some(group) = group.strip_suffix("KT") {
+        (group, 1.852)
+    } else if let Some(group) = group.strip_suffix("MPS") {
+        (group, 3.6)
+    } else {
+        return None;
+    };
+    let direction = group.get(..3)?;
+    let direction = match direction {
+        "VRB" => None,
+        _ => Some(direction.parse().ok()?),
+    };
+    // Ignore any gusts.
+    let speed: f64 = group.get(3..)?.split('G').next()?.parse().ok()?;
+    Some((direction, speed * factor))
+}
+
+// Parses a temperature group, e.g. `12/07` or `M02/M05`, where `M` means
+// minus. The dew point may be missing, e.g. `12/`.
+fn parse_temperatures(group: &str) -> Option<(i32, Option<i32>)> {
+    let parse = |value: &str| -> Option<i32> {
+        match value.strip_prefix('M') {
+            Some(value) => value.parse::<i32>().ok().map(|value| -value),
+            None if value.len() == 2 => value.parse().ok(),
+            None => None,
+        }
+    };
+    let (temperature, dew_point) = group.split_once('/')?;
+    Some((parse(temperature)?, parse(dew_point)))
+}
+
+// Parses a pressure group, e.g. `Q1013` in hPa or `A2992` in hundredths of
+// an inch of mercury, into hPa.
+fn parse_pressure(group: &str) -> Option<f64> {
+    let value = group.get(1..).filter(|value| value.len() == 4)?;
+    let value: f64 = value.parse().ok()?;
+    match group.chars().next()? {
+        'Q' => Some(value),
+        'A' => Some(value / 100.0 * 33.8639),
+        _ => None,
+    }
+}
+
+// Returns the point of the compass nearest to `degrees`, e.g. `SW`.
+fn compass(degrees: u32) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((degrees + 22) % 360 / 45) as usize]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_metar() {
+        let body = "2024/01/15 11:50\nEGLL 151150Z AUTO 24012KT 9999 FEW030 12/07 Q1013 NOSIG\n";
+        let metar = Metar::parse(body).unwrap();
+        assert_eq!(metar.station, "EGLL");
+        assert_eq!(metar.temperature, Some(12));
+        assert_eq!(metar.dew_point, Some(7));
+        assert_eq!(metar.wind_direction, Some(240));
+        assert_eq!(metar.pressure, Some(1013.0));
+        assert_eq!(
+            metar.format("%l: %c %t %w %h %P", &Units::default()),
+            "EGLL:  +12°C SW 22km/h 71% 1013hPa"
+        );
+
+        let body = "KJFK 151151Z VRB03KT 10SM M02/M05 A3000 RMK AO2 SLP160 T10221050";
+        let metar = Metar::parse(body).unwrap();
+        assert_eq!(metar.temperature, Some(-2));
+        assert_eq!(metar.dew_point, Some(-5));
+        assert_eq!(metar.wind_direction, None);
+        assert_eq!(metar.format("%t %w", &Units::default()), "-2°C 6km/h");
+        assert_eq!(metar.format("%t %w", &Units::imperial()), "+28°F 3mph");
+        assert_eq!(metar.format("%P", &Units::default()), "1016hPa");
+
+        assert!(Metar::parse("Not found").is_err());
+    }
+
+    #[test]
+    fn metar_groups() {
+        assert_eq!(parse_wind("31015G25KT"), Some((Some(310), 15.0 * 1.852)));
+        assert_eq!(parse_wind("24005MPS"), Some((Some(240), 18.0)));
+        assert_eq!(parse_wind("9999"), None);
+        assert_eq!(parse_temperatures("12/"), Some((12, None)));
+        assert_eq!(parse_temperatures("1/4SM"), None);
+        assert_eq!(parse_pressure("Q0998"), Some(998.0));
+        assert_eq!(parse_pressure("FEW030"), None);
+        assert_eq!(compass(0), "N");
+        assert_eq!(compass(350), "N");
+        assert_eq!(compass(200), "S");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::secret::Secret;
+use anyhow::{Context, Result};
+use async_stream::stream;
+use cnx::text::{Attributes, Text};
+use cnx::untrusted;
+use cnx::widgets::{Widget, WidgetStream};
+use futures_util::SinkExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time;
+use tokio_stream::StreamExt;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async_with_config, MaybeTlsStream, WebSocketStream};
+
+// The delay before reconnecting the first time, which doubles each time the
+// connection fails again, up to the longest.
+const MIN_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+type Render = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Shows the messages received over a WebSocket, for live data sources such
+/// as Home Assistant's event stream or a ticker feed.
+///
+/// Each message received replaces the text shown, after being turned into
+/// text by the `render` closure, which can also skip messages which
+/// shouldn't change it, such as replies to the messages sent with
+/// [`WebSocket::with_message()`]. An empty text hides the widget, which is
+/// also hidden until the first message.
+///
+/// If the connection fails or is closed, the widget keeps showing the last
+/// text and reconnects, after a second at first and then waiting twice as
+/// long each time it fails again, up to 5 minutes. The messages and headers
+/// are sent again on each connection.
+pub struct WebSocket {
+    attr: Attributes,
+    url: String,
+    headers: Vec<(String, Secret)>,
+    messages: Vec<Secret>,
+    render: Option<Render>,
+}
+
+impl WebSocket {
+    /// Creates a new [`WebSocket`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `url` - The `ws://` or `wss://` URL to connect to.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given each message received, as text, and
+    /// returns the text to show, or `None` to ignore the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::secret::Secret;
+    /// # use cnx_contrib::widgets::websocket::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|message: &str| {
+    ///     let price = message.split("\"price\":\"").nth(1)?.split('"').next()?;
+    ///     Some(format!("BTC {price}"))
+    /// });
+    /// let ticker = WebSocket::new(attr, "wss://ws.example.com/ticker".into(), Some(render))
+    ///     .with_message(r#"{"type":"subscribe","channels":["ticker"]}"#.to_owned());
+    /// cnx.add_widget(ticker);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, url: String, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            url,
+            headers: Vec::new(),
+            messages: Vec::new(),
+            render,
+        }
+    }
+
+    /// Sends the header `name` with the request to connect, e.g.
+    /// `Authorization` with an API token. The value is looked up before
+    /// each connection.
+    pub fn with_header(mut self, name: &str, value: impl Into<Secret>) -> Self {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Sends `message` as text once connected, after any messages given
+    /// before it, e.g. to authenticate or to subscribe to events. As it may
+    /// hold a token, the message is a [`Secret`], which is looked up before
+    /// each connection.
+    pub fn with_message(mut self, message: impl Into<Secret>) -> Self {
+        self.messages.push(message.into());
+        self
+    }
+
+    async fn connect(&self) -> Result<Socket> {
+        let mut request = self.url.as_str().into_client_request()?;
+        for (name, value) in &self.headers {
+            let value = HeaderValue::from_str(&value.get().await?)
+                .with_context(|| format!("Invalid value for header {name}"))?;
+            request
+                .headers_mut()
+                .insert(HeaderName::from_bytes(name.as_bytes())?, value);
+        }
+        let config = WebSocketConfig {
+            max_message_size: Some(untrusted::MAX_MESSAGE),
+            max_frame_size: Some(untrusted::MAX_MESSAGE),
+            ..WebSocketConfig::default()
+        };
+        let (mut socket, _) = connect_async_with_config(request, Some(config))
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        for message in &self.messages {
+            socket.send(Message::Text(message.get().await?)).await?;
+        }
+        Ok(socket)
+    }
+
+    // Returns what to show for `message`, or `None` if it is to be ignored.
+    fn render(&self, message: &str) -> Option<Vec<Text>> {
+        let text = match &self.render {
+            Some(render) => (render)(message)?,
+            None => untrusted::text(message),
+        };
+        if text.is_empty() {
+            return Some(Vec::new());
+        }
+        Some(vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }])
+    }
+}
+
+impl Widget for WebSocket {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            yield Ok(Vec::new());
+            let mut delay = MIN_DELAY;
+            loop {
+                match self.connect().await {
+                    Ok(mut socket) => {
+                        while let Some(message) = socket.next().await {
+                            let message = match message {
+                                Ok(Message::Text(text)) => text,
+                                Ok(Message::Binary(bytes)) => {
+                                    String::from_utf8_lossy(&bytes).into_owned()
+                                }
+                                Ok(Message::Close(_)) => break,
+                                // Pings are answered as the socket is read.
+                                Ok(_) => continue,
+                                Err(err) => {
+                                    yield Err(err.into());
+                                    break;
+                                }
+                            };
+                            // Only a connection which works for a while
+                            // resets the delay, rather than one which is
+                            // closed as soon as it is opened.
+                            delay = MIN_DELAY;
+                            if let Some(texts) = self.render(&message) {
+                                yield Ok(texts);
+                            }
+                        }
+                    }
+                    Err(err) => yield Err(err),
+                }
+                time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_DELAY);
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cnx::text::{Color, Font, Padding};
+
+    #[test]
+    fn messages() {
+        let attr = Attributes {
+            font: Font::new("Sans 10"),
+            fg_color: Color::white(),
+            bg_color: None,
+            padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+        };
+        let render = Box::new(|message: &str| {
+            let state = message.strip_prefix("state:")?;
+            Some(state.trim().to_owned())
+        });
+        let widget = WebSocket::new(attr.clone(), "ws://localhost".into(), Some(render));
+        assert_eq!(widget.render("auth_ok"), None);
+        assert_eq!(widget.render("state: 21.5").unwrap()[0].text, "21.5");
+        assert!(widget.render("state:").unwrap().is_empty());
+
+        let widget = WebSocket::new(attr, "ws://localhost".into(), None);
+        assert_eq!(widget.render("a\nb").unwrap()[0].text, "a b");
+    }
+}
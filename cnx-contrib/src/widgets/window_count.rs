@@ -0,0 +1,213 @@
+use crate::wm::{i3, WindowManager};
+use anyhow::{anyhow, Context, Result};
+use async_stream::try_stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use cnx::xcb::{watch_properties, xcb_properties_stream};
+use tokio_stream::StreamExt;
+use xcb_util::ewmh;
+
+/// The number of windows on the current workspace.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Windows {
+    /// Number of tiled windows
+    pub tiled: usize,
+    /// Number of floating windows
+    pub floating: usize,
+}
+
+impl Windows {
+    /// Total number of windows
+    pub fn total(&self) -> usize {
+        self.tiled + self.floating
+    }
+}
+
+/// Shows how many windows are on the current workspace, and how many of them
+/// are floating.
+///
+/// With [`WindowManager::I3`], the counts come from i3/sway's layout tree and
+/// are updated on window and workspace events.
+///
+/// Without a window manager, the windows on the current desktop are counted
+/// using [`EWMH`]. As EWMH has no notion of floating windows, dialogs,
+/// utility windows, toolbars and splash screens are counted as floating, as
+/// most tiling window managers float them. Iconified windows aren't counted.
+///
+/// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
+pub struct WindowCount {
+    attr: Attributes,
+    wm: Option<WindowManager>,
+    render: Option<Box<dyn Fn(Windows) -> String>>,
+}
+
+impl WindowCount {
+    /// Creates a new [`WindowCount`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `wm` - The [`WindowManager`] to ask, or `None` to use EWMH. Only
+    /// [`WindowManager::I3`] is supported.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. [`Windows`] contains the number of tiled and
+    /// floating windows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::window_count::*;
+    /// # use cnx_contrib::wm::WindowManager;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let render = Box::new(|windows: Windows| {
+    ///     format!("{} tiled, {} floating", windows.tiled, windows.floating)
+    /// });
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(WindowCount::new(attr, Some(WindowManager::I3), Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        wm: Option<WindowManager>,
+        render: Option<Box<dyn Fn(Windows) -> String>>,
+    ) -> Self {
+        Self { attr, wm, render }
+    }
+
+    fn on_change(&self, windows: Windows) -> Vec<Text> {
+        let default_text = || match windows.floating {
+            0 => format!("[{}]", windows.tiled),
+            floating => format!("[{}+{}]", windows.tiled, floating),
+        };
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(default_text, |x| (x)(windows));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Counts the windows of the focused workspace in an i3 layout tree.
+fn i3_windows(tree: &i3::Node) -> Windows {
+    let path = tree.focused_path();
+    let workspace = path.iter().find(|node| node.node_type == "workspace");
+    match workspace {
+        Some(workspace) => Windows {
+            tiled: workspace.nodes.iter().map(i3::Node::window_count).sum(),
+            floating: workspace
+                .floating_nodes
+                .iter()
+                .map(i3::Node::window_count)
+                .sum(),
+        },
+        None => Windows::default(),
+    }
+}
+
+// Counts the windows on the current desktop using EWMH.
+//
+// This also registers for PROPERTY_CHANGE events on every client window, so
+// that we're notified when they move between desktops or are iconified.
+fn ewmh_windows(conn: &ewmh::Connection, screen_idx: i32) -> Windows {
+    let clients = match ewmh::get_client_list(conn, screen_idx).get_reply() {
+        Ok(reply) => reply.windows().to_vec(),
+        Err(_) => return Windows::default(),
+    };
+    watch_properties(conn, &clients);
+
+    let current = ewmh::get_current_desktop(conn, screen_idx).get_reply().ok();
+    let floating_types = [
+        conn.WM_WINDOW_TYPE_DIALOG(),
+        conn.WM_WINDOW_TYPE_UTILITY(),
+        conn.WM_WINDOW_TYPE_TOOLBAR(),
+        conn.WM_WINDOW_TYPE_SPLASH(),
+    ];
+
+    let mut windows = Windows::default();
+    for window in clients {
+        // Sticky windows are on all desktops.
+        let on_current = match ewmh::get_wm_desktop(conn, window).get_reply() {
+            Ok(desktop) => Some(desktop) == current || desktop == 0xFFFF_FFFF,
+            Err(_) => false,
+        };
+        let hidden = match ewmh::get_wm_state(conn, window).get_reply() {
+            Ok(state) => state.atoms().contains(&conn.WM_STATE_HIDDEN()),
+            Err(_) => false,
+        };
+        if !on_current || hidden {
+            continue;
+        }
+
+        let floating = match ewmh::get_wm_window_type(conn, window).get_reply() {
+            Ok(types) => types.atoms().iter().any(|t| floating_types.contains(t)),
+            Err(_) => false,
+        };
+        if floating {
+            windows.floating += 1;
+        } else {
+            windows.tiled += 1;
+        }
+    }
+    windows
+}
+
+impl Widget for WindowCount {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        match self.wm {
+            Some(WindowManager::I3) => {
+                let stream = try_stream! {
+                    let mut conn = i3::Connection::connect().await?;
+                    yield self.on_change(i3_windows(&conn.get_tree().await?));
+
+                    let mut events = Box::pin(i3::events(&["window", "workspace"]));
+                    while let Some(event) = events.next().await {
+                        event?;
+                        yield self.on_change(i3_windows(&conn.get_tree().await?));
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
+            None => {
+                let screen_idx = 0; // XXX assume
+                let properties = &[
+                    "_NET_CLIENT_LIST",
+                    "_NET_CURRENT_DESKTOP",
+                    "_NET_WM_DESKTOP",
+                    "_NET_WM_STATE",
+                ];
+                let (conn, stream) =
+                    xcb_properties_stream(properties).context("Initialising WindowCount")?;
+                let stream =
+                    stream.map(move |()| Ok(self.on_change(ewmh_windows(&conn, screen_idx))));
+                Ok(Box::pin(stream))
+            }
+            Some(wm) => Err(anyhow!("WindowCount does not support {:?}", wm)),
+        }
+    }
+}
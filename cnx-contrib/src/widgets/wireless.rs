@@ -1,18 +1,59 @@
-use anyhow::Result;
-use cnx::text::{Attributes, Text, Threshold};
+use anyhow::{Context, Result};
+use cnx::text::{Attributes, Color, Ramp, Text, Threshold};
 use cnx::widgets::{Widget, WidgetStream};
-use iwlib::*;
 use std::time::Duration;
 use tokio::time;
 use tokio_stream::wrappers::IntervalStream;
 use tokio_stream::StreamExt;
 
-/// Wireless widget to show wireless information for a particular ESSID
+mod nl80211;
+
+use nl80211::Nl80211;
+
+/// The network a wireless interface is connected to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WirelessInfo {
+    /// The SSID of the network
+    pub ssid: String,
+    /// The signal strength in dBm, e.g. `-60`, if the kernel reports it
+    pub signal: Option<i32>,
+    /// The signal quality as a percentage, if the kernel reports the signal
+    /// strength
+    pub quality: Option<u8>,
+}
+
+// Converts a signal strength in dBm to a quality percentage, as
+// NetworkManager does: -100 dBm or less is 0%, and -50 dBm or more is 100%.
+fn quality(signal: i32) -> u8 {
+    (2 * (signal + 100)).clamp(0, 100) as u8
+}
+
+// Escapes `text` for use in Pango markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Wireless widget to show the network a wireless interface is connected to,
+/// and the strength of its signal.
+///
+/// This asks the kernel over nl80211, as `iw` does. The widget is updated as
+/// soon as the interface connects to, disconnects from or roams between
+/// networks, and the signal strength is checked every 30 seconds.
+///
+/// The color of the text can be picked from a [`Ramp`] of colors by signal
+/// quality, and the quality can be shown with an icon picked from a `Ramp` of
+/// icons; see [`Wireless::with_colors`] and [`Wireless::with_icons`].
 pub struct Wireless {
     attr: Attributes,
     interface: String,
     update_interval: Duration,
     threshold: Option<Threshold>,
+    colors: Option<Ramp<Color>>,
+    icons: Option<Ramp<String>>,
+    disconnected_text: String,
+    render: Option<Box<dyn Fn(Option<WirelessInfo>) -> String>>,
 }
 
 impl Wireless {
@@ -57,50 +98,166 @@ impl Wireless {
     /// ```
     pub fn new(attr: Attributes, interface: String, threshold: Option<Threshold>) -> Wireless {
         Wireless {
-            update_interval: Duration::from_secs(3600),
+            update_interval: Duration::from_secs(30),
             interface,
             attr,
             threshold,
+            colors: None,
+            icons: None,
+            disconnected_text: "NA".to_owned(),
+            render: None,
+        }
+    }
+
+    /// Sets how often the signal strength is checked. The default is every
+    /// 30 seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// Colors the text with a color picked according to the signal quality,
+    /// rather than by `threshold`.
+    ///
+    /// ```
+    /// # use cnx::text::{Ramp, Role};
+    /// let colors = Ramp::roles(vec![
+    ///     (40.0, Role::Crit),
+    ///     (60.0, Role::Warn),
+    ///     (100.0, Role::Ok),
+    /// ]);
+    /// ```
+    pub fn with_colors(self, colors: Ramp<Color>) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    /// Shows an icon picked according to the signal quality before the
+    /// SSID.
+    ///
+    /// ```
+    /// # use cnx::text::Ramp;
+    /// let icons = Ramp::new(vec![
+    ///     (25.0, "\u{f091f}".to_owned()),
+    ///     (50.0, "\u{f0922}".to_owned()),
+    ///     (75.0, "\u{f0925}".to_owned()),
+    ///     (100.0, "\u{f0928}".to_owned()),
+    /// ]);
+    /// ```
+    pub fn with_icons(self, icons: Ramp<String>) -> Self {
+        Self {
+            icons: Some(icons),
+            ..self
+        }
+    }
+
+    /// Sets the text shown while the interface isn't connected to a
+    /// network. The default is `NA`.
+    pub fn with_disconnected_text(self, disconnected_text: String) -> Self {
+        Self {
+            disconnected_text,
+            ..self
+        }
+    }
+
+    /// Sets a closure to control the way output is displayed in the bar,
+    /// which is given `None` while the interface isn't connected. This
+    /// overrides `threshold`, the icons and the disconnected text.
+    pub fn with_render(self, render: Box<dyn Fn(Option<WirelessInfo>) -> String>) -> Self {
+        Self {
+            render: Some(render),
+            ..self
+        }
+    }
+
+    // The text is colored by `threshold` with markup, unless there is a
+    // color ramp.
+    fn markup(&self) -> bool {
+        self.render.is_some() || (self.threshold.is_some() && self.colors.is_none())
+    }
+
+    fn default_text(&self, info: &WirelessInfo) -> String {
+        let ssid = match self
+            .icons
+            .as_ref()
+            .zip(info.quality)
+            .and_then(|(icons, quality)| icons.get(f64::from(quality)))
+        {
+            Some(icon) => format!("{icon} {}", info.ssid),
+            None => info.ssid.clone(),
+        };
+        match (&self.threshold, info.quality) {
+            (Some(thold), Some(quality)) if self.markup() => {
+                let color = if quality <= thold.low.threshold {
+                    &thold.low.color
+                } else if quality <= thold.normal.threshold {
+                    &thold.normal.color
+                } else {
+                    &thold.high.color
+                };
+                format!(
+                    "<span foreground=\"#808080\">[</span>{} <span foreground=\"{}\">{}%</span><span foreground=\"#808080\">]</span>",
+                    escape(&ssid),
+                    color.to_hex(),
+                    quality
+                )
+            }
+            (_, Some(quality)) => format!("{ssid} {quality}%"),
+            (_, None) if self.markup() => escape(&ssid),
+            (_, None) => ssid,
         }
     }
 
-    fn tick(&self) -> Vec<Text> {
-        let wireless_info = get_wireless_info(self.interface.clone());
-
-        let text = match wireless_info {
-            Some(info) => match &self.threshold {
-                Some(thold) => {
-                    let color = if info.wi_quality <= thold.low.threshold {
-                        &thold.low.color
-                    } else if info.wi_quality <= thold.normal.threshold {
-                        &thold.normal.color
-                    } else {
-                        &thold.high.color
-                    };
-                    format!(
-                        "<span foreground=\"#808080\">[</span>{} <span foreground=\"{}\">{}%</span><span foreground=\"#808080\">]</span>",
-                        info.wi_essid,
-                        color.to_hex(),
-                        info.wi_quality
-                    )
-                }
-                None => format!("{} {}%", info.wi_essid, info.wi_quality),
-            },
-            None => "NA".to_owned(),
+    fn render(&self, info: Option<WirelessInfo>) -> Vec<Text> {
+        let quality = info.as_ref().and_then(|info| info.quality);
+        let text = match (&self.render, &info) {
+            (Some(render), _) => (render)(info.clone()),
+            (None, Some(info)) => self.default_text(info),
+            (None, None) => self.disconnected_text.clone(),
         };
+
+        let mut attr = self.attr.clone();
+        if let Some(color) = self
+            .colors
+            .as_ref()
+            .zip(quality)
+            .and_then(|(colors, quality)| colors.get(f64::from(quality)))
+        {
+            attr.fg_color = color.clone();
+        }
+
         vec![Text {
-            attr: self.attr.clone(),
+            attr,
             text,
             stretch: false,
-            markup: self.threshold.is_some(),
+            markup: self.markup(),
+            canvas: None,
         }]
     }
 }
 
 impl Widget for Wireless {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let mut nl80211 = Nl80211::connect().context("Initialising Wireless")?;
+
+        // nl80211 reports connecting and disconnecting, but not changes to
+        // the signal strength, so also check it periodically.
         let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+        let ticks = IntervalStream::new(interval).map(|_| Ok(()));
+        let events = nl80211.events()?;
+        let stream = ticks.merge(events).map(move |event| {
+            event?;
+            let info = nl80211.link(&self.interface)?.map(|link| WirelessInfo {
+                ssid: link.ssid,
+                signal: link.signal,
+                quality: link.signal.map(quality),
+            });
+            Ok(self.render(info))
+        });
 
         Ok(Box::pin(stream))
     }
@@ -0,0 +1,206 @@
+use crate::netlink::{attr, attrs, put_attr, Socket};
+use anyhow::{anyhow, Context, Result};
+use nix::net::if_::if_nametoindex;
+use std::io;
+use tokio_stream::Stream;
+
+// See `include/uapi/linux/genetlink.h` and `include/uapi/linux/nl80211.h`
+// in the kernel sources.
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
+const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
+
+const NL80211_CMD_GET_INTERFACE: u8 = 5;
+const NL80211_CMD_GET_STATION: u8 = 17;
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_STA_INFO: u16 = 21;
+const NL80211_ATTR_SSID: u16 = 52;
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
+
+// The multicast group on which nl80211 reports connecting to and
+// disconnecting from networks.
+const MLME_GROUP: &str = "mlme";
+
+const GENL_HDRLEN: usize = 4;
+
+// Returns the SSID in the reply to `NL80211_CMD_GET_INTERFACE`, which is
+// only there while the interface is connected.
+fn parse_ssid(reply: &[u8]) -> Option<String> {
+    attr(reply, NL80211_ATTR_SSID).map(|ssid| String::from_utf8_lossy(ssid).into_owned())
+}
+
+// Returns the signal strength in dBm in the reply to
+// `NL80211_CMD_GET_STATION`.
+fn parse_signal(reply: &[u8]) -> Option<i32> {
+    let info = attr(reply, NL80211_ATTR_STA_INFO)?;
+    let signal = attr(info, NL80211_STA_INFO_SIGNAL)?;
+    Some(i32::from(*signal.first()? as i8))
+}
+
+// Returns the ID of the multicast group named `name` in the reply to
+// `CTRL_CMD_GETFAMILY`.
+fn parse_group(reply: &[u8], name: &str) -> Option<u32> {
+    attrs(attr(reply, CTRL_ATTR_MCAST_GROUPS)?)
+        .find(|(_, group)| {
+            attr(group, CTRL_ATTR_MCAST_GRP_NAME) == Some(format!("{name}\0").as_bytes())
+        })
+        .and_then(|(_, group)| attr(group, CTRL_ATTR_MCAST_GRP_ID))
+        .and_then(|id| Some(u32::from_ne_bytes(id.try_into().ok()?)))
+}
+
+/// The connection of a wireless interface, as reported by nl80211.
+pub(super) struct Link {
+    pub(super) ssid: String,
+    /// The signal strength in dBm, if the kernel reports it
+    pub(super) signal: Option<i32>,
+}
+
+/// A connection to the kernel's nl80211 interface, which is what `iw` uses.
+pub(super) struct Nl80211 {
+    socket: Socket,
+    family: u16,
+    mlme_group: Option<u32>,
+}
+
+impl Nl80211 {
+    pub(super) fn connect() -> Result<Self> {
+        let socket =
+            Socket::open(libc::NETLINK_GENERIC, false).context("Failed to open netlink socket")?;
+        let mut nl80211 = Nl80211 {
+            socket,
+            family: GENL_ID_CTRL,
+            mlme_group: None,
+        };
+        let replies = nl80211
+            .request(
+                CTRL_CMD_GETFAMILY,
+                0,
+                &[(CTRL_ATTR_FAMILY_NAME, b"nl80211\0")],
+            )
+            .context("nl80211 isn't available")?;
+        let reply = replies.first().map_or(&[][..], Vec::as_slice);
+        nl80211.family = attr(reply, CTRL_ATTR_FAMILY_ID)
+            .and_then(|id| Some(u16::from_ne_bytes(id.try_into().ok()?)))
+            .ok_or_else(|| anyhow!("Invalid reply resolving nl80211"))?;
+        nl80211.mlme_group = parse_group(reply, MLME_GROUP);
+        Ok(nl80211)
+    }
+
+    // Sends a generic netlink request and returns the attributes of the
+    // replies, of which there are several if it is a dump.
+    fn request(
+        &mut self,
+        cmd: u8,
+        flags: u16,
+        attributes: &[(u16, &[u8])],
+    ) -> Result<Vec<Vec<u8>>> {
+        // The generic netlink header, with version 1 of the command.
+        let mut body = vec![cmd, 1, 0, 0];
+        for (kind, payload) in attributes {
+            put_attr(&mut body, *kind, payload);
+        }
+        let replies = self.socket.request(self.family, flags, &body)?;
+        Ok(replies
+            .into_iter()
+            .map(|reply| reply.get(GENL_HDRLEN..).unwrap_or_default().to_vec())
+            .collect())
+    }
+
+    /// Returns the network that `interface` is connected to, or `None` if
+    /// it isn't connected or doesn't exist.
+    pub(super) fn link(&mut self, interface: &str) -> Result<Option<Link>> {
+        let index = match if_nametoindex(interface) {
+            Ok(index) => index.to_ne_bytes(),
+            Err(_) => return Ok(None),
+        };
+        let index = [(NL80211_ATTR_IFINDEX, &index[..])];
+        let ssid = match self.request(NL80211_CMD_GET_INTERFACE, 0, &index) {
+            Ok(replies) => replies.iter().find_map(|reply| parse_ssid(reply)),
+            // The interface went away, or isn't a wireless one.
+            Err(err)
+                if err
+                    .downcast_ref::<io::Error>()
+                    .and_then(io::Error::raw_os_error)
+                    == Some(libc::ENODEV) =>
+            {
+                None
+            }
+            Err(err) => return Err(err),
+        };
+        let ssid = match ssid {
+            Some(ssid) => ssid,
+            None => return Ok(None),
+        };
+        // A connected interface's only station is its access point.
+        let stations = self.request(NL80211_CMD_GET_STATION, libc::NLM_F_DUMP as u16, &index)?;
+        let signal = stations.iter().find_map(|reply| parse_signal(reply));
+        Ok(Some(Link { ssid, signal }))
+    }
+
+    /// Returns a stream which yields whenever a wireless interface connects
+    /// to, disconnects from or roams between networks.
+    pub(super) fn events(&self) -> Result<impl Stream<Item = Result<()>>> {
+        let group = self
+            .mlme_group
+            .ok_or_else(|| anyhow!("nl80211 has no {} multicast group", MLME_GROUP))?;
+        let socket =
+            Socket::open(libc::NETLINK_GENERIC, true).context("Failed to open netlink socket")?;
+        socket.subscribe(group)?;
+        socket.events()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interface() {
+        let mut reply = Vec::new();
+        put_attr(&mut reply, NL80211_ATTR_IFINDEX, &3u32.to_ne_bytes());
+        put_attr(&mut reply, NL80211_ATTR_SSID, b"Caf\xc3\xa9");
+        assert_eq!(parse_ssid(&reply).as_deref(), Some("Café"));
+
+        let mut reply = Vec::new();
+        put_attr(&mut reply, NL80211_ATTR_IFINDEX, &3u32.to_ne_bytes());
+        assert_eq!(parse_ssid(&reply), None);
+    }
+
+    #[test]
+    fn station() {
+        let mut info = Vec::new();
+        put_attr(&mut info, 1, &60_000u32.to_ne_bytes());
+        put_attr(&mut info, NL80211_STA_INFO_SIGNAL, &[-57i8 as u8]);
+        let mut reply = Vec::new();
+        put_attr(&mut reply, NL80211_ATTR_IFINDEX, &3u32.to_ne_bytes());
+        // Nested attributes are flagged in the top bit of their type.
+        put_attr(&mut reply, NL80211_ATTR_STA_INFO | 0x8000, &info);
+        assert_eq!(parse_signal(&reply), Some(-57));
+    }
+
+    #[test]
+    fn family() {
+        let group = |id: u32, name: &str| {
+            let mut group = Vec::new();
+            put_attr(&mut group, CTRL_ATTR_MCAST_GRP_ID, &id.to_ne_bytes());
+            put_attr(
+                &mut group,
+                CTRL_ATTR_MCAST_GRP_NAME,
+                format!("{name}\0").as_bytes(),
+            );
+            group
+        };
+        let mut groups = Vec::new();
+        put_attr(&mut groups, 1, &group(4, "config"));
+        put_attr(&mut groups, 2, &group(6, "mlme"));
+        let mut reply = Vec::new();
+        put_attr(&mut reply, CTRL_ATTR_FAMILY_ID, &28u16.to_ne_bytes());
+        put_attr(&mut reply, CTRL_ATTR_MCAST_GROUPS, &groups);
+        assert_eq!(parse_group(&reply, "mlme"), Some(6));
+        assert_eq!(parse_group(&reply, "scan"), None);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::wm::{bspwm, herbstluftwm, i3, WindowManager};
+use anyhow::{anyhow, Result};
+use async_stream::try_stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+// The attribute holding the layout algorithm of the focused frame.
+const HLWM_ALGORITHM: &str = "tags.focus.tiling.focused_frame.algorithm";
+
+/// Shows the layout of the focused workspace/container.
+///
+/// The layout name is taken directly from the window manager:
+///
+/// - i3/sway: the layout of the container holding the focused window
+///   (`splith`, `splitv`, `stacked` or `tabbed`).
+/// - bspwm: the layout of the focused desktop (`tiled` or `monocle`).
+/// - herbstluftwm: the algorithm of the focused frame (`vertical`,
+///   `horizontal`, `max` or `grid`).
+///
+/// The widget waits for events from the window manager rather than polling.
+/// Hyprland is not supported.
+pub struct WmLayout {
+    attr: Attributes,
+    wm: WindowManager,
+    render: Option<Box<dyn Fn(String) -> String>>,
+}
+
+impl WmLayout {
+    /// Creates a new [`WmLayout`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `wm` - The [`WindowManager`] to query for the layout.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. The `String` is the layout name as reported by
+    /// the window manager, which allows mapping it to a symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::wm_layout::*;
+    /// # use cnx_contrib::wm::WindowManager;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let render = Box::new(|layout: String| match layout.as_str() {
+    ///     "monocle" | "max" | "tabbed" | "stacked" => "[M]".to_owned(),
+    ///     _ => "[]=".to_owned(),
+    /// });
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(WmLayout::new(attr, WindowManager::Bspwm, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        wm: WindowManager,
+        render: Option<Box<dyn Fn(String) -> String>>,
+    ) -> Self {
+        Self { attr, wm, render }
+    }
+
+    fn on_change(&self, layout: &str) -> Vec<Text> {
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| layout.to_owned(), |x| (x)(layout.to_owned()));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: true,
+            canvas: None,
+        }]
+    }
+}
+
+/// Returns the layout of the container holding the focused window, or of
+/// the focused workspace if it is empty.
+fn i3_layout(tree: &i3::Node) -> Option<&str> {
+    let path = tree.focused_path();
+    let focused = path.last()?;
+    let container = if focused.node_type == "workspace" {
+        focused
+    } else {
+        path.get(path.len().checked_sub(2)?)?
+    };
+    Some(&container.layout)
+}
+
+impl Widget for WmLayout {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        match self.wm {
+            WindowManager::I3 => {
+                let stream = try_stream! {
+                    let mut conn = i3::Connection::connect().await?;
+                    let mut events = i3::Connection::connect().await?;
+                    // Layout changes are reported as "binding" events if they
+                    // were triggered by a key binding, and as "window" events
+                    // when the focus moves.
+                    events.subscribe(&["window", "workspace", "binding"]).await?;
+                    loop {
+                        let tree = conn.get_tree().await?;
+                        yield self.on_change(i3_layout(&tree).unwrap_or_default());
+                        events.next_event().await?;
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
+            WindowManager::Bspwm => {
+                let stream = try_stream! {
+                    let mut events = bspwm::subscribe(&["desktop_focus", "desktop_layout"])?;
+                    loop {
+                        let desktop = bspwm::focused_desktop().await?;
+                        yield self.on_change(&desktop.layout);
+                        events
+                            .next()
+                            .await
+                            .ok_or_else(|| anyhow!("bspc subscribe exited"))??;
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
+            WindowManager::Herbstluftwm => {
+                let stream = try_stream! {
+                    herbstluftwm::watch(HLWM_ALGORITHM).await?;
+                    let mut hooks = herbstluftwm::idle()?;
+                    loop {
+                        let layout = herbstluftwm::get_attr(HLWM_ALGORITHM).await?;
+                        yield self.on_change(&layout);
+                        hooks
+                            .next()
+                            .await
+                            .ok_or_else(|| anyhow!("herbstclient --idle exited"))??;
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
+            wm => Err(anyhow!("WmLayout does not support {:?}", wm)),
+        }
+    }
+}
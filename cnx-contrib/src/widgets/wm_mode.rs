@@ -0,0 +1,130 @@
+use crate::wm::{hyprland, i3, WindowManager};
+use anyhow::{anyhow, Result};
+use async_stream::try_stream;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+/// Shows the active keybinding mode (i3/sway) or submap (Hyprland).
+///
+/// The widget is hidden while the default mode is active, so it only takes up
+/// space when a non-default mode (e.g. `resize`) is entered. This is useful
+/// feedback for modal keybinding setups, where it is otherwise easy to forget
+/// which mode is active.
+///
+/// The widget waits for events from the window manager rather than polling.
+/// Only [`WindowManager::I3`] and [`WindowManager::Hyprland`] are supported.
+pub struct WmMode {
+    attr: Attributes,
+    wm: WindowManager,
+    render: Option<Box<dyn Fn(String) -> String>>,
+}
+
+impl WmMode {
+    /// Creates a new [`WmMode`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `wm` - The [`WindowManager`] to listen to.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. The `String` is the name of the active mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::wm_mode::*;
+    /// # use cnx_contrib::wm::WindowManager;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::black(),
+    ///     bg_color: Some(Color::yellow()),
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_widget(WmMode::new(attr, WindowManager::I3, None));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(
+        attr: Attributes,
+        wm: WindowManager,
+        render: Option<Box<dyn Fn(String) -> String>>,
+    ) -> Self {
+        Self { attr, wm, render }
+    }
+
+    fn on_change(&self, mode: &str) -> Vec<Text> {
+        // i3 and sway call the default mode "default", whereas Hyprland
+        // reports an empty submap when it is reset.
+        if mode.is_empty() || mode == "default" {
+            return Vec::new();
+        }
+
+        let text = self
+            .render
+            .as_ref()
+            .map_or_else(|| mode.to_owned(), |x| (x)(mode.to_owned()));
+        vec![Text {
+            attr: self.attr.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+impl Widget for WmMode {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        match self.wm {
+            WindowManager::I3 => {
+                let stream = try_stream! {
+                    let mut conn = i3::Connection::connect().await?;
+                    // Older versions of i3 don't support GET_BINDING_STATE, in
+                    // which case assume we're starting in the default mode.
+                    let mode = conn.get_binding_state().await.unwrap_or_default();
+                    yield self.on_change(&mode);
+
+                    conn.subscribe(&["mode"]).await?;
+                    loop {
+                        let (event, payload) = conn.next_event().await?;
+                        if event == i3::MODE_EVENT {
+                            yield self.on_change(payload["change"].as_str().unwrap_or_default());
+                        }
+                    }
+                };
+                Ok(Box::pin(stream))
+            }
+            WindowManager::Hyprland => {
+                let stream = try_stream! {
+                    let mut events = hyprland::events().await?;
+                    yield self.on_change("");
+
+                    while let Some(event) = events.next().await {
+                        let (event, data) = event?;
+                        if event == "submap" {
+                            yield self.on_change(&data);
+                        }
+                    }
+                    Err(anyhow!("Hyprland event socket closed"))?;
+                };
+                Ok(Box::pin(stream))
+            }
+            wm => Err(anyhow!("WmMode does not support {:?}", wm)),
+        }
+    }
+}
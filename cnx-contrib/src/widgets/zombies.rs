@@ -0,0 +1,195 @@
+use crate::procfs::{self, Process};
+use anyhow::Result;
+use async_stream::stream;
+use cnx::text::{Attributes, Color, Role, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use std::time::Duration;
+use tokio::time;
+
+/// A zombie process, as shown by [`Zombies`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Zombie {
+    /// The zombie's process ID
+    pub pid: u32,
+    /// The name of the zombie's executable
+    pub name: String,
+    /// The process ID of its parent, which hasn't waited for it
+    pub parent: u32,
+    /// The name of its parent's executable, unless the parent has since
+    /// exited
+    pub parent_name: Option<String>,
+}
+
+type Render = Box<dyn Fn(&[Zombie]) -> String>;
+
+/// Shows how many zombie processes there are, and which processes they
+/// belong to, e.g. `☠ 3 (cargo, make)`, so that a tool which leaks its
+/// children is noticed before it runs out of process IDs.
+///
+/// A zombie is a process which has exited, but whose parent hasn't yet
+/// waited for it. The widget is shown in the theme's `Warn` color, and is
+/// hidden while there are no zombies. It is updated every 5 seconds by
+/// default.
+pub struct Zombies {
+    attr: Attributes,
+    update_interval: Duration,
+    render: Option<Render>,
+}
+
+impl Zombies {
+    /// Creates a new [`Zombies`] widget.
+    ///
+    /// Arguments
+    ///
+    /// * `attr` - Represents `Attributes` which controls properties like
+    /// `Font`, foreground and background color etc.
+    ///
+    /// * `render` - We use the closure to control the way output is
+    /// displayed in the bar. It is given the zombie processes, of which
+    /// there is always at least one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate cnx;
+    /// #
+    /// # use cnx::*;
+    /// # use cnx::text::*;
+    /// # use cnx_contrib::widgets::zombies::*;
+    /// # use anyhow::Result;
+    /// #
+    /// # fn run() -> Result<()> {
+    /// let attr = Attributes {
+    ///     font: Font::new("SourceCodePro 21"),
+    ///     fg_color: Color::white(),
+    ///     bg_color: None,
+    ///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// };
+    ///
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// let render = Box::new(|zombies: &[Zombie]| format!("{} zombies", zombies.len()));
+    /// cnx.add_widget(Zombies::new(attr, Some(render)));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap(); }
+    /// ```
+    pub fn new(attr: Attributes, render: Option<Render>) -> Self {
+        Self {
+            attr,
+            update_interval: Duration::from_secs(5),
+            render,
+        }
+    }
+
+    /// Sets how often to look for zombies. The default is every 5 seconds.
+    pub fn with_update_interval(self, update_interval: Duration) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    fn render(&self, zombies: &[Zombie]) -> Vec<Text> {
+        if zombies.is_empty() {
+            return Vec::new();
+        }
+        let mut attr = self.attr.clone();
+        attr.fg_color = Color::role(Role::Warn);
+        let text = match &self.render {
+            Some(render) => (render)(zombies),
+            None => {
+                let mut parents: Vec<&str> = Vec::new();
+                for name in zombies.iter().filter_map(|z| z.parent_name.as_deref()) {
+                    if !parents.contains(&name) {
+                        parents.push(name);
+                    }
+                }
+                match parents.is_empty() {
+                    true => format!("☠ {}", zombies.len()),
+                    false => format!("☠ {} ({})", zombies.len(), parents.join(", ")),
+                }
+            }
+        };
+        vec![Text {
+            attr,
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+            canvas: None,
+        }]
+    }
+}
+
+// Returns the zombies out of `processes`.
+fn zombies(processes: &[Process]) -> Vec<Zombie> {
+    processes
+        .iter()
+        .filter(|process| process.state == 'Z')
+        .map(|zombie| Zombie {
+            pid: zombie.pid,
+            name: zombie.name.clone(),
+            parent: zombie.parent,
+            parent_name: processes
+                .iter()
+                .find(|process| process.pid == zombie.parent)
+                .map(|parent| parent.name.clone()),
+        })
+        .collect()
+}
+
+impl Widget for Zombies {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                yield Ok(self.render(&zombies(&procfs::processes())));
+                time::sleep(self.update_interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_zombies() {
+        let process = |pid, name: &str, parent, state| Process {
+            pid,
+            name: name.to_owned(),
+            parent,
+            state,
+            cpu_ticks: 0,
+            rss_pages: 0,
+        };
+        let processes = [
+            process(1, "systemd", 0, 'S'),
+            process(100, "cargo", 1, 'S'),
+            process(101, "rustc", 100, 'Z'),
+            process(102, "build-script", 100, 'R'),
+            process(200, "sh", 300, 'Z'),
+        ];
+        let found = zombies(&processes);
+        assert_eq!(
+            found,
+            vec![
+                Zombie {
+                    pid: 101,
+                    name: "rustc".to_owned(),
+                    parent: 100,
+                    parent_name: Some("cargo".to_owned()),
+                },
+                Zombie {
+                    pid: 200,
+                    name: "sh".to_owned(),
+                    parent: 300,
+                    parent_name: None,
+                },
+            ]
+        );
+        assert!(zombies(&processes[..2]).is_empty());
+    }
+}
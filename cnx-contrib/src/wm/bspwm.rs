@@ -0,0 +1,22 @@
+use anyhow::{Context, Result};
+use serde_derive::Deserialize;
+use tokio_stream::Stream;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Desktop {
+    pub layout: String,
+}
+
+/// Queries the state of the focused desktop.
+pub(crate) async fn focused_desktop() -> Result<Desktop> {
+    let output = super::command_output("bspc", &["query", "-T", "-d", "focused"]).await?;
+    let desktop = serde_json::from_str(&output).context("Failed to parse bspwm desktop")?;
+    Ok(desktop)
+}
+
+/// Subscribes to the given bspwm events, e.g. `["desktop_focus"]`.
+pub(crate) fn subscribe(events: &[&str]) -> Result<impl Stream<Item = Result<String>>> {
+    let mut args = vec!["subscribe"];
+    args.extend_from_slice(events);
+    super::command_lines("bspc", &args)
+}
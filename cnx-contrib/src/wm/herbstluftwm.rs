@@ -0,0 +1,22 @@
+use anyhow::Result;
+use tokio_stream::Stream;
+
+/// Reads an attribute from the object tree, e.g.
+/// `tags.focus.tiling.focused_frame.algorithm`.
+pub(crate) async fn get_attr(path: &str) -> Result<String> {
+    let output = super::command_output("herbstclient", &["get_attr", path]).await?;
+    Ok(output.trim().to_owned())
+}
+
+/// Returns a stream of hooks emitted by herbstluftwm, one per line, with
+/// tab-separated arguments.
+pub(crate) fn idle() -> Result<impl Stream<Item = Result<String>>> {
+    super::command_lines("herbstclient", &["--idle"])
+}
+
+/// Asks herbstluftwm to emit an `attribute_changed` hook whenever the given
+/// attribute changes.
+pub(crate) async fn watch(path: &str) -> Result<()> {
+    super::command_output("herbstclient", &["watch", path]).await?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::untrusted;
+use std::env;
+use std::path::PathBuf;
+use tokio::io::BufReader;
+use tokio::net::UnixStream;
+use tokio_stream::{Stream, StreamExt};
+
+fn socket2_path() -> Result<PathBuf> {
+    let signature = env::var_os("HYPRLAND_INSTANCE_SIGNATURE")
+        .ok_or_else(|| anyhow!("HYPRLAND_INSTANCE_SIGNATURE is not set"))?;
+    // Hyprland moved its sockets from /tmp into $XDG_RUNTIME_DIR in v0.40.
+    let mut candidates = Vec::new();
+    if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
+        candidates.push(PathBuf::from(runtime_dir).join("hypr"));
+    }
+    candidates.push(PathBuf::from("/tmp/hypr"));
+    candidates
+        .into_iter()
+        .map(|dir| dir.join(&signature).join(".socket2.sock"))
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow!("Failed to find Hyprland event socket"))
+}
+
+/// Returns a stream of `(event, data)` pairs read from Hyprland's event
+/// socket, e.g. `("submap", "resize")`.
+pub(crate) async fn events() -> Result<impl Stream<Item = Result<(String, String)>>> {
+    let path = socket2_path()?;
+    let stream = UnixStream::connect(&path)
+        .await
+        .with_context(|| format!("Failed to connect to {}", path.display()))?;
+    let lines = Box::pin(untrusted::lines(
+        BufReader::new(stream),
+        untrusted::MAX_LINE,
+    ));
+    Ok(lines.map(|line| {
+        let line = line?;
+        let (event, data) = line.split_once(">>").unwrap_or((&line, ""));
+        Ok((event.to_owned(), data.to_owned()))
+    }))
+}
@@ -0,0 +1,332 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::untrusted;
+use serde_derive::Deserialize;
+use std::env;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio_stream::Stream;
+
+// https://i3wm.org/docs/ipc.html#_sending_messages_to_i3
+const MAGIC: &[u8] = b"i3-ipc";
+const EVENT_BIT: u32 = 1 << 31;
+
+pub(crate) const RUN_COMMAND: u32 = 0;
+pub(crate) const SUBSCRIBE: u32 = 2;
+pub(crate) const GET_TREE: u32 = 4;
+pub(crate) const GET_BINDING_STATE: u32 = 12;
+
+pub(crate) const MODE_EVENT: u32 = 2;
+
+/// A node of the layout tree, as returned by `GET_TREE`.
+///
+/// Only the fields we are interested in are deserialized.
+#[derive(Deserialize, Debug)]
+pub(crate) struct Node {
+    pub id: i64,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub layout: String,
+    pub focused: bool,
+    #[serde(default)]
+    pub focus: Vec<i64>,
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub floating_nodes: Vec<Node>,
+}
+
+impl Node {
+    /// Returns the path from this node to the focused node, following the
+    /// focus stack of each container.
+    pub fn focused_path(&self) -> Vec<&Node> {
+        let mut path = vec![self];
+        let mut node = self;
+        while !node.focused {
+            let next = node.focus.first().and_then(|id| {
+                node.nodes
+                    .iter()
+                    .chain(node.floating_nodes.iter())
+                    .find(|child| child.id == *id)
+            });
+            match next {
+                Some(next) => {
+                    path.push(next);
+                    node = next;
+                }
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// Returns the number of windows in the scratchpad, which i3 keeps as
+    /// floating windows of the hidden `__i3_scratch` workspace.
+    pub fn scratchpad_len(&self) -> usize {
+        if self.node_type == "workspace" && self.name.as_deref() == Some("__i3_scratch") {
+            return self.floating_nodes.len();
+        }
+        self.nodes.iter().map(Node::scratchpad_len).sum()
+    }
+
+    /// Returns the number of windows (i.e. leaf containers) within this node.
+    pub fn window_count(&self) -> usize {
+        // On sway, a floating window is itself a `floating_con`, while i3
+        // wraps it in one.
+        if self.nodes.is_empty() && self.floating_nodes.is_empty() {
+            return usize::from(self.node_type == "con" || self.node_type == "floating_con");
+        }
+        self.nodes
+            .iter()
+            .chain(&self.floating_nodes)
+            .map(Node::window_count)
+            .sum()
+    }
+}
+
+/// A connection to the IPC socket of i3 (or sway, which speaks the same
+/// protocol).
+pub(crate) struct Connection {
+    stream: UnixStream,
+}
+
+impl Connection {
+    pub async fn connect() -> Result<Connection> {
+        let path = socket_path().await?;
+        let stream = UnixStream::connect(&path)
+            .await
+            .with_context(|| format!("Failed to connect to i3 socket: {}", path.display()))?;
+        Ok(Connection { stream })
+    }
+
+    async fn send(&mut self, message_type: u32, payload: &[u8]) -> Result<()> {
+        let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload);
+        self.stream.write_all(&message).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<(u32, Vec<u8>)> {
+        let mut header = [0; 14];
+        self.stream.read_exact(&mut header).await?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(anyhow!("Invalid i3 IPC message header"));
+        }
+        let mut length = [0; 4];
+        length.copy_from_slice(&header[6..10]);
+        let mut message_type = [0; 4];
+        message_type.copy_from_slice(&header[10..14]);
+
+        // The length is checked before anything is allocated for it.
+        let length = u32::from_ne_bytes(length) as usize;
+        let mut payload = vec![0; untrusted::check_len(length, untrusted::MAX_MESSAGE)?];
+        self.stream.read_exact(&mut payload).await?;
+        Ok((u32::from_ne_bytes(message_type), payload))
+    }
+
+    /// Sends a message and waits for its reply.
+    ///
+    /// This must not be used on a connection that has subscribed to events,
+    /// as the reply may be interleaved with them.
+    pub async fn request(&mut self, message_type: u32, payload: &str) -> Result<Vec<u8>> {
+        self.send(message_type, payload.as_bytes()).await?;
+        let (reply_type, reply) = self.receive().await?;
+        if reply_type != message_type {
+            return Err(anyhow!("Unexpected i3 IPC reply type: {}", reply_type));
+        }
+        Ok(reply)
+    }
+
+    pub async fn get_tree(&mut self) -> Result<Node> {
+        let reply = self.request(GET_TREE, "").await?;
+        let tree = serde_json::from_slice(&reply).context("Failed to parse i3 tree")?;
+        Ok(tree)
+    }
+
+    /// Runs the given i3 command(s), e.g. `scratchpad show`.
+    pub async fn run_command(&mut self, command: &str) -> Result<()> {
+        let reply = self.request(RUN_COMMAND, command).await?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply)?;
+        let failed = reply
+            .as_array()
+            .map_or(true, |results| results.iter().any(|r| r["success"] != true));
+        if failed {
+            return Err(anyhow!("i3 command `{}` failed: {}", command, reply));
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the currently active binding mode.
+    pub async fn get_binding_state(&mut self) -> Result<String> {
+        let reply = self.request(GET_BINDING_STATE, "").await?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply)?;
+        reply["name"]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| anyhow!("Invalid binding state reply: {}", reply))
+    }
+
+    /// Subscribes to the given events, e.g. `["window", "workspace"]`.
+    ///
+    /// Afterwards, [`Connection::next_event()`] should be used to wait for
+    /// events.
+    pub async fn subscribe(&mut self, events: &[&str]) -> Result<()> {
+        let payload = serde_json::to_string(events)?;
+        let reply = self.request(SUBSCRIBE, &payload).await?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply)?;
+        if reply["success"] != serde_json::Value::Bool(true) {
+            return Err(anyhow!("Failed to subscribe to i3 events: {}", reply));
+        }
+        Ok(())
+    }
+
+    /// Waits for the next event, returning its type (without the event bit
+    /// set) and its JSON payload.
+    pub async fn next_event(&mut self) -> Result<(u32, serde_json::Value)> {
+        loop {
+            let (message_type, payload) = self.receive().await?;
+            if message_type & EVENT_BIT != 0 {
+                let payload = serde_json::from_slice(&payload)?;
+                return Ok((message_type & !EVENT_BIT, payload));
+            }
+        }
+    }
+}
+
+/// Returns a stream of the given events, on a new connection.
+///
+/// Unlike [`Connection::next_event()`], the stream may safely be polled
+/// alongside other streams (e.g. merged with a widget's clicks) without
+/// losing partially read events.
+pub(crate) fn events(
+    events: &'static [&'static str],
+) -> impl Stream<Item = Result<(u32, serde_json::Value)>> {
+    async_stream::try_stream! {
+        let mut conn = Connection::connect().await?;
+        conn.subscribe(events).await?;
+        loop {
+            yield conn.next_event().await?;
+        }
+    }
+}
+
+async fn socket_path() -> Result<PathBuf> {
+    for var in &["I3SOCK", "SWAYSOCK"] {
+        if let Some(path) = env::var_os(var) {
+            return Ok(path.into());
+        }
+    }
+    let path = super::command_output("i3", &["--get-socketpath"])
+        .await
+        .context("Failed to find i3 socket path")?;
+    Ok(path.trim().into())
+}
+
+// Parses `tree` as a reply to `GET_TREE`, and walks it, for the `i3_tree`
+// fuzz target.
+#[cfg(fuzzing)]
+pub(crate) fn fuzz(tree: &[u8]) {
+    if let Ok(tree) = serde_json::from_slice::<Node>(tree) {
+        tree.focused_path();
+        tree.scratchpad_len();
+        tree.window_count();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Node;
+
+    const TREE: &str = r#"{
+        "id": 1, "name": "root", "type": "root", "layout": "splith", "focused": false,
+        "focus": [2],
+        "nodes": [{
+            "id": 2, "name": "eDP-1", "type": "output", "layout": "output", "focused": false,
+            "focus": [3],
+            "nodes": [{
+                "id": 3, "name": "1", "type": "workspace", "layout": "tabbed", "focused": false,
+                "focus": [5, 4],
+                "nodes": [
+                    {"id": 4, "name": "a", "type": "con", "layout": "splith", "focused": false},
+                    {"id": 5, "name": "b", "type": "con", "layout": "splith", "focused": true}
+                ],
+                "floating_nodes": [{
+                    "id": 6, "type": "floating_con", "layout": "splith", "focused": false,
+                    "nodes": [{"id": 7, "name": "c", "type": "con", "layout": "splith", "focused": false}]
+                }]
+            }]
+        }]
+    }"#;
+
+    #[test]
+    fn focused_path() {
+        let tree: Node = serde_json::from_str(TREE).unwrap();
+        let ids: Vec<i64> = tree.focused_path().iter().map(|node| node.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn window_count() {
+        let tree: Node = serde_json::from_str(TREE).unwrap();
+        let workspace = &tree.nodes[0].nodes[0];
+        let tiled: usize = workspace.nodes.iter().map(Node::window_count).sum();
+        let floating: usize = workspace
+            .floating_nodes
+            .iter()
+            .map(Node::window_count)
+            .sum();
+        assert_eq!((tiled, floating), (2, 1));
+        assert_eq!(tree.window_count(), 3);
+
+        let sway: Node = serde_json::from_str(
+            r#"{
+            "id": 3, "name": "1", "type": "workspace", "layout": "splith", "focused": false,
+            "nodes": [{"id": 4, "name": "a", "type": "con", "layout": "none", "focused": true}],
+            "floating_nodes": [
+                {"id": 5, "name": "b", "type": "floating_con", "layout": "none", "focused": false}
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(sway.window_count(), 2);
+
+        let empty: Node = serde_json::from_str(
+            r#"{"id": 3, "name": "1", "type": "workspace", "layout": "splith", "focused": false}"#,
+        )
+        .unwrap();
+        assert_eq!(empty.window_count(), 0);
+    }
+
+    #[test]
+    fn scratchpad_len() {
+        let tree: Node = serde_json::from_str(TREE).unwrap();
+        assert_eq!(tree.scratchpad_len(), 0);
+
+        let tree: Node = serde_json::from_str(
+            r#"{
+            "id": 1, "name": "root", "type": "root", "layout": "splith", "focused": false,
+            "nodes": [{
+                "id": 2, "name": "__i3", "type": "output", "layout": "output", "focused": false,
+                "nodes": [{
+                    "id": 3, "name": "content", "type": "con", "layout": "splith", "focused": false,
+                    "nodes": [{
+                        "id": 4, "name": "__i3_scratch", "type": "workspace", "layout": "splith",
+                        "focused": false,
+                        "floating_nodes": [
+                            {"id": 5, "type": "floating_con", "layout": "splith", "focused": false},
+                            {"id": 6, "type": "floating_con", "layout": "splith", "focused": false}
+                        ]
+                    }]
+                }]
+            }]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(tree.scratchpad_len(), 2);
+    }
+}
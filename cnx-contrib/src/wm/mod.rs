@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Context, Result};
+use cnx::untrusted;
+use cnx::widgets::Rename;
+use std::process::Stdio;
+use tokio::io::BufReader;
+use tokio::process::Command;
+use tokio::task;
+use tokio_stream::{Stream, StreamExt};
+
+pub(crate) mod bspwm;
+pub(crate) mod herbstluftwm;
+pub(crate) mod hyprland;
+pub(crate) mod i3;
+
+/// The window manager whose IPC interface a widget should use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WindowManager {
+    /// [i3](https://i3wm.org/) or [sway](https://swaywm.org/), using the
+    /// socket given by `I3SOCK`/`SWAYSOCK` or `i3 --get-socketpath`.
+    I3,
+    /// [bspwm](https://github.com/baskerville/bspwm), using `bspc`.
+    Bspwm,
+    /// [herbstluftwm](https://herbstluftwm.org/), using `herbstclient`.
+    Herbstluftwm,
+    /// [Hyprland](https://hyprland.org/), using the event socket of the
+    /// instance given by `HYPRLAND_INSTANCE_SIGNATURE`.
+    Hyprland,
+}
+
+/// Returns a [`Rename`] for [`Pager::with_rename()`] which renames workspaces
+/// with `wm`'s IPC, for window managers which don't take the names of their
+/// workspaces from `_NET_DESKTOP_NAMES`.
+///
+/// Renaming Hyprland's workspaces isn't supported.
+///
+/// [`Pager::with_rename()`]: cnx::widgets::Pager::with_rename
+pub fn rename_workspace(wm: WindowManager) -> Rename {
+    Rename::Custom(Box::new(move |old, new| {
+        task::spawn_local(async move {
+            if let Err(err) = rename(wm, &old, &new).await {
+                println!("Error renaming workspace {old:?} to {new:?}: {err}");
+            }
+        });
+    }))
+}
+
+async fn rename(wm: WindowManager, old: &str, new: &str) -> Result<()> {
+    match wm {
+        WindowManager::I3 => {
+            let command = format!("rename workspace {} to {}", quote(old), quote(new));
+            i3::Connection::connect().await?.run_command(&command).await
+        }
+        WindowManager::Bspwm => command_output("bspc", &["desktop", old, "-n", new])
+            .await
+            .map(drop),
+        WindowManager::Herbstluftwm => command_output("herbstclient", &["rename", old, new])
+            .await
+            .map(drop),
+        WindowManager::Hyprland => Err(anyhow!("Renaming Hyprland workspaces is not supported")),
+    }
+}
+
+// Quotes an argument of an i3 command.
+fn quote(argument: &str) -> String {
+    let escaped = argument.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Runs a command to completion and returns its standard output.
+pub(crate) async fn command_output(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run `{program}`"))?;
+    if !output.status.success() {
+        return Err(anyhow!("`{}` exited with {}", program, output.status));
+    }
+    let output = String::from_utf8(output.stdout)
+        .with_context(|| format!("Invalid UTF-8 in `{program}` output"))?;
+    Ok(output)
+}
+
+/// Spawns a long-running command and returns a stream of the lines it
+/// writes to its standard output.
+///
+/// The child is killed when the stream is dropped.
+pub(crate) fn command_lines(
+    program: &str,
+    args: &[&str],
+) -> Result<impl Stream<Item = Result<String>>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to spawn `{program}`"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture stdout of `{}`", program))?;
+    let lines = Box::pin(untrusted::lines(
+        BufReader::new(stdout),
+        untrusted::MAX_LINE,
+    ));
+    // Keep the child alive for as long as the stream is.
+    Ok(lines.map(move |line| {
+        let _child = &child;
+        line.map_err(Into::into)
+    }))
+}
@@ -0,0 +1,181 @@
+//! Alerts raised by rules over the data widgets publish on the [`Bus`].
+//!
+//! Each [`Rule`] watches one type of data on the bus, and fires once its
+//! condition has held for long enough, e.g. when the CPU temperature has been
+//! over 90°C for 30 seconds. When a rule fires, Cnx logs it, and can run a
+//! command such as `notify-send` and flash the bar with a [`Theme`] until the
+//! condition no longer holds. Rules are added with [`Cnx::add_alert()`], and
+//! are evaluated centrally, so any widget's data can raise an alert without a
+//! custom widget.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::alerts::Rule;
+//! use cnx::text::{Color, Theme};
+//! use cnx::{Cnx, Position};
+//! use std::time::Duration;
+//!
+//! // Published on the bus by some widget.
+//! #[derive(Clone)]
+//! struct CpuTemperature(f64);
+//!
+//! let mut cnx = Cnx::new(Position::Top);
+//! let flash = Theme {
+//!     bg_color: Some(Color::red()),
+//!     ..Theme::default()
+//! };
+//! cnx.add_alert(
+//!     Rule::new("CPU too hot", |temperature: &CpuTemperature| temperature.0 > 90.0)
+//!         .with_duration(Duration::from_secs(30))
+//!         .with_command("notify-send 'CPU too hot'".to_owned())
+//!         .with_flash(flash),
+//! );
+//! ```
+//!
+//! [`Bus`]: crate::bus::Bus
+//! [`Cnx::add_alert()`]: crate::Cnx::add_alert
+
+use crate::bus::Bus;
+use crate::text::Theme;
+use async_stream::stream;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::{self, Instant};
+use tokio_stream::{Stream, StreamExt, StreamMap};
+
+type Conditions = Pin<Box<dyn Stream<Item = bool>>>;
+
+/// A rule which fires an alert when a condition over data on the bus holds
+/// for long enough.
+pub struct Rule {
+    name: String,
+    subscribe: Box<dyn Fn(&Bus) -> Conditions>,
+    duration: Duration,
+    command: Option<String>,
+    flash: Option<Theme>,
+}
+
+impl Rule {
+    /// Creates a new rule called `name`, which fires as soon as `condition`
+    /// holds for a value of type `T` published on the bus.
+    pub fn new<T, F>(name: &str, condition: F) -> Self
+    where
+        T: Clone + 'static,
+        F: Fn(&T) -> bool + 'static,
+    {
+        let condition = Rc::new(condition);
+        Rule {
+            name: name.to_owned(),
+            subscribe: Box::new(move |bus| {
+                let condition = condition.clone();
+                Box::pin(bus.subscribe::<T>().map(move |value| condition(&value)))
+            }),
+            duration: Duration::ZERO,
+            command: None,
+            flash: None,
+        }
+    }
+
+    /// Only fires once the condition has held for `duration`, i.e. for every
+    /// value published in that time.
+    pub fn with_duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Runs `command` with `sh -c` each time the rule fires, e.g.
+    /// `notify-send 'CPU too hot'`.
+    pub fn with_command(self, command: String) -> Self {
+        Self {
+            command: Some(command),
+            ..self
+        }
+    }
+
+    /// Adjusts the colors of the bar with `theme` while the rule is firing,
+    /// e.g. to turn it red. If several rules are firing, the one added first
+    /// is shown.
+    pub fn with_flash(self, theme: Theme) -> Self {
+        Self {
+            flash: Some(theme),
+            ..self
+        }
+    }
+
+    // Returns a stream which yields `true` when the rule fires, and `false`
+    // when its condition stops holding afterwards.
+    fn watch(&self, bus: &Bus) -> impl Stream<Item = bool> {
+        let mut conditions = (self.subscribe)(bus);
+        let duration = self.duration;
+        stream! {
+            // When the condition started holding, if it does.
+            let mut since: Option<Instant> = None;
+            let mut firing = false;
+            loop {
+                let deadline = since.filter(|_| !firing).map(|since| since + duration);
+                tokio::select! {
+                    condition = conditions.next() => match condition {
+                        Some(true) => {
+                            since.get_or_insert_with(Instant::now);
+                        }
+                        Some(false) => {
+                            since = None;
+                            if firing {
+                                firing = false;
+                                yield false;
+                            }
+                        }
+                        None => break,
+                    },
+
+                    _ = time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                        if deadline.is_some() => {
+                        firing = true;
+                        yield true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates `rules` over the data published on `bus`, running their
+/// commands as they fire.
+///
+/// Returns a stream of the theme to flash the bar with, which yields whenever
+/// it changes.
+pub(crate) fn evaluate(rules: Vec<Rule>, bus: &Bus) -> impl Stream<Item = Option<Theme>> {
+    let mut watches = StreamMap::with_capacity(rules.len());
+    for (idx, rule) in rules.iter().enumerate() {
+        watches.insert(idx, Box::pin(rule.watch(bus)));
+    }
+
+    stream! {
+        let mut firing = vec![false; rules.len()];
+        let mut flash = None;
+        while let Some((idx, fired)) = watches.next().await {
+            let rule = &rules[idx];
+            firing[idx] = fired;
+            if fired {
+                println!("Alert: {}", rule.name);
+                if let Some(command) = &rule.command {
+                    // The child is reaped in the background once it exits.
+                    if let Err(err) = Command::new("sh").arg("-c").arg(command).spawn() {
+                        println!("Error running command for alert {}: {err}", rule.name);
+                    }
+                }
+            }
+
+            let next = rules
+                .iter()
+                .zip(&firing)
+                .find_map(|(rule, firing)| rule.flash.clone().filter(|_| *firing));
+            if next != flash {
+                flash = next;
+                yield flash.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Sunrise and sunset times, e.g. to switch to a night theme with
+//! [`Cnx::with_night_theme()`].
+//!
+//! The times are computed with the [sunrise equation], which is accurate to
+//! within a minute or two away from the poles.
+//!
+//! [`Cnx::with_night_theme()`]: crate::Cnx::with_night_theme
+//! [sunrise equation]: https://en.wikipedia.org/wiki/Sunrise_equation
+
+use async_stream::stream;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use tokio::time;
+use tokio_stream::Stream;
+
+// The longest we sleep before checking the time again, as timers may not
+// account for time spent suspended.
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(600);
+
+// The Julian date of the Unix epoch, and of the J2000 epoch.
+const UNIX_EPOCH: f64 = 2_440_587.5;
+const J2000: f64 = 2_451_545.0;
+
+/// Returns the times of sunrise and sunset on `date` at the given latitude
+/// and longitude, in degrees north and east.
+///
+/// Returns `None` if the sun doesn't rise or set that day, i.e. during the
+/// polar night or midnight sun.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{NaiveDate, Timelike};
+/// use cnx::astronomy::sun_times;
+///
+/// // London, on the summer solstice.
+/// let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+/// let (sunrise, sunset) = sun_times(date, 51.5, -0.13).unwrap();
+/// assert_eq!((sunrise.hour(), sunrise.minute() / 10), (3, 4));
+/// assert_eq!((sunset.hour(), sunset.minute() / 10), (20, 2));
+///
+/// // Tromsø, during the polar night.
+/// let date = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+/// assert_eq!(sun_times(date, 69.65, 18.96), None);
+/// ```
+pub fn sun_times(
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    // The Julian day number of `date`, which starts at noon.
+    let day = (date - epoch).num_days() as f64 + UNIX_EPOCH + 0.5;
+    let mean_solar_time = day - J2000 + 0.0008 - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.985_600_28 * mean_solar_time).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    // The sun's centre is 0.833° below the horizon at sunrise, because of
+    // refraction and the size of its disc.
+    let cos_hour_angle = ((-0.833_f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    let to_utc = |julian: f64| {
+        let millis = ((julian - UNIX_EPOCH) * 86_400_000.0).round() as i64;
+        Utc.timestamp_millis_opt(millis).single()
+    };
+    Some((
+        to_utc(transit - hour_angle / 360.0)?,
+        to_utc(transit + hour_angle / 360.0)?,
+    ))
+}
+
+// Returns whether it is night at `now`, and when that next changes.
+//
+// Returns `None` for whether it is night if the sun doesn't rise or set
+// around `now`, in which case it should be checked again at the returned
+// time.
+fn night_until(now: DateTime<Utc>, latitude: f64, longitude: f64) -> (Option<bool>, DateTime<Utc>) {
+    // Depending on the longitude, the sun may rise or set on a different day
+    // in UTC than in local time, so look at the days around `now`.
+    let today = now.naive_utc().date();
+    let next = (-1..=2)
+        .filter_map(|offset| sun_times(today + Duration::days(offset), latitude, longitude))
+        .flat_map(|(sunrise, sunset)| [(sunrise, true), (sunset, false)])
+        .filter(|(time, _)| *time > now)
+        .min_by_key(|(time, _)| *time);
+    match next {
+        // It is night until the next sunrise.
+        Some((time, sunrise)) => (Some(sunrise), time),
+        None => (None, now + Duration::hours(1)),
+    }
+}
+
+// Returns a stream which yields whether it is night at the given location
+// now, and then again at each sunrise and sunset.
+pub(crate) fn night_changes(latitude: f64, longitude: f64) -> impl Stream<Item = bool> {
+    stream! {
+        let mut was_night = None;
+        loop {
+            let now = Utc::now();
+            let (night, until) = night_until(now, latitude, longitude);
+            if night.is_some() && night != was_night {
+                was_night = night;
+                yield night.unwrap_or_default();
+            }
+            let wait = (until - now).to_std().unwrap_or_default().min(MAX_SLEEP);
+            time::sleep(wait).await;
+        }
+    }
+}
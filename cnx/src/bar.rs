@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::f64;
 use std::rc::Rc;
 
@@ -5,7 +6,8 @@ use anyhow::{anyhow, Context, Result};
 use ordered_float::OrderedFloat;
 use xcb_util::ewmh;
 
-use crate::text::{ComputedText, Text};
+use crate::text::{Color, ComputedText, Text, Theme};
+use crate::widgets::ClickEvent;
 // use crate::widgets::{Widget, WidgetList};
 // use crate::xcb::XcbEventStream;
 
@@ -60,7 +62,10 @@ fn create_surface(
         .ok_or_else(|| anyhow!("Invalid screen"))?;
     let values = [
         (xcb::CW_BACK_PIXEL, screen.black_pixel()),
-        (xcb::CW_EVENT_MASK, xcb::EVENT_MASK_EXPOSURE),
+        (
+            xcb::CW_EVENT_MASK,
+            xcb::EVENT_MASK_EXPOSURE | xcb::EVENT_MASK_BUTTON_PRESS,
+        ),
     ];
 
     let width = width.unwrap_or_else(|| screen.width_in_pixels());
@@ -112,6 +117,27 @@ pub enum Position {
     Bottom,
 }
 
+/// How the Cnx bar is stacked relative to other windows.
+///
+/// Passed to [`Cnx::with_layer()`]. Window managers stack docks differently,
+/// e.g. some leave a window which has left fullscreen above the bar, so the
+/// bar is restacked as other windows change.
+///
+/// [`Cnx::with_layer()`]: struct.Cnx.html#method.with_layer
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Layer {
+    /// Stack the bar as the window manager stacks docks, which is usually
+    /// above normal windows but below fullscreen ones. The bar is raised
+    /// again whenever a window leaves fullscreen.
+    #[default]
+    Normal,
+    /// Keep the bar above every other window, including fullscreen ones.
+    Above,
+    /// Keep the bar below every other window, e.g. to show it on the
+    /// desktop.
+    Below,
+}
+
 /// A struct specifying the `x` and `y` offset
 #[derive(Default, Clone, Copy)]
 pub struct Offset {
@@ -130,12 +156,38 @@ pub struct Bar {
     width: u16,
     height: u16,
     offset: Offset,
+    // The space other docks on the same edge of the screen reserve, which
+    // the bar is stacked next to.
+    docked: u16,
+    click_through: bool,
+    interactive_only: bool,
+    interactive: Vec<bool>,
+    layer: Layer,
+    // The other windows which cover the whole screen, i.e. are fullscreen.
+    fullscreen: HashSet<u32>,
+    // The index of the only widget shown, while in zen mode.
+    zen: Option<usize>,
+    theme: Option<Theme>,
+    // The scale of the fonts in each row, from the top.
+    scales: Vec<f64>,
+    // The row of each widget.
+    rows: Vec<usize>,
+    // The number of columns, while showing a fullscreen dashboard rather
+    // than a strip.
+    dashboard: Option<usize>,
 
     contents: Vec<Vec<ComputedText>>,
 }
 
 impl Bar {
-    pub fn new(position: Position, width: Option<u16>, offset: Offset) -> Result<Bar> {
+    // Creates a bar with a row for each of `scales`, whose fonts are scaled by
+    // it.
+    pub fn new(
+        position: Position,
+        width: Option<u16>,
+        offset: Offset,
+        scales: Vec<f64>,
+    ) -> Result<Bar> {
         let (conn, screen_idx) =
             xcb::Connection::connect(None).context("Failed to connect to X server")?;
         let screen_idx = screen_idx as usize;
@@ -159,6 +211,17 @@ impl Bar {
             width,
             height,
             offset,
+            docked: 0,
+            click_through: false,
+            interactive_only: false,
+            interactive: Vec::new(),
+            layer: Layer::Normal,
+            fullscreen: HashSet::new(),
+            zen: None,
+            theme: None,
+            scales,
+            rows: Vec::new(),
+            dashboard: None,
             position,
             contents: Vec::new(),
         };
@@ -183,11 +246,32 @@ impl Bar {
     }
 
     fn set_ewmh_properties(&self) {
-        ewmh::set_wm_window_type(
-            &self.conn,
-            self.window_id,
-            &[self.conn.WM_WINDOW_TYPE_DOCK()],
-        );
+        // A dashboard covers the whole screen, so it's a normal window in
+        // the fullscreen state rather than a dock.
+        if self.dashboard.is_some() {
+            ewmh::set_wm_window_type(
+                &self.conn,
+                self.window_id,
+                &[self.conn.WM_WINDOW_TYPE_NORMAL()],
+            );
+            ewmh::set_wm_state(
+                &self.conn,
+                self.window_id,
+                &[self.conn.WM_STATE_FULLSCREEN()],
+            );
+        } else {
+            ewmh::set_wm_window_type(
+                &self.conn,
+                self.window_id,
+                &[self.conn.WM_WINDOW_TYPE_DOCK()],
+            );
+            let state = match self.layer {
+                Layer::Normal => vec![],
+                Layer::Above => vec![self.conn.WM_STATE_ABOVE()],
+                Layer::Below => vec![self.conn.WM_STATE_BELOW()],
+            };
+            ewmh::set_wm_state(&self.conn, self.window_id, &state);
+        }
 
         // TODO: Update _WM_STRUT_PARTIAL if the height/position of the bar changes?
         let mut strut_partial = ewmh::StrutPartial {
@@ -204,9 +288,15 @@ impl Bar {
             bottom_start_x: 0,
             bottom_end_x: 0,
         };
-        match self.position {
-            Position::Top => strut_partial.top = u32::from(self.height),
-            Position::Bottom => strut_partial.bottom = u32::from(self.height),
+        // A dashboard doesn't reserve any space for itself.
+        match (self.dashboard, &self.position) {
+            (Some(_), _) => {}
+            (None, Position::Top) => {
+                strut_partial.top = u32::from(self.docked) + u32::from(self.height)
+            }
+            (None, Position::Bottom) => {
+                strut_partial.bottom = u32::from(self.docked) + u32::from(self.height)
+            }
         }
         ewmh::set_wm_strut_partial(&self.conn, self.window_id, strut_partial);
     }
@@ -227,19 +317,27 @@ impl Bar {
 
             // If we're at the bottom of the screen, we'll need to update the
             // position of the window.
-            let y = match self.position {
-                Position::Top => self.offset.y.max(0) as u16,
-                Position::Bottom => {
-                    let h = (self.screen()?.height_in_pixels() - self.height) as i32;
-                    h.checked_add(self.offset.y as i32).unwrap_or(h).max(0) as u16
+            let (x, y) = match (self.dashboard, &self.position) {
+                (Some(_), _) => (0, 0),
+                (None, Position::Top) => {
+                    let y = i32::from(self.docked) + i32::from(self.offset.y);
+                    (self.offset.x, y.max(0) as u16)
+                }
+                (None, Position::Bottom) => {
+                    let h = (self.screen()?.height_in_pixels() - self.height) as i32
+                        - i32::from(self.docked);
+                    let y = h.checked_add(self.offset.y as i32).unwrap_or(h).max(0) as u16;
+                    (self.offset.x, y)
                 }
             };
 
-            // Update the height/position of the XCB window and the height of the Cairo surface.
+            // Update the size/position of the XCB window and the size of the Cairo surface.
             let values = [
+                (xcb::CONFIG_WINDOW_X as u16, x as u32),
                 (xcb::CONFIG_WINDOW_Y as u16, u32::from(y)),
+                (xcb::CONFIG_WINDOW_WIDTH as u16, u32::from(self.width)),
                 (xcb::CONFIG_WINDOW_HEIGHT as u16, u32::from(self.height)),
-                (xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE),
+                (xcb::CONFIG_WINDOW_STACK_MODE as u16, self.stack_mode()),
             ];
             xcb::configure_window(&self.conn, self.window_id, &values);
             self.map_window();
@@ -254,6 +352,260 @@ impl Bar {
         Ok(())
     }
 
+    // Shows the widgets in a grid with `columns` columns covering the whole
+    // screen, rather than in a strip.
+    pub fn set_dashboard(&mut self, columns: usize) -> Result<()> {
+        let screen = self.screen()?;
+        let (width, height) = (screen.width_in_pixels(), screen.height_in_pixels());
+        self.dashboard = Some(columns.max(1));
+        self.width = width;
+        // The window manager only makes the window fullscreen if it's asked
+        // to before the window is mapped.
+        self.set_ewmh_properties();
+        self.update_bar_height(height)
+    }
+
+    // Sets how the bar is stacked relative to other windows. This must be
+    // called before the bar is mapped, i.e. before any content is added, for
+    // the window manager to see the bar's state.
+    pub fn set_layer(&mut self, layer: Layer) -> Result<()> {
+        self.layer = layer;
+        self.set_ewmh_properties();
+        // Watch the other windows, to restack the bar as they change.
+        let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY)];
+        xcb::change_window_attributes(&self.conn, self.screen()?.root(), &attributes);
+        self.flush();
+        Ok(())
+    }
+
+    // Stacks the bar next to the docks already on the same edge of the
+    // screen, e.g. polybar or another Cnx bar, rather than overlapping them:
+    // below them at the top of the screen, or above them at the bottom. This
+    // must be called before the bar is mapped, i.e. before any content is
+    // added.
+    pub fn stack_on_docks(&mut self) -> Result<()> {
+        self.docked = self.reserved_by_docks()?;
+        self.set_ewmh_properties();
+        self.flush();
+        Ok(())
+    }
+
+    // Returns how much space the mapped windows on the bar's edge of the
+    // screen reserve from it with their struts, where they overlap the bar.
+    // Window managers don't all list docks as clients, so every top-level
+    // window is looked at.
+    fn reserved_by_docks(&self) -> Result<u16> {
+        let tree = xcb::query_tree(&self.conn, self.screen()?.root())
+            .get_reply()
+            .map_err(|_| anyhow!("Failed to list the windows"))?;
+        let start = u32::from(self.offset.x.max(0) as u16);
+        let end = start + u32::from(self.width);
+        let mut reserved = 0;
+        for &window in tree.children() {
+            if window == self.window_id {
+                continue;
+            }
+            let mapped = xcb::get_window_attributes(&self.conn, window)
+                .get_reply()
+                .map_or(false, |attributes| {
+                    attributes.map_state() == xcb::MAP_STATE_VIEWABLE as u8
+                });
+            let Ok(strut) = ewmh::get_wm_strut_partial(&self.conn, window).get_reply() else {
+                continue;
+            };
+            let (size, strut_start, strut_end) = match self.position {
+                Position::Top => (strut.top, strut.top_start_x, strut.top_end_x),
+                Position::Bottom => (strut.bottom, strut.bottom_start_x, strut.bottom_end_x),
+            };
+            // Some docks leave the range unset, to reserve the whole edge.
+            let overlaps =
+                (strut_start == 0 && strut_end == 0) || (strut_start < end && strut_end >= start);
+            if mapped && overlaps {
+                reserved = reserved.max(size);
+            }
+        }
+        Ok(u16::try_from(reserved).unwrap_or(u16::MAX))
+    }
+
+    fn stack_mode(&self) -> u32 {
+        match self.layer {
+            Layer::Normal | Layer::Above => xcb::STACK_MODE_ABOVE,
+            Layer::Below => xcb::STACK_MODE_BELOW,
+        }
+    }
+
+    fn restack(&self) {
+        let values = [(xcb::CONFIG_WINDOW_STACK_MODE as u16, self.stack_mode())];
+        xcb::configure_window(&self.conn, self.window_id, &values);
+        self.flush();
+    }
+
+    // Whether `window` is a dock, e.g. another bar or a panel.
+    fn is_dock(&self, window: u32) -> bool {
+        ewmh::get_wm_window_type(&self.conn, window)
+            .get_reply()
+            .map_or(false, |types| {
+                types.atoms().contains(&self.conn.WM_WINDOW_TYPE_DOCK())
+            })
+    }
+
+    // Restacks the bar if need be after another window was configured or
+    // unmapped, given whether it now covers the whole screen and, if it was
+    // configured, the window it is now stacked directly above.
+    fn on_window_changed(&mut self, window: u32, covers: bool, above_sibling: Option<u32>) {
+        if window == self.window_id || self.dashboard.is_some() {
+            return;
+        }
+        let left_fullscreen = match covers {
+            true => {
+                self.fullscreen.insert(window);
+                false
+            }
+            false => self.fullscreen.remove(&window),
+        };
+        // The bar is kept at the top (or bottom) of the stack, so a window
+        // placed above (or below) it is directly above the bar (or at the
+        // bottom). Moving or resizing a window doesn't change that.
+        let covered = match (self.layer, above_sibling) {
+            (Layer::Above, Some(sibling)) => sibling == self.window_id,
+            (Layer::Below, Some(sibling)) => sibling == xcb::NONE,
+            _ => false,
+        };
+        // Other docks, e.g. a second bar, may be kept above (or below) too,
+        // and restacking over each other would never end.
+        if left_fullscreen || (covered && !self.is_dock(window)) {
+            self.restack();
+        }
+    }
+
+    pub fn click_through(&self) -> bool {
+        self.click_through
+    }
+
+    // Sets whether clicks pass through the bar to the windows underneath.
+    pub fn set_click_through(&mut self, click_through: bool) {
+        self.click_through = click_through;
+        self.update_input_shape();
+    }
+
+    // Sets whether only clicks on interactive widgets are received, letting
+    // clicks anywhere else pass through to the windows underneath.
+    //
+    // `interactive` says which widgets are interactive, by index.
+    pub fn set_interactive_only(&mut self, interactive_only: bool, interactive: Vec<bool>) {
+        self.interactive_only = interactive_only;
+        self.interactive = interactive;
+        self.update_input_shape();
+    }
+
+    // Sets the input shape of the window, i.e. the region which receives
+    // clicks.
+    //
+    // With click-through, this is empty. With `interactive_only`, it is made
+    // up of the texts of the interactive widgets, so it needs updating
+    // whenever they move. Otherwise it is reset to the default (the whole
+    // window).
+    fn update_input_shape(&self) {
+        if self.click_through || self.interactive_only {
+            let rectangles: Vec<xcb::Rectangle> = if self.click_through {
+                Vec::new()
+            } else {
+                self.contents
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| self.is_visible(*idx))
+                    .filter(|(idx, _)| self.interactive.get(*idx).copied().unwrap_or(false))
+                    .flat_map(|(_, texts)| texts)
+                    .map(|text| {
+                        xcb::Rectangle::new(
+                            text.x.floor() as i16,
+                            text.y.floor() as i16,
+                            text.width.ceil() as u16,
+                            text.height.ceil() as u16,
+                        )
+                    })
+                    .collect()
+            };
+            xcb::shape::rectangles(
+                &self.conn,
+                xcb::shape::SO_SET as u8,
+                xcb::shape::SK_INPUT as u8,
+                xcb::CLIP_ORDERING_UNSORTED as u8,
+                self.window_id,
+                0,
+                0,
+                &rectangles,
+            );
+        } else {
+            xcb::shape::mask(
+                &self.conn,
+                xcb::shape::SO_SET as u8,
+                xcb::shape::SK_INPUT as u8,
+                self.window_id,
+                0,
+                0,
+                xcb::NONE,
+            );
+        }
+        self.flush();
+    }
+
+    // Enters zen mode, showing only the widget at index `zen` in the middle of
+    // the bar, or leaves it if `zen` is `None`.
+    pub fn set_zen(&mut self, zen: Option<usize>) -> Result<()> {
+        if let Some(idx) = zen {
+            if idx >= self.contents.len() {
+                return Err(anyhow!("No widget at index {}", idx));
+            }
+        }
+        self.zen = zen;
+        // The widgets will be drawn in different places, so clear the bar
+        // of everything drawn before.
+        self.clear()?;
+        self.redraw_entire_bar()
+    }
+
+    // Whether the widget at `idx` is shown, i.e. we aren't in zen mode or it
+    // is the zen widget.
+    fn is_visible(&self, idx: usize) -> bool {
+        self.zen.map_or(true, |zen| zen == idx)
+    }
+
+    // Fills the whole bar with the background color of the first visible
+    // text, or black.
+    fn clear(&self) -> Result<()> {
+        let bg_color = self
+            .contents
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.is_visible(*idx))
+            .flat_map(|(_, texts)| texts)
+            .find_map(|text| match &self.theme {
+                Some(theme) => theme.colors(&text.attr).1,
+                None => text.attr.bg_color.clone(),
+            })
+            .unwrap_or_else(Color::black);
+        let context = cairo::Context::new(&self.surface)?;
+        bg_color.apply_to_context(&context);
+        context.paint()?;
+        Ok(())
+    }
+
+    // Adjusts the colors of all widgets with `theme`, or stops adjusting them,
+    // and redraws the bar with the new colors.
+    pub fn set_theme(&mut self, theme: Option<Theme>) -> Result<()> {
+        if self.theme == theme {
+            return Ok(());
+        }
+        self.theme = theme;
+        // Nothing has been drawn yet.
+        if self.contents.is_empty() {
+            return Ok(());
+        }
+        self.clear()?;
+        self.redraw_entire_bar()
+    }
+
     // Returns the connection to the X server.
     //
     // The owner of the `Bar` is responsible for polling this for events,
@@ -263,21 +615,69 @@ impl Bar {
     }
 
     // Process an X event received from the `Bar::connection()`.
-    pub fn process_event(&mut self, event: xcb::GenericEvent) -> Result<()> {
-        let expose = event.response_type() & !0x80 == xcb::EXPOSE;
-        if expose {
-            println!("Redrawing entire bar - expose event.");
-            self.redraw_entire_bar()?;
+    //
+    // If the event was a click on one of the widgets' texts, returns the index
+    // of the widget along with the click, so that it can be passed on.
+    pub fn process_event(
+        &mut self,
+        event: xcb::GenericEvent,
+    ) -> Result<Option<(usize, ClickEvent)>> {
+        match event.response_type() & !0x80 {
+            xcb::EXPOSE => {
+                println!("Redrawing entire bar - expose event.");
+                self.redraw_entire_bar()?;
+            }
+            xcb::BUTTON_PRESS => {
+                let event: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(&event) };
+                return Ok(self.hit_test(event));
+            }
+            xcb::CONFIGURE_NOTIFY => {
+                let event: &xcb::ConfigureNotifyEvent = unsafe { xcb::cast_event(&event) };
+                let screen = self.screen()?;
+                let covers = event.x() <= 0
+                    && event.y() <= 0
+                    && i32::from(event.x()) + i32::from(event.width())
+                        >= i32::from(screen.width_in_pixels())
+                    && i32::from(event.y()) + i32::from(event.height())
+                        >= i32::from(screen.height_in_pixels());
+                self.on_window_changed(event.window(), covers, Some(event.above_sibling()));
+            }
+            xcb::UNMAP_NOTIFY => {
+                let event: &xcb::UnmapNotifyEvent = unsafe { xcb::cast_event(&event) };
+                self.on_window_changed(event.window(), false, None);
+            }
+            _ => {}
         }
-        Ok(())
+        Ok(None)
     }
 
-    // Add a new widget's content to the `Bar`.
+    // Finds the text under a button press, if any.
+    fn hit_test(&self, event: &xcb::ButtonPressEvent) -> Option<(usize, ClickEvent)> {
+        let (x, y) = (f64::from(event.event_x()), f64::from(event.event_y()));
+        self.contents.iter().enumerate().find_map(|(idx, texts)| {
+            if !self.is_visible(idx) {
+                return None;
+            }
+            let index = texts.iter().position(|text| {
+                text.x <= x && x < text.x + text.width && text.y <= y && y < text.y + text.height
+            })?;
+            let click = ClickEvent {
+                button: event.detail().into(),
+                index,
+                x: event.root_x(),
+                y: event.root_y(),
+            };
+            Some((idx, click))
+        })
+    }
+
+    // Add a new widget's content to the `Bar`, in the given row.
     //
     // Returns the index of the widget within the bar, so that subsequent
     // updates can be made by calling `Bar::update_content()`.
-    pub fn add_content(&mut self, content: Vec<Text>) -> Result<usize> {
+    pub fn add_content(&mut self, row: usize, content: Vec<Text>) -> Result<usize> {
         let idx = self.contents.len();
+        self.rows.push(row);
         self.contents.push(Vec::new());
         self.update_content(idx, content)?;
         Ok(idx)
@@ -285,6 +685,9 @@ impl Bar {
 
     // Updates an existing widget's content in the `Bar`.
     pub fn update_content(&mut self, idx: usize, content: Vec<Text>) -> Result<()> {
+        let scale = self.scales[self.rows[idx]];
+        let content: Vec<Text> = content.into_iter().map(|text| text.scaled(scale)).collect();
+
         // If the text is the same, don't bother re-computing the text or
         // redrawing it. This is a spurious wake-up.
         let old = &self.contents[idx];
@@ -333,8 +736,11 @@ impl Bar {
     }
 
     fn redraw_content(&mut self, idx: usize) -> Result<()> {
+        if !self.is_visible(idx) {
+            return Ok(());
+        }
         for text in &mut self.contents[idx] {
-            text.render(&self.surface)?;
+            text.render(&self.surface, self.theme.as_ref())?;
         }
 
         self.flush();
@@ -345,61 +751,145 @@ impl Bar {
     pub fn redraw_entire_bar(&mut self) -> Result<()> {
         self.recompute_dimensions()?;
 
+        // In zen mode, the widget doesn't cover the whole bar, and may have
+        // moved. Neither do the widgets of a dashboard.
+        if self.zen.is_some() || self.dashboard.is_some() {
+            self.clear()?;
+        }
+
         for idx in 0..self.contents.len() {
             self.redraw_content(idx)?;
         }
+
+        // The texts may have moved or changed size.
+        if self.interactive_only {
+            self.update_input_shape();
+        }
         Ok(())
     }
 
     fn recompute_dimensions(&mut self) -> Result<()> {
-        // Set the height to the max height of any content.
-        let height = self
-            .contents
-            .iter()
-            .flatten()
-            .map(|text| text.height)
-            .max_by_key(|height| OrderedFloat(*height))
-            .unwrap_or(0.0);
-        for text in self.contents.iter_mut().flatten() {
-            text.height = height;
+        if let Some(columns) = self.dashboard {
+            self.lay_out_grid(columns);
+            return Ok(());
         }
-        self.update_bar_height(height as u16)?;
 
-        // Sum the width of all non-stretch texts. Subtract from the screen
-        // width to get width remaining for stretch texts.
-        let used: f64 = self
-            .contents
-            .iter()
-            .flatten()
-            .filter(|text| !text.stretch)
-            .map(|text| text.width)
-            .sum();
-        let remaining = f64::from(self.width) - used;
-
-        // Distribute remaining width evenly between stretch texts.
-        let stretches_count = self
+        // Set the height of each row to the max height of any content in it,
+        // and stack the rows from the top.
+        let mut y = 0.0;
+        for row in 0..self.scales.len() {
+            let mut texts: Vec<&mut ComputedText> = self
+                .contents
+                .iter_mut()
+                .zip(&self.rows)
+                .filter(|(_, widget_row)| **widget_row == row)
+                .flat_map(|(texts, _)| texts)
+                .collect();
+            let height = texts
+                .iter()
+                .map(|text| text.height)
+                .max_by_key(|height| OrderedFloat(*height))
+                .unwrap_or(0.0);
+            for text in &mut texts {
+                text.y = y;
+                text.height = height;
+            }
+            y += height;
+        }
+        self.update_bar_height(y as u16)?;
+
+        for row in 0..self.scales.len() {
+            self.lay_out_row(row);
+        }
+        Ok(())
+    }
+
+    // Sets the horizontal position and width of the texts in `row`.
+    fn lay_out_row(&mut self, row: usize) {
+        // Only the visible widgets are laid out. The others keep their old
+        // dimensions, but aren't drawn.
+        let zen = self.zen;
+        let visible: Vec<&mut ComputedText> = self
             .contents
-            .iter()
-            .flatten()
-            .filter(|text| text.stretch)
-            .count();
-        let stretch_width = remaining / (stretches_count as f64);
-        let stretches = self
+            .iter_mut()
+            .zip(&self.rows)
+            .enumerate()
+            .filter(|(idx, (_, widget_row))| {
+                **widget_row == row && zen.map_or(true, |zen| zen == *idx)
+            })
+            .flat_map(|(_, (texts, _))| texts)
+            .collect();
+        // In zen mode, the widget is centered unless it stretches to fill
+        // the bar.
+        lay_out(visible, 0.0, f64::from(self.width), zen.is_some());
+    }
+
+    // Lays the visible widgets out in a grid with `columns` columns covering
+    // the window, filling each row from the left. Each widget is centered in
+    // its cell, at the height of its texts.
+    fn lay_out_grid(&mut self, columns: usize) {
+        let zen = self.zen;
+        let cells: Vec<&mut Vec<ComputedText>> = self
             .contents
             .iter_mut()
-            .flatten()
-            .filter(|text| text.stretch);
-        for text in stretches {
-            text.width = stretch_width;
+            .enumerate()
+            .filter(|(idx, _)| zen.map_or(true, |zen| zen == *idx))
+            .map(|(_, texts)| texts)
+            .collect();
+        if cells.is_empty() {
+            return;
         }
 
-        // Set x based on computed widths.
-        let mut x = 0.0;
-        for text in self.contents.iter_mut().flatten() {
-            text.x = x;
-            x += text.width;
+        let columns = columns.min(cells.len());
+        let rows = cells.len() / columns + usize::from(cells.len() % columns != 0);
+        let cell_width = f64::from(self.width) / columns as f64;
+        let cell_height = f64::from(self.height) / rows as f64;
+        for (idx, texts) in cells.into_iter().enumerate() {
+            let (row, column) = (idx / columns, idx % columns);
+            let top = row as f64 * cell_height;
+            for text in texts.iter_mut() {
+                text.height = text.height.min(cell_height);
+                text.y = top + (cell_height - text.height) / 2.0;
+            }
+            lay_out(
+                texts.iter_mut().collect(),
+                column as f64 * cell_width,
+                cell_width,
+                true,
+            );
         }
+    }
+}
 
-        Ok(())
+// Sets the horizontal position and width of `texts`, laying them out from
+// left to right in the space of `width` starting at `left`. Any space left is
+// shared between the stretch texts, or if there aren't any and `center` is
+// set, split either side of the texts.
+fn lay_out(mut texts: Vec<&mut ComputedText>, left: f64, width: f64, center: bool) {
+    // Sum the width of all non-stretch texts. Subtract from the available
+    // width to get width remaining for stretch texts.
+    let used: f64 = texts
+        .iter()
+        .filter(|text| !text.stretch)
+        .map(|text| text.width)
+        .sum();
+    let remaining = width - used;
+
+    // Distribute remaining width evenly between stretch texts.
+    let stretches_count = texts.iter().filter(|text| text.stretch).count();
+    let stretch_width = remaining / (stretches_count as f64);
+    for text in texts.iter_mut().filter(|text| text.stretch) {
+        text.width = stretch_width;
+    }
+
+    // Set x based on computed widths.
+    let mut x = if center && stretches_count == 0 {
+        left + (remaining / 2.0).max(0.0)
+    } else {
+        left
+    };
+    for text in texts {
+        text.x = x;
+        x += text.width;
     }
 }
@@ -0,0 +1,108 @@
+//! A bus on which widgets share data with each other.
+//!
+//! Widgets can publish data they have derived, such as the battery's state,
+//! on the [`Bus`], and other widgets can subscribe to it without knowing
+//! which widget publishes it. Each topic is a type, so publishers and
+//! subscribers agree on a type rather than on a name, e.g. a newtype such as
+//! `struct CpuTemperature(f64)`.
+//!
+//! There is one bus per [`Cnx`], shared through its [`Services`]; see
+//! [`Bus::shared()`].
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::bus::Bus;
+//! use futures::executor::block_on;
+//! use futures::StreamExt;
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct CpuTemperature(f64);
+//!
+//! let bus = Bus::default();
+//! let mut temperatures = bus.subscribe::<CpuTemperature>();
+//! bus.publish(CpuTemperature(45.0));
+//! assert_eq!(block_on(temperatures.next()), Some(CpuTemperature(45.0)));
+//!
+//! // Late subscribers start with the latest value.
+//! let mut temperatures = bus.subscribe::<CpuTemperature>();
+//! assert_eq!(block_on(temperatures.next()), Some(CpuTemperature(45.0)));
+//! ```
+//!
+//! [`Cnx`]: crate::Cnx
+
+use crate::services::Services;
+use futures::channel::mpsc;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tokio_stream::Stream;
+
+// The latest value published on a topic, and the subscribers to it.
+struct Topic<T> {
+    latest: Option<T>,
+    subscribers: Vec<mpsc::UnboundedSender<T>>,
+}
+
+/// A bus on which widgets publish data for other widgets to subscribe to,
+/// with one topic for each type of data.
+///
+/// Cloning a `Bus` gives another handle to the same bus.
+#[derive(Clone, Default)]
+pub struct Bus {
+    topics: Rc<RefCell<HashMap<TypeId, Box<dyn Any>>>>,
+}
+
+impl Bus {
+    /// Returns the bus shared between the widgets, e.g. from a widget's
+    /// [`Widget::use_services()`].
+    ///
+    /// [`Widget::use_services()`]: crate::widgets::Widget::use_services
+    pub fn shared(services: &Services) -> Bus {
+        (*services.get_or_insert_with(Bus::default)).clone()
+    }
+
+    // Calls `f` with the topic of type `T`, creating it if there isn't one
+    // yet.
+    fn with_topic<T: 'static, R>(&self, f: impl FnOnce(&mut Topic<T>) -> R) -> R {
+        let mut topics = self.topics.borrow_mut();
+        let topic = topics.entry(TypeId::of::<T>()).or_insert_with(|| {
+            Box::new(Topic::<T> {
+                latest: None,
+                subscribers: Vec::new(),
+            })
+        });
+        // Topics are keyed by their type, so this can't fail.
+        f(topic.downcast_mut().expect("Topic of the wrong type"))
+    }
+
+    /// Publishes `value` to the subscribers of its type.
+    pub fn publish<T: Clone + 'static>(&self, value: T) {
+        self.with_topic(|topic| {
+            // Subscribers which have been dropped are forgotten.
+            topic
+                .subscribers
+                .retain(|subscriber| subscriber.unbounded_send(value.clone()).is_ok());
+            topic.latest = Some(value);
+        });
+    }
+
+    /// Returns a stream of the values of type `T` which are published,
+    /// starting with the latest one if there is one.
+    pub fn subscribe<T: Clone + 'static>(&self) -> impl Stream<Item = T> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.with_topic(|topic: &mut Topic<T>| {
+            if let Some(latest) = &topic.latest {
+                let _ = sender.unbounded_send(latest.clone());
+            }
+            topic.subscribers.push(sender);
+        });
+        receiver
+    }
+
+    /// Returns the latest value of type `T` which was published, if any.
+    pub fn latest<T: Clone + 'static>(&self) -> Option<T> {
+        self.with_topic(|topic: &mut Topic<T>| topic.latest.clone())
+    }
+}
@@ -0,0 +1,261 @@
+//! Detecting the features of the system which parts of Cnx need.
+//!
+//! Minimal X servers, e.g. Xvfb or a nested Xephyr, and containers may lack
+//! features which some widgets rely on, such as the XKB extension or a
+//! D-Bus session bus. Rather than failing on every update, a widget which
+//! needs a missing [`Capability`] shows `n/a` in place of its text and stops
+//! (see [`unavailable()`]), and the missing capability is logged once.
+//!
+//! Each capability is detected once, the first time it is asked about, and
+//! the result is shared between the widgets using the same [`Services`]; see
+//! [`Capabilities::shared()`]. Detecting some capabilities blocks, e.g. while
+//! a process runs, so async code should detect them with
+//! [`Capabilities::prefetch()`] before asking about them.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::capabilities::{Capabilities, Capability};
+//!
+//! let capabilities = Capabilities::default();
+//! match capabilities.require(Capability::SessionBus) {
+//!     Ok(()) => println!("Connecting to the session bus"),
+//!     Err(err) => println!("{err}"),
+//! }
+//! ```
+
+use crate::error::{ErrorKind, WidgetError};
+use crate::i18n;
+use crate::services::Services;
+use crate::text::{Attributes, Text};
+use crate::widgets::WidgetStream;
+use anyhow::anyhow;
+use async_stream::stream;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+// Where the system bus listens, unless `DBUS_SYSTEM_BUS_ADDRESS` says
+// otherwise.
+const SYSTEM_BUS: &str = "/var/run/dbus/system_bus_socket";
+
+/// A feature of the system which some parts of Cnx need.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Capability {
+    /// The X server's SHAPE extension, which the bar needs to let clicks
+    /// through
+    Shape,
+    /// The X server's XKB extension, for keyboard layouts
+    Xkb,
+    /// The user's D-Bus session bus
+    SessionBus,
+    /// The D-Bus system bus
+    SystemBus,
+    /// Unprivileged user namespaces, with which a command can be run
+    /// without network access
+    UserNamespaces,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Shape => "SHAPE extension",
+            Capability::Xkb => "XKB extension",
+            Capability::SessionBus => "D-Bus session bus",
+            Capability::SystemBus => "D-Bus system bus",
+            Capability::UserNamespaces => "unprivileged user namespaces",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The capabilities of the system, detected as they are asked about.
+///
+/// Cloning a `Capabilities` gives another handle to the same results.
+#[derive(Clone, Default)]
+pub struct Capabilities {
+    detected: Rc<RefCell<HashMap<Capability, bool>>>,
+}
+
+impl Capabilities {
+    /// Returns the capabilities shared between the widgets using `services`,
+    /// e.g. from a widget's [`Widget::use_services()`].
+    ///
+    /// [`Widget::use_services()`]: crate::widgets::Widget::use_services
+    pub fn shared(services: &Services) -> Capabilities {
+        (*services.get_or_insert_with(Capabilities::default)).clone()
+    }
+
+    /// Returns whether the system has `capability`, detecting it if it
+    /// hasn't been asked about before, and logging it if it is missing.
+    pub fn is_available(&self, capability: Capability) -> bool {
+        if let Some(available) = self.detected.borrow().get(&capability) {
+            return *available;
+        }
+        let available = detect(capability);
+        self.record(capability, available);
+        available
+    }
+
+    /// Detects `capability` on a thread on which blocking is allowed, if it
+    /// hasn't been asked about before, so that asking about it afterwards
+    /// doesn't block. [`Capability::UserNamespaces`] is detected by running
+    /// a process.
+    pub async fn prefetch(&self, capability: Capability) {
+        if self.detected.borrow().contains_key(&capability) {
+            return;
+        }
+        let available = tokio::task::spawn_blocking(move || detect(capability))
+            .await
+            .unwrap_or(false);
+        self.record(capability, available);
+    }
+
+    // Saves whether the system has `capability`, and logs it if it's missing
+    // and wasn't already saved, e.g. by another widget's `prefetch()`.
+    fn record(&self, capability: Capability, available: bool) {
+        let previous = self.detected.borrow_mut().insert(capability, available);
+        if previous.is_none() && !available {
+            println!("No {capability}, so the features which need it are disabled");
+        }
+    }
+
+    /// Returns an [`ErrorKind::Unsupported`] error if the system doesn't
+    /// have `capability`.
+    pub fn require(&self, capability: Capability) -> Result<(), WidgetError> {
+        match self.is_available(capability) {
+            true => Ok(()),
+            false => Err(WidgetError::new(
+                ErrorKind::Unsupported,
+                anyhow!("No {}", capability),
+            )),
+        }
+    }
+}
+
+/// Returns the text shown in place of a widget which can't work on this
+/// system, in the widget's `attr`.
+pub fn not_available(attr: Attributes) -> Text {
+    Text {
+        attr,
+        text: i18n::tr("n/a"),
+        stretch: false,
+        markup: false,
+        canvas: None,
+    }
+}
+
+/// Returns the stream of a widget which can't work on this system because
+/// of `err`, e.g. from [`Capabilities::require()`].
+///
+/// The stream shows `n/a` in the widget's `attr`, and then yields `err`,
+/// after which Cnx stops the widget.
+pub fn unavailable(attr: Attributes, err: WidgetError) -> WidgetStream {
+    Box::pin(stream! {
+        yield Ok(vec![not_available(attr)]);
+        yield Err(err.into());
+    })
+}
+
+fn detect(capability: Capability) -> bool {
+    match capability {
+        Capability::Shape => has_extension("SHAPE"),
+        Capability::Xkb => has_extension("XKEYBOARD"),
+        Capability::SessionBus => {
+            let default = env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("bus"));
+            has_bus("DBUS_SESSION_BUS_ADDRESS", default)
+        }
+        Capability::SystemBus => has_bus("DBUS_SYSTEM_BUS_ADDRESS", Some(SYSTEM_BUS.into())),
+        Capability::UserNamespaces => has_user_namespaces(),
+    }
+}
+
+fn has_extension(name: &str) -> bool {
+    let Ok((conn, _)) = xcb::Connection::connect(None) else {
+        return false;
+    };
+    xcb::query_extension(&conn, name)
+        .get_reply()
+        .map_or(false, |reply| reply.present())
+}
+
+// User namespaces may be turned off for unprivileged users, e.g. by a sysctl
+// or a security module, so the only sure way to know is to try to create one,
+// in a child process.
+#[cfg(target_os = "linux")]
+fn has_user_namespaces() -> bool {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new("true");
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+    // Safe, as `unshare()` is async-signal-safe.
+    unsafe {
+        command.pre_exec(
+            || match libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) {
+                0 => Ok(()),
+                _ => Err(std::io::Error::last_os_error()),
+            },
+        );
+    }
+    command.status().map_or(false, |status| status.success())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn has_user_namespaces() -> bool {
+    false
+}
+
+// Returns whether there is a bus at the address in the environment
+// `variable`, or else at the `default` socket.
+fn has_bus(variable: &str, default: Option<PathBuf>) -> bool {
+    match env::var(variable) {
+        // Only sockets in the filesystem can be checked without connecting,
+        // so other addresses are assumed to work.
+        Ok(address) => socket_path(&address).map_or(true, |path| path.exists()),
+        Err(_) => default.map_or(false, |path| path.exists()),
+    }
+}
+
+// Returns the path of the socket of the first of the D-Bus `addresses`, if
+// it is one, e.g. `unix:path=/run/user/1000/bus,guid=...`.
+fn socket_path(addresses: &str) -> Option<PathBuf> {
+    let address = addresses.split(';').next()?;
+    let params = address.strip_prefix("unix:")?;
+    params
+        .split(',')
+        .find_map(|param| param.strip_prefix("path="))
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        assert_eq!(
+            socket_path("unix:path=/run/user/1000/bus,guid=0123"),
+            Some(PathBuf::from("/run/user/1000/bus"))
+        );
+        assert_eq!(
+            socket_path("unix:guid=0123,path=/tmp/bus;tcp:host=localhost"),
+            Some(PathBuf::from("/tmp/bus"))
+        );
+        assert_eq!(socket_path("unix:abstract=/tmp/dbus-x"), None);
+        assert_eq!(socket_path("tcp:host=localhost,port=4000"), None);
+
+        // A capability is only detected once.
+        let capabilities = Capabilities::default();
+        capabilities
+            .detected
+            .borrow_mut()
+            .insert(Capability::Xkb, false);
+        let err = capabilities.require(Capability::Xkb).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Unsupported);
+        assert_eq!(err.to_string(), "No XKB extension (unsupported error)");
+    }
+}
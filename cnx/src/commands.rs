@@ -0,0 +1,138 @@
+//! Commands for widgets, sent on the control socket.
+//!
+//! Besides the commands Cnx handles itself, a command sent on the control
+//! socket (see [`Cnx::with_control_socket()`]) is passed to the widget which
+//! registered the command's first word with [`Commands::register()`], e.g.
+//! `timer start 25m tea` is passed to the handler registered as `timer` as
+//! `["start", "25m", "tea"]`. The handler's reply is sent back on the socket.
+//!
+//! There is one registry of commands per [`Cnx`], shared through its
+//! [`Services`]; see [`Commands::shared()`].
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::commands::Commands;
+//!
+//! let commands = Commands::default();
+//! let registration = commands.register("greet", |args| Ok(format!("Hello {}", args.join(" "))));
+//! assert_eq!(commands.dispatch("greet the world").unwrap(), "Hello the world");
+//!
+//! // The command is handled for as long as the registration is kept.
+//! drop(registration);
+//! assert!(commands.dispatch("greet the world").is_err());
+//! ```
+//!
+//! [`Cnx`]: crate::Cnx
+//! [`Cnx::with_control_socket()`]: crate::Cnx::with_control_socket
+
+use crate::services::Services;
+use anyhow::{anyhow, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Handler = Rc<dyn Fn(&[&str]) -> Result<String>>;
+
+/// A registry of the commands widgets handle, by the command's name.
+///
+/// Cloning a `Commands` gives another handle to the same registry.
+#[derive(Clone, Default)]
+pub struct Commands {
+    // Each handler along with the number of its registration.
+    handlers: Rc<RefCell<HashMap<String, (u64, Handler)>>>,
+    registered: Rc<Cell<u64>>,
+}
+
+impl Commands {
+    /// Returns the registry shared between the widgets, e.g. from a widget's
+    /// [`Widget::use_services()`].
+    ///
+    /// [`Widget::use_services()`]: crate::widgets::Widget::use_services
+    pub fn shared(services: &Services) -> Commands {
+        (*services.get_or_insert_with(Commands::default)).clone()
+    }
+
+    /// Registers `handler` to handle the commands whose first word is
+    /// `name`, replacing any handler registered before, until the returned
+    /// [`Registration`] is dropped.
+    ///
+    /// The handler is given the rest of the command's words, and returns the
+    /// output to reply with, which may be empty, or an error.
+    pub fn register(
+        &self,
+        name: &str,
+        handler: impl Fn(&[&str]) -> Result<String> + 'static,
+    ) -> Registration {
+        let id = self.registered.get() + 1;
+        self.registered.set(id);
+        self.handlers
+            .borrow_mut()
+            .insert(name.to_owned(), (id, Rc::new(handler)));
+        Registration {
+            commands: self.clone(),
+            name: name.to_owned(),
+            id,
+        }
+    }
+
+    /// Passes `command`, a line of words separated by whitespace, to the
+    /// handler registered for its first word, and returns its reply.
+    pub fn dispatch(&self, command: &str) -> Result<String> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        // The handler may register other commands, so it mustn't be called
+        // while the registry is borrowed.
+        let handler = self.handlers.borrow().get(name).map(|(_, h)| h.clone());
+        match handler {
+            Some(handler) => handler(&args),
+            None => Err(anyhow!("Unknown command: {}", command.trim())),
+        }
+    }
+}
+
+/// Keeps a command registered with [`Commands::register()`], and
+/// unregisters it when dropped, e.g. along with the stream of the widget
+/// handling it.
+pub struct Registration {
+    commands: Commands,
+    name: String,
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut handlers = self.commands.handlers.borrow_mut();
+        // The command may have been registered again since.
+        if matches!(handlers.get(&self.name), Some((id, _)) if *id == self.id) {
+            handlers.remove(&self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registrations() {
+        let commands = Commands::default();
+        let first = commands.register("timer", |_| Ok("first".to_owned()));
+        let second = commands.register("timer", |args| match args {
+            ["stop"] => Ok(String::new()),
+            _ => Err(anyhow!("Expected stop")),
+        });
+        assert_eq!(commands.dispatch("timer  stop ").unwrap(), "");
+        assert!(commands.dispatch("timer start").is_err());
+
+        // Only the latest registration removes the command.
+        drop(first);
+        assert!(commands.dispatch("timer stop").is_ok());
+        drop(second);
+        assert_eq!(
+            commands.dispatch("timer stop").unwrap_err().to_string(),
+            "Unknown command: timer stop"
+        );
+    }
+}
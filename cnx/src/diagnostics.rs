@@ -0,0 +1,266 @@
+//! Diagnostics of how much CPU time and memory each widget uses.
+//!
+//! If enabled with [`Cnx::with_diagnostics()`], Cnx measures the CPU time
+//! spent polling each widget's stream, which is where a widget does its work,
+//! and logs the widgets which used the most in the last minute. The totals
+//! since Cnx started can be queried at any time with the `diagnostics`
+//! command of the [control socket](crate#control-socket).
+//!
+//! Allocations are only counted if the [`CountingAllocator`] is installed as
+//! the global allocator of the program. It is also needed by
+//! `Cnx::soak_test()`, with the `soak` feature, which checks each widget for
+//! memory leaks.
+//!
+//! Only work done while a widget's stream is polled is attributed to it, so
+//! e.g. a blocking call on a thread of its own isn't counted.
+//!
+//! [`Cnx::with_diagnostics()`]: crate::Cnx::with_diagnostics
+
+use crate::widgets::WidgetStream;
+use futures::task::{Context, Poll};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::ops::AddAssign;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::task;
+use tokio::time::{self, Instant};
+use tokio_stream::Stream;
+
+// How often the widgets which used the most are logged, and how many of them.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+const REPORT_TOP: usize = 5;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+// The allocations made and freed on a thread.
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    allocations: u64,
+    allocated: u64,
+    freed: u64,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            allocations: 0,
+            allocated: 0,
+            freed: 0,
+        })
+    };
+}
+
+/// A global allocator which counts allocations, so that diagnostics can
+/// attribute them to widgets.
+///
+/// It passes each allocation on to the [`System`] allocator. Counting costs
+/// a little time on every allocation, so only install it while looking for a
+/// widget which uses too much memory.
+///
+/// # Examples
+///
+/// ```
+/// use cnx::diagnostics::CountingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        COUNTING.store(true, Ordering::Relaxed);
+        // The thread local may already have been destroyed while a thread
+        // exits, in which case the allocation isn't counted.
+        let _ = COUNTERS.try_with(|counters| {
+            let mut c = counters.get();
+            c.allocations += 1;
+            c.allocated += layout.size() as u64;
+            counters.set(c);
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = COUNTERS.try_with(|counters| {
+            let mut c = counters.get();
+            c.freed += layout.size() as u64;
+            counters.set(c);
+        });
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn counters() -> Counters {
+    COUNTERS.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Returns whether the [`CountingAllocator`] is installed.
+#[cfg(feature = "soak")]
+pub(crate) fn counting() -> bool {
+    // Make sure that something has been allocated.
+    drop(std::hint::black_box(vec![0u8]));
+    COUNTING.load(Ordering::Relaxed)
+}
+
+/// Returns the number of bytes allocated and not yet freed on this thread,
+/// which is negative if it freed memory allocated on other threads.
+#[cfg(feature = "soak")]
+pub(crate) fn live() -> i64 {
+    let counters = counters();
+    counters.allocated as i64 - counters.freed as i64
+}
+
+// Returns the CPU time this thread has used so far.
+fn thread_cpu_time() -> Duration {
+    let mut spec = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // This can only fail for an unknown clock.
+    unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut spec) };
+    Duration::new(spec.tv_sec as u64, spec.tv_nsec as u32)
+}
+
+// The resources used by a widget.
+#[derive(Clone, Copy, Debug, Default)]
+struct Usage {
+    polls: u64,
+    cpu_time: Duration,
+    allocations: u64,
+    allocated: u64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.polls += other.polls;
+        self.cpu_time += other.cpu_time;
+        self.allocations += other.allocations;
+        self.allocated += other.allocated;
+    }
+}
+
+// The resources used by each widget, in total and since they were last
+// logged.
+struct State {
+    names: Vec<&'static str>,
+    total: Vec<Usage>,
+    recent: Vec<Usage>,
+}
+
+/// Measures the resources used by each widget.
+#[derive(Clone)]
+pub(crate) struct Diagnostics {
+    state: Rc<RefCell<State>>,
+}
+
+impl Diagnostics {
+    /// Starts measuring the widgets with the given names, logging the ones
+    /// which used the most every minute.
+    ///
+    /// This must be called from within a `task::LocalSet`.
+    pub(crate) fn start(names: Vec<&'static str>) -> Self {
+        let state = State {
+            total: vec![Usage::default(); names.len()],
+            recent: vec![Usage::default(); names.len()],
+            names,
+        };
+        let diagnostics = Diagnostics {
+            state: Rc::new(RefCell::new(state)),
+        };
+
+        let reporter = diagnostics.clone();
+        task::spawn_local(async move {
+            let mut interval = time::interval_at(Instant::now() + REPORT_INTERVAL, REPORT_INTERVAL);
+            loop {
+                interval.tick().await;
+                let mut state = reporter.state.borrow_mut();
+                let report = report(&state.names, &state.recent, REPORT_TOP);
+                println!("Widgets which used the most in the last minute:\n{report}");
+                state.recent.fill(Usage::default());
+            }
+        });
+
+        diagnostics
+    }
+
+    /// Wraps the stream of the widget at `idx`, measuring each time it is
+    /// polled.
+    pub(crate) fn measure(&self, idx: usize, stream: WidgetStream) -> WidgetStream {
+        Box::pin(Measured {
+            idx,
+            stream,
+            diagnostics: self.clone(),
+        })
+    }
+
+    /// Returns the widgets which used the most since Cnx started, for the
+    /// `diagnostics` command of the control socket.
+    pub(crate) fn report(&self) -> String {
+        let state = self.state.borrow();
+        report(&state.names, &state.total, state.names.len())
+    }
+
+    fn record(&self, idx: usize, usage: Usage) {
+        let mut state = self.state.borrow_mut();
+        state.total[idx] += usage;
+        state.recent[idx] += usage;
+    }
+}
+
+// Formats the `top` widgets which used the most CPU time, one per line.
+fn report(names: &[&str], usage: &[Usage], top: usize) -> String {
+    let mut widgets: Vec<_> = names.iter().zip(usage).enumerate().collect();
+    widgets.sort_by_key(|(_, (_, usage))| Reverse(usage.cpu_time));
+
+    let mut report = String::new();
+    for (idx, (name, usage)) in widgets.into_iter().take(top) {
+        let _ = write!(
+            report,
+            "{idx} {name}: {:.3}s CPU in {} polls",
+            usage.cpu_time.as_secs_f64(),
+            usage.polls
+        );
+        if COUNTING.load(Ordering::Relaxed) {
+            let _ = write!(
+                report,
+                ", {} allocations of {:.1} KiB",
+                usage.allocations,
+                usage.allocated as f64 / 1024.0
+            );
+        }
+        report.push('\n');
+    }
+    report
+}
+
+// A widget's stream, which records the resources used each time it is
+// polled.
+struct Measured {
+    idx: usize,
+    stream: WidgetStream,
+    diagnostics: Diagnostics,
+}
+
+impl Stream for Measured {
+    type Item = <WidgetStream as Stream>::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let (cpu_before, before) = (thread_cpu_time(), counters());
+        let poll = self.stream.as_mut().poll_next(cx);
+        let (cpu_after, after) = (thread_cpu_time(), counters());
+        let usage = Usage {
+            polls: 1,
+            cpu_time: cpu_after.saturating_sub(cpu_before),
+            allocations: after.allocations - before.allocations,
+            allocated: after.allocated - before.allocated,
+        };
+        self.diagnostics.record(self.idx, usage);
+        poll
+    }
+}
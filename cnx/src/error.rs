@@ -0,0 +1,430 @@
+//! Structured errors from widgets, and what Cnx does about them.
+//!
+//! Widgets' streams yield [`anyhow::Error`]s, which Cnx turns into
+//! [`WidgetError`]s as they arrive, naming the widget which failed and the
+//! [`ErrorKind`] of failure. A widget can say what kind of failure it was by
+//! yielding a `WidgetError` itself, converted into an `anyhow::Error`:
+//!
+//! ```
+//! use anyhow::Result;
+//! use cnx::error::{ErrorKind, WidgetError};
+//!
+//! fn parse_reading(reading: &str) -> Result<f64> {
+//!     reading
+//!         .trim()
+//!         .parse()
+//!         .map_err(|err| WidgetError::new(ErrorKind::Parse, err).into())
+//! }
+//! # assert!(parse_reading("nan?").is_err());
+//! ```
+//!
+//! Otherwise the kind is worked out from the error's causes, e.g. an
+//! [`io::Error`] of [`io::ErrorKind::PermissionDenied`] is a
+//! [`ErrorKind::Permission`] error.
+//!
+//! Cnx then applies a [`Policy`]: it keeps showing the widget's last text
+//! while the widget tries again, shows the error in place of the text, hides
+//! the widget, or shows `n/a` and stops the widget. Each kind has a default
+//! policy, which can be changed with [`Cnx::with_error_policy()`]. How many
+//! errors of each kind each widget has had is reported by the `errors`
+//! command of the [control socket](crate#control-socket).
+//!
+//! [`Cnx::with_error_policy()`]: crate::Cnx::with_error_policy
+
+use crate::capabilities;
+use crate::text::{Color, Role, Text};
+use crate::widgets::WidgetStream;
+use async_stream::stream;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use tokio_stream::{Stream, StreamExt};
+
+// The longest error shown in place of a widget's text, in characters.
+const MAX_DISPLAYED: usize = 60;
+
+/// What kind of failure a [`WidgetError`] is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ErrorKind {
+    /// A network request failed, e.g. because the network is down
+    Network,
+    /// A service, device or program the widget needs isn't there, e.g. a
+    /// daemon which isn't running
+    Unavailable,
+    /// The widget isn't allowed to do what it needs to, e.g. read a file
+    Permission,
+    /// Data from a file, program or service couldn't be understood
+    Parse,
+    /// The widget is configured wrongly, e.g. with a name that doesn't exist
+    Config,
+    /// The system lacks a feature the widget needs, e.g. an X extension or
+    /// D-Bus, so it won't work until Cnx is restarted; see
+    /// [`capabilities`](crate::capabilities)
+    Unsupported,
+    /// Any other failure
+    Other,
+}
+
+impl ErrorKind {
+    /// The policy applied to errors of this kind which aren't retryable,
+    /// unless another is set with [`Cnx::with_error_policy()`].
+    ///
+    /// Configuration and permission errors are shown, as they need fixing,
+    /// a widget whose service is unavailable is hidden, and a widget which
+    /// the system doesn't support is disabled. Otherwise the widget's last
+    /// text is kept.
+    ///
+    /// [`Cnx::with_error_policy()`]: crate::Cnx::with_error_policy
+    pub fn default_policy(self) -> Policy {
+        match self {
+            ErrorKind::Config | ErrorKind::Permission => Policy::Display,
+            ErrorKind::Unavailable => Policy::Drop,
+            ErrorKind::Unsupported => Policy::Disable,
+            ErrorKind::Network | ErrorKind::Parse | ErrorKind::Other => Policy::Retry,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Config => "config",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What Cnx does when a widget fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Policy {
+    /// Log the error and keep showing the widget's last text, as the widget
+    /// will try again
+    Retry,
+    /// Show the error in place of the widget's text, in the theme's `Crit`
+    /// color, until the widget next updates
+    Display,
+    /// Log the error and hide the widget until it next updates
+    Drop,
+    /// Log the error, show `n/a` in place of the widget's text, or hide it
+    /// if it has never shown anything, and stop the widget, as it would only
+    /// fail again
+    Disable,
+}
+
+/// An error from a widget, saying which widget failed and how.
+#[derive(Debug)]
+pub struct WidgetError {
+    /// The name of the widget's type, e.g. `Weather`, once Cnx knows which
+    /// widget yielded the error
+    pub widget: Option<&'static str>,
+    /// What kind of failure it was
+    pub kind: ErrorKind,
+    /// The error itself
+    pub source: anyhow::Error,
+    /// Whether the failure is expected to go away by itself, e.g. when the
+    /// network comes back, in which case the widget's last text is kept
+    pub retryable: bool,
+}
+
+impl WidgetError {
+    /// Creates an error of the given `kind`. Only network errors are
+    /// retryable, unless [`WidgetError::with_retryable()`] says otherwise.
+    pub fn new(kind: ErrorKind, source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            widget: None,
+            kind,
+            source: source.into(),
+            retryable: kind == ErrorKind::Network,
+        }
+    }
+
+    /// Sets whether the failure is expected to go away by itself.
+    pub fn with_retryable(self, retryable: bool) -> Self {
+        Self { retryable, ..self }
+    }
+
+    /// Turns an error yielded by a widget into a `WidgetError`.
+    ///
+    /// If `err` is, or was caused by, a `WidgetError`, its kind is kept.
+    /// Otherwise the kind is worked out from the types of its causes.
+    pub fn classify(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<WidgetError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let cause = err.chain().find_map(|cause| {
+            if let Some(cause) = cause.downcast_ref::<WidgetError>() {
+                return Some((cause.kind, cause.retryable));
+            }
+            kind_of(cause).map(|kind| (kind, kind == ErrorKind::Network))
+        });
+        let (kind, retryable) = cause.unwrap_or((ErrorKind::Other, false));
+        Self::new(kind, err).with_retryable(retryable)
+    }
+
+    /// The policy for this error, out of the `policies` set for each kind.
+    pub fn policy(&self, policies: &HashMap<ErrorKind, Policy>) -> Policy {
+        match policies.get(&self.kind) {
+            Some(policy) => *policy,
+            None if self.retryable => Policy::Retry,
+            None => self.kind.default_policy(),
+        }
+    }
+}
+
+impl fmt::Display for WidgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(widget) = self.widget {
+            write!(f, "{widget}: ")?;
+        }
+        write!(f, "{:#} ({} error)", self.source, self.kind)
+    }
+}
+
+impl Error for WidgetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let source: &(dyn Error + 'static) = self.source.as_ref();
+        Some(source)
+    }
+}
+
+// Works out the kind of error `cause` is from its type, if it is a type
+// whose kind is known.
+fn kind_of(cause: &(dyn Error + 'static)) -> Option<ErrorKind> {
+    if let Some(err) = cause.downcast_ref::<io::Error>() {
+        return match err.kind() {
+            io::ErrorKind::PermissionDenied => Some(ErrorKind::Permission),
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => {
+                Some(ErrorKind::Unavailable)
+            }
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::TimedOut => Some(ErrorKind::Network),
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Some(ErrorKind::Parse),
+            _ => None,
+        };
+    }
+    let parse = cause.is::<std::num::ParseIntError>()
+        || cause.is::<std::num::ParseFloatError>()
+        || cause.is::<std::str::Utf8Error>()
+        || cause.is::<std::string::FromUtf8Error>();
+    parse.then_some(ErrorKind::Parse)
+}
+
+// The errors each widget has had, for the `errors` command of the control
+// socket.
+struct WidgetErrors {
+    name: &'static str,
+    counts: BTreeMap<ErrorKind, u64>,
+    last: Option<String>,
+}
+
+/// Counts the errors of each kind that each widget has had.
+#[derive(Clone)]
+pub(crate) struct ErrorLog {
+    widgets: Rc<RefCell<Vec<WidgetErrors>>>,
+}
+
+impl ErrorLog {
+    /// Starts counting the errors of the widgets with the given names.
+    pub(crate) fn new(names: &[&'static str]) -> Self {
+        let widgets = names
+            .iter()
+            .map(|&name| WidgetErrors {
+                name,
+                counts: BTreeMap::new(),
+                last: None,
+            })
+            .collect();
+        Self {
+            widgets: Rc::new(RefCell::new(widgets)),
+        }
+    }
+
+    fn record(&self, idx: usize, err: &WidgetError) {
+        let mut widgets = self.widgets.borrow_mut();
+        let Some(widget) = widgets.get_mut(idx) else {
+            return;
+        };
+        *widget.counts.entry(err.kind).or_default() += 1;
+        widget.last = Some(format!("{:#}", err.source));
+    }
+
+    /// Returns the widgets which have had errors since Cnx started, one per
+    /// line, with the number of each kind and the last error.
+    pub(crate) fn report(&self) -> String {
+        let mut report = String::new();
+        for (idx, widget) in self.widgets.borrow().iter().enumerate() {
+            if widget.counts.is_empty() {
+                continue;
+            }
+            let counts: Vec<String> = widget
+                .counts
+                .iter()
+                .map(|(kind, count)| format!("{count} {kind}"))
+                .collect();
+            let _ = write!(report, "{idx} {}: {}", widget.name, counts.join(", "));
+            if let Some(last) = &widget.last {
+                let _ = write!(report, "; last: {last}");
+            }
+            report.push('\n');
+        }
+        if report.is_empty() {
+            report.push_str("No errors");
+        }
+        report
+    }
+}
+
+/// The stream of a widget's updates, with its errors turned into
+/// [`WidgetError`]s.
+pub(crate) type WidgetResults = Pin<Box<dyn Stream<Item = Result<Vec<Text>, WidgetError>>>>;
+
+/// Wraps the stream of the widget at `idx`, called `name`, turning its
+/// errors into [`WidgetError`]s and applying the `policies` to them.
+///
+/// Errors whose policy is to show or hide them are yielded as the texts to
+/// show instead, and the rest as errors, for the caller to log.
+pub(crate) fn apply_policies(
+    idx: usize,
+    name: &'static str,
+    mut stream: WidgetStream,
+    policies: Rc<HashMap<ErrorKind, Policy>>,
+    log: ErrorLog,
+) -> WidgetResults {
+    Box::pin(stream! {
+        // The attributes of the widget's last text, to show errors in.
+        let mut attr = None;
+        while let Some(result) = stream.next().await {
+            let err = match result {
+                Ok(texts) => {
+                    if let Some(text) = texts.first() {
+                        attr = Some(text.attr.clone());
+                    }
+                    yield Ok(texts);
+                    continue;
+                }
+                Err(err) => err,
+            };
+            let mut err = WidgetError::classify(err);
+            err.widget = Some(name);
+            log.record(idx, &err);
+            match (err.policy(&policies), &attr) {
+                (Policy::Display, Some(attr)) => {
+                    println!("Error from widget {idx}: {err}");
+                    let mut attr = attr.clone();
+                    attr.fg_color = Color::role(Role::Crit);
+                    yield Ok(vec![Text {
+                        attr,
+                        text: displayed(&err),
+                        stretch: false,
+                        markup: false,
+                        canvas: None,
+                    }]);
+                }
+                (Policy::Drop, _) => {
+                    println!("Error from widget {idx}, hiding it: {err}");
+                    yield Ok(Vec::new());
+                }
+                (Policy::Disable, attr) => {
+                    println!("Error from widget {idx}, disabling it: {err}");
+                    let texts = attr.clone().map(capabilities::not_available);
+                    yield Ok(texts.into_iter().collect());
+                    // Dropping the widget's stream stops its work.
+                    break;
+                }
+                // A widget which has never shown anything has no attributes
+                // to show the error in.
+                _ => yield Err(err),
+            }
+        }
+    })
+}
+
+// Returns the text shown in place of a widget's, for `err`.
+fn displayed(err: &WidgetError) -> String {
+    let message = err.source.to_string();
+    match message.char_indices().nth(MAX_DISPLAYED) {
+        Some((end, _)) => format!("⚠ {}…", &message[..end]),
+        None => format!("⚠ {message}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn classify() {
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let err = WidgetError::classify(anyhow::Error::from(denied).context("Failed to read"));
+        assert_eq!(err.kind, ErrorKind::Permission);
+        assert!(!err.retryable);
+        assert_eq!(err.to_string(), "Failed to read: denied (permission error)");
+
+        let err = WidgetError::classify("x".parse::<u32>().context("Bad reading").unwrap_err());
+        assert_eq!(err.kind, ErrorKind::Parse);
+
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "reset");
+        let err = WidgetError::classify(reset.into());
+        assert_eq!(err.kind, ErrorKind::Network);
+        assert!(err.retryable);
+
+        let config = WidgetError::new(ErrorKind::Config, anyhow!("No such interface"));
+        let err = WidgetError::classify(anyhow::Error::from(config).context("Initialising"));
+        assert_eq!(err.kind, ErrorKind::Config);
+
+        let err = WidgetError::classify(anyhow!("Something else"));
+        assert_eq!(err.kind, ErrorKind::Other);
+    }
+
+    #[test]
+    fn policies() {
+        let mut policies = HashMap::new();
+        let network = WidgetError::new(ErrorKind::Network, anyhow!("down"));
+        let config = WidgetError::new(ErrorKind::Config, anyhow!("wrong"));
+        let unavailable = WidgetError::new(ErrorKind::Unavailable, anyhow!("gone"));
+        let unsupported = WidgetError::new(ErrorKind::Unsupported, anyhow!("No XKB extension"));
+        assert_eq!(network.policy(&policies), Policy::Retry);
+        assert_eq!(config.policy(&policies), Policy::Display);
+        assert_eq!(unavailable.policy(&policies), Policy::Drop);
+        assert_eq!(unsupported.policy(&policies), Policy::Disable);
+        assert_eq!(
+            unavailable.with_retryable(true).policy(&policies),
+            Policy::Retry
+        );
+
+        policies.insert(ErrorKind::Network, Policy::Drop);
+        assert_eq!(network.policy(&policies), Policy::Drop);
+    }
+
+    #[test]
+    fn log() {
+        let log = ErrorLog::new(&["Clock", "Weather"]);
+        assert_eq!(log.report(), "No errors");
+        let mut err = WidgetError::new(ErrorKind::Network, anyhow!("timed out"));
+        log.record(1, &err);
+        err.kind = ErrorKind::Parse;
+        log.record(1, &err);
+        assert_eq!(
+            log.report(),
+            "1 Weather: 1 network, 1 parse; last: timed out\n"
+        );
+
+        let long = WidgetError::new(ErrorKind::Other, anyhow!("{}", "é".repeat(100)));
+        assert_eq!(displayed(&long).chars().count(), MAX_DISPLAYED + 3);
+    }
+}
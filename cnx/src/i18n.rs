@@ -0,0 +1,100 @@
+//! Translation of the labels shown by widgets.
+//!
+//! Widgets show some labels of their own, such as the days of the week in
+//! [`Clock`], the names of workspaces in [`Pager`], and status words such as
+//! "Charging" or "MPD disconnected" in the cnx-contrib widgets. These are in
+//! English, or whatever the window manager calls them, but can be translated
+//! by giving a map of translations to [`set_translations()`] before running
+//! Cnx.
+//!
+//! Labels which contain a value, such as `Cnx {} available`, are translated
+//! as a whole, with `{}` marking where the value goes.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::i18n;
+//! use std::collections::HashMap;
+//!
+//! i18n::set_translations(HashMap::from([
+//!     ("Mon".to_owned(), "Lun".to_owned()),
+//!     ("Charging".to_owned(), "En charge".to_owned()),
+//!     ("Cnx {} available".to_owned(), "Cnx {} disponible".to_owned()),
+//! ]));
+//!
+//! assert_eq!(i18n::tr("Charging"), "En charge");
+//! assert_eq!(i18n::tr("Full"), "Full");
+//! assert_eq!(i18n::tr_with("Cnx {} available", "0.4.0"), "Cnx 0.4.0 disponible");
+//! assert_eq!(i18n::tr_words("<b>Mon</b> 12:00"), "<b>Lun</b> 12:00");
+//! ```
+//!
+//! [`Clock`]: crate::widgets::Clock
+//! [`Pager`]: crate::widgets::Pager
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+static TRANSLATIONS: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// Sets the translations of labels, replacing any set before.
+///
+/// The keys are the labels as the widgets would show them, and the values
+/// their translations. Labels without a translation are shown unchanged.
+pub fn set_translations(translations: HashMap<String, String>) {
+    let mut lock = TRANSLATIONS.write().unwrap_or_else(|err| err.into_inner());
+    *lock = Some(translations);
+}
+
+/// Returns the translation of `label`, or `label` itself if it has none.
+pub fn tr(label: &str) -> String {
+    lookup(label).unwrap_or_else(|| label.to_owned())
+}
+
+/// Translates `label`, and then replaces the `{}` in it with `value`.
+pub fn tr_with(label: &str, value: &str) -> String {
+    tr(label).replacen("{}", value, 1)
+}
+
+/// Translates each word of `text` which has a translation, leaving the rest
+/// of it (including any Pango markup tags) unchanged.
+///
+/// This is for text which is made up of labels, such as the output of a
+/// date format like `%a %d %b`.
+pub fn tr_words(text: &str) -> String {
+    let lock = TRANSLATIONS.read().unwrap_or_else(|err| err.into_inner());
+    let translations = match lock.as_ref() {
+        Some(translations) if !translations.is_empty() => translations,
+        _ => return text.to_owned(),
+    };
+
+    let mut translated = String::with_capacity(text.len());
+    let mut word = String::new();
+    let mut in_tag = false;
+    let flush = |word: &mut String, translated: &mut String| {
+        match translations.get(word.as_str()) {
+            Some(translation) => translated.push_str(translation),
+            None => translated.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if !in_tag && c.is_alphabetic() {
+            word.push(c);
+            continue;
+        }
+        flush(&mut word, &mut translated);
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ => {}
+        }
+        translated.push(c);
+    }
+    flush(&mut word, &mut translated);
+    translated
+}
+
+fn lookup(label: &str) -> Option<String> {
+    let lock = TRANSLATIONS.read().unwrap_or_else(|err| err.into_inner());
+    lock.as_ref()?.get(label).cloned()
+}
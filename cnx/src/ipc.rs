@@ -0,0 +1,192 @@
+use anyhow::{anyhow, bail, Context, Error, Result};
+use futures::channel::{mpsc, oneshot};
+use std::fs;
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::task;
+
+// A command received on the control socket.
+//
+// Commands are sent as one line of text each, e.g. `click-through toggle`.
+// Each command is answered with a line of `ok` (optionally followed by some
+// output) or `error: <message>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    ClickThrough(Switch),
+    // Show only the widget at the given index, or every widget if `None`.
+    Zen(Option<usize>),
+    // Report the resources used by each widget.
+    Diagnostics,
+    // Report the errors each widget has had.
+    Errors,
+    // Any other command, for the widget which registered it.
+    Widget(String),
+}
+
+// The argument of commands which turn something on or off.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Switch {
+    On,
+    Off,
+    Toggle,
+}
+
+impl Switch {
+    pub fn apply(self, current: bool) -> bool {
+        match self {
+            Switch::On => true,
+            Switch::Off => false,
+            Switch::Toggle => !current,
+        }
+    }
+}
+
+impl FromStr for Switch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "on" => Ok(Switch::On),
+            "off" => Ok(Switch::Off),
+            "toggle" => Ok(Switch::Toggle),
+            _ => Err(anyhow!("Expected on, off or toggle, got: {}", s)),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        match (command, args.as_slice()) {
+            ("click-through", [switch]) => Ok(Command::ClickThrough(switch.parse()?)),
+            ("zen", ["off"]) => Ok(Command::Zen(None)),
+            ("zen", [idx]) => {
+                let idx = idx
+                    .parse()
+                    .map_err(|_| anyhow!("Expected a widget index or off, got: {}", idx))?;
+                Ok(Command::Zen(Some(idx)))
+            }
+            ("diagnostics", []) => Ok(Command::Diagnostics),
+            ("errors", []) => Ok(Command::Errors),
+            _ => Ok(Command::Widget(s.trim().to_owned())),
+        }
+    }
+}
+
+// A command along with the means to reply to it.
+pub type Request = (Command, oneshot::Sender<Result<String>>);
+
+// Binds a Unix socket at `path`, removing any stale socket left behind by a
+// previous instance first. Anything else at `path`, including the socket of
+// an instance which is still running, is left alone.
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            bail!("Not a socket: {}", path.display())
+        }
+        // Nothing listens on a stale socket, so connecting to it is refused.
+        Ok(_) => match std::os::unix::net::UnixStream::connect(path) {
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket: {}", path.display()))?,
+            Ok(_) => bail!("Socket is in use by another instance: {}", path.display()),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to connect to socket: {}", path.display()))
+            }
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+    UnixListener::bind(path).with_context(|| format!("Failed to bind socket: {}", path.display()))
+}
+
+// Listens on the Unix socket at `path`, returning a stream of the commands
+// received on it.
+//
+// This must be called from within a `task::LocalSet`.
+pub fn listen(path: &Path) -> Result<mpsc::UnboundedReceiver<Request>> {
+    let listener = bind(path)?;
+
+    let (sender, receiver) = mpsc::unbounded();
+    task::spawn_local(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    task::spawn_local(handle_client(stream, sender.clone()));
+                }
+                Err(err) => println!("Error accepting control socket connection: {err}"),
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+async fn handle_client(stream: UnixStream, sender: mpsc::UnboundedSender<Request>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match line.parse() {
+            Ok(command) => {
+                let (reply, response) = oneshot::channel();
+                if sender.unbounded_send((command, reply)).is_err() {
+                    return;
+                }
+                response
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("Command was not handled")))
+            }
+            Err(err) => Err(err),
+        };
+        let response = match result {
+            Ok(output) if output.is_empty() => "ok\n".to_owned(),
+            Ok(output) => format!("ok\n{}\n", output.trim_end()),
+            Err(err) => format!("error: {err}\n"),
+        };
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replaces_only_stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("cnx-ipc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cnx.sock");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // A file which isn't a socket is left alone.
+            fs::write(&path, "notes").unwrap();
+            assert!(bind(&path).is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "notes");
+            fs::remove_file(&path).unwrap();
+
+            // So is the socket of an instance which is still listening.
+            let listener = bind(&path).unwrap();
+            assert!(bind(&path).is_err());
+
+            // Once it stops, its socket is stale and is replaced.
+            drop(listener);
+            assert!(bind(&path).is_ok());
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
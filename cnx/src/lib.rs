@@ -58,21 +58,110 @@
 //!   the currently focused window ([`EWMH`]'s `_NEW_ACTIVE_WINDOW`).
 //! - [`crate::widgets::Pager`] — Shows the WM's workspaces/groups, highlighting whichever is
 //!   currently active. (Uses [`EWMH`]'s `_NET_DESKTOP_NAMES`,
-//!   `_NET_NUMBER_OF_DESKTOPS` and `_NET_CURRENT_DESKTOP`).
+//!   `_NET_NUMBER_OF_DESKTOPS` and `_NET_CURRENT_DESKTOP`). Workspaces
+//!   can be renamed by right clicking them.
 //! - [`crate::widgets::Clock`] — Shows the time.
+//! - [`crate::widgets::AnalogClock`] — Shows the time on a clock face.
+//! - [`crate::widgets::Polled`] — Shows the texts returned by an async closure,
+//!   called on an interval, for writing small widgets of your own.
 //!
 //! The cnx-contrib crate contains additional widgets:
 //!
-//! - **Sensors** — Periodically parses and displays the output of the
-//!   sensors provided by the system.
+//! - **Sensors** — Shows the temperatures of the system's sensors, from hwmon
+//!   or lm_sensors, turning red when they reach their critical temperature.
 //! - **Volume** - Shows the current volume/mute status of the default output
 //!   device.
-//! - **Battery** - Shows the remaining battery and charge status.
-//! - **Wireless** - Shows the wireless strength of your current network.
+//! - **Battery** - Shows the remaining battery and charge status, combining
+//!   multiple batteries.
+//! - **Wireless** - Shows the SSID and signal strength of your current network.
 //! - **CPU** - Shows the current CPU consumption
 //! - **Weather** - Shows the Weather information of your location
-//! - **Disk Usage** - Show the current usage of your monted filesystem
+//! - **Disk Usage** - Show the current usage of your mounted filesystems
 //! - **LeftWM** - Shows the monitors and tags from LeftWM
+//! - **WM Layout** - Shows the layout of the focused workspace on i3/sway,
+//!   bspwm and herbstluftwm
+//! - **WM Mode** - Shows the active i3/sway binding mode or Hyprland submap
+//! - **Scratchpad** - Shows the number of windows in the i3/sway scratchpad
+//!   (or iconified windows), with click to cycle through them
+//! - **Window Count** - Shows the number of tiled and floating windows on the
+//!   current workspace
+//! - **Ring Gauge** - Shows a percentage, e.g. the battery's charge, as a ring
+//! - **MPD** - Shows the song that MPD is playing
+//! - **Update Check** - Shows a badge when a newer version of Cnx is available
+//! - **Greeting** - Shows a greeting or fortune for a while after the bar starts
+//! - **Backlight** - Shows the screen's brightness, with scroll to change it
+//! - **Keyboard Layout** - Shows the active keyboard layout, with click to switch it
+//! - **Charge Limit** - Shows whether the battery's charge is capped, e.g. at 80%,
+//!   with click to toggle it
+//! - **ACPI** - Shows the lid and AC adapter state and special button presses, as
+//!   reported by acpid
+//! - **Login Session** - Shows the seat, other logged in sessions and whether the
+//!   session is locked, with click to lock it
+//! - **Network Link** - Shows whether a network interface is up and plugged in,
+//!   and its IP address, with scroll to cycle through several interfaces and
+//!   click to list all of its addresses
+//! - **VPN** - Shows a lock and the names of the WireGuard and TUN/TAP tunnels
+//!   which are up
+//! - **Ping** - Shows the round-trip time to a host, over ICMP or TCP, and the
+//!   recent packet loss
+//! - **GPU** - Shows the utilization, video memory and temperature of an NVIDIA
+//!   (through NVML) or AMD GPU
+//! - **Weather Report** - Shows the weather from wttr.in or a METAR report,
+//!   without needing an API key
+//! - **Air Quality** - Shows the air quality index from WAQI or OpenAQ in the
+//!   color of its category, marking it when the measurement is stale
+//! - **GitHub Notifications** - Shows how many GitHub notifications are unread,
+//!   optionally by reason, with click to open them
+//! - **IP Address** - Shows the IP address of an interface or of the default
+//!   route, optionally obfuscated for streaming
+//! - **Host Badge** - Shows the hostname or a label for the host's role, in a
+//!   color of the host's own
+//! - **Clock Sync** - Shows whether the clock is synchronized by chrony or
+//!   systemd-timesyncd, and its offset, warning when it is too far off
+//! - **Mail** - Shows how many messages are unread, from a notmuch query or
+//!   Maildirs' `new/` directories
+//! - **PipeWire** - Shows the quantum and sample rate the PipeWire graph is
+//!   running at, and its latency
+//! - **Feeds** - Shows the newest unseen headline from RSS or Atom feeds, or
+//!   how many there are, remembering which have been seen
+//! - **Transport** - Shows whether JACK's or Ardour's transport is rolling or
+//!   recording, and its tempo and position
+//! - **Package updates** - Shows how many package updates are pending, from
+//!   pacman, apt or dnf
+//! - **App volume** - Shows the volume of the audio the focused window is
+//!   playing, and scrolls to change just that application's volume
+//! - **Pomodoro** - A Pomodoro timer, which can start and stop a Taskwarrior
+//!   task while working on it
+//! - **Systemd units** - Shows which of a list of systemd units have failed or
+//!   are inactive
+//! - **Focused process** - Shows the CPU and memory used by the focused window's
+//!   process
+//! - **Kubernetes** - Shows kubectl's current context and namespace, in warning
+//!   colors for production clusters
+//! - **Zombies** - Shows how many zombie processes there are, while there are
+//!   any
+//! - **Bluetooth** - Shows whether Bluetooth is on and the connected devices with
+//!   their batteries, with click to toggle power
+//! - **FD usage** - Warns when the user's inotify watches, or a process's file
+//!   descriptors, are nearly used up
+//! - **Do not disturb** - Shows whether dunst's notifications are paused and how
+//!   many are waiting, with click to pause or unpause them
+//! - **Timer** - Counts down for a duration or to a time of day, started by
+//!   clicks or the control socket, and flashes when it reaches zero
+//! - **Stopwatch** - Shows the time since it was last reset, by a click or by
+//!   another program writing a timestamp file, e.g. since the last backup
+//! - **Calendar** - Shows the next event in iCalendar files, e.g. synced by
+//!   vdirsyncer, with a countdown until it starts
+//! - **Fifo** - Shows the last line written to a named pipe, so that scripts
+//!   can push text into the bar
+//! - **Stdin** - Shows the last line read from standard input, so that the
+//!   output of another program can be piped into the bar
+//! - **HttpJson** - Shows a value picked out of a JSON API with a JSON Pointer,
+//!   polled on an interval, e.g. from a home automation hub or CI
+//! - **WebSocket** - Shows the messages received over a WebSocket, e.g. from
+//!   Home Assistant or a ticker feed, reconnecting with backoff
+//! - **DbusProperty** - Shows any property of an object on DBus as it changes,
+//!   e.g. NetworkManager's connectivity, rendered through a closure
 //!
 //! The Sensors, Volume and Battery widgets require platform
 //! support. They currently support Linux (see dependencies below) and OpenBSD.
@@ -84,6 +173,7 @@
 //! these system libraries:
 //!
 //!  - `xcb-util`: `xcb-ewmh` / `xcb-icccm` / `xcb-keysyms`
+//!  - `xcb-shape`
 //!  - `x11-xcb`
 //!  - `pango`
 //!  - `cairo`
@@ -92,8 +182,74 @@
 //! Some widgets have additional dependencies on Linux:
 //!
 //!  - **Volume** widget relies on `alsa-lib`
-//!  - **Sensors** widget relies on [`lm_sensors`] being installed.
-//!  - **Wireless** widget relies on `libiw-dev`.
+//!  - **Sensors** widget relies on [`lm_sensors`] being installed, if used
+//!    with its `LmSensors` backend.
+//!
+//! # Control socket
+//!
+//! If enabled with [`Cnx::with_control_socket()`], Cnx listens for commands on
+//! a Unix socket, one per line. Each command is answered with a line of `ok`
+//! or `error: <message>`. For example:
+//!
+//! ```sh
+//! echo "click-through toggle" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/cnx.sock
+//! ```
+//!
+//! The following commands are supported:
+//!
+//! - `click-through on|off|toggle` — Whether clicks on the bar pass through
+//!   to the windows underneath it. See [`Cnx::with_click_through()`].
+//! - `zen <index>|off` — Zen mode: show only the widget at the given index
+//!   (counting from 0 in the order they were added), in the middle of the
+//!   bar, e.g. just the clock during a presentation. `zen off` shows every
+//!   widget again.
+//! - `diagnostics` — The CPU time and allocations used by each widget since
+//!   Cnx started, if enabled with [`Cnx::with_diagnostics()`]. See
+//!   [`diagnostics`].
+//! - `errors` — How many errors of each kind each widget has had, and the
+//!   last of them. See [`error`].
+//!
+//! Any other command is passed to the widget which handles it, e.g.
+//! `timer start 25m tea`. See [`commands`].
+//!
+//! # Root window name
+//!
+//! If enabled with [`Cnx::with_root_window_name()`], Cnx doesn't show a bar,
+//! but sets the name of the root window to the text of its widgets, so that
+//! the widgets can be shown in the native bar of e.g. dwm, in place of
+//! `xsetroot -name`.
+//!
+//! # tmux status line
+//!
+//! Similarly, if enabled with [`Cnx::with_tmux_status_line()`], Cnx doesn't
+//! show a bar, but shows the text of its widgets in the status line of tmux,
+//! in the colors of their attributes, e.g. to use the same widgets in a
+//! terminal. The status line is either written to a file, for tmux to read
+//! with `#(cat <file>)`, or set as a tmux option such as `status-right`; see
+//! [`TmuxOutput`].
+//!
+//! # External renderers
+//!
+//! With the `serde` feature, Cnx can leave drawing the bar to another
+//! program, e.g. to show its widgets in eww or a web page. If enabled with
+//! [`Cnx::with_external_renderer()`], Cnx doesn't show a bar, but sends the
+//! output of its widgets to every program connected to a Unix socket.
+//!
+//! Each message is a line of JSON with the `version` of the protocol and a
+//! `type`. The first message on each connection is a `hello` with the number
+//! of widgets, followed by an `update` with the content of each widget, and
+//! then another `update` whenever a widget changes:
+//!
+//! ```json
+//! {"version":1,"type":"hello","widgets":1}
+//! {"version":1,"type":"update","widget":0,"texts":[{"attr":{"font":"Envy Code R 21","fg_color":"#FFFFFF","bg_color":null,"padding":{"left":8.0,"right":8.0,"top":0.0,"bottom":0.0}},"text":"12:34","stretch":false,"markup":true}]}
+//! ```
+//!
+//! See [`renderer`] for the types of these messages, which renderers written
+//! in Rust can use to parse them.
+//!
+//! With the `web` feature, [`Cnx::with_web_view()`] uses the same protocol to
+//! serve a read-only view of the bar to web browsers.
 //!
 //! # Creating new widgets
 //!
@@ -116,23 +272,70 @@
 //! [`cnx-contrib`]: https://github.com/mjkillough/cnx/tree/master/cnx-contrib
 
 #![recursion_limit = "256"]
+#![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod alerts;
+pub mod astronomy;
 mod bar;
+pub mod bus;
+pub mod capabilities;
+pub mod commands;
+pub mod diagnostics;
+pub mod error;
+pub mod i18n;
+mod ipc;
+pub mod prompt;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod renderer;
+mod root_name;
+pub mod services;
+#[cfg(feature = "soak")]
+mod soak;
+pub mod teardown;
 pub mod text;
+mod tmux;
+pub mod units;
+pub mod untrusted;
+#[cfg(feature = "web")]
+mod web;
 pub mod widgets;
-mod xcb;
+/// Helpers for widgets that listen to events from the X server.
+pub mod xcb;
 
 use anyhow::Result;
+use futures::channel::mpsc;
+use std::collections::HashMap;
+use std::future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::rc::Rc;
 use tokio::runtime::Runtime;
 use tokio::task;
-use tokio_stream::{StreamExt, StreamMap};
+use tokio_stream::{self as stream, Stream, StreamExt, StreamMap};
 
+use crate::alerts::Rule;
 use crate::bar::Bar;
+use crate::capabilities::{Capabilities, Capability};
+use crate::commands::Commands;
+use crate::diagnostics::Diagnostics;
+use crate::error::{ErrorKind, ErrorLog, Policy, WidgetResults};
+use crate::ipc::Command;
+use crate::root_name::RootName;
+use crate::services::Services;
+use crate::text::{Text, Theme};
+use crate::tmux::Tmux;
+use crate::units::Units;
 use crate::widgets::Widget;
 use crate::xcb::XcbEventStream;
 
+pub use bar::Layer;
 pub use bar::Offset;
 pub use bar::Position;
+pub use tmux::TmuxOutput;
+
+/// The version of Cnx, e.g. for widgets checking for updates.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// The main object, used to instantiate an instance of Cnx.
 ///
@@ -147,12 +350,58 @@ pub struct Cnx {
     position: Position,
     /// The list of widgets attached to the Cnx bar
     widgets: Vec<Box<dyn Widget>>,
+    /// The names of the widgets' types, for diagnostics
+    names: Vec<&'static str>,
+    /// The scale of the fonts in each row of the bar, from the top
+    row_scales: Vec<f64>,
+    /// The row of each widget
+    widget_rows: Vec<usize>,
+    /// The (optional) number of columns of a fullscreen dashboard to show
+    /// instead of the bar
+    dashboard: Option<usize>,
+    /// The services shared between the widgets
+    services: Services,
+    /// The rules which raise alerts over the data on the bus
+    alerts: Vec<Rule>,
     /// The (x,y) offset of the bar
     /// It can be used in order to run multiple bars in a multi-monitor setup
     offset: Offset,
     /// The (optional) width of the bar
     /// It can be used in order to run multiple bars in a multi-monitor setup
     width: Option<u16>,
+    /// How the bar is stacked relative to other windows
+    layer: Layer,
+    /// Whether the bar is stacked next to other docks on the same edge
+    stack_on_docks: bool,
+    /// Whether clicks pass through the bar to the windows underneath
+    click_through: bool,
+    /// Whether only clicks on interactive widgets are received
+    interactive_only: bool,
+    /// The (optional) path of the control socket
+    control_socket: Option<PathBuf>,
+    /// The (optional) theme to use all the time, unless another applies
+    theme: Option<Theme>,
+    /// The (optional) theme to use at night, and the latitude and longitude
+    /// to compute sunrise and sunset for
+    night_theme: Option<(Theme, f64, f64)>,
+    /// Whether to measure the resources used by each widget
+    diagnostics: bool,
+    /// The policies for widgets' errors, for the kinds whose default policy
+    /// was changed
+    error_policies: HashMap<ErrorKind, Policy>,
+    /// The (optional) separator between widgets when showing them as the
+    /// root window's name, instead of showing the bar
+    root_window_name: Option<String>,
+    /// Where to put the tmux status line, if showing the widgets in it
+    /// instead of showing the bar
+    tmux_status_line: Option<TmuxOutput>,
+    /// The (optional) path of the socket to send widget output to external
+    /// renderers on, instead of showing the bar
+    #[cfg(feature = "serde")]
+    external_renderer: Option<PathBuf>,
+    /// The (optional) address to serve a web view of the bar on
+    #[cfg(feature = "web")]
+    web_view: Option<std::net::SocketAddr>,
 }
 
 impl Cnx {
@@ -167,8 +416,29 @@ impl Cnx {
         Self {
             position,
             widgets,
+            names: Vec::new(),
+            row_scales: vec![1.0],
+            widget_rows: Vec::new(),
+            dashboard: None,
+            services: Services::default(),
+            alerts: Vec::new(),
             offset: Offset::default(),
             width: None,
+            layer: Layer::Normal,
+            stack_on_docks: false,
+            click_through: false,
+            interactive_only: false,
+            control_socket: None,
+            theme: None,
+            night_theme: None,
+            diagnostics: false,
+            error_policies: HashMap::new(),
+            root_window_name: None,
+            tmux_status_line: None,
+            #[cfg(feature = "serde")]
+            external_renderer: None,
+            #[cfg(feature = "web")]
+            web_view: None,
         }
     }
 
@@ -197,54 +467,525 @@ impl Cnx {
         }
     }
 
+    /// Returns a new instance of `Cnx` whose bar is stacked in `layer`, i.e.
+    /// always above or below other windows, or as the window manager stacks
+    /// docks, which is the default.
+    ///
+    /// The bar is restacked as other windows enter or leave fullscreen, for
+    /// window managers which otherwise leave it underneath them. This has no
+    /// effect on a dashboard.
+    pub fn with_layer(self, layer: Layer) -> Self {
+        Self { layer, ..self }
+    }
+
+    /// Returns a new instance of `Cnx` whose bar is stacked next to any
+    /// docks already on the same edge of the screen when it starts, e.g.
+    /// polybar or another Cnx bar, rather than overlapping them.
+    ///
+    /// At the top of the screen, the bar is placed below the space the docks
+    /// reserve, and at the bottom, above it, with any offset from
+    /// [`with_offset()`] added. The bar reserves the space of the docks and
+    /// its own, so that windows aren't placed under either.
+    ///
+    /// [`with_offset()`]: #method.with_offset
+    pub fn with_dock_stacking(self, stack_on_docks: bool) -> Self {
+        Self {
+            stack_on_docks,
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` with click-through enabled or disabled.
+    ///
+    /// When enabled, the bar doesn't receive any clicks, and they pass through
+    /// to the windows underneath it instead. This is useful for a purely
+    /// informational bar which overlaps other windows. It can be toggled at
+    /// runtime using the `click-through` command of the control socket.
+    ///
+    /// This needs the X server's SHAPE extension, without which it is
+    /// disabled.
+    pub fn with_click_through(self, click_through: bool) -> Self {
+        Self {
+            click_through,
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which only receives clicks on
+    /// interactive widgets.
+    ///
+    /// When enabled, clicks on widgets which handle them are received as
+    /// usual, but clicks anywhere else on the bar pass through to the windows
+    /// underneath it. This is useful for a bar which overlaps other windows,
+    /// but still has some clickable widgets. A widget is interactive if it
+    /// keeps hold of the [`ClickStream`] passed to
+    /// [`Widget::into_stream_with_clicks()`].
+    ///
+    /// Like click-through, this needs the X server's SHAPE extension.
+    ///
+    /// [`ClickStream`]: widgets::ClickStream
+    /// [`Widget::into_stream_with_clicks()`]: widgets::Widget::into_stream_with_clicks
+    pub fn with_interactive_only(self, interactive_only: bool) -> Self {
+        Self {
+            interactive_only,
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which listens for commands on a Unix
+    /// socket at the given `path`.
+    ///
+    /// See the [crate documentation](crate#control-socket) for the commands
+    /// which are supported.
+    pub fn with_control_socket<P: Into<PathBuf>>(self, path: P) -> Self {
+        Self {
+            control_socket: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which adjusts the colors of all widgets
+    /// with `theme`, e.g. to use a [`Palette`] which is safe for people with
+    /// color blindness.
+    ///
+    /// The night theme and the themes of alerts take its place while they
+    /// apply, but keep its palette unless they have their own.
+    ///
+    /// [`Palette`]: text::Palette
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self {
+            theme: Some(theme),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` whose widgets show values in `units`,
+    /// e.g. temperatures in Fahrenheit or times with a 24-hour clock.
+    ///
+    /// See the [`units`] module for the widgets' side of this.
+    pub fn with_units(self, units: Units) -> Self {
+        self.services.insert(units);
+        self
+    }
+
+    /// Returns a new instance of `Cnx` which adjusts the colors of all widgets
+    /// with `theme` between sunset and sunrise.
+    ///
+    /// The times of sunset and sunrise are computed by the [`astronomy`]
+    /// module for the given `latitude` and `longitude`, in degrees north and
+    /// east, and the bar is redrawn as each one passes.
+    pub fn with_night_theme(self, theme: Theme, latitude: f64, longitude: f64) -> Self {
+        Self {
+            night_theme: Some((theme, latitude, longitude)),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which measures the CPU time used by
+    /// each widget, and its allocations if the
+    /// [`CountingAllocator`](diagnostics::CountingAllocator) is installed.
+    ///
+    /// The widgets which used the most are logged every minute. See the
+    /// [`diagnostics`] module for details.
+    pub fn with_diagnostics(self, diagnostics: bool) -> Self {
+        Self {
+            diagnostics,
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which applies `policy` to the widgets'
+    /// errors of the given `kind`, e.g. to hide widgets whose network
+    /// requests fail rather than keep showing their last text.
+    ///
+    /// See the [`error`] module for the default policies.
+    pub fn with_error_policy(mut self, kind: ErrorKind, policy: Policy) -> Self {
+        self.error_policies.insert(kind, policy);
+        self
+    }
+
+    /// Returns a new instance of `Cnx` which shows its widgets in a
+    /// fullscreen grid with `columns` columns, rather than in a bar, e.g. to
+    /// turn a spare monitor or a Raspberry Pi into a status display.
+    ///
+    /// The widgets fill the grid a row at a time, from the left, in the order
+    /// they were added, and each is centered in its cell. Fonts are still
+    /// scaled as set with [`add_row()`], which is useful to make the text big
+    /// enough to read from afar.
+    ///
+    /// [`add_row()`]: #method.add_row
+    pub fn with_dashboard(self, columns: usize) -> Self {
+        Self {
+            dashboard: Some(columns),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which shows the text of its widgets
+    /// as the name of the root window, rather than showing the bar itself.
+    ///
+    /// This is for window managers such as dwm, whose own bar shows the
+    /// root window's name, as set by e.g. `xsetroot -name`. The text of the
+    /// widgets which show anything is joined with `separator`, without any
+    /// markup, and the name is set each time a widget changes.
+    pub fn with_root_window_name<S: Into<String>>(self, separator: S) -> Self {
+        Self {
+            root_window_name: Some(separator.into()),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which shows the text of its widgets
+    /// in tmux's status line, rather than showing the bar itself.
+    ///
+    /// Each text is shown without markup, in the foreground and background
+    /// colors of its attributes, with a space for its padding, and the
+    /// status line is output to `output` each time a widget changes.
+    pub fn with_tmux_status_line(self, output: TmuxOutput) -> Self {
+        Self {
+            tmux_status_line: Some(output),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which sends the output of its widgets
+    /// to external renderers connected to a Unix socket at the given `path`,
+    /// rather than showing the bar itself.
+    ///
+    /// See the [crate documentation](crate#external-renderers) for the
+    /// protocol.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn with_external_renderer<P: Into<PathBuf>>(self, path: P) -> Self {
+        Self {
+            external_renderer: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Returns a new instance of `Cnx` which serves a read-only web view of
+    /// the bar on the given address.
+    ///
+    /// The view is a page which updates live as the widgets change, e.g. to
+    /// check on the machine from a phone. It shows the widgets' text, but not
+    /// any custom drawing, and can't be clicked.
+    ///
+    /// There is no authentication, so anyone who can reach the address can see
+    /// the bar. Use a loopback address such as `127.0.0.1:8080` unless you
+    /// want it to be reachable from the rest of your network.
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    pub fn with_web_view(self, addr: std::net::SocketAddr) -> Self {
+        Self {
+            web_view: Some(addr),
+            ..self
+        }
+    }
+
     /// Adds a widget to the `Cnx` instance.
     ///
     /// Takes ownership of the [`Widget`] and adds it to the Cnx instance to
-    /// the right of any existing widgets.
+    /// the right of any existing widgets. The widget is given the
+    /// [`services()`] shared between the widgets.
     ///
     /// [`Widget`]: widgets/trait.Widget.html
-    pub fn add_widget<W>(&mut self, widget: W)
+    /// [`services()`]: #method.services
+    pub fn add_widget<W>(&mut self, mut widget: W)
     where
         W: Widget + 'static,
     {
+        widget.use_services(&self.services);
+        // Strip the module path and any type parameters, e.g. of
+        // `cnx::widgets::Clock`.
+        let name = std::any::type_name::<W>();
+        let name = name.split('<').next().unwrap_or(name);
+        self.names.push(name.rsplit("::").next().unwrap_or(name));
         self.widgets.push(Box::new(widget));
+        self.widget_rows.push(self.row_scales.len() - 1);
+    }
+
+    /// Starts a new row of the bar, below the existing ones, in which the
+    /// fonts, padding and drawings of the widgets are scaled by `scale`.
+    ///
+    /// Widgets added after this are put in the new row. The bar starts with
+    /// a single row, at a scale of `1.0`; if no widgets have been added to
+    /// the last row yet, its scale is changed instead of adding a row. Each
+    /// row is as tall as its tallest widget, and is laid out on its own,
+    /// e.g. with its own stretch widgets.
+    ///
+    /// This makes for a "dashboard" bar, e.g. on a wall-mounted display,
+    /// with a large clock above a row of normal widgets:
+    ///
+    /// ```
+    /// # use cnx::text::{Attributes, Color, Font, Padding};
+    /// # use cnx::widgets::{ActiveWindowTitle, Clock};
+    /// # use cnx::{Cnx, Position};
+    /// # let attr = Attributes {
+    /// #     font: Font::new("SourceCodePro 21"),
+    /// #     fg_color: Color::white(),
+    /// #     bg_color: None,
+    /// #     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+    /// # };
+    /// let mut cnx = Cnx::new(Position::Top);
+    /// cnx.add_row(4.0);
+    /// cnx.add_widget(Clock::new(attr.clone(), None));
+    /// cnx.add_row(1.0);
+    /// cnx.add_widget(ActiveWindowTitle::new(attr.clone()));
+    /// ```
+    pub fn add_row(&mut self, scale: f64) {
+        let last = self.row_scales.len() - 1;
+        if self.widget_rows.last() == Some(&last) {
+            self.row_scales.push(scale);
+        } else {
+            self.row_scales[last] = scale;
+        }
+    }
+
+    /// Adds a rule which raises an alert over the data widgets publish on
+    /// the [`bus()`].
+    ///
+    /// See the [`alerts`] module for an example.
+    ///
+    /// [`bus()`]: #method.bus
+    pub fn add_alert(&mut self, rule: Rule) {
+        self.alerts.push(rule);
+    }
+
+    /// Returns the registry of services shared between the widgets, e.g. to
+    /// add a service of your own before adding the widgets which use it.
+    pub fn services(&self) -> Services {
+        self.services.clone()
+    }
+
+    /// Returns the [`Bus`](bus::Bus) on which the widgets share data with
+    /// each other, e.g. to publish data of your own or to subscribe to what a
+    /// widget publishes.
+    pub fn bus(&self) -> bus::Bus {
+        bus::Bus::shared(&self.services)
+    }
+
+    /// Soak tests the widgets, rather than running the bar, to check them
+    /// for memory leaks.
+    ///
+    /// Each widget's stream is driven until it has yielded `iterations`
+    /// updates, with Tokio's clock skipping ahead whenever the widgets are
+    /// waiting for a timer, so that widgets which update e.g. every minute
+    /// are driven thousands of times in a few seconds. The memory each
+    /// widget held on to after warming up is then printed, and an error is
+    /// returned if any of them kept growing.
+    ///
+    /// This needs the
+    /// [`CountingAllocator`](diagnostics::CountingAllocator) to be installed
+    /// as the global allocator. Widgets which update on events rather than
+    /// timers, such as those showing X11 properties, may only be driven a
+    /// few times.
+    #[cfg(feature = "soak")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "soak")))]
+    pub fn soak_test(self, iterations: u32) -> Result<()> {
+        soak::run(self.widgets, self.names, iterations)
     }
 
     /// Runs the Cnx instance.
     ///
     /// This method takes ownership of the Cnx instance and runs it until either
-    /// the process is terminated, or an internal error is returned.
+    /// it receives `SIGTERM` or `SIGINT`, or an internal error is returned.
+    ///
+    /// As it stops, the widgets' streams and the tasks they spawned are
+    /// dropped, and blocking work is told to stop. See the [`teardown`]
+    /// module for what widgets are expected to do.
     pub fn run(self) -> Result<()> {
+        let shutdown = teardown::shutdown(&self.services);
         // Use a single-threaded event loop. We aren't interested in
         // performance too much, so don't mind if we block the loop
         // occasionally. We are using events to get woken up as
         // infrequently as possible (to save battery).
         let rt = Runtime::new()?;
         let local = task::LocalSet::new();
-        local.block_on(&rt, self.run_inner())?;
-        Ok(())
+        let result = local.block_on(&rt, async {
+            tokio::select! {
+                result = self.run_inner() => result,
+                result = teardown::signalled() => result,
+            }
+        });
+        // Tell blocking work to stop before dropping the widgets' tasks, and
+        // detach any blocking threads which don't return in time, rather
+        // than waiting for them forever.
+        shutdown.cancel();
+        {
+            let _runtime = rt.enter();
+            drop(local);
+        }
+        rt.shutdown_timeout(teardown::SHUTDOWN_TIMEOUT);
+        result
     }
 
     async fn run_inner(self) -> Result<()> {
-        let mut bar = Bar::new(self.position, self.width, self.offset)?;
+        #[cfg(feature = "serde")]
+        if let Some(path) = &self.external_renderer {
+            let path = path.clone();
+            return self.run_external(&path).await;
+        }
+        if let Some(separator) = &self.root_window_name {
+            let mut root_name = RootName::new(self.widgets.len(), separator.clone())?;
+            return self
+                .run_without_bar(move |idx, texts| root_name.update(idx, texts))
+                .await;
+        }
+        if let Some(output) = &self.tmux_status_line {
+            let mut tmux = Tmux::new(self.widgets.len(), output.clone());
+            return self
+                .run_without_bar(move |idx, texts| tmux.update(idx, texts))
+                .await;
+        }
+
+        let mut bar = Bar::new(self.position, self.width, self.offset, self.row_scales)?;
+        if let Some(columns) = self.dashboard {
+            bar.set_dashboard(columns)?;
+        }
+        bar.set_layer(self.layer)?;
+        if self.stack_on_docks && self.dashboard.is_none() {
+            bar.stack_on_docks()?;
+        }
+        // Letting clicks through needs the SHAPE extension, which is only
+        // looked for if it is wanted.
+        let capabilities = Capabilities::shared(&self.services);
+        let can_shape = move || capabilities.is_available(Capability::Shape);
+        bar.set_click_through(self.click_through && can_shape());
+        bar.set_theme(self.theme.clone())?;
+
+        // If there's no control socket, the sender is dropped immediately and
+        // the stream of requests simply ends.
+        let mut requests = match &self.control_socket {
+            Some(path) => ipc::listen(path)?,
+            None => mpsc::unbounded().1,
+        };
+        let commands = Commands::shared(&self.services);
+
+        #[cfg(feature = "web")]
+        let web_view = self
+            .web_view
+            .map(|addr| web::serve(addr, self.widgets.len()))
+            .transpose()?;
+
+        let names = self.names.clone();
+        let errors = ErrorLog::new(&names);
+        let policies = Rc::new(self.error_policies);
+        let diagnostics = self.diagnostics.then(|| Diagnostics::start(self.names));
 
         let mut widgets = StreamMap::with_capacity(self.widgets.len());
-        for widget in self.widgets {
-            let idx = bar.add_content(Vec::new())?;
-            widgets.insert(idx, widget.into_stream()?);
+        let mut clicks = Vec::with_capacity(self.widgets.len());
+        for ((widget, row), name) in self.widgets.into_iter().zip(self.widget_rows).zip(names) {
+            let idx = bar.add_content(row, Vec::new())?;
+            let (sender, receiver) = mpsc::unbounded();
+            clicks.push(sender);
+            let stream = widget.into_stream_with_clicks(Box::pin(receiver))?;
+            let stream = match &diagnostics {
+                Some(diagnostics) => diagnostics.measure(idx, stream),
+                None => stream,
+            };
+            // Pass a copy of each update to the web view.
+            #[cfg(feature = "web")]
+            let stream: widgets::WidgetStream = match &web_view {
+                Some(renderers) => {
+                    let renderers = renderers.clone();
+                    Box::pin(stream.map(move |result| {
+                        if let Ok(texts) = &result {
+                            if let Err(err) = renderers.borrow_mut().update(idx, texts.clone()) {
+                                println!("Error updating web view: {err}");
+                            }
+                        }
+                        result
+                    }))
+                }
+                None => stream,
+            };
+            let stream = error::apply_policies(idx, name, stream, policies.clone(), errors.clone());
+            widgets.insert(idx, stream);
         }
 
+        // Widgets which ignore clicks have dropped their receivers by now.
+        let interactive = clicks.iter().map(|sender| !sender.is_closed()).collect();
+        bar.set_interactive_only(self.interactive_only && can_shape(), interactive);
+
+        // Switch to the night theme, if any, at sunset, and back at sunrise.
+        let base_theme = self.theme;
+        let night_theme = self.night_theme.as_ref().map(|(theme, _, _)| theme.clone());
+        let mut night_changes: Pin<Box<dyn Stream<Item = bool>>> = match self.night_theme {
+            Some((_, latitude, longitude)) => {
+                Box::pin(astronomy::night_changes(latitude, longitude))
+            }
+            None => Box::pin(stream::pending()),
+        };
+        let mut night = false;
+
+        // Flash the bar with the theme of any alert which is firing, in
+        // preference to the night theme.
+        let mut alerts = Box::pin(alerts::evaluate(
+            self.alerts,
+            &bus::Bus::shared(&self.services),
+        ));
+        let mut flash = None;
+
         let mut event_stream = XcbEventStream::new(bar.connection().clone())?;
         task::spawn_local(async move {
+            let theme = |flash: &Option<Theme>, night: bool| {
+                flash
+                    .clone()
+                    .or_else(|| night_theme.clone().filter(|_| night))
+                    .map(|theme| theme.or_palette_of(base_theme.as_ref()))
+                    .or_else(|| base_theme.clone())
+            };
             loop {
                 tokio::select! {
-                    // Pass each XCB event to the Bar.
+                    // Pass each XCB event to the Bar, and any resulting clicks
+                    // on to the widget that was clicked. A widget which
+                    // ignores clicks will have dropped its receiver, which
+                    // is fine.
                     Some(event) = event_stream.next() => {
-                        if let Err(err) = bar.process_event(event) {
-                            println!("Error processing XCB event: {err}");
+                        match bar.process_event(event) {
+                            Err(err) => println!("Error processing XCB event: {err}"),
+                            Ok(Some((idx, click))) => {
+                                let _ = clicks[idx].unbounded_send(click);
+                            }
+                            Ok(None) => {}
                         }
                     },
 
+                    // Handle commands from the control socket.
+                    Some((command, reply)) = requests.next() => {
+                        let result = match command {
+                            Command::ClickThrough(_) if !can_shape() => {
+                                Err(anyhow::anyhow!("Click-through needs the SHAPE extension"))
+                            }
+                            Command::ClickThrough(switch) => {
+                                bar.set_click_through(switch.apply(bar.click_through()));
+                                Ok(String::new())
+                            }
+                            Command::Zen(zen) => bar.set_zen(zen).map(|_| String::new()),
+                            Command::Diagnostics => diagnostics
+                                .as_ref()
+                                .map(Diagnostics::report)
+                                .ok_or_else(|| anyhow::anyhow!("Diagnostics are not enabled")),
+                            Command::Errors => Ok(errors.report()),
+                            Command::Widget(command) => commands.dispatch(&command),
+                        };
+                        let _ = reply.send(result);
+                    }
+
+                    Some(changed) = night_changes.next() => {
+                        night = changed;
+                        if let Err(err) = bar.set_theme(theme(&flash, night)) {
+                            println!("Error switching theme: {err}");
+                        }
+                    }
+
+                    Some(changed) = alerts.next() => {
+                        flash = changed;
+                        if let Err(err) = bar.set_theme(theme(&flash, night)) {
+                            println!("Error switching theme: {err}");
+                        }
+                    }
+
                     // Each time a widget yields new values, pass to the bar.
                     // Ignore (but log) any errors from widgets.
                     Some((idx, result)) = widgets.next() => {
@@ -264,4 +1005,120 @@ impl Cnx {
 
         Ok(())
     }
+
+    // Runs without a bar, sending the output of the widgets to external
+    // renderers instead.
+    #[cfg(feature = "serde")]
+    async fn run_external(self, path: &std::path::Path) -> Result<()> {
+        let mut clients = renderer::listen(path)?;
+        let renderers = renderer::Renderers::new(self.widgets.len());
+        let renderers = Rc::new(std::cell::RefCell::new(renderers));
+
+        let accept = {
+            let renderers = renderers.clone();
+            async move {
+                while let Some(client) = clients.next().await {
+                    let added = renderers.borrow_mut().add(client, renderer::Framing::Lines);
+                    if let Err(err) = added {
+                        println!("Error sending to renderer: {err}");
+                    }
+                }
+                future::pending().await
+            }
+        };
+        let run = self.run_without_bar(move |idx, texts| renderers.borrow_mut().update(idx, texts));
+        tokio::select! {
+            result = accept => result,
+            result = run => result,
+        }
+    }
+
+    // Runs without a bar, passing the output of each widget to `update`
+    // instead, e.g. to show it as the name of the root window or to send it
+    // to external renderers.
+    async fn run_without_bar<F>(self, mut update: F) -> Result<()>
+    where
+        F: FnMut(usize, Vec<Text>) -> Result<()>,
+    {
+        let mut requests = match &self.control_socket {
+            Some(path) => ipc::listen(path)?,
+            None => mpsc::unbounded().1,
+        };
+        let commands = Commands::shared(&self.services);
+
+        let errors = ErrorLog::new(&self.names);
+        let policies = Rc::new(self.error_policies);
+        let names = self.names.clone();
+        let diagnostics = self.diagnostics.then(|| Diagnostics::start(self.names));
+        let mut widgets = into_streams(
+            self.widgets,
+            &names,
+            diagnostics.as_ref(),
+            policies,
+            &errors,
+        )?;
+
+        // Alerts still run their commands, but there's no bar to flash.
+        let mut alerts = Box::pin(alerts::evaluate(
+            self.alerts,
+            &bus::Bus::shared(&self.services),
+        ));
+
+        loop {
+            tokio::select! {
+                // None of the other commands apply without a bar.
+                Some((command, reply)) = requests.next() => {
+                    let result = match (command, &diagnostics) {
+                        (Command::Diagnostics, Some(diagnostics)) => Ok(diagnostics.report()),
+                        (Command::Diagnostics, None) => {
+                            Err(anyhow::anyhow!("Diagnostics are not enabled"))
+                        }
+                        (Command::Errors, _) => Ok(errors.report()),
+                        (Command::Widget(command), _) => commands.dispatch(&command),
+                        _ => Err(anyhow::anyhow!("Not supported without a bar")),
+                    };
+                    let _ = reply.send(result);
+                }
+
+                Some(_) = alerts.next() => {}
+
+                Some((idx, result)) = widgets.next() => {
+                    match result {
+                        Err(err) => println!("Error from widget {idx}: {err}"),
+                        Ok(texts) => {
+                            if let Err(err) = update(idx, texts) {
+                                println!("Error updating widget {idx}: {err}");
+                            }
+                        }
+                    }
+                }
+
+                // Every stream has ended, e.g. because the widgets' input
+                // did, so the last output stays until Cnx is stopped.
+                else => future::pending().await,
+            }
+        }
+    }
+}
+
+// Returns the streams of `widgets`, for running without a bar, in which case
+// they can't be clicked.
+fn into_streams(
+    widgets: Vec<Box<dyn Widget>>,
+    names: &[&'static str],
+    diagnostics: Option<&Diagnostics>,
+    policies: Rc<HashMap<ErrorKind, Policy>>,
+    errors: &ErrorLog,
+) -> Result<StreamMap<usize, WidgetResults>> {
+    let mut streams = StreamMap::with_capacity(widgets.len());
+    for ((idx, widget), &name) in widgets.into_iter().enumerate().zip(names) {
+        let stream = widget.into_stream()?;
+        let stream = match diagnostics {
+            Some(diagnostics) => diagnostics.measure(idx, stream),
+            None => stream,
+        };
+        let stream = error::apply_policies(idx, name, stream, policies.clone(), errors.clone());
+        streams.insert(idx, stream);
+    }
+    Ok(streams)
 }
@@ -0,0 +1,168 @@
+//! An inline text prompt, for widgets which let the user type into the bar.
+//!
+//! While a prompt is open, it grabs the keyboard, so that typing goes to the
+//! prompt rather than to the focused window. The widget is responsible for
+//! showing the text as it is edited, which is given by
+//! [`PromptEvent::Edited`].
+//!
+//! Return accepts the text and Escape cancels the prompt, after which the
+//! keyboard is released again.
+
+use anyhow::{anyhow, Context, Result};
+use async_stream::stream;
+use std::rc::Rc;
+use tokio_stream::{Stream, StreamExt};
+use xcb_util::ewmh;
+
+use crate::xcb::XcbEventStream;
+
+// The keysyms we treat specially, from X11/keysymdef.h.
+const XK_BACKSPACE: xcb::Keysym = 0xff08;
+const XK_RETURN: xcb::Keysym = 0xff0d;
+const XK_ESCAPE: xcb::Keysym = 0xff1b;
+const XK_KP_ENTER: xcb::Keysym = 0xff8d;
+
+// Keysyms for Unicode characters are the code point with this bit set.
+const UNICODE_KEYSYM: xcb::Keysym = 0x0100_0000;
+
+/// A change to the state of a prompt opened with [`prompt()`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PromptEvent {
+    /// The text has been edited, or the prompt has just opened.
+    Edited(String),
+    /// Return was pressed. This is the last event.
+    Accepted(String),
+    /// Escape was pressed. This is the last event.
+    Cancelled,
+}
+
+/// Opens a prompt containing `initial`, returning a stream of the changes
+/// to it.
+///
+/// The stream starts with [`PromptEvent::Edited`] for the initial text, and
+/// ends after the prompt is accepted or cancelled. Dropping the stream
+/// cancels the prompt.
+pub fn prompt(initial: String) -> Result<impl Stream<Item = PromptEvent>> {
+    let keyboard = Keyboard::grab().context("Failed to open prompt")?;
+    let mut events = XcbEventStream::new(keyboard.conn.clone())?;
+
+    let stream = stream! {
+        let mut text = initial;
+        yield PromptEvent::Edited(text.clone());
+
+        while let Some(event) = events.next().await {
+            if event.response_type() & !0x80 != xcb::KEY_PRESS {
+                continue;
+            }
+            let event: &xcb::KeyPressEvent = unsafe { xcb::cast_event(&event) };
+            match keyboard.keysym(event) {
+                XK_RETURN | XK_KP_ENTER => {
+                    yield PromptEvent::Accepted(text);
+                    break;
+                }
+                XK_ESCAPE => {
+                    yield PromptEvent::Cancelled;
+                    break;
+                }
+                XK_BACKSPACE => {
+                    if text.pop().is_some() {
+                        yield PromptEvent::Edited(text.clone());
+                    }
+                }
+                keysym => {
+                    if let Some(c) = keysym_to_char(keysym).filter(|c| !c.is_control()) {
+                        text.push(c);
+                        yield PromptEvent::Edited(text.clone());
+                    }
+                }
+            }
+        }
+        keyboard.ungrab();
+    };
+    Ok(stream)
+}
+
+// Returns the character typed by a keysym, if any.
+fn keysym_to_char(keysym: xcb::Keysym) -> Option<char> {
+    match keysym {
+        // Latin-1 keysyms are the same as their code points.
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        _ if keysym & 0xff00_0000 == UNICODE_KEYSYM => char::from_u32(keysym & !UNICODE_KEYSYM),
+        _ => None,
+    }
+}
+
+// An X connection with the keyboard grabbed, along with the keyboard mapping
+// to translate key presses into keysyms.
+struct Keyboard {
+    conn: Rc<ewmh::Connection>,
+    min_keycode: xcb::Keycode,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<xcb::Keysym>,
+}
+
+impl Keyboard {
+    fn grab() -> Result<Self> {
+        let (conn, screen_idx) =
+            xcb::Connection::connect(None).context("Failed to connect to X server")?;
+        let setup = conn.get_setup();
+        let root = setup
+            .roots()
+            .nth(screen_idx as usize)
+            .ok_or_else(|| anyhow!("Invalid screen"))?
+            .root();
+        let min_keycode = setup.min_keycode();
+        let count = setup.max_keycode() - min_keycode + 1;
+
+        let mapping = xcb::get_keyboard_mapping(&conn, min_keycode, count).get_reply()?;
+        let keysyms_per_keycode = mapping.keysyms_per_keycode() as usize;
+        let keysyms = mapping.keysyms().to_vec();
+
+        let reply = xcb::grab_keyboard(
+            &conn,
+            false,
+            root,
+            xcb::TIME_CURRENT_TIME,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+        )
+        .get_reply()?;
+        if reply.status() != xcb::GRAB_STATUS_SUCCESS as u8 {
+            return Err(anyhow!("Failed to grab the keyboard"));
+        }
+
+        let conn = ewmh::Connection::connect(conn)
+            .map_err(|(e, _)| e)
+            .context("Failed to wrap xcb::Connection in ewmh::Connection")?;
+        Ok(Keyboard {
+            conn: Rc::new(conn),
+            min_keycode,
+            keysyms_per_keycode,
+            keysyms,
+        })
+    }
+
+    // Returns the keysym of a key press, taking Shift and Caps Lock into
+    // account but ignoring any other modifiers.
+    fn keysym(&self, event: &xcb::KeyPressEvent) -> xcb::Keysym {
+        let state = event.state() as xcb::ModMask;
+        let shift = state & xcb::MOD_MASK_SHIFT != 0;
+        let caps_lock = state & xcb::MOD_MASK_LOCK != 0;
+
+        let start = (event.detail() - self.min_keycode) as usize * self.keysyms_per_keycode;
+        let keysyms = &self.keysyms[start..start + self.keysyms_per_keycode];
+        let lower = keysyms.first().copied().unwrap_or(0);
+        let upper = keysyms.get(1).copied().filter(|&k| k != 0).unwrap_or(lower);
+        let is_letter = keysym_to_char(lower).map_or(false, char::is_lowercase);
+        if shift != (caps_lock && is_letter) {
+            upper
+        } else {
+            lower
+        }
+    }
+
+    fn ungrab(&self) {
+        xcb::ungrab_keyboard(&self.conn, xcb::TIME_CURRENT_TIME);
+        self.conn.flush();
+    }
+}
@@ -0,0 +1,177 @@
+//! The protocol used to send widget output to external renderers.
+//!
+//! See the [crate documentation](crate#external-renderers) for how this is
+//! used.
+
+use crate::ipc;
+use crate::text::Text;
+use anyhow::Result;
+use futures::channel::mpsc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::task;
+use tokio_stream::StreamExt;
+
+/// The version of the protocol, sent with every [`Message`].
+///
+/// This is increased whenever the protocol changes in a way which would break
+/// existing renderers. Fields may be added to messages without increasing
+/// it, so renderers should ignore any they don't know.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// How many updates a renderer may fall behind by, on top of the content of
+// every widget sent as it connects, before it is dropped.
+const MAX_BACKLOG: usize = 256;
+
+/// A message sent to external renderers, each as one line of JSON.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx::renderer::{Event, Message, PROTOCOL_VERSION};
+/// let line = r#"{"version":1,"type":"hello","widgets":2}"#;
+/// let message: Message = serde_json::from_str(line).unwrap();
+/// assert_eq!(message.version, PROTOCOL_VERSION);
+/// assert_eq!(message.event, Event::Hello { widgets: 2 });
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// The content of a [`Message`], tagged by its `type`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Sent first on each connection, with the number of widgets.
+    Hello { widgets: usize },
+    /// The new content of the widget at index `widget`, counting from 0 in
+    /// the order the widgets were added. This is sent for every widget after
+    /// the `Hello`, and then whenever a widget changes.
+    ///
+    /// Any [`Canvas`](crate::text::Canvas) is left out, as it can only be
+    /// drawn by Cnx itself.
+    Update { widget: usize, texts: Vec<Text> },
+}
+
+// Encodes an event as JSON.
+fn encode(event: Event) -> Result<String> {
+    let message = Message {
+        version: PROTOCOL_VERSION,
+        event,
+    };
+    Ok(serde_json::to_string(&message)?)
+}
+
+// How messages are separated on a connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Framing {
+    // One message per line.
+    Lines,
+    // Server-sent events, for web browsers.
+    #[cfg(feature = "web")]
+    EventStream,
+}
+
+impl Framing {
+    fn frame(self, json: &str) -> String {
+        match self {
+            Framing::Lines => format!("{json}\n"),
+            #[cfg(feature = "web")]
+            Framing::EventStream => format!("data: {json}\n\n"),
+        }
+    }
+}
+
+// Listens on the Unix socket at `path`, returning a stream of the renderers
+// which connect to it.
+//
+// This must be called from within a `task::LocalSet`.
+pub(crate) fn listen(path: &Path) -> Result<mpsc::UnboundedReceiver<UnixStream>> {
+    let listener = ipc::bind(path)?;
+    let (sender, receiver) = mpsc::unbounded();
+    task::spawn_local(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let _ = sender.unbounded_send(stream);
+                }
+                Err(err) => println!("Error accepting renderer connection: {err}"),
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+// The connected renderers, along with the latest content of each widget to
+// send to renderers as they connect.
+//
+// Each renderer is written to by its own task, so that a slow renderer
+// doesn't hold up the others or the bar. A renderer which stops reading is
+// dropped once it falls `MAX_BACKLOG` updates behind, rather than keeping
+// every update in memory.
+pub(crate) struct Renderers {
+    clients: Vec<mpsc::Sender<String>>,
+    contents: Vec<Vec<Text>>,
+}
+
+impl Renderers {
+    pub fn new(widgets: usize) -> Self {
+        Renderers {
+            clients: Vec::new(),
+            contents: vec![Vec::new(); widgets],
+        }
+    }
+
+    // Adds a new renderer, sending it the content of every widget so far.
+    //
+    // This must be called from within a `task::LocalSet`.
+    pub fn add<W>(&mut self, mut stream: W, framing: Framing) -> Result<()>
+    where
+        W: AsyncWrite + Unpin + 'static,
+    {
+        let (mut sender, mut receiver) =
+            mpsc::channel::<String>(self.contents.len() + 1 + MAX_BACKLOG);
+        sender.try_send(encode(Event::Hello {
+            widgets: self.contents.len(),
+        })?)?;
+        for (widget, texts) in self.contents.iter().enumerate() {
+            sender.try_send(encode(Event::Update {
+                widget,
+                texts: texts.clone(),
+            })?)?;
+        }
+
+        task::spawn_local(async move {
+            while let Some(json) = receiver.next().await {
+                // If this fails, the renderer has gone away.
+                if stream
+                    .write_all(framing.frame(&json).as_bytes())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.clients.push(sender);
+        Ok(())
+    }
+
+    // Sends the new content of a widget to every renderer, dropping any which
+    // have disconnected or fallen too far behind.
+    pub fn update(&mut self, widget: usize, texts: Vec<Text>) -> Result<()> {
+        let json = encode(Event::Update {
+            widget,
+            texts: texts.clone(),
+        })?;
+        self.contents[widget] = texts;
+        self.clients
+            .retain_mut(|client| client.try_send(json.clone()).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::text::Text;
+use anyhow::{anyhow, Context, Result};
+
+/// Shows the text of the widgets as the name of the root window, which is
+/// what e.g. dwm shows in its own bar.
+pub struct RootName {
+    conn: xcb::Connection,
+    root: xcb::Window,
+    separator: String,
+    contents: Vec<String>,
+}
+
+impl RootName {
+    pub fn new(widgets: usize, separator: String) -> Result<Self> {
+        let (conn, screen_idx) =
+            xcb::Connection::connect(None).context("Failed to connect to X server")?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx as usize)
+            .ok_or_else(|| anyhow!("Invalid screen"))?
+            .root();
+        Ok(RootName {
+            conn,
+            root,
+            separator,
+            contents: vec![String::new(); widgets],
+        })
+    }
+
+    /// Updates the content of the widget at `idx`, and sets the root window's
+    /// name to the text of every widget which shows anything.
+    pub fn update(&mut self, idx: usize, texts: Vec<Text>) -> Result<()> {
+        let content: String = texts.iter().map(Text::plain_text).collect();
+        if self.contents[idx] == content {
+            return Ok(());
+        }
+        self.contents[idx] = content;
+
+        let name = self
+            .contents
+            .iter()
+            .filter(|content| !content.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(&self.separator);
+        // This is what `xsetroot -name` does, which dwm expects.
+        xcb::change_property(
+            &self.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.root,
+            xcb::ATOM_WM_NAME,
+            xcb::ATOM_STRING,
+            8,
+            name.as_bytes(),
+        )
+        .request_check()
+        .context("Failed to set root window name")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Services shared between widgets, such as a D-Bus connection.
+//!
+//! Some widgets depend on a long-lived service, such as a connection to the
+//! system bus or a sampler of system statistics. Rather than each widget
+//! opening its own, [`Cnx`] keeps a registry of [`Services`], which it gives
+//! to each widget as it is added (see [`Widget::use_services()`]). The first
+//! widget to need a service creates it, and the others share it.
+//!
+//! A service is dropped, closing e.g. its connection, once Cnx stops and no
+//! widget holds on to it any more.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::services::Services;
+//! use std::cell::Cell;
+//!
+//! #[derive(Default)]
+//! struct Counter(Cell<u32>);
+//!
+//! let services = Services::default();
+//! services.get_or_insert_with(Counter::default).0.set(1);
+//! assert_eq!(services.get_or_insert_with(Counter::default).0.get(), 1);
+//! ```
+//!
+//! [`Cnx`]: crate::Cnx
+//! [`Widget::use_services()`]: crate::widgets::Widget::use_services
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A registry of services shared between widgets, with at most one service
+/// of each type.
+///
+/// Cloning a `Services` gives another handle to the same registry.
+#[derive(Clone, Default)]
+pub struct Services {
+    services: Rc<RefCell<HashMap<TypeId, Rc<dyn Any>>>>,
+}
+
+impl Services {
+    /// Returns the service of type `T`, if one has been created.
+    pub fn get<T: 'static>(&self) -> Option<Rc<T>> {
+        let service = self.services.borrow().get(&TypeId::of::<T>())?.clone();
+        service.downcast().ok()
+    }
+
+    /// Returns the service of type `T`, calling `create` to create it if
+    /// there isn't one yet.
+    ///
+    /// Services which take a while to start, such as connections, can be
+    /// created asynchronously by wrapping them in e.g. a
+    /// [`tokio::sync::OnceCell`], so that widgets which need them at the same
+    /// time all wait for the same one.
+    pub fn get_or_insert_with<T: 'static>(&self, create: impl FnOnce() -> T) -> Rc<T> {
+        if let Some(service) = self.get() {
+            return service;
+        }
+        // `create` may itself use other services, so it mustn't be called
+        // while the registry is borrowed.
+        let service = Rc::new(create());
+        self.services
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), service.clone());
+        service
+    }
+
+    /// Sets the service of type `T`, replacing any there was. Widgets which
+    /// got the old one keep using it.
+    pub fn insert<T: 'static>(&self, service: T) {
+        self.services
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(service));
+    }
+}
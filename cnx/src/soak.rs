@@ -0,0 +1,162 @@
+use crate::diagnostics;
+use crate::widgets::Widget;
+use anyhow::{anyhow, bail, Result};
+use futures::stream::{self, StreamExt};
+use std::cell::Cell;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::runtime;
+use tokio::task;
+use tokio::time;
+use tokio_stream::StreamMap;
+
+// How much a widget's memory may grow after warming up before it is reported
+// as a possible leak, to allow for e.g. a cache which is filled slowly.
+const LEAK_THRESHOLD: i64 = 4096;
+
+// How long a widget is driven for at most, for each update asked for. As the
+// clock is paused, this passes in an instant unless the widget is busy.
+const TIME_PER_UPDATE: Duration = Duration::from_secs(3600);
+
+// The outcome of soak testing a widget.
+#[derive(Default)]
+struct Soak {
+    updates: u32,
+    errors: u32,
+    // The number of updates after warming up and the memory held on to by
+    // the widget's stream then, and the memory it holds on to now.
+    warm: Option<(u32, i64)>,
+    retained: Rc<Cell<i64>>,
+}
+
+impl Soak {
+    // Returns how many bytes the widget's memory grew by after warming up, in
+    // total and per update.
+    fn growth(&self) -> Option<(i64, f64)> {
+        let (updates, retained) = self.warm?;
+        let growth = self.retained.get() - retained;
+        let per_update = match self.updates - updates {
+            0 => 0.0,
+            updates => growth as f64 / f64::from(updates),
+        };
+        Some((growth, per_update))
+    }
+}
+
+/// Drives each widget's stream until it has yielded `iterations` updates,
+/// reporting how much memory each one held on to.
+///
+/// Tokio's clock is paused, and skips ahead whenever every widget is waiting
+/// for a timer, so widgets which update on a timer do so as fast as they can.
+pub(crate) fn run(
+    widgets: Vec<Box<dyn Widget>>,
+    names: Vec<&'static str>,
+    iterations: u32,
+) -> Result<()> {
+    if !diagnostics::counting() {
+        bail!("Soak tests need the CountingAllocator to be installed as the global allocator");
+    }
+    let rt = runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()?;
+    let local = task::LocalSet::new();
+    let results = local.block_on(&rt, soak(widgets, iterations));
+
+    let mut leaks = Vec::new();
+    for (idx, (name, result)) in names.iter().zip(results).enumerate() {
+        let soak = match result {
+            Ok(soak) => soak,
+            Err(err) => {
+                println!("{idx} {name}: failed to start: {err}");
+                continue;
+            }
+        };
+        let mut line = format!(
+            "{idx} {name}: {} updates, {} errors",
+            soak.updates, soak.errors
+        );
+        match soak.growth() {
+            Some((growth, per_update)) => {
+                let _ = write!(
+                    line,
+                    ", {growth:+} bytes after warming up ({per_update:+.1} per update)"
+                );
+                if growth > LEAK_THRESHOLD {
+                    line.push_str(" - possible leak");
+                    leaks.push(format!("{idx} {name}"));
+                }
+            }
+            None => line.push_str(", too few to warm up"),
+        }
+        println!("{line}");
+    }
+
+    if !leaks.is_empty() {
+        return Err(anyhow!("Possible leaks in: {}", leaks.join(", ")));
+    }
+    Ok(())
+}
+
+async fn soak(widgets: Vec<Box<dyn Widget>>, iterations: u32) -> Vec<Result<Soak>> {
+    // Memory allocated in the first tenth of the updates is assumed to be
+    // e.g. caches being filled rather than a leak.
+    let warm_up = iterations / 10;
+
+    let mut soaks = Vec::with_capacity(widgets.len());
+    let mut streams = StreamMap::with_capacity(widgets.len());
+    for (idx, widget) in widgets.into_iter().enumerate() {
+        let stream = match widget.into_stream() {
+            Ok(stream) => stream,
+            Err(err) => {
+                soaks.push(Err(err));
+                continue;
+            }
+        };
+        let soak = Soak::default();
+
+        // Count the memory allocated and not freed while polling the stream,
+        // dropping each update straight away so that only memory held on to
+        // by the widget is counted.
+        let retained = soak.retained.clone();
+        let mut stream = stream.map(|result| result.is_ok());
+        let stream = stream::poll_fn(move |cx| {
+            let before = diagnostics::live();
+            let poll = stream.poll_next_unpin(cx);
+            retained.set(retained.get() + diagnostics::live() - before);
+            poll
+        });
+        streams.insert(idx, stream);
+        soaks.push(Ok(soak));
+    }
+
+    // Widgets which update on events, rather than timers, may not get to
+    // `iterations` updates, so give up on them eventually.
+    let deadline = time::sleep(TIME_PER_UPDATE.saturating_mul(iterations));
+    tokio::pin!(deadline);
+    // Streams are removed once they end or have done enough updates.
+    while !streams.is_empty() {
+        tokio::select! {
+            Some((idx, ok)) = streams.next() => {
+                // Only widgets which started have a stream.
+                if let Ok(soak) = &mut soaks[idx] {
+                    soak.updates += 1;
+                    if !ok {
+                        soak.errors += 1;
+                    }
+                    if soak.updates == warm_up.max(1) {
+                        soak.warm = Some((soak.updates, soak.retained.get()));
+                    }
+                    if soak.updates >= iterations {
+                        streams.remove(&idx);
+                    }
+                }
+            }
+
+            _ = &mut deadline => break,
+        }
+    }
+
+    soaks
+}
@@ -0,0 +1,227 @@
+//! Stopping the work widgets do in the background.
+//!
+//! A widget's stream is dropped when the widget is removed from the bar,
+//! e.g. because its stream ended, and all of them are dropped when Cnx stops,
+//! e.g. on `SIGTERM` or `SIGINT`. Any work a widget does in the background
+//! must stop with its stream, rather than leaking a task or a thread which
+//! holds on to e.g. a connection or a lock:
+//!
+//! - Tasks should be spawned with [`spawn_local()`], or wrapped in an
+//!   [`AbortOnDrop`], and the guard kept by the stream or service they work
+//!   for, so that they are aborted once it is dropped.
+//! - Blocking work, e.g. in `spawn_blocking`, can't be aborted, so it should
+//!   check the [`CancellationToken`] returned by [`shutdown()`] between
+//!   steps, and return once it is cancelled. Cnx cancels it as it stops, and
+//!   then waits at most [`SHUTDOWN_TIMEOUT`] for blocking threads, after which
+//!   any which are still running are detached.
+//! - Child processes should be spawned with
+//!   [`kill_on_drop`](tokio::process::Command::kill_on_drop), so that they
+//!   are killed if the widget stops waiting for them.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::teardown::CancellationToken;
+//! use futures::executor::block_on;
+//!
+//! let token = CancellationToken::new();
+//! let guard = token.clone().drop_guard();
+//! assert!(!token.is_cancelled());
+//! drop(guard);
+//! block_on(token.cancelled());
+//! assert!(token.is_cancelled());
+//! ```
+
+use crate::services::Services;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+use tokio::task::{self, JoinHandle};
+
+/// How long Cnx waits for blocking threads to return once it has stopped.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A token which can be cancelled once, e.g. to tell blocking work to stop.
+///
+/// Cloning a `CancellationToken` gives another handle to the same token.
+/// Unlike Cnx's other types, it can be sent to other threads.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Creates a new token, which isn't cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, waking any tasks waiting in
+    /// [`CancellationToken::cancelled()`].
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Returns whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        // Waiters are only woken if they started waiting before `cancel()`
+        // was called, so we check afterwards.
+        let notified = self.inner.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Returns a guard which cancels the token when it is dropped, e.g. to
+    /// tie blocking work to the stream waiting for it.
+    pub fn drop_guard(self) -> DropGuard {
+        DropGuard { token: self }
+    }
+}
+
+/// Cancels a [`CancellationToken`] when dropped.
+#[derive(Debug)]
+pub struct DropGuard {
+    token: CancellationToken,
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+/// Aborts a task when dropped.
+///
+/// A task spawned with [`tokio::task::spawn_local()`] or
+/// [`tokio::task::spawn()`] keeps running once its `JoinHandle` is dropped.
+/// Wrapping the handle in an `AbortOnDrop` stops the task with whatever
+/// holds the guard instead.
+#[derive(Debug)]
+pub struct AbortOnDrop<T> {
+    handle: JoinHandle<T>,
+}
+
+impl<T> AbortOnDrop<T> {
+    /// Wraps the handle of a task which is to be aborted once the returned
+    /// guard is dropped.
+    pub fn new(handle: JoinHandle<T>) -> Self {
+        Self { handle }
+    }
+}
+
+impl<T> From<JoinHandle<T>> for AbortOnDrop<T> {
+    fn from(handle: JoinHandle<T>) -> Self {
+        Self::new(handle)
+    }
+}
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Spawns a task on the current `task::LocalSet`, which is aborted once the
+/// returned guard is dropped.
+///
+/// This must be called from within a `task::LocalSet`, as [`Widget`]s'
+/// `into_stream()` methods are.
+///
+/// [`Widget`]: crate::widgets::Widget
+pub fn spawn_local<F>(future: F) -> AbortOnDrop<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    AbortOnDrop::new(task::spawn_local(future))
+}
+
+// The token cancelled as Cnx stops, as a service.
+#[derive(Default)]
+struct Shutdown(CancellationToken);
+
+/// Returns the token which is cancelled as the [`Cnx`] using `services`
+/// stops, e.g. for blocking work to check.
+///
+/// [`Cnx`]: crate::Cnx
+pub fn shutdown(services: &Services) -> CancellationToken {
+    services.get_or_insert_with(Shutdown::default).0.clone()
+}
+
+// Waits until Cnx is asked to stop, with `SIGTERM` or `SIGINT`.
+//
+// This must be called from within the runtime.
+pub(crate) async fn signalled() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+    use std::rc::Rc;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn cancellation() {
+        let token = CancellationToken::new();
+        let waiter = token.clone();
+        let thread = std::thread::spawn(move || block_on(waiter.cancelled()));
+        token.cancel();
+        thread.join().unwrap();
+        assert!(token.is_cancelled());
+        // Waiting for a token which was already cancelled returns at once.
+        block_on(token.cancelled());
+
+        let services = Services::default();
+        assert!(!shutdown(&services).is_cancelled());
+        shutdown(&services).cancel();
+        assert!(shutdown(&services).is_cancelled());
+    }
+
+    #[test]
+    fn abort_on_drop() {
+        let rt = Builder::new_current_thread().enable_time().build().unwrap();
+        let local = task::LocalSet::new();
+        local.block_on(&rt, async {
+            // The task holds a reference until it is dropped.
+            let held = Rc::new(());
+            let task = {
+                let held = held.clone();
+                spawn_local(async move {
+                    let _held = held;
+                    std::future::pending::<()>().await;
+                })
+            };
+            task::yield_now().await;
+            assert_eq!(Rc::strong_count(&held), 2);
+            drop(task);
+            task::yield_now().await;
+            assert_eq!(Rc::strong_count(&held), 1);
+        });
+    }
+}
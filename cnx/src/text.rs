@@ -4,16 +4,26 @@
 //! implementations for inspiration.
 
 use anyhow::Result;
-use cairo::{Context, Surface};
+use cairo::{Context, ImageSurface, Surface};
 use colors_transform::{Color as ColorTransform, Rgb};
 use pango::{EllipsizeMode, FontDescription};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
-
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A color.
+///
+/// A color made from a [`Role`] with [`Color::role()`] is drawn in the color
+/// the [`Palette`] of the bar's [`Theme`] gives that role, if there is one.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Color {
     red: f64,
     green: f64,
     blue: f64,
+    role: Option<Role>,
 }
 
 macro_rules! color {
@@ -24,6 +34,7 @@ macro_rules! color {
                 red: $r,
                 green: $g,
                 blue: $b,
+                role: None,
             }
         }
     };
@@ -46,6 +57,27 @@ impl Color {
             red: r as f64 / 255.0,
             green: g as f64 / 255.0,
             blue: b as f64 / 255.0,
+            role: None,
+        }
+    }
+
+    /// The color for `role`, which is the color the [`Palette`] of the bar's
+    /// [`Theme`] gives it if there is one, and otherwise the color the
+    /// default palette gives it.
+    ///
+    /// Widgets should prefer this to a fixed color for anything showing
+    /// whether a value is fine or not, so that the whole bar can be switched
+    /// to e.g. [`Palette::deuteranopia()`] at once.
+    ///
+    /// ```
+    /// # use cnx::text::{Color, Palette, Role};
+    /// let crit = Color::role(Role::Crit);
+    /// assert_eq!(crit.to_hex(), Palette::default().crit.to_hex());
+    /// ```
+    pub fn role(role: Role) -> Self {
+        Self {
+            role: Some(role),
+            ..Palette::default().color(role).clone()
         }
     }
 
@@ -62,11 +94,15 @@ impl Color {
             Ok(rgb) => rgb,
             Err(_) => Rgb::from(0.0, 0.0, 0.0),
         };
+        Self::from_transform(rgb)
+    }
 
+    fn from_transform(rgb: Rgb) -> Self {
         Self {
             red: rgb.get_red() as f64 / 255.0,
             green: rgb.get_green() as f64 / 255.0,
             blue: rgb.get_blue() as f64 / 255.0,
+            role: None,
         }
     }
 
@@ -90,7 +126,34 @@ impl Color {
     }
 }
 
+impl From<Role> for Color {
+    fn from(role: Role) -> Self {
+        Color::role(role)
+    }
+}
+
+// Colors are (de)serialized as hex strings, e.g. `"#1E1E2E"`.
+#[cfg(feature = "serde")]
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        // Unlike `Color::from_hex()`, reject invalid colors rather than
+        // silently using black.
+        Rgb::from_hex_str(&hex)
+            .map(Color::from_transform)
+            .map_err(|_| de::Error::custom(format!("invalid hex color: {hex}")))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Padding {
     left: f64,
     right: f64,
@@ -107,6 +170,22 @@ impl Padding {
             bottom,
         }
     }
+
+    // Returns whether there is any padding on the left and on the right, for
+    // outputs which can only pad with spaces.
+    pub(crate) fn sides(&self) -> (bool, bool) {
+        (self.left > 0.0, self.right > 0.0)
+    }
+
+    // Returns this padding, multiplied by `scale`.
+    pub(crate) fn scaled(&self, scale: f64) -> Padding {
+        Padding::new(
+            self.left * scale,
+            self.right * scale,
+            self.top * scale,
+            self.bottom * scale,
+        )
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -116,6 +195,18 @@ impl Font {
     pub fn new(name: &str) -> Font {
         Font(FontDescription::from_string(name))
     }
+
+    // Returns this font, with its size multiplied by `scale`.
+    pub(crate) fn scaled(&self, scale: f64) -> Font {
+        let mut description = self.0.clone();
+        let size = f64::from(description.size()) * scale;
+        if description.is_size_absolute() {
+            description.set_absolute_size(size);
+        } else {
+            description.set_size(size.round() as i32);
+        }
+        Font(description)
+    }
 }
 
 impl fmt::Debug for Font {
@@ -124,7 +215,25 @@ impl fmt::Debug for Font {
     }
 }
 
+// Fonts are (de)serialized as Pango font descriptions, e.g.
+// `"SourceCodePro 21"`.
+#[cfg(feature = "serde")]
+impl Serialize for Font {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Font {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Font::new(&name))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Attributes {
     pub font: Font,
     pub fg_color: Color,
@@ -132,6 +241,166 @@ pub struct Attributes {
     pub padding: Padding,
 }
 
+/// Adjustments to the colors of every widget's texts, e.g. a darker theme to
+/// use at night with [`Cnx::with_night_theme()`].
+///
+/// Colors given in a text's Pango markup aren't affected.
+///
+/// A theme can also set the [`Palette`] which picks the colors for each
+/// [`Role`], e.g. one which is safe for people with color blindness:
+///
+/// ```
+/// # use cnx::text::{Palette, Theme};
+/// let theme = Theme {
+///     palette: Some(Palette::deuteranopia()),
+///     ..Theme::default()
+/// };
+/// ```
+///
+/// ```
+/// # use cnx::text::{Color, Theme};
+/// let night = Theme {
+///     bg_color: Some(Color::from_hex("#11111b")),
+///     fg_brightness: 0.7,
+///     ..Theme::default()
+/// };
+/// ```
+///
+/// [`Cnx::with_night_theme()`]: crate::Cnx::with_night_theme
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Replaces the foreground color of every text, if set
+    pub fg_color: Option<Color>,
+    /// Replaces the background color of every text, if set
+    pub bg_color: Option<Color>,
+    /// Scales the brightness of the foreground colors, e.g. `0.7` to dim them
+    pub fg_brightness: f64,
+    /// Picks the colors for each [`Role`], if set
+    pub palette: Option<Palette>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            fg_color: None,
+            bg_color: None,
+            fg_brightness: 1.0,
+            palette: None,
+        }
+    }
+}
+
+impl Theme {
+    // Returns the foreground and background colors to draw a text with
+    // `attr` in.
+    pub(crate) fn colors(&self, attr: &Attributes) -> (Color, Option<Color>) {
+        let fg_color = self.resolve(self.fg_color.as_ref().unwrap_or(&attr.fg_color));
+        let scale = |value: f64| (value * self.fg_brightness).clamp(0.0, 1.0);
+        let fg_color = Color {
+            red: scale(fg_color.red),
+            green: scale(fg_color.green),
+            blue: scale(fg_color.blue),
+            role: None,
+        };
+        let bg_color = self
+            .bg_color
+            .as_ref()
+            .or(attr.bg_color.as_ref())
+            .map(|color| self.resolve(color));
+        (fg_color, bg_color)
+    }
+
+    // Returns the color of `color`'s role in the palette, if it has one and
+    // there is a palette, or else `color` itself.
+    fn resolve(&self, color: &Color) -> Color {
+        match (color.role, &self.palette) {
+            (Some(role), Some(palette)) => palette.color(role).clone(),
+            _ => color.clone(),
+        }
+    }
+
+    // Returns this theme, with the palette of `base` if it doesn't have one
+    // of its own.
+    pub(crate) fn or_palette_of(self, base: Option<&Theme>) -> Theme {
+        Theme {
+            palette: self
+                .palette
+                .or_else(|| base.and_then(|base| base.palette.clone())),
+            ..self
+        }
+    }
+}
+
+/// What a color shows about a value, e.g. that a battery is nearly empty.
+///
+/// Colors are made from roles with [`Color::role()`], and are drawn in the
+/// color the [`Palette`] of the bar's [`Theme`] gives them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The value is fine
+    Ok,
+    /// The value is getting close to a problem
+    Warn,
+    /// The value is a problem
+    Crit,
+}
+
+/// The colors for each [`Role`].
+///
+/// The default palette is green, yellow and red, which people with the most
+/// common kinds of color blindness find hard to tell apart. The other
+/// palettes are picked from the [Okabe-Ito] palette instead, so that they
+/// differ in brightness as well as in hue.
+///
+/// [Okabe-Ito]: https://jfly.uni-koeln.de/color/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub ok: Color,
+    pub warn: Color,
+    pub crit: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            ok: Color::green(),
+            warn: Color::yellow(),
+            crit: Color::red(),
+        }
+    }
+}
+
+impl Palette {
+    /// A palette of blue, yellow and vermilion, which is safe for people with
+    /// deuteranopia (and also tritanopia).
+    pub fn deuteranopia() -> Self {
+        Palette {
+            ok: Color::from_hex("#0072B2"),
+            warn: Color::from_hex("#F0E442"),
+            crit: Color::from_hex("#D55E00"),
+        }
+    }
+
+    /// A palette of sky blue, yellow and reddish purple, which is safe for
+    /// people with protanopia, to whom red looks dark.
+    pub fn protanopia() -> Self {
+        Palette {
+            ok: Color::from_hex("#56B4E9"),
+            warn: Color::from_hex("#F0E442"),
+            crit: Color::from_hex("#CC79A7"),
+        }
+    }
+
+    /// Returns the color for `role`.
+    pub fn color(&self, role: Role) -> &Color {
+        match role {
+            Role::Ok => &self.ok,
+            Role::Warn => &self.warn,
+            Role::Crit => &self.crit,
+        }
+    }
+}
+
 pub struct PagerAttributes {
     /// Active attributes are applied to the currently active workspace
     pub active_attr: Attributes,
@@ -149,17 +418,182 @@ fn show_pango_layout(cairo_context: &cairo::Context, layout: &pango::Layout) {
     pangocairo::functions::show_layout(cairo_context, layout);
 }
 
+type DrawFn = dyn Fn(&Context, f64, f64);
+
+/// A custom drawing, shown by a [`Text`] in place of its text.
+///
+/// The closure is called with a Cairo context, and the width and height to
+/// draw within (excluding the [`Padding`]), whenever the widget needs to be
+/// redrawn. The origin of the context is the top-left corner of the drawing,
+/// and its source is set to the foreground color.
+///
+/// A new `Canvas` never compares equal to an existing one, so a widget
+/// should only yield one when its drawing has changed.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx::text::Canvas;
+/// // A ring gauge, three-quarters full.
+/// let canvas = Canvas::new(16.0, 16.0, |cr, width, height| {
+///     let radius = width.min(height) / 2.0 - 1.0;
+///     let start = -std::f64::consts::FRAC_PI_2;
+///     cr.set_line_width(2.0);
+///     cr.arc(width / 2.0, height / 2.0, radius, start, start + 0.75 * std::f64::consts::TAU);
+///     let _ = cr.stroke();
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Canvas {
+    width: f64,
+    height: f64,
+    draw: Rc<DrawFn>,
+}
+
+impl Canvas {
+    /// Creates a new `Canvas` of the given size, drawn by `draw`.
+    pub fn new<F>(width: f64, height: f64, draw: F) -> Canvas
+    where
+        F: Fn(&Context, f64, f64) + 'static,
+    {
+        Canvas {
+            width,
+            height,
+            draw: Rc::new(draw),
+        }
+    }
+
+    /// Creates a new `Canvas` of the given size showing `image`, scaled to
+    /// fit and centred.
+    pub fn image(image: ImageSurface, width: f64, height: f64) -> Canvas {
+        Canvas::new(width, height, move |cr, width, height| {
+            let (image_width, image_height) = (f64::from(image.width()), f64::from(image.height()));
+            if image_width == 0.0 || image_height == 0.0 {
+                return;
+            }
+            let scale = (width / image_width).min(height / image_height);
+            cr.translate(
+                (width - image_width * scale) / 2.0,
+                (height - image_height * scale) / 2.0,
+            );
+            cr.scale(scale, scale);
+            if cr.set_source_surface(&image, 0.0, 0.0).is_ok() {
+                let _ = cr.paint();
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Canvas")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Canvas {
+    fn eq(&self, other: &Self) -> bool {
+        // Only the data pointers are compared, as the same closure's vtable
+        // can be duplicated across codegen units.
+        let draw = Rc::as_ptr(&self.draw).cast::<u8>();
+        draw == Rc::as_ptr(&other.draw).cast::<u8>()
+    }
+}
+
+/// The content of a widget.
+///
+/// With the `serde` feature, this can be serialized and deserialized, except
+/// for the `canvas`, which is skipped.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Text {
     pub attr: Attributes,
     pub text: String,
     pub stretch: bool,
     pub markup: bool,
+    /// If set, this is drawn instead of the `text`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub canvas: Option<Canvas>,
 }
 
 impl Text {
+    /// Creates a new `Text` showing `text` in `attr`, which isn't stretched,
+    /// isn't Pango markup and has no canvas.
+    ///
+    /// Unlike a struct literal, this keeps compiling when fields are added
+    /// to `Text`.
+    pub fn new<S: Into<String>>(attr: Attributes, text: S) -> Text {
+        Text {
+            attr,
+            text: text.into(),
+            stretch: false,
+            markup: false,
+            canvas: None,
+        }
+    }
+
+    /// Stretches the text to take up the bar's spare width.
+    pub fn with_stretch(self, stretch: bool) -> Self {
+        Self { stretch, ..self }
+    }
+
+    /// Treats the text as Pango markup.
+    pub fn with_markup(self, markup: bool) -> Self {
+        Self { markup, ..self }
+    }
+
+    /// Draws `canvas` instead of the text.
+    pub fn with_canvas(self, canvas: Canvas) -> Self {
+        Self {
+            canvas: Some(canvas),
+            ..self
+        }
+    }
+
+    // Returns the plain text, without any Pango markup.
+    pub(crate) fn plain_text(&self) -> String {
+        if !self.markup {
+            return self.text.clone();
+        }
+        match pango::parse_markup(&self.text, '\0') {
+            Ok((_, plain, _)) => plain.to_string(),
+            // Show invalid markup as it is, as the bar would.
+            Err(_) => self.text.clone(),
+        }
+    }
+
+    // Returns this text as drawn in a row of the bar scaled by `scale`, with
+    // its font, padding and canvas all scaled.
+    pub(crate) fn scaled(self, scale: f64) -> Text {
+        if scale == 1.0 {
+            return self;
+        }
+        let attr = Attributes {
+            font: self.attr.font.scaled(scale),
+            padding: self.attr.padding.scaled(scale),
+            ..self.attr
+        };
+        let canvas = self.canvas.map(|canvas| Canvas {
+            width: canvas.width * scale,
+            height: canvas.height * scale,
+            ..canvas
+        });
+        Text {
+            attr,
+            canvas,
+            ..self
+        }
+    }
+
     pub(crate) fn compute(self, surface: &Surface) -> Result<ComputedText> {
-        let (width, height) = {
+        let (width, height) = if let Some(canvas) = &self.canvas {
+            let padding = &self.attr.padding;
+            let width = canvas.width + padding.left + padding.right;
+            let height = canvas.height + padding.top + padding.bottom;
+            (width, height)
+        } else {
             let context = Context::new(surface)?;
             let layout = create_pango_layout(&context);
             if self.markup {
@@ -185,6 +619,7 @@ impl Text {
             width,
             height,
             markup: self.markup,
+            canvas: self.canvas,
         })
     }
 }
@@ -193,7 +628,10 @@ impl Text {
 // having to call the (relatively) expensive .compute().
 impl PartialEq<ComputedText> for Text {
     fn eq(&self, other: &ComputedText) -> bool {
-        self.attr == other.attr && self.text == other.text && self.stretch == other.stretch
+        self.attr == other.attr
+            && self.text == other.text
+            && self.stretch == other.stretch
+            && self.canvas == other.canvas
     }
 }
 
@@ -208,10 +646,11 @@ pub(crate) struct ComputedText {
     pub width: f64,
     pub height: f64,
     pub markup: bool,
+    pub canvas: Option<Canvas>,
 }
 
 impl ComputedText {
-    pub fn render(&self, surface: &Surface) -> Result<()> {
+    pub fn render(&self, surface: &Surface, theme: Option<&Theme>) -> Result<()> {
         let context = Context::new(surface)?;
         let layout = create_pango_layout(&context);
         if self.markup {
@@ -231,8 +670,13 @@ impl ComputedText {
         layout.set_width(text_width as i32 * pango::SCALE);
         layout.set_height(text_height as i32 * pango::SCALE);
 
-        let bg_color = &self.attr.bg_color.clone().unwrap_or_else(Color::black);
-        bg_color.apply_to_context(&context);
+        let (fg_color, bg_color) = match theme {
+            Some(theme) => theme.colors(&self.attr),
+            None => (self.attr.fg_color.clone(), self.attr.bg_color.clone()),
+        };
+        bg_color
+            .unwrap_or_else(Color::black)
+            .apply_to_context(&context);
         // FIXME: The use of `height` isnt' right here: we want to do the
         // full height of the bar, not the full height of the text. It
         // would be useful if we could do Surface.get_height(), but that
@@ -240,9 +684,16 @@ impl ComputedText {
         context.rectangle(0.0, 0.0, self.width, self.height);
         context.fill()?;
 
-        self.attr.fg_color.apply_to_context(&context);
+        fg_color.apply_to_context(&context);
         context.translate(padding.left, padding.top);
-        show_pango_layout(&context, &layout);
+        match &self.canvas {
+            Some(canvas) => {
+                context.rectangle(0.0, 0.0, text_width, text_height);
+                context.clip();
+                (canvas.draw)(&context, text_width, text_height);
+            }
+            None => show_pango_layout(&context, &layout),
+        }
 
         Ok(())
     }
@@ -266,16 +717,124 @@ impl Default for Threshold {
         Threshold {
             low: ThresholdValue {
                 threshold: 40,
-                color: Color::red(),
+                color: Color::role(Role::Crit),
             },
             normal: ThresholdValue {
                 threshold: 60,
-                color: Color::yellow(),
+                color: Color::role(Role::Warn),
             },
             high: ThresholdValue {
                 threshold: 100,
-                color: Color::green(),
+                color: Color::role(Role::Ok),
             },
         }
     }
 }
+
+/// Maps a value onto one of a list of steps, e.g. to pick a [`Color`] or an
+/// icon based on a percentage.
+///
+/// As with [`Threshold`], each step is used for values up to and including
+/// its threshold. Values above the highest threshold use the last step.
+///
+/// A ramp of colors should usually be made from [`Role`]s with
+/// [`Ramp::roles()`], so that it follows the [`Palette`] of the bar's
+/// [`Theme`].
+///
+/// # Examples
+///
+/// ```
+/// # use cnx::text::{Color, Ramp};
+/// let ramp = Ramp::new(vec![
+///     (10.0, Color::red()),
+///     (50.0, Color::yellow()),
+///     (100.0, Color::green()),
+/// ]);
+/// assert_eq!(ramp.get(5.0), Some(&Color::red()));
+/// assert_eq!(ramp.get(50.0), Some(&Color::yellow()));
+/// assert_eq!(ramp.get(75.0), Some(&Color::green()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ramp<T> {
+    steps: Vec<(f64, T)>,
+}
+
+impl<T> Ramp<T> {
+    /// Creates a new `Ramp` from `(threshold, value)` pairs, in any order.
+    pub fn new(mut steps: Vec<(f64, T)>) -> Self {
+        steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ramp { steps }
+    }
+
+    /// Returns the step for `value`, or `None` if the ramp is empty.
+    pub fn get(&self, value: f64) -> Option<&T> {
+        self.steps
+            .iter()
+            .find(|(threshold, _)| value <= *threshold)
+            .or_else(|| self.steps.last())
+            .map(|(_, step)| step)
+    }
+}
+
+impl Ramp<Color> {
+    /// Creates a new `Ramp` of the colors for `(threshold, role)` pairs, in
+    /// any order.
+    ///
+    /// ```
+    /// # use cnx::text::{Color, Ramp, Role};
+    /// let ramp = Ramp::roles(vec![
+    ///     (10.0, Role::Crit),
+    ///     (50.0, Role::Warn),
+    ///     (100.0, Role::Ok),
+    /// ]);
+    /// assert_eq!(ramp.get(5.0), Some(&Color::role(Role::Crit)));
+    /// ```
+    pub fn roles(steps: Vec<(f64, Role)>) -> Self {
+        Ramp::new(
+            steps
+                .into_iter()
+                .map(|(threshold, role)| (threshold, Color::role(role)))
+                .collect(),
+        )
+    }
+}
+
+/// Truncates `text` to at most `width` columns, ending it with `…` if
+/// anything was cut off.
+///
+/// Widths are counted in terminal columns, so e.g. CJK characters and most
+/// emoji count as two, and the text is only cut between grapheme clusters, so
+/// that e.g. an emoji with a skin tone modifier is never split. This is only
+/// an approximation of the width at which the text is drawn, which depends on
+/// the font, but it is usually close enough to keep a widget to a roughly
+/// predictable size.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx::text::truncate;
+/// assert_eq!(truncate("Hello, world", 8), "Hello, …");
+/// assert_eq!(truncate("Hello", 8), "Hello");
+/// assert_eq!(truncate("日本語のテキスト", 7), "日本語…");
+/// ```
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_owned();
+    }
+
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    // Leave space for the ellipsis.
+    let available = width.saturating_sub(1);
+    for grapheme in text.graphemes(true) {
+        truncated_width += grapheme.width();
+        if truncated_width > available {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
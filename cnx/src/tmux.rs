@@ -0,0 +1,126 @@
+use crate::text::{Color, Text};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use tokio::process::Command;
+use tokio::sync::watch;
+
+/// Where [`Cnx::with_tmux_status_line()`] puts the status line.
+///
+/// [`Cnx::with_tmux_status_line()`]: crate::Cnx::with_tmux_status_line
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TmuxOutput {
+    /// Writes it to a file, for tmux to read with e.g.
+    /// `set -g status-right '#(cat ~/.cache/cnx-status)'`. tmux only reads
+    /// it every `status-interval` seconds.
+    File(PathBuf),
+    /// Sets a global tmux option such as `status-right` to it with
+    /// `tmux set-option -g`, so that tmux shows it straight away.
+    Option(String),
+}
+
+// Returns `color` as tmux expects it. The hex digits are in lower case, as
+// e.g. `#F` in upper case is expanded to the window's flags.
+fn color(color: &Color) -> String {
+    color.to_hex().to_lowercase()
+}
+
+// Returns `text` in tmux's format, in the colors of its attributes. Padding is
+// shown as a space.
+fn format(text: &Text) -> String {
+    let attr = &text.attr;
+    let bg_color = attr
+        .bg_color
+        .as_ref()
+        .map_or_else(|| "default".to_owned(), color);
+    let (left, right) = attr.padding.sides();
+    format!(
+        "#[fg={},bg={bg_color}]{}{}{}",
+        color(&attr.fg_color),
+        if left { " " } else { "" },
+        // `#` starts a format, so it has to be escaped.
+        text.plain_text().replace('#', "##"),
+        if right { " " } else { "" },
+    )
+}
+
+// Sets the tmux option `option` to `status`.
+async fn set_option(option: &str, status: &str) -> Result<()> {
+    let output = Command::new("tmux")
+        .args(["set-option", "-g", option, status])
+        .output()
+        .await
+        .context("Failed to run `tmux`")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to set tmux option {}: {}",
+            option,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Spawns a task which sets the tmux option `option` to each status line sent
+// to it, so that the widgets aren't held up while `tmux` runs. If the status
+// line changes more than once while `tmux` runs, only the latest is set.
+fn spawn_setter(option: String) -> watch::Sender<String> {
+    let (sender, mut receiver) = watch::channel(String::new());
+    tokio::spawn(async move {
+        while receiver.changed().await.is_ok() {
+            let status = receiver.borrow_and_update().clone();
+            if let Err(err) = set_option(&option, &status).await {
+                println!("Error updating the tmux status line: {err}");
+            }
+        }
+    });
+    sender
+}
+
+// Where the status line goes: the file, or the task setting the tmux option.
+enum Sink {
+    File(PathBuf),
+    Option(watch::Sender<String>),
+}
+
+/// Shows the text of the widgets in tmux's status line, in their colors.
+pub struct Tmux {
+    sink: Sink,
+    contents: Vec<String>,
+}
+
+impl Tmux {
+    /// Must be called from within the Tokio runtime, as it spawns the task
+    /// which sets the tmux option.
+    pub fn new(widgets: usize, output: TmuxOutput) -> Self {
+        let sink = match output {
+            TmuxOutput::File(path) => Sink::File(path),
+            TmuxOutput::Option(option) => Sink::Option(spawn_setter(option)),
+        };
+        Tmux {
+            sink,
+            contents: vec![String::new(); widgets],
+        }
+    }
+
+    /// Updates the content of the widget at `idx`, and outputs the status
+    /// line with the content of every widget.
+    pub fn update(&mut self, idx: usize, texts: Vec<Text>) -> Result<()> {
+        let content: String = texts.iter().map(format).collect();
+        if self.contents[idx] == content {
+            return Ok(());
+        }
+        self.contents[idx] = content;
+
+        // Reset the colors at the end, for whatever tmux shows after it.
+        let status = self.contents.concat() + "#[default]";
+        match &self.sink {
+            Sink::File(path) => fs::write(path, status + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?,
+            Sink::Option(setter) => setter
+                .send(status)
+                .map_err(|_| anyhow!("The task setting the tmux option has stopped"))?,
+        }
+        Ok(())
+    }
+}
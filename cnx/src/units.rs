@@ -0,0 +1,131 @@
+//! The units and styles in which widgets show values.
+//!
+//! Rather than each widget having its own options for e.g. Fahrenheit or a
+//! 24-hour clock, they all consult the same [`Units`], which is set for the
+//! whole bar with [`Cnx::with_units()`]. Widgets get it from the
+//! [`Services`] they are given, with [`Units::shared()`].
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::units::{ClockStyle, Units};
+//!
+//! let units = Units {
+//!     clock: ClockStyle::TwentyFourHour,
+//!     ..Units::imperial()
+//! };
+//! assert_eq!(units.temperature(100.0), 212.0);
+//! assert_eq!(units.temperature_symbol(), "°F");
+//! assert_eq!(units.time_format(), "%H:%M");
+//! ```
+//!
+//! [`Cnx::with_units()`]: crate::Cnx::with_units
+
+use crate::services::Services;
+
+/// The unit in which temperatures are shown.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Temperature {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+/// The unit in which speeds, such as the wind's, are shown.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Speed {
+    #[default]
+    KilometersPerHour,
+    MilesPerHour,
+    MetersPerSecond,
+}
+
+/// Whether times are shown with a 12-hour or a 24-hour clock.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ClockStyle {
+    /// e.g. `01:30 PM`, as the [`Clock`](crate::widgets::Clock) shows by
+    /// default
+    #[default]
+    TwelveHour,
+    /// e.g. `13:30`
+    TwentyFourHour,
+}
+
+/// The units and styles in which widgets show values.
+///
+/// The default is Celsius, km/h and a 12-hour clock, which is what the
+/// widgets showed before they consulted `Units`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Units {
+    pub temperature: Temperature,
+    pub speed: Speed,
+    pub clock: ClockStyle,
+}
+
+impl Units {
+    /// Celsius, km/h and a 24-hour clock.
+    pub fn metric() -> Self {
+        Units {
+            temperature: Temperature::Celsius,
+            speed: Speed::KilometersPerHour,
+            clock: ClockStyle::TwentyFourHour,
+        }
+    }
+
+    /// Fahrenheit, mph and a 12-hour clock.
+    pub fn imperial() -> Self {
+        Units {
+            temperature: Temperature::Fahrenheit,
+            speed: Speed::MilesPerHour,
+            clock: ClockStyle::TwelveHour,
+        }
+    }
+
+    /// Returns the units shared by the widgets using `services`, which are
+    /// the default ones unless others have been set.
+    pub fn shared(services: &Services) -> Units {
+        *services.get_or_insert_with(Units::default)
+    }
+
+    /// Converts a temperature in degrees Celsius into these units.
+    pub fn temperature(&self, celsius: f64) -> f64 {
+        match self.temperature {
+            Temperature::Celsius => celsius,
+            Temperature::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Returns the symbol of the temperature unit, e.g. `°C`.
+    pub fn temperature_symbol(&self) -> &'static str {
+        match self.temperature {
+            Temperature::Celsius => "°C",
+            Temperature::Fahrenheit => "°F",
+        }
+    }
+
+    /// Converts a speed in km/h into these units.
+    pub fn speed(&self, kmh: f64) -> f64 {
+        match self.speed {
+            Speed::KilometersPerHour => kmh,
+            Speed::MilesPerHour => kmh / 1.609_344,
+            Speed::MetersPerSecond => kmh / 3.6,
+        }
+    }
+
+    /// Returns the symbol of the speed unit, e.g. `km/h`.
+    pub fn speed_symbol(&self) -> &'static str {
+        match self.speed {
+            Speed::KilometersPerHour => "km/h",
+            Speed::MilesPerHour => "mph",
+            Speed::MetersPerSecond => "m/s",
+        }
+    }
+
+    /// Returns the `strftime` format of the time of day, e.g. `%H:%M`.
+    pub fn time_format(&self) -> &'static str {
+        match self.clock {
+            ClockStyle::TwelveHour => "%I:%M %p",
+            ClockStyle::TwentyFourHour => "%H:%M",
+        }
+    }
+}
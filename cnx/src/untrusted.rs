@@ -0,0 +1,199 @@
+//! Limits on the data widgets read from other programs.
+//!
+//! Widgets show and parse data which Cnx doesn't control: window titles,
+//! replies from window managers and MPD, and responses from web APIs. Any of
+//! these may be malformed, or even malicious, e.g. a web page whose title is
+//! megabytes long, or a reply which claims to be gigabytes long. The helpers
+//! here bound how much of such data is read and kept, so that it can't stall
+//! the bar or exhaust its memory, and turn anything too large into an
+//! [`io::ErrorKind::InvalidData`] error, which is a
+//! [`ErrorKind::Parse`](crate::error::ErrorKind::Parse) error.
+//!
+//! The parsers of this data are exercised by the fuzz targets in the `fuzz`
+//! directory of the repository, which are run with `cargo fuzz`.
+//!
+//! # Examples
+//!
+//! ```
+//! use cnx::untrusted;
+//!
+//! let title = format!("Inbox\n{}", "(1) ".repeat(1000));
+//! let title = untrusted::text(&title);
+//! assert!(title.starts_with("Inbox (1) (1)"));
+//! assert_eq!(title.chars().count(), untrusted::MAX_TEXT);
+//! assert!(untrusted::check_len(1 << 30, untrusted::MAX_MESSAGE).is_err());
+//! ```
+
+use async_stream::stream;
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_stream::Stream;
+
+/// The most characters of a text from another program, e.g. a window title,
+/// which are shown.
+pub const MAX_TEXT: usize = 1024;
+
+/// The longest line, in bytes, read from another program.
+pub const MAX_LINE: usize = 64 * 1024;
+
+/// The largest message or response body, in bytes, read from another
+/// program or a web API.
+pub const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// Returns `text` as it can be shown in the bar: on one line, without
+/// control characters, and at most [`MAX_TEXT`] characters long, ending with
+/// `…` if it was cut short.
+pub fn text(text: &str) -> String {
+    let mut chars = text.chars().map(|c| if c.is_control() { ' ' } else { c });
+    let mut shown: String = chars.by_ref().take(MAX_TEXT).collect();
+    if chars.next().is_some() {
+        shown.pop();
+        shown.push('…');
+    }
+    shown
+}
+
+/// Returns `len` if it is at most `max`, e.g. the length of a message which
+/// is about to be read, or else an error.
+pub fn check_len(len: usize, max: usize) -> io::Result<usize> {
+    if len > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{len} bytes is more than the limit of {max}"),
+        ));
+    }
+    Ok(len)
+}
+
+/// Reads a line, including its newline, and appends it to `line`, like
+/// [`AsyncBufReadExt::read_line()`], but fails if it is longer than `max`
+/// bytes.
+///
+/// A line which is too long is still read to its end, without being kept,
+/// so that the next call reads the line after it. Returns the number of
+/// bytes read, which is zero at the end of the input.
+pub async fn read_line<R>(reader: &mut R, line: &mut String, max: usize) -> io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut bytes = Vec::new();
+    let mut read = 0;
+    let mut too_long = false;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            break;
+        }
+        let (chunk, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(end) => (&available[..=end], true),
+            None => (available, false),
+        };
+        let len = chunk.len();
+        if bytes.len() + len > max {
+            too_long = true;
+            bytes = Vec::new();
+        }
+        if !too_long {
+            bytes.extend_from_slice(chunk);
+        }
+        reader.consume(len);
+        read += len;
+        if done {
+            break;
+        }
+    }
+    if too_long {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Line of {read} bytes is longer than the limit of {max}"),
+        ));
+    }
+    let text =
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    line.push_str(&text);
+    Ok(read)
+}
+
+/// Returns a stream of the lines read from `reader`, without their line
+/// endings, like [`AsyncBufReadExt::lines()`], but which yields an error in
+/// place of each line longer than `max` bytes, or which isn't UTF-8.
+pub fn lines<R>(mut reader: R, max: usize) -> impl Stream<Item = io::Result<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    stream! {
+        loop {
+            let mut line = String::new();
+            match read_line(&mut reader, &mut line, max).await {
+                Ok(0) => break,
+                Ok(_) => {
+                    if line.ends_with('\n') {
+                        line.pop();
+                        if line.ends_with('\r') {
+                            line.pop();
+                        }
+                    }
+                    yield Ok(line);
+                }
+                // The bad line has been skipped, so the next one can be read.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => yield Err(err),
+                Err(err) => {
+                    yield Err(err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn texts() {
+        assert_eq!(text("Inbox\t(3)\r\n"), "Inbox (3)  ");
+        assert_eq!(text(&"é".repeat(MAX_TEXT)), "é".repeat(MAX_TEXT));
+        let long = text(&"é".repeat(MAX_TEXT + 1));
+        assert_eq!(long.chars().count(), MAX_TEXT);
+        assert!(long.ends_with("é…"));
+        assert_eq!(check_len(10, 10).unwrap(), 10);
+        assert_eq!(
+            check_len(11, 10).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn limited_lines() {
+        let input = format!(
+            "OK MPD 0.23\n{}\nvolume: 50\r\n\u{fffd}\nend",
+            "x".repeat(100)
+        );
+        let mut reader = input.as_bytes();
+        let mut line = String::new();
+        assert_eq!(block_on(read_line(&mut reader, &mut line, 20)).unwrap(), 12);
+        assert_eq!(line, "OK MPD 0.23\n");
+        assert!(block_on(read_line(&mut reader, &mut line, 20)).is_err());
+        assert_eq!(line, "OK MPD 0.23\n");
+
+        let lines: Vec<_> = block_on(lines(input.as_bytes(), 20).collect());
+        let lines: Vec<_> = lines.iter().map(|line| line.as_deref().ok()).collect();
+        assert_eq!(
+            lines,
+            [
+                Some("OK MPD 0.23"),
+                None,
+                Some("volume: 50"),
+                Some("\u{fffd}"),
+                Some("end")
+            ]
+        );
+
+        // Input which isn't UTF-8 is an error too.
+        let mut reader = &b"\xff\xfe\n"[..];
+        assert!(block_on(read_line(&mut reader, &mut line, 20)).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::renderer::{Framing, Renderers};
+use crate::untrusted;
+use anyhow::{anyhow, Context, Result};
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task;
+use tokio::time;
+
+// The page showing the bar, which listens for updates from `/events`.
+const PAGE: &str = include_str!("web.html");
+
+// How long a client has to send its request, and how many header lines it
+// may send, so that clients can't hold connections open or use up memory.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HEADERS: usize = 100;
+
+// Serves a read-only view of the bar over HTTP at `addr`, returning the
+// renderers to send the widgets' output to.
+//
+// The page at `/` receives the output of the widgets from `/events` as
+// server-sent events, in the same protocol as external renderers.
+//
+// This must be called from within a `task::LocalSet`.
+pub(crate) fn serve(addr: SocketAddr, widgets: usize) -> Result<Rc<RefCell<Renderers>>> {
+    let listener = std::net::TcpListener::bind(addr)
+        .with_context(|| format!("Failed to bind web view: {addr}"))?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+
+    let renderers = Rc::new(RefCell::new(Renderers::new(widgets)));
+    let result = renderers.clone();
+    task::spawn_local(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    task::spawn_local(handle_client(stream, renderers.clone()));
+                }
+                Err(err) => println!("Error accepting web view connection: {err}"),
+            }
+        }
+    });
+    Ok(result)
+}
+
+async fn handle_client(stream: TcpStream, renderers: Rc<RefCell<Renderers>>) {
+    if let Err(err) = respond(stream, renderers).await {
+        println!("Error serving web view: {err}");
+    }
+}
+
+// Reads a request, returning its request line, e.g. `GET / HTTP/1.1`.
+//
+// We only need the path from the request line, but read the headers too so
+// that the client isn't cut off.
+async fn read_request<R>(reader: &mut R) -> Result<String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut request = String::new();
+    untrusted::read_line(reader, &mut request, untrusted::MAX_LINE).await?;
+    let mut header = String::new();
+    for _ in 0..MAX_HEADERS {
+        header.clear();
+        if untrusted::read_line(reader, &mut header, untrusted::MAX_LINE).await? <= 2 {
+            return Ok(request);
+        }
+    }
+    Err(anyhow!("More than {} header lines", MAX_HEADERS))
+}
+
+async fn respond(mut stream: TcpStream, renderers: Rc<RefCell<Renderers>>) -> Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let request = time::timeout(REQUEST_TIMEOUT, read_request(&mut reader))
+        .await
+        .map_err(|_| anyhow!("Timed out reading the request"))??;
+
+    let mut words = request.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("GET"), Some("/")) => {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{PAGE}",
+                PAGE.len()
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        (Some("GET"), Some("/events")) => {
+            let response = "HTTP/1.1 200 OK\r\n\
+                            Content-Type: text/event-stream\r\n\
+                            Cache-Control: no-cache\r\n\r\n";
+            stream.write_all(response.as_bytes()).await?;
+            renderers.borrow_mut().add(stream, Framing::EventStream)?;
+        }
+        _ => {
+            let response = "HTTP/1.1 404 Not Found\r\n\
+                            Content-Length: 0\r\n\
+                            Connection: close\r\n\r\n";
+            stream.write_all(response.as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut request: &[u8] = b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n";
+            let line = read_request(&mut request).await.unwrap();
+            assert_eq!(line, "GET /events HTTP/1.1\r\n");
+
+            let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS + 1);
+            let request = format!("GET / HTTP/1.1\r\n{headers}\r\n");
+            assert!(read_request(&mut request.as_bytes()).await.is_err());
+
+            let path = "a".repeat(untrusted::MAX_LINE);
+            let request = format!("GET /{path} HTTP/1.1\r\n\r\n");
+            assert!(read_request(&mut request.as_bytes()).await.is_err());
+        });
+    }
+}
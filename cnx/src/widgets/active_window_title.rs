@@ -2,7 +2,8 @@ use anyhow::{Context, Result};
 use futures::stream::StreamExt;
 use xcb_util::ewmh;
 
-use crate::text::{Attributes, Text};
+use crate::text::{truncate, Attributes, Text};
+use crate::untrusted;
 use crate::widgets::{Widget, WidgetStream};
 use crate::xcb::xcb_properties_stream;
 
@@ -13,17 +14,31 @@ use crate::xcb::xcb_properties_stream;
 /// of the root window to determine which window is currently focused.
 ///
 /// The widgets content stretches to fill all available space. If the title is
-/// too large for the available space, it will be truncated.
+/// too large for the available space, it will be truncated. It can also be
+/// truncated to a fixed width; see [`ActiveWindowTitle::with_max_width`].
 ///
 /// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
 pub struct ActiveWindowTitle {
     attr: Attributes,
+    max_width: Option<usize>,
 }
 
 impl ActiveWindowTitle {
     /// Creates a new Active Window Title widget.
     pub fn new(attr: Attributes) -> ActiveWindowTitle {
-        ActiveWindowTitle { attr }
+        ActiveWindowTitle {
+            attr,
+            max_width: None,
+        }
+    }
+
+    /// Truncates the title to at most `max_width` columns, as measured by
+    /// [`truncate()`].
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self {
+            max_width: Some(max_width),
+            ..self
+        }
     }
 
     fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Vec<Text> {
@@ -42,14 +57,20 @@ impl ActiveWindowTitle {
 
                 ewmh::get_wm_name(conn, active_window).get_reply()
             })
-            .map(|reply| reply.string().to_owned())
+            // Any program can set its window's title to anything.
+            .map(|reply| untrusted::text(reply.string()))
             .unwrap_or_else(|_| "".to_owned());
+        let title = match self.max_width {
+            Some(max_width) => truncate(&title, max_width),
+            None => title,
+        };
 
         vec![Text {
             attr: self.attr.clone(),
             text: title,
             stretch: true,
             markup: false,
+            canvas: None,
         }]
     }
 }
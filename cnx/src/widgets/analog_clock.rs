@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::Timelike;
+use std::f64::consts::TAU;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+use super::clock::ClockStream;
+use crate::text::{Attributes, Canvas, Text};
+use crate::widgets::{Widget, WidgetStream};
+
+/// Shows the current time on a clock face.
+///
+/// This widget draws a round clock face with hour and minute hands (and
+/// optionally a second hand) in the foreground color, using a [`Canvas`].
+///
+/// Like [`Clock`](super::Clock), it updates at the start of every minute (or
+/// second), and as soon as the system resumes from suspend or the system
+/// clock is changed.
+pub struct AnalogClock {
+    attr: Attributes,
+    size: f64,
+    seconds: bool,
+}
+
+impl AnalogClock {
+    /// Creates a new AnalogClock widget, `size` pixels wide and high.
+    pub fn new(attr: Attributes, size: f64) -> Self {
+        Self {
+            attr,
+            size,
+            seconds: false,
+        }
+    }
+
+    /// Returns a new instance of `AnalogClock` which also draws a second
+    /// hand, updating every second.
+    pub fn with_seconds(self, seconds: bool) -> Self {
+        Self { seconds, ..self }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let now = chrono::Local::now();
+        let seconds = self.seconds.then(|| f64::from(now.second()));
+        let minutes = f64::from(now.minute()) + seconds.unwrap_or(0.0) / 60.0;
+        let hours = f64::from(now.hour() % 12) + minutes / 60.0;
+
+        let canvas = Canvas::new(self.size, self.size, move |cr, width, height| {
+            let radius = width.min(height) / 2.0 - 1.0;
+            cr.translate(width / 2.0, height / 2.0);
+            cr.set_line_cap(cairo::LineCap::Round);
+
+            cr.set_line_width(1.0);
+            cr.arc(0.0, 0.0, radius, 0.0, TAU);
+            let _ = cr.stroke();
+
+            let hand = |fraction: f64, length: f64, line_width: f64| {
+                let angle = fraction * TAU;
+                cr.set_line_width(line_width);
+                cr.move_to(0.0, 0.0);
+                cr.line_to(length * angle.sin(), -length * angle.cos());
+                let _ = cr.stroke();
+            };
+            hand(hours / 12.0, radius * 0.5, 2.0);
+            hand(minutes / 60.0, radius * 0.8, 1.5);
+            if let Some(seconds) = seconds {
+                hand(seconds / 60.0, radius * 0.9, 0.5);
+            }
+        });
+
+        vec![Text {
+            attr: self.attr.clone(),
+            text: String::new(),
+            stretch: false,
+            markup: false,
+            canvas: Some(canvas),
+        }]
+    }
+}
+
+impl Widget for AnalogClock {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let period = Duration::from_secs(if self.seconds { 1 } else { 60 });
+        let stream = ClockStream::new(period)?.map(move |()| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
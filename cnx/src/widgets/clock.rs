@@ -1,39 +1,49 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Duration;
-use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
 
+use crate::i18n;
+use crate::services::Services;
 use crate::text::{Attributes, Text};
+use crate::units::Units;
 use crate::widgets::{Widget, WidgetStream};
 
 /// Shows the current time and date.
 ///
 /// This widget shows the current time and date, in the form `%Y-%m-%d %a %I:%M
-/// %p`, e.g. `2017-09-01 Fri 12:51 PM`.
+/// %p`, e.g. `2017-09-01 Fri 12:51 PM`, or `%Y-%m-%d %a %H:%M` with a 24-hour
+/// clock set in the bar's [`Units`], unless it is given a format.
+///
+/// The widget updates at the start of every minute. It also updates as soon
+/// as the system resumes from suspend or the system clock is changed, so it
+/// never shows a stale time.
 pub struct Clock {
     attr: Attributes,
     format_str: Option<String>,
+    services: Option<Services>,
 }
 
 impl Clock {
     // Creates a new Clock widget.
     pub fn new(attr: Attributes, format_str: Option<String>) -> Self {
-        Self { attr, format_str }
+        Self {
+            attr,
+            format_str,
+            services: None,
+        }
     }
 
-    fn tick(&self) -> Vec<Text> {
+    fn tick(&self, format_time: &str) -> Vec<Text> {
         let now = chrono::Local::now();
-        let format_time: String = self
-            .format_str
-            .clone()
-            .map_or("%Y-%m-%d %a %I:%M %p".to_string(), |item| item);
-        let text = now.format(&format_time).to_string();
+        let text = i18n::tr_words(&now.format(format_time).to_string());
         let texts = vec![Text {
             attr: self.attr.clone(),
             text,
             stretch: false,
             markup: true,
+            canvas: None,
         }];
         texts
     }
@@ -44,9 +54,211 @@ impl Widget for Clock {
         // As we're not showing seconds, we can sleep for however long
         // it takes until the minutes changes between updates.
         let one_minute = Duration::from_secs(60);
-        let interval = time::interval(one_minute);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+        let units = self
+            .services
+            .as_ref()
+            .map(Units::shared)
+            .unwrap_or_default();
+        let format_time = self
+            .format_str
+            .clone()
+            .unwrap_or_else(|| format!("%Y-%m-%d %a {}", units.time_format()));
+        let stream = ClockStream::new(one_minute)?.map(move |()| Ok(self.tick(&format_time)));
 
         Ok(Box::pin(stream))
     }
+
+    fn use_services(&mut self, services: &Services) {
+        self.services = Some(services.clone());
+    }
+}
+
+/// Returns the number of seconds since the epoch at which the next multiple
+/// of `period` starts, in local time.
+fn next_boundary(period: Duration) -> i64 {
+    let now = chrono::Local::now();
+    let period = period.as_secs().max(1) as i64;
+    // Align to local time, so that e.g. hourly ticks happen on the hour in
+    // timezones that aren't a whole number of hours from UTC.
+    let offset = i64::from(now.offset().local_minus_utc());
+    let local = now.timestamp() + offset;
+    (local / period + 1) * period - offset
+}
+
+/// A `Stream` that yields immediately, and then whenever the wall-clock time
+/// crosses a multiple of `period` (e.g. the start of each minute).
+///
+/// A plain `tokio::time::interval()` is driven by the monotonic clock, which
+/// stops while the system is suspended. After a resume it would only fire once
+/// the remainder of the period had elapsed, showing a stale time until then.
+/// On Linux, we instead arm a `CLOCK_REALTIME` timerfd for the absolute time of
+/// the next boundary: the kernel fires such timers as soon as the system
+/// resumes if their expiry passed while asleep. `TFD_TIMER_CANCEL_ON_SET` also
+/// wakes us when the clock is changed (e.g. by NTP or the user).
+pub(crate) struct ClockStream {
+    period: Duration,
+    initial: bool,
+    #[cfg(target_os = "linux")]
+    timer: tokio::io::unix::AsyncFd<timerfd::TimerFd>,
+    #[cfg(not(target_os = "linux"))]
+    sleep: Pin<Box<tokio::time::Sleep>>,
+}
+
+#[cfg(target_os = "linux")]
+impl ClockStream {
+    pub fn new(period: Duration) -> Result<ClockStream> {
+        let timer = timerfd::TimerFd::new().context("Failed to create timerfd")?;
+        timer
+            .set_absolute(next_boundary(period))
+            .context("Failed to arm timerfd")?;
+        Ok(ClockStream {
+            period,
+            initial: true,
+            timer: tokio::io::unix::AsyncFd::new(timer)?,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Stream for ClockStream {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.initial {
+            self.initial = false;
+            return Poll::Ready(Some(()));
+        }
+
+        loop {
+            let mut guard = match self.timer.poll_read_ready(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(_)) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            match guard.try_io(|timer| timer.get_ref().read()) {
+                // Either the timer expired, or the clock was changed and the
+                // timer was cancelled. Either way, re-arm it for the next
+                // boundary and let the caller re-read the time.
+                Ok(Ok(())) => {
+                    let next = next_boundary(self.period);
+                    if self.timer.get_ref().set_absolute(next).is_err() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(()));
+                }
+                Ok(Err(_)) => return Poll::Ready(None),
+                // Spurious wake-up: readiness has been cleared, so poll again.
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl ClockStream {
+    pub fn new(period: Duration) -> Result<ClockStream> {
+        Ok(ClockStream {
+            period,
+            initial: true,
+            sleep: Box::pin(tokio::time::sleep(Self::until_next(period))),
+        })
+    }
+
+    fn until_next(period: Duration) -> Duration {
+        let now = chrono::Local::now().timestamp();
+        Duration::from_secs((next_boundary(period) - now).max(1) as u64)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Stream for ClockStream {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.initial {
+            self.initial = false;
+            return Poll::Ready(Some(()));
+        }
+
+        match std::future::Future::poll(self.sleep.as_mut(), cx) {
+            Poll::Ready(()) => {
+                let deadline = tokio::time::Instant::now() + Self::until_next(self.period);
+                self.sleep.as_mut().reset(deadline);
+                Poll::Ready(Some(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod timerfd {
+    use std::io;
+    use std::os::unix::io::{AsRawFd, RawFd};
+
+    // A thin wrapper around a non-blocking `CLOCK_REALTIME` timerfd.
+    pub struct TimerFd(RawFd);
+
+    impl TimerFd {
+        pub fn new() -> io::Result<TimerFd> {
+            let flags = libc::TFD_NONBLOCK | libc::TFD_CLOEXEC;
+            let fd = unsafe { libc::timerfd_create(libc::CLOCK_REALTIME, flags) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(TimerFd(fd))
+        }
+
+        // Arms the timer to fire once at `secs` seconds since the epoch.
+        pub fn set_absolute(&self, secs: i64) -> io::Result<()> {
+            let spec = libc::itimerspec {
+                it_interval: libc::timespec {
+                    tv_sec: 0,
+                    tv_nsec: 0,
+                },
+                it_value: libc::timespec {
+                    tv_sec: secs as libc::time_t,
+                    tv_nsec: 0,
+                },
+            };
+            let flags = libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET;
+            let ret = unsafe { libc::timerfd_settime(self.0, flags, &spec, std::ptr::null_mut()) };
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        // Consumes an expiry. A cancellation due to the clock being changed
+        // (`ECANCELED`) is reported as success, as it also warrants an update.
+        pub fn read(&self) -> io::Result<()> {
+            let mut expirations = [0u8; 8];
+            let ret = unsafe {
+                libc::read(
+                    self.0,
+                    expirations.as_mut_ptr() as *mut libc::c_void,
+                    expirations.len(),
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() != Some(libc::ECANCELED) {
+                    return Err(err);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl AsRawFd for TimerFd {
+        fn as_raw_fd(&self) -> RawFd {
+            self.0
+        }
+    }
+
+    impl Drop for TimerFd {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0) };
+        }
+    }
 }
@@ -2,11 +2,16 @@
 
 mod active_window_title;
 
+mod analog_clock;
 mod clock;
 mod pager;
+mod polled;
 pub use self::active_window_title::ActiveWindowTitle;
+pub use self::analog_clock::AnalogClock;
 pub use self::clock::Clock;
-pub use self::pager::Pager;
+pub use self::pager::{Pager, Rename};
+pub use self::polled::Polled;
+use crate::services::Services;
 use crate::text::Text;
 use anyhow::Result;
 use futures::stream::Stream;
@@ -19,10 +24,66 @@ use std::pin::Pin;
 /// refer to the documentation on the [`Widget`] trait.
 ///
 /// Any errors on the stream are logged but do not affect the runtime of the
-/// main [`crate::Cnx`] instance.
+/// main [`crate::Cnx`] instance. A widget may yield a
+/// [`WidgetError`](crate::error::WidgetError) to say what kind of error it
+/// had, which decides whether the error is logged, shown in place of the
+/// widget or hides it. See [`crate::error`].
+///
+/// The stream may end, e.g. for a widget that is only shown for a while. The
+/// bar keeps showing the last `Vec<Text>` the widget yielded, so it should
+/// yield an empty `Vec` first to hide itself.
+///
+/// Once the stream is dropped, any work the widget does in the background
+/// must stop with it. See [`crate::teardown`].
 ///
 pub type WidgetStream = Pin<Box<dyn Stream<Item = Result<Vec<Text>>>>>;
 
+/// A mouse button (or scroll wheel direction) that was pressed over the bar.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    /// Any other button, identified by its X11 button number.
+    Other(u8),
+}
+
+impl From<u8> for MouseButton {
+    fn from(button: u8) -> Self {
+        match button {
+            1 => MouseButton::Left,
+            2 => MouseButton::Middle,
+            3 => MouseButton::Right,
+            4 => MouseButton::ScrollUp,
+            5 => MouseButton::ScrollDown,
+            6 => MouseButton::ScrollLeft,
+            7 => MouseButton::ScrollRight,
+            other => MouseButton::Other(other),
+        }
+    }
+}
+
+/// A click on one of the [`Text`]s most recently yielded by a widget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClickEvent {
+    /// The button that was pressed.
+    pub button: MouseButton,
+    /// The index of the clicked [`Text`] within the widget's `Vec<Text>`.
+    pub index: usize,
+    /// The x position of the pointer, relative to the root window.
+    pub x: i16,
+    /// The y position of the pointer, relative to the root window.
+    pub y: i16,
+}
+
+/// The stream of [`ClickEvent`]s passed to
+/// [`Widget::into_stream_with_clicks()`].
+pub type ClickStream = Pin<Box<dyn Stream<Item = ClickEvent>>>;
+
 /// The main trait implemented by all widgets.
 ///
 /// This simple trait defines a widget. A widget is essentially just a
@@ -32,6 +93,28 @@ pub type WidgetStream = Pin<Box<dyn Stream<Item = Result<Vec<Text>>>>>;
 /// See the [`WidgetStream`] type alias for the exact type of stream that
 /// should be returned.
 ///
+/// Widgets that want to react to mouse clicks should also implement
+/// [`Widget::into_stream_with_clicks()`], which is what [`crate::Cnx`]
+/// actually calls.
+///
 pub trait Widget {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream>;
+
+    /// Like [`Widget::into_stream()`], but also receives a stream of clicks
+    /// on the widget's texts.
+    ///
+    /// The default implementation ignores all clicks.
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
+        drop(clicks);
+        self.into_stream()
+    }
+
+    /// Receives the [`Services`] shared by the bar's widgets, so that the
+    /// widget can use e.g. a shared D-Bus connection rather than opening its
+    /// own. This is called by [`crate::Cnx::add_widget()`].
+    ///
+    /// The default implementation ignores them.
+    fn use_services(&mut self, services: &Services) {
+        let _ = services;
+    }
 }
@@ -1,12 +1,33 @@
 use anyhow::{Context, Result};
-use futures::stream::StreamExt;
+use futures::channel::mpsc;
+use pango::glib;
 use std::cmp::Ordering;
+use tokio::task;
+use tokio_stream::{self as stream, StreamExt};
 use xcb_util::ewmh;
 
+use crate::i18n;
+use crate::prompt::{self, PromptEvent};
 use crate::text::{Attributes, PagerAttributes, Text};
-use crate::widgets::{Widget, WidgetStream};
+use crate::widgets::{ClickEvent, ClickStream, MouseButton, Widget, WidgetStream};
 use crate::xcb::xcb_properties_stream;
 
+/// How [`Pager::with_rename()`] applies a new name to a workspace.
+pub enum Rename {
+    /// Sets the name in `_NET_DESKTOP_NAMES`, which most EWMH window managers
+    /// use as the names of their workspaces.
+    DesktopNames,
+    /// Calls the function with the workspace's old and new names, e.g. to
+    /// rename it with the window manager's own IPC.
+    Custom(Box<dyn Fn(String, String)>),
+}
+
+enum Event {
+    Changed,
+    Click(ClickEvent),
+    Prompt(usize, PromptEvent),
+}
+
 /// Shows the WM's workspaces/groups.
 ///
 /// This widget can highlight the currently active, inactive and non empty
@@ -16,11 +37,15 @@ use crate::xcb::xcb_properties_stream;
 /// `_NET_NUMBER_OF_DESKTOPS`, `_NET_DESKTOP_NAMES`, `_NET_CURRENT_DESKTOP`,
 /// `_NET_CLIENT_LIST`, `_NET_WM_WINDOW_TYPE` and `_NET_WM_DESKTOP` properties.
 ///
+/// With [`Pager::with_rename()`], workspaces can also be renamed by right
+/// clicking them.
+///
 /// [`EWMH`]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html
 pub struct Pager {
     active_attr: Attributes,
     inactive_attr: Attributes,
     non_empty_attr: Attributes,
+    rename: Option<Rename>,
 }
 
 impl Pager {
@@ -30,34 +55,31 @@ impl Pager {
             active_attr: pager_attrs.active_attr,
             inactive_attr: pager_attrs.inactive_attr,
             non_empty_attr: pager_attrs.non_empty_attr,
+            rename: None,
         }
     }
 
-    fn on_change(&self, conn: &ewmh::Connection, screen_idx: i32) -> Vec<Text> {
-        let number = ewmh::get_number_of_desktops(conn, screen_idx)
-            .get_reply()
-            .unwrap_or(0) as usize;
+    /// Lets workspaces be renamed from the bar: right clicking a workspace
+    /// opens a prompt in its place, in which the new name can be typed.
+    /// Return applies the name as given by `rename`, and Escape cancels.
+    pub fn with_rename(self, rename: Rename) -> Self {
+        Self {
+            rename: Some(rename),
+            ..self
+        }
+    }
+
+    fn on_change(
+        &self,
+        conn: &ewmh::Connection,
+        screen_idx: i32,
+        editing: Option<&(usize, String)>,
+    ) -> Vec<Text> {
         let current = ewmh::get_current_desktop(conn, screen_idx)
             .get_reply()
             .unwrap_or(0) as usize;
-        let names_reply = ewmh::get_desktop_names(conn, screen_idx).get_reply();
-        let mut names = match names_reply {
-            Ok(ref r) => r.strings(),
-            Err(_) => Vec::new(),
-        };
-
-        // EWMH states that `number` may not equal `names.len()`, as there may
-        // be unnamed desktops, or more desktops than are currently in use.
-        match names.len().cmp(&number) {
-            Ordering::Equal => (),
-            Ordering::Greater => names.truncate(number),
-            Ordering::Less => {
-                let num_unnamed = number - names.len();
-                names.extend(vec!["?"; num_unnamed]);
-            }
-        }
 
-        names
+        desktop_names(conn, screen_idx)
             .into_iter()
             .enumerate()
             .map(|(i, name)| {
@@ -68,18 +90,50 @@ impl Pager {
                 } else {
                     self.inactive_attr.clone()
                 };
+                // Show the text being typed, followed by a cursor.
+                let text = match editing {
+                    Some((idx, text)) if *idx == i => {
+                        format!("<u>{}</u>_", glib::markup_escape_text(text))
+                    }
+                    _ => i18n::tr(&name),
+                };
 
                 Text {
                     attr,
-                    text: name.to_owned(),
+                    text,
                     stretch: false,
                     markup: true,
+                    canvas: None,
                 }
             })
             .collect()
     }
 }
 
+fn desktop_names(conn: &ewmh::Connection, screen_idx: i32) -> Vec<String> {
+    let number = ewmh::get_number_of_desktops(conn, screen_idx)
+        .get_reply()
+        .unwrap_or(0) as usize;
+    let names_reply = ewmh::get_desktop_names(conn, screen_idx).get_reply();
+    let mut names = match names_reply {
+        Ok(ref r) => r.strings(),
+        Err(_) => Vec::new(),
+    };
+
+    // EWMH states that `number` may not equal `names.len()`, as there may
+    // be unnamed desktops, or more desktops than are currently in use.
+    match names.len().cmp(&number) {
+        Ordering::Equal => (),
+        Ordering::Greater => names.truncate(number),
+        Ordering::Less => {
+            let num_unnamed = number - names.len();
+            names.extend(vec!["?"; num_unnamed]);
+        }
+    }
+
+    names.into_iter().map(str::to_owned).collect()
+}
+
 fn non_empty_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Vec<u32> {
     let client_list = ewmh::get_client_list(conn, screen_idx).get_reply();
     let windows: &[u32] = match client_list {
@@ -105,15 +159,81 @@ fn non_empty_desktops(conn: &ewmh::Connection, screen_idx: i32) -> Vec<u32> {
 
 impl Widget for Pager {
     fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        self.into_stream_with_clicks(Box::pin(stream::empty()))
+    }
+
+    fn into_stream_with_clicks(self: Box<Self>, clicks: ClickStream) -> Result<WidgetStream> {
         let properties = &[
             "_NET_NUMBER_OF_DESKTOPS",
             "_NET_CURRENT_DESKTOP",
             "_NET_DESKTOP_NAMES",
         ];
         let screen_idx = 0; // XXX assume
-        let (conn, stream) = xcb_properties_stream(properties).context("Initialising Pager")?;
+        let (conn, changes) = xcb_properties_stream(properties).context("Initialising Pager")?;
+
+        // The events of an open prompt are forwarded to us along with the
+        // index of the workspace being renamed.
+        let (sender, prompts) = mpsc::unbounded();
+        let events = changes
+            .map(|()| Event::Changed)
+            .merge(clicks.map(Event::Click))
+            .merge(prompts.map(|(idx, event)| Event::Prompt(idx, event)));
 
-        let stream = stream.map(move |()| Ok(self.on_change(&conn, screen_idx)));
+        let mut editing = None;
+        let stream = events.filter_map(move |event| match event {
+            Event::Changed => Some(Ok(self.on_change(&conn, screen_idx, editing.as_ref()))),
+            Event::Click(click) => {
+                if click.button != MouseButton::Right || self.rename.is_none() || editing.is_some()
+                {
+                    return None;
+                }
+                let name = desktop_names(&conn, screen_idx)
+                    .into_iter()
+                    .nth(click.index)?;
+                let mut events = match prompt::prompt(name) {
+                    Ok(events) => Box::pin(events),
+                    Err(err) => return Some(Err(err)),
+                };
+                let sender = sender.clone();
+                task::spawn_local(async move {
+                    while let Some(event) = events.next().await {
+                        if sender.unbounded_send((click.index, event)).is_err() {
+                            break;
+                        }
+                    }
+                });
+                None
+            }
+            Event::Prompt(idx, PromptEvent::Edited(text)) => {
+                editing = Some((idx, text));
+                Some(Ok(self.on_change(&conn, screen_idx, editing.as_ref())))
+            }
+            Event::Prompt(idx, PromptEvent::Accepted(new)) => {
+                editing = None;
+                let mut names = desktop_names(&conn, screen_idx);
+                let name = names.get_mut(idx)?;
+                match &self.rename {
+                    Some(Rename::DesktopNames) => {
+                        // The resulting property change will update the
+                        // names shown.
+                        *name = new;
+                        ewmh::set_desktop_names(
+                            &conn,
+                            screen_idx,
+                            names.iter().map(String::as_str),
+                        );
+                        conn.flush();
+                    }
+                    Some(Rename::Custom(rename)) => (rename)(name.clone(), new),
+                    None => {}
+                }
+                Some(Ok(self.on_change(&conn, screen_idx, None)))
+            }
+            Event::Prompt(_, PromptEvent::Cancelled) => {
+                editing = None;
+                Some(Ok(self.on_change(&conn, screen_idx, None)))
+            }
+        });
 
         Ok(Box::pin(stream))
     }
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use async_stream::stream;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time;
+
+use crate::text::Text;
+use crate::widgets::{Widget, WidgetStream};
+
+/// Shows the texts returned by an async closure, which is called again every
+/// `interval`.
+///
+/// This is the quickest way to write a small widget of your own, without
+/// implementing [`Widget`] and building its stream. The closure is called as
+/// the widget starts, and then `interval` after each call finishes, so calls
+/// never overlap, however long they take. An error the closure returns is
+/// handled like any other widget's (see [`crate::error`]), and the closure is
+/// still called again after `interval`.
+///
+/// # Examples
+///
+/// ```
+/// # use cnx::text::{Attributes, Color, Font, Padding, Text};
+/// # use cnx::widgets::Polled;
+/// # use cnx::{Cnx, Position};
+/// # use std::time::Duration;
+/// let attr = Attributes {
+///     font: Font::new("SourceCodePro 21"),
+///     fg_color: Color::white(),
+///     bg_color: None,
+///     padding: Padding::new(8.0, 8.0, 0.0, 0.0),
+/// };
+///
+/// let mut cnx = Cnx::new(Position::Top);
+/// cnx.add_widget(Polled::new(Duration::from_secs(10), move || {
+///     let attr = attr.clone();
+///     async move {
+///         let load = std::fs::read_to_string("/proc/loadavg")?;
+///         let text = load.split_whitespace().next().unwrap_or("?").to_owned();
+///         Ok::<_, anyhow::Error>(vec![Text::new(attr, text)])
+///     }
+/// }));
+/// ```
+pub struct Polled<F> {
+    interval: Duration,
+    poll: F,
+}
+
+impl<F, Fut> Polled<F>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<Vec<Text>>> + 'static,
+{
+    /// Creates a new [`Polled`] widget, which calls `poll` every `interval`.
+    pub fn new(interval: Duration, poll: F) -> Self {
+        Self { interval, poll }
+    }
+}
+
+impl<F, Fut> Widget for Polled<F>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<Vec<Text>>> + 'static,
+{
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = stream! {
+            loop {
+                yield (self.poll)().await;
+                time::sleep(self.interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -23,7 +23,7 @@ impl AsRawFd for XcbEvented {
     }
 }
 
-// A `Stream` of `xcb::GenericEvent` for the provided `xcb::Connection`.
+/// A `Stream` of `xcb::GenericEvent` for the provided `xcb::Connection`.
 pub struct XcbEventStream {
     conn: Rc<ewmh::Connection>,
     poll: AsyncFd<XcbEvented>,
@@ -79,12 +79,12 @@ impl Stream for XcbEventStream {
     }
 }
 
-// A `Stream` that listens to `PROPERTY_CHANGE` notifications.
-//
-// By default it listens to `PROPERTY_CHANGE` notifications for the provided
-// `properties` on the root window. The `ewhm::Connection` is returned so that
-// the caller may listen to `PROPERTY_CHANGE` notifications on additional
-// windows.
+/// A `Stream` that listens to `PROPERTY_CHANGE` notifications.
+///
+/// By default it listens to `PROPERTY_CHANGE` notifications for the provided
+/// `properties` on the root window. The `ewhm::Connection` is returned so that
+/// the caller may listen to `PROPERTY_CHANGE` notifications on additional
+/// windows.
 pub fn xcb_properties_stream(
     properties: &[&str],
 ) -> Result<(Rc<ewmh::Connection>, impl Stream<Item = ()>)> {
@@ -137,3 +137,15 @@ pub fn xcb_properties_stream(
 
     Ok((conn, stream))
 }
+
+/// Registers for `PROPERTY_CHANGE` notifications on the given `windows`.
+///
+/// This allows a stream returned by [`xcb_properties_stream()`] to also see
+/// changes to the properties of e.g. client windows.
+pub fn watch_properties(conn: &xcb::Connection, windows: &[xcb::Window]) {
+    let attributes = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)];
+    for window in windows {
+        xcb::change_window_attributes(conn, *window, &attributes);
+    }
+    conn.flush();
+}
@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|tree: &[u8]| {
+    cnx_contrib::fuzzing::i3_tree(tree);
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use cnx::untrusted;
+use futures::executor::block_on;
+use futures::StreamExt;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_LINE: usize = 64;
+
+fuzz_target!(|input: &[u8]| {
+    let lines: Vec<_> = block_on(untrusted::lines(input, MAX_LINE).collect());
+    for line in lines.into_iter().flatten() {
+        assert!(line.len() <= MAX_LINE);
+    }
+});
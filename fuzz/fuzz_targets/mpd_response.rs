@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|response: &[u8]| {
+    cnx_contrib::fuzzing::mpd_response(response);
+});
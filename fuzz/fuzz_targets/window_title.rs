@@ -0,0 +1,13 @@
+#![no_main]
+
+use cnx::text::truncate;
+use cnx::untrusted;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|title: &str| {
+    let shown = untrusted::text(title);
+    assert!(shown.chars().count() <= untrusted::MAX_TEXT);
+    assert!(!shown.chars().any(char::is_control));
+    // As `ActiveWindowTitle::with_max_width()` does.
+    truncate(&shown, 40);
+});